@@ -0,0 +1,108 @@
+// Fixtures shared by the doctests that need an NPF.
+//
+// They're included, hidden, by these doctests, so that each of them only shows the API it documents.
+
+/// Writes the NPF of the package of the given ID at the given path.
+///
+/// The manifest holds the given top-level fields on top of the name, category and version of the package, and
+/// `dependencies = []` unless the fields list dependencies. The scripts, like `post-install.sh`, are given as their
+/// name and content, and the files of the data archive as their path, mode and content. Paths ending with a slash
+/// are directories, those written `path => target` are hard links to the given target, and packages without files
+/// have no data archive.
+#[allow(dead_code)]
+fn write_npf(
+    path: &std::path::Path,
+    id: &str,
+    fields: &str,
+    scripts: &[(&str, &str)],
+    files: &[(&str, u32, &str)],
+) -> Result<(), failure::Error> {
+    let id = libnest::package::PackageID::parse(id)?;
+    let mut manifest = format!(
+        "name = \"{}\"\ncategory = \"{}\"\nversion = \"{}\"\nwrap_date = \"2019-05-27T16:34:15Z\"\n{}\n",
+        id.name(),
+        id.category(),
+        id.version(),
+        fields,
+    );
+    if !fields.lines().any(|line| line.trim_start().starts_with("dependencies")) {
+        manifest.push_str("dependencies = []\n");
+    }
+    manifest.push_str(
+        "[metadata]\ndescription = \"A test package\"\ntags = []\nmaintainer = \"nest@raven-os.org\"\nlicenses = []\n",
+    );
+
+    let append = |npf: &mut tar::Builder<std::fs::File>, name: &str, content: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        npf.append_data(&mut header, name, content)
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut npf = tar::Builder::new(std::fs::File::create(path)?);
+    append(&mut npf, "manifest.toml", manifest.as_bytes())?;
+    for (name, content) in scripts {
+        append(&mut npf, name, content.as_bytes())?;
+    }
+
+    if !files.is_empty() {
+        let mut data = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, mode, content) in files {
+            let mut header = tar::Header::new_gnu();
+            let mut path = *path;
+            if path.ends_with('/') {
+                header.set_entry_type(tar::EntryType::Directory);
+                path = path.trim_end_matches('/');
+            }
+            if let Some(index) = path.find(" => ") {
+                header.set_entry_type(tar::EntryType::Link);
+                header.set_link_name(&path[index + 4..])?;
+                path = &path[..index];
+            }
+            header.set_size(content.len() as u64);
+            header.set_mode(*mode);
+            header.set_cksum();
+            data.append_data(&mut header, path, content.as_bytes())?;
+        }
+        append(&mut npf, "data.tar.gz", &data.into_inner()?.finish()?)?;
+    }
+    npf.finish()?;
+    Ok(())
+}
+
+/// Writes the NPF of the package of the given ID in the cache of downloaded packages, like [`write_npf`], and
+/// registers its manifest in the cache of available packages, as a pull would.
+#[allow(dead_code)]
+fn download_npf(
+    config: &libnest::config::Config,
+    lock_file_ownership: &libnest::lock_file::LockFileOwnership,
+    id: &str,
+    fields: &str,
+    scripts: &[(&str, &str)],
+    files: &[(&str, u32, &str)],
+) -> Result<libnest::package::PackageID, failure::Error> {
+    let id = libnest::package::PackageID::parse(id)?;
+    let path = config
+        .paths()
+        .downloaded()
+        .join(id.repository().as_str())
+        .join(id.category().as_str())
+        .join(id.name().as_str())
+        .join(format!("{}-{}.nest", id.name(), id.version()));
+    write_npf(&path, &id.to_string(), fields, scripts, files)?;
+
+    let npf = config
+        .downloaded_packages_cache(lock_file_ownership)
+        .explore_package(&id)?;
+    config
+        .available_packages_cache(lock_file_ownership)
+        .register(npf.manifest().package_manifest(id.repository().clone()))?;
+    Ok(id)
+}
@@ -0,0 +1,46 @@
+//! An in-memory cache of the manifests read from the available packages cache.
+//!
+//! Solving a dependency graph performs many queries on overlapping requirements. To avoid reading and
+//! parsing the same manifest files over and over, each manifest is kept in memory once it has been read,
+//! for the remainder of the run.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use failure::Error;
+use lazy_static::lazy_static;
+
+use crate::package::PackageManifest;
+
+lazy_static! {
+    static ref MANIFESTS: Mutex<HashMap<PathBuf, Arc<PackageManifest>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Returns the manifest stored at the given path, reading it only if it isn't already in memory
+pub(crate) fn load(path: &Path) -> Result<Arc<PackageManifest>, Error> {
+    if let Some(manifest) = MANIFESTS
+        .lock()
+        .expect("the manifests cache is poisoned")
+        .get(path)
+    {
+        return Ok(manifest.clone());
+    }
+
+    // The file is read without holding the lock, so that other threads can still query the cache
+    let manifest = Arc::new(PackageManifest::load_from_cache(path)?);
+    MANIFESTS
+        .lock()
+        .expect("the manifests cache is poisoned")
+        .insert(path.to_path_buf(), manifest.clone());
+    Ok(manifest)
+}
+
+/// Forgets all the manifests stored at the given path or below it, so they are read again on their next use
+pub(crate) fn invalidate(path: &Path) {
+    MANIFESTS
+        .lock()
+        .expect("the manifests cache is poisoned")
+        .retain(|manifest_path, _| !manifest_path.starts_with(path));
+}
@@ -1,46 +1,156 @@
 //! Module to query and manipulate the cache of available packages
 //! This cache is populated and updated by pull operations.
 
+mod manifests;
 mod query;
+mod validators;
 
 pub use self::query::{
-    AvailablePackagesCacheQuery, AvailablePackagesCacheQueryStrategy, QueryResult,
+    AvailablePackagesCacheQuery, AvailablePackagesCacheQueryNameMatchMode,
+    AvailablePackagesCacheQueryStrategy, QueryResult,
 };
+pub use self::validators::{PullValidator, PullValidators};
 
 use super::errors::*;
 
+use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File};
 use std::io::Write;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use data_encoding::HEXUPPER;
 use failure::{Error, ResultExt};
 use serde_json;
+use sha2::{Digest, Sha256};
+use strsim::levenshtein;
 
-use crate::lock_file::LockFileOwnership;
-use crate::package::{PackageManifest, SoftPackageRequirement};
+use crate::config::{MirrorListError, MirrorListErrorKind, MirrorUrl};
+use crate::lock_file::{LockFileOwnership, LockFileReadOwnership};
+use crate::package::{
+    CategoryName, PackageFullName, PackageID, PackageManifest, PackageName, PackageRequirement,
+    PackageShortName, RepositoryName, SoftPackageRequirement, Tag,
+};
 use crate::repository::Repository;
 
-/// Structure representing the cache of available packages
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct AvailablePackages<'cache_root, 'lock_file> {
+/// Returns the path of the file holding the checksum of the manifest stored at the given path.
+///
+/// It's hidden, so it isn't mistaken for a package when browsing the cache.
+fn checksum_path(manifest_path: &Path) -> PathBuf {
+    let name = manifest_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    manifest_path.with_file_name(format!(".{}.sha256", name))
+}
+
+/// Returns the path of the file holding the date of the last successful pull of the given repository.
+///
+/// It's hidden, so it isn't mistaken for a category when browsing the cache.
+pub(crate) fn last_pull_path(cache_root: &Path, repository: &str) -> PathBuf {
+    cache_root.join(repository).join(".last_pull")
+}
+
+/// Returns the path of the cached mirror list of the given repository
+pub(crate) fn mirror_list_path(cache_root: &Path, repository: &str) -> PathBuf {
+    cache_root.join(".mirrorlists").join(repository)
+}
+
+/// Returns the hexadecimal representation of the SHA-256 of the given bytes
+fn checksum(bytes: &[u8]) -> String {
+    let mut sha256 = Sha256::default();
+    sha256.input(bytes);
+    HEXUPPER.encode(sha256.result().as_ref())
+}
+
+/// Returns the most recent version of the given package
+fn latest_version(
+    repository: RepositoryName,
+    package_manifest: &PackageManifest,
+) -> Option<QueryResult> {
+    package_manifest.versions().keys().max().map(|version| {
+        QueryResult::from(
+            repository,
+            package_manifest
+                .get_manifest_for_version(version.clone())
+                .expect("the version doesn't belong to the package"),
+        )
+    })
+}
+
+/// Structure representing the cache of available packages.
+///
+/// It's obtained through an ownership over Nest's lock file, whose type is `L`. The cache can be read with any
+/// [`LockFileReadOwnership`], but writing to it requires the exclusive [`LockFileOwnership`].
+///
+/// [`LockFileReadOwnership`]: ../../lock_file/trait.LockFileReadOwnership.html
+/// [`LockFileOwnership`]: ../../lock_file/struct.LockFileOwnership.html
+pub struct AvailablePackages<'cache_root, 'lock_file, L = LockFileOwnership>
+where
+    L: LockFileReadOwnership,
+{
     cache_root: &'cache_root Path,
-    phantom: PhantomData<&'lock_file LockFileOwnership>,
+    checksums: bool,
+    phantom: PhantomData<&'lock_file L>,
 }
 
-impl<'cache_root, 'lock_file> AvailablePackages<'cache_root, 'lock_file> {
-    pub(crate) fn from(
-        cache_root: &'cache_root Path,
-        phantom: PhantomData<&'lock_file LockFileOwnership>,
-    ) -> Self {
+// Implemented by hand, as deriving them would require the ownership itself to implement them
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership> Clone
+    for AvailablePackages<'cache_root, 'lock_file, L>
+{
+    fn clone(&self) -> Self {
+        AvailablePackages::from(self.cache_root, PhantomData).with_checksums(self.checksums)
+    }
+}
+
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership> PartialEq
+    for AvailablePackages<'cache_root, 'lock_file, L>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cache_root == other.cache_root && self.checksums == other.checksums
+    }
+}
+
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership> Eq
+    for AvailablePackages<'cache_root, 'lock_file, L>
+{
+}
+
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership> std::fmt::Debug
+    for AvailablePackages<'cache_root, 'lock_file, L>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AvailablePackages")
+            .field("cache_root", &self.cache_root)
+            .field("checksums", &self.checksums)
+            .finish()
+    }
+}
+
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership>
+    AvailablePackages<'cache_root, 'lock_file, L>
+{
+    pub(crate) fn from(cache_root: &'cache_root Path, phantom: PhantomData<&'lock_file L>) -> Self {
         AvailablePackages {
             cache_root,
+            checksums: false,
             phantom,
         }
     }
 
+    /// Sets whether the checksum of the manifests is recorded when they are written
+    pub(crate) fn with_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+}
+
+impl<'cache_root, 'lock_file> AvailablePackages<'cache_root, 'lock_file> {
     /// Erases the whole cache
     pub fn erase(&self) -> Result<(), Error> {
+        manifests::invalidate(self.cache_root);
         if self.cache_root.exists() {
             fs::remove_dir_all(self.cache_root)
                 .context(self.cache_root.display().to_string())
@@ -53,6 +163,7 @@ impl<'cache_root, 'lock_file> AvailablePackages<'cache_root, 'lock_file> {
     pub fn erase_repository(&self, repository: &Repository) -> Result<(), Error> {
         let path = self.cache_root.join(repository.name());
 
+        manifests::invalidate(&path);
         if path.exists() {
             fs::remove_dir_all(&path)
                 .context(path.display().to_string())
@@ -61,29 +172,413 @@ impl<'cache_root, 'lock_file> AvailablePackages<'cache_root, 'lock_file> {
         Ok(())
     }
 
+    /// Erases from the cache the repositories that aren't part of the given ones, like those that were removed from
+    /// the configuration, and returns their names, sorted.
+    ///
+    /// Packages installed from a local NPF belong to the [`LOCAL_REPOSITORY`][1], which should usually be kept too.
+    ///
+    /// [1]: ../../config/constant.LOCAL_REPOSITORY.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, RepositoryName};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-prune-to-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for repository in &["stable", "beta", "unstable"] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gcc",
+    ///             "category": "sys-devel",
+    ///             "repository": "{}",
+    ///             "metadata": {{
+    ///                 "description": "The GNU Compiler Collection",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "9.1.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         repository,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let removed = cache.prune_to(&[RepositoryName::parse("stable")?])?;
+    /// assert_eq!(removed, vec![RepositoryName::parse("beta")?, RepositoryName::parse("unstable")?]);
+    /// assert_eq!(cache.repositories()?, vec![RepositoryName::parse("stable")?]);
+    ///
+    /// // Pruning again has nothing left to remove
+    /// assert!(cache.prune_to(&[RepositoryName::parse("stable")?])?.is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prune_to(&self, keep: &[RepositoryName]) -> Result<Vec<RepositoryName>, Error> {
+        let mut removed = Vec::new();
+
+        for repository in self.repositories()? {
+            if keep.contains(&repository) {
+                continue;
+            }
+
+            let path = self.cache_root.join(repository.as_str());
+            manifests::invalidate(&path);
+            fs::remove_dir_all(&path)
+                .context(path.display().to_string())
+                .context(CacheErrorKind::CacheClearError)?;
+            removed.push(repository);
+        }
+        Ok(removed)
+    }
+
+    /// Saves the validators of the last responses of the mirrors of the given [`Repository`] to a pull
+    pub fn save_pull_validators(
+        &self,
+        repository: &Repository,
+        validators: &PullValidators,
+    ) -> Result<(), Error> {
+        let path = self.pull_validators_path(repository);
+
+        let res: Result<_, Error> = try {
+            let content = serde_json::to_string_pretty(validators)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)?;
+        };
+        Ok(res
+            .context(path.display().to_string())
+            .context(CacheErrorKind::CacheWriteError)?)
+    }
+
+    /// Records the date of the last successful pull of the given [`Repository`]
+    pub fn save_last_pull(
+        &self,
+        repository: &Repository,
+        date: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let path = last_pull_path(self.cache_root, repository.name());
+
+        let res: Result<_, Error> = try {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, date.to_rfc3339())?;
+        };
+        Ok(res
+            .context(path.display().to_string())
+            .context(CacheErrorKind::CacheWriteError)?)
+    }
+
+    /// Caches the mirror list of the given [`Repository`], so it's used by [`Config::load_mirror_lists`][1] until the
+    /// next pull.
+    ///
+    /// [1]: ../../config/struct.Config.html#method.load_mirror_lists
+    pub fn save_mirror_list(
+        &self,
+        repository: &Repository,
+        mirrors: &[MirrorUrl],
+    ) -> Result<(), MirrorListError> {
+        let path = mirror_list_path(self.cache_root, repository.name());
+        let content: String = mirrors
+            .iter()
+            .map(|mirror| format!("{}\n", mirror.as_str()))
+            .collect();
+
+        let res: Result<_, std::io::Error> = try {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)?;
+        };
+        Ok(res
+            .context(path.display().to_string())
+            .context(MirrorListErrorKind::MirrorListWriteError)?)
+    }
+
     /// Creates or updates the cache entry for a given [`Package`]
+    ///
+    /// If [enabled by the configuration][1], the checksum of the written manifest is recorded next to it, so that
+    /// [`verify_integrity`][2] can detect its corruption later on. Otherwise, any checksum recorded earlier is removed.
+    ///
+    /// [1]: ../../config/struct.Config.html#method.manifest_checksums
+    /// [2]: #method.verify_integrity
     pub fn update(&self, package: &PackageManifest) -> Result<(), Error> {
-        let cache_path = self
-            .cache_root
-            .join(package.repository().as_str())
-            .join(package.category().as_str())
-            .join(package.name().as_str());
+        let cache_path =
+            self.package_path(package.repository(), package.category(), package.name());
+
+        // The manifest is written next to its final location and then moved over it, so an interrupted
+        // pull can't leave a truncated manifest behind
+        let tmp_path = cache_path.with_file_name(format!(".{}.tmp", package.name().as_str()));
+        let tmp_checksum_path =
+            cache_path.with_file_name(format!(".{}.sha256.tmp", package.name().as_str()));
 
         let res: Result<_, Error> = try {
             if let Some(parent) = cache_path.parent() {
                 fs::create_dir_all(parent)?;
             }
 
-            let mut file = File::create(&cache_path)?;
-            file.write_all(serde_json::to_string_pretty(package)?.as_bytes())?;
-            file.write_all(&[b'\n'])?;
+            let mut content = serde_json::to_string_pretty(package)?.into_bytes();
+            content.push(b'\n');
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&content)?;
+            file.sync_all()?;
+
+            let checksum_path = checksum_path(&cache_path);
+            if self.checksums {
+                let mut file = File::create(&tmp_checksum_path)?;
+                file.write_all(checksum(&content).as_bytes())?;
+                file.sync_all()?;
+            }
+
+            fs::rename(&tmp_path, &cache_path)?;
+            if self.checksums {
+                fs::rename(&tmp_checksum_path, &checksum_path)?;
+            } else if checksum_path.exists() {
+                // It would no longer match the manifest
+                fs::remove_file(&checksum_path)?;
+            }
         };
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+            let _ = fs::remove_file(&tmp_checksum_path);
+        }
+        manifests::invalidate(&cache_path);
         res.context(cache_path.display().to_string())
             .context(CacheErrorKind::CacheWriteError)?;
         Ok(())
     }
 
+    /// Adds the versions of the given [`PackageManifest`] to its cache entry, keeping the versions
+    /// that are already cached
+    pub fn register(&self, package: PackageManifest) -> Result<(), Error> {
+        let package = match self.package_manifest(&package.full_name())? {
+            Some(mut cached) => {
+                cached.versions_mut().extend(package.versions().clone());
+                cached
+            }
+            None => package,
+        };
+        self.update(&package)
+    }
+}
+
+impl<'cache_root, 'lock_file, L: LockFileReadOwnership>
+    AvailablePackages<'cache_root, 'lock_file, L>
+{
+    /// Returns the path of the file holding the validators of the last responses to a pull of the given repository.
+    ///
+    /// It's hidden, so it isn't mistaken for a category when browsing the cache.
+    fn pull_validators_path(&self, repository: &Repository) -> PathBuf {
+        self.cache_root
+            .join(repository.name())
+            .join(".pull_validators")
+    }
+
+    /// Returns the path of the manifest of the given package
+    fn package_path(
+        &self,
+        repository: &RepositoryName,
+        category: &CategoryName,
+        name: &PackageName,
+    ) -> PathBuf {
+        self.cache_root
+            .join(repository.as_str())
+            .join(category.as_str())
+            .join(name.as_str())
+    }
+
+    /// Returns the validators of the last responses of the mirrors of the given [`Repository`] to a pull
+    ///
+    /// They are empty if the repository was never pulled, or if the mirrors didn't send any validator.
+    pub fn pull_validators(&self, repository: &Repository) -> Result<PullValidators, Error> {
+        let path = self.pull_validators_path(repository);
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(PullValidators::default())
+            }
+            Err(e) => Err(e)
+                .context(path.display().to_string())
+                .context(CacheErrorKind::CacheLoadError)?,
+        };
+        Ok(serde_json::from_str(&content)
+            .context(path.display().to_string())
+            .context(CacheErrorKind::CacheLoadError)?)
+    }
+
+    /// Returns the paths of the manifests whose content doesn't match the checksum recorded when they
+    /// were written, which means the cache is corrupted and should be pulled again.
+    ///
+    /// Manifests without any recorded checksum, like those written while [checksums were disabled][1] or by an
+    /// older version of Nest, are considered intact.
+    ///
+    /// [1]: ../../config/struct.Config.html#method.manifest_checksums
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::PackageManifest;
+    ///
+    /// let mut config: Config = toml::from_str("manifest_checksums = true")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-integrity-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let mut manifests = Vec::new();
+    /// for name in &["gcc", "binutils"] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-devel",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         name,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    ///     manifests.push(manifest);
+    /// }
+    ///
+    /// // A freshly written cache is intact
+    /// assert!(cache.verify_integrity()?.is_empty());
+    ///
+    /// // Tampering with a manifest is detected
+    /// let path = config.paths().available().join("tests/sys-devel/gcc");
+    /// fs::write(&path, "{ \"name\": \"gcc\" ")?;
+    /// assert_eq!(cache.verify_integrity()?, vec![path.clone()]);
+    ///
+    /// // Without checksums, rewriting the manifest drops its outdated checksum
+    /// *config.manifest_checksums_mut() = false;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// cache.update(&manifests[0])?;
+    /// assert!(!path.with_file_name(".gcc.sha256").exists());
+    /// assert!(cache.verify_integrity()?.is_empty());
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_integrity(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut corrupted = Vec::new();
+
+        for (_, path) in query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let expected = match fs::read_to_string(checksum_path(&path)) {
+                Ok(expected) => expected,
+                Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => Err(e)
+                    .context(path.display().to_string())
+                    .context(CacheErrorKind::CacheLoadError)?,
+            };
+            let content = fs::read(&path)
+                .context(path.display().to_string())
+                .context(CacheErrorKind::CacheLoadError)?;
+
+            if checksum(&content) != expected.trim() {
+                corrupted.push(path);
+            }
+        }
+        corrupted.sort();
+        Ok(corrupted)
+    }
+
     /// Returns an [`AvailablePackagesCacheQuery`] allowing to browse the cache according to the given [`PackageRequirement`]
+    ///
+    /// Manifests are read from the disk only once per run: later queries reuse the manifests they already
+    /// read, until the cache is modified through this handle (like when pulling a repository).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, SoftPackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-query-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let manifest_with = |description: &str| -> Result<PackageManifest, failure::Error> {
+    ///     Ok(serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gcc",
+    ///             "category": "sys-devel",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "{}",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         description,
+    ///     ))?)
+    /// };
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// cache.update(&manifest_with("old")?)?;
+    ///
+    /// let requirement = SoftPackageRequirement::parse("sys-devel/gcc")?;
+    /// let description = || -> Result<String, failure::Error> {
+    ///     Ok(cache.query(&requirement).perform()?[0].manifest().metadata().description().to_string())
+    /// };
+    /// assert_eq!(description()?, "old");
+    ///
+    /// // Modifying the file behind the cache's back isn't noticed, as the manifest was already read
+    /// let path = config.paths().available().join("tests/sys-devel/gcc");
+    /// fs::write(&path, serde_json::to_string(&manifest_with("new")?)?)?;
+    /// assert_eq!(description()?, "old");
+    ///
+    /// // Updating the cache invalidates the manifest that was read
+    /// cache.update(&manifest_with("new")?)?;
+    /// assert_eq!(description()?, "new");
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     #[inline]
     pub fn query<'pkg_req>(
         &self,
@@ -91,4 +586,598 @@ impl<'cache_root, 'lock_file> AvailablePackages<'cache_root, 'lock_file> {
     ) -> AvailablePackagesCacheQuery<'cache_root, 'pkg_req> {
         AvailablePackagesCacheQuery::from(&self.cache_root, requirement)
     }
+
+    /// Returns the manifest of the package of the given name, with all its versions, or [`None`] if there is no such
+    /// package in the cache.
+    ///
+    /// Unlike [`query`][1], only the manifest of this package is read, without browsing the cache.
+    ///
+    /// [1]: #method.query
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-package-manifest-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "gcc",
+    ///         "category": "sys-devel",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "The GNU Compiler Collection",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "8.3.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] },
+    ///             "9.1.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// cache.update(&manifest)?;
+    ///
+    /// let gcc = cache
+    ///     .package_manifest(&PackageFullName::parse("tests::sys-devel/gcc")?)?
+    ///     .expect("gcc isn't in the cache");
+    /// assert_eq!(gcc, manifest);
+    /// assert_eq!(gcc.versions().len(), 2);
+    ///
+    /// assert!(cache.package_manifest(&PackageFullName::parse("tests::sys-devel/clang")?)?.is_none());
+    /// assert!(cache.package_manifest(&PackageFullName::parse("stable::sys-devel/gcc")?)?.is_none());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn package_manifest(
+        &self,
+        name: &PackageFullName,
+    ) -> Result<Option<PackageManifest>, Error> {
+        let path = self.package_path(name.repository(), name.category(), name.name());
+
+        if path.is_file() {
+            Ok(Some(manifests::load(&path)?.as_ref().clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the requirements matching the packages the given package can't be installed alongside.
+    ///
+    /// Unlike a query, the manifest is looked up by its path and isn't copied, so this is cheap to call for every
+    /// package of a dependency graph. Packages that aren't in the cache anymore don't have any conflict.
+    pub(crate) fn package_conflicts(
+        &self,
+        id: &PackageID,
+    ) -> Result<HashSet<PackageRequirement>, Error> {
+        let path = self.package_path(id.repository(), id.category(), id.name());
+
+        if path.is_file() {
+            Ok(manifests::load(&path)?
+                .versions()
+                .get(id.version())
+                .map(|version_data| version_data.conflicts().clone())
+                .unwrap_or_default())
+        } else {
+            Ok(HashSet::new())
+        }
+    }
+
+    /// Lists the subdirectories of the given directory of the cache whose name can be parsed, sorted
+    fn directory_names<T, F>(path: &Path, parse: F) -> Result<Vec<T>, Error>
+    where
+        T: Ord,
+        F: Fn(&str) -> Option<T>,
+    {
+        let mut names: Vec<_> = query::cache_entries(path)?
+            .filter(|name| path.join(name).is_dir())
+            .filter_map(|name| parse(&name))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns the repositories present in the cache, sorted.
+    ///
+    /// Only the directories of the cache are listed, without reading any manifest. Those that aren't named
+    /// like a repository are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::{CategoryName, PackageManifest, RepositoryName};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-categories-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (repository, category) in &[
+    ///     ("stable", "sys-apps"),
+    ///     ("stable", "sys-devel"),
+    ///     ("stable", "dev-libs"),
+    ///     ("beta", "games-arcade"),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "package",
+    ///             "category": "{}",
+    ///             "repository": "{}",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         category, repository,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// // Entries that aren't directories or aren't valid names are skipped
+    /// fs::create_dir_all(config.paths().available().join("stable/Not_A_Category"))?;
+    /// fs::create_dir_all(config.paths().available().join("Not_A_Repository"))?;
+    /// fs::write(config.paths().available().join("stable/file"), "")?;
+    ///
+    /// let repositories: Vec<_> = cache.repositories()?.iter().map(|name| name.to_string()).collect();
+    /// assert_eq!(repositories, vec!["beta", "stable"]);
+    ///
+    /// let categories = |repository: &str| -> Result<Vec<String>, failure::Error> {
+    ///     Ok(cache
+    ///         .categories(&RepositoryName::parse(repository)?)?
+    ///         .iter()
+    ///         .map(CategoryName::to_string)
+    ///         .collect())
+    /// };
+    /// assert_eq!(categories("stable")?, vec!["dev-libs", "sys-apps", "sys-devel"]);
+    /// assert_eq!(categories("beta")?, vec!["games-arcade"]);
+    /// assert!(categories("unknown")?.is_empty());
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn repositories(&self) -> Result<Vec<RepositoryName>, Error> {
+        Self::directory_names(self.cache_root, |name| RepositoryName::parse(name).ok())
+    }
+
+    /// Returns the categories present in the cache for the given repository, sorted.
+    ///
+    /// Like [`repositories`][1], only the directories of the cache are listed, and those that aren't named
+    /// like a category are skipped. A repository that isn't in the cache has no category.
+    ///
+    /// [1]: #method.repositories
+    pub fn categories(&self, repository: &RepositoryName) -> Result<Vec<CategoryName>, Error> {
+        Self::directory_names(&self.cache_root.join(repository.as_str()), |name| {
+            CategoryName::parse(name).ok()
+        })
+    }
+
+    /// Returns the manifests of all the packages of the cache, indexed by their full name
+    pub(crate) fn package_manifests(
+        &self,
+    ) -> Result<BTreeMap<PackageFullName, Arc<PackageManifest>>, Error> {
+        let mut package_manifests = BTreeMap::new();
+
+        for (repository, path) in
+            query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let package_manifest = manifests::load(&path)?;
+            package_manifests.insert(
+                PackageFullName::from_short_name(package_manifest.short_name(), repository),
+                package_manifest,
+            );
+        }
+        Ok(package_manifests)
+    }
+
+    /// Returns the names of the packages named closely to the given one, like the package a user meant
+    /// when making a typo, the closest first.
+    ///
+    /// Names are compared using the Levenshtein distance, and only those within a third of
+    /// the length of the given name (but at least one edit) are kept. At most `max` names are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageName, PackageShortName};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-suggest-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (repository, category, name) in &[
+    ///     ("stable", "sys-apps", "coreutils"),
+    ///     ("beta", "sys-apps", "coreutils"),
+    ///     ("stable", "sys-apps", "util-linux"),
+    ///     ("stable", "dev-libs", "corelibs"),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "{}",
+    ///             "repository": "{}",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, category, repository,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// assert_eq!(
+    ///     cache.suggest(&PackageName::parse("corutils")?, 3)?,
+    ///     vec![PackageShortName::parse("sys-apps/coreutils")?],
+    /// );
+    /// assert_eq!(
+    ///     cache.suggest(&PackageName::parse("corelib")?, 3)?,
+    ///     vec![PackageShortName::parse("dev-libs/corelibs")?],
+    /// );
+    /// assert!(cache.suggest(&PackageName::parse("emacs")?, 3)?.is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn suggest(&self, name: &PackageName, max: usize) -> Result<Vec<PackageShortName>, Error> {
+        let max_distance = (name.chars().count() / 3).max(1);
+        let mut suggestions = Vec::new();
+
+        for (_, path) in query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let package = path.file_name().and_then(|name| name.to_str());
+            let category = path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(|category| category.to_str());
+
+            // Entries whose names can't be parsed aren't packages, and are skipped
+            let category = category.and_then(|category| CategoryName::parse(category).ok());
+            let package = package.and_then(|package| PackageName::parse(package).ok());
+
+            if let (Some(category), Some(package)) = (category, package) {
+                let distance = levenshtein(name, &package);
+                if distance <= max_distance {
+                    suggestions.push((distance, PackageShortName::from(category, package)));
+                }
+            }
+        }
+
+        suggestions.sort();
+        suggestions.dedup_by(|(_, a), (_, b)| a == b);
+        Ok(suggestions
+            .into_iter()
+            .take(max)
+            .map(|(_, name)| name)
+            .collect())
+    }
+
+    /// Returns the error of a requirement matching no package, advising the packages named closely to it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::QueryErrorKind;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, SoftPackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-no-package-found-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "coreutils",
+    ///         "category": "sys-apps",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {}
+    ///     }"#,
+    /// )?;
+    /// cache.update(&manifest)?;
+    ///
+    /// let error = cache.no_package_found(&SoftPackageRequirement::parse("corutils")?);
+    /// assert_eq!(error.advice(), Some("did you mean sys-apps/coreutils?".to_string()));
+    ///
+    /// let error = cache.no_package_found(&SoftPackageRequirement::parse("emacs")?);
+    /// assert!(match error.kind() {
+    ///     QueryErrorKind::NoPackageFound { suggestions, .. } => suggestions.is_empty(),
+    /// });
+    /// assert_eq!(error.advice(), None);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn no_package_found(&self, requirement: &SoftPackageRequirement) -> QueryError {
+        // The maximum number of names suggested
+        const MAX_SUGGESTIONS: usize = 3;
+
+        // Failing to list the suggestions must not hide the original error
+        let suggestions = self
+            .suggest(requirement.name(), MAX_SUGGESTIONS)
+            .unwrap_or_default();
+        QueryError::from(QueryErrorKind::NoPackageFound {
+            requirement: requirement.to_string(),
+            suggestions,
+        })
+    }
+
+    /// Returns the most recent version of each package carrying the given tag, ignoring case
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::convert::TryFrom;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest, Tag};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-query-by-tag-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (repository, name, tags) in &[
+    ///     ("stable", "vim", r#""editor", "terminal""#),
+    ///     ("stable", "emacs", r#""editor""#),
+    ///     ("beta", "nano", r#""editor""#),
+    ///     ("stable", "bash", r#""shell", "terminal""#),
+    ///     ("stable", "coreutils", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-apps",
+    ///             "repository": "{}",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [{}],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }},
+    ///                 "2.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, repository, tags,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let tagged = |tag: &str| -> Result<Vec<PackageID>, failure::Error> {
+    ///     let mut ids: Vec<_> = cache.query_by_tag(&Tag::try_from(tag)?)?.iter().map(|result| result.id()).collect();
+    ///     ids.sort();
+    ///     Ok(ids)
+    /// };
+    ///
+    /// assert_eq!(
+    ///     tagged("editor")?,
+    ///     vec![
+    ///         PackageID::parse("beta::sys-apps/nano#2.0.0")?,
+    ///         PackageID::parse("stable::sys-apps/emacs#2.0.0")?,
+    ///         PackageID::parse("stable::sys-apps/vim#2.0.0")?,
+    ///     ],
+    /// );
+    /// assert_eq!(
+    ///     tagged("terminal")?,
+    ///     vec![PackageID::parse("stable::sys-apps/bash#2.0.0")?, PackageID::parse("stable::sys-apps/vim#2.0.0")?],
+    /// );
+    /// assert!(tagged("games")?.is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_by_tag(&self, tag: &Tag) -> Result<Vec<QueryResult>, Error> {
+        let mut results = Vec::new();
+
+        for (repository, path) in
+            query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let package_manifest = manifests::load(&path)?;
+            let tagged = package_manifest
+                .metadata()
+                .tags()
+                .iter()
+                .any(|package_tag| package_tag.as_str().eq_ignore_ascii_case(tag.as_str()));
+
+            if tagged {
+                results.extend(latest_version(repository, &package_manifest));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Returns all the versions of the packages providing the virtual package of the given name
+    pub fn query_providers(&self, name: &PackageShortName) -> Result<Vec<QueryResult>, Error> {
+        let mut results = Vec::new();
+
+        for (repository, path) in
+            query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let package_manifest = manifests::load(&path)?;
+            results.extend(
+                package_manifest
+                    .iter_manifests()
+                    .filter(|manifest| manifest.provides().contains(name))
+                    .map(|manifest| QueryResult::from(repository.clone(), manifest)),
+            );
+        }
+        Ok(results)
+    }
+
+    /// Returns the most recent version of each package whose name or description contains all the given keywords,
+    /// ignoring case.
+    ///
+    /// The results are sorted by relevance, that is, by the number of occurrences of the keywords in the name
+    /// and description of each package, the most relevant first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-search-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (name, description) in &[
+    ///     ("vim", "A highly configurable text editor"),
+    ///     ("emacs", "An extensible text editor, and an editor of everything else"),
+    ///     ("less", "A pager displaying text files"),
+    ///     ("gimp", "An image editor"),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-apps",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "{}",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }},
+    ///                 "1.1.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, description,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let search = |keywords: &[&str]| -> Result<Vec<PackageID>, failure::Error> {
+    ///     Ok(cache.search(keywords)?.iter().map(|result| result.id()).collect())
+    /// };
+    /// let ids = |ids: &[&str]| -> Result<Vec<PackageID>, failure::Error> {
+    ///     ids.iter().map(|id| Ok(PackageID::parse(id)?)).collect()
+    /// };
+    ///
+    /// // Descriptions are searched, and the most relevant packages come first
+    /// assert_eq!(
+    ///     search(&["Editor"])?,
+    ///     ids(&["tests::sys-apps/emacs#1.1.0", "tests::sys-apps/gimp#1.1.0", "tests::sys-apps/vim#1.1.0"])?,
+    /// );
+    ///
+    /// // All the keywords must match
+    /// assert_eq!(
+    ///     search(&["text", "editor"])?,
+    ///     ids(&["tests::sys-apps/emacs#1.1.0", "tests::sys-apps/vim#1.1.0"])?,
+    /// );
+    ///
+    /// // Names are searched too
+    /// assert_eq!(search(&["LESS", "pager"])?, ids(&["tests::sys-apps/less#1.1.0"])?);
+    /// assert!(search(&["editor", "pager"])?.is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search(&self, keywords: &[&str]) -> Result<Vec<QueryResult>, Error> {
+        let keywords: Vec<_> = keywords
+            .iter()
+            .map(|keyword| keyword.to_lowercase())
+            .collect();
+        let mut results = Vec::new();
+
+        for (repository, path) in
+            query::cache_package_paths(self.cache_root, |_| true, |_| true, |_| true)?
+        {
+            let package_manifest = manifests::load(&path)?;
+            let name = package_manifest.name().to_lowercase();
+            let description = package_manifest.metadata().description().to_lowercase();
+
+            let hits: Vec<_> = keywords
+                .iter()
+                .map(|keyword| name.matches(keyword).count() + description.matches(keyword).count())
+                .collect();
+
+            if hits.iter().all(|hits| *hits > 0) {
+                if let Some(result) = latest_version(repository, &package_manifest) {
+                    results.push((hits.iter().sum::<usize>(), result));
+                }
+            }
+        }
+
+        results.sort_by(|(a_hits, a), (b_hits, b)| {
+            b_hits
+                .cmp(a_hits)
+                .then_with(|| a.full_name().cmp(&b.full_name()))
+        });
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
 }
@@ -1,16 +1,25 @@
 use std::collections::HashMap;
 use std::fs;
+use std::io;
 use std::iter::FromIterator;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
 
-use failure::{Error, ResultExt};
+use failure::{format_err, Error, ResultExt};
+use glob::Pattern;
+use semver::Version;
+use serde_derive::Serialize;
+use threadpool::ThreadPool;
 
 use crate::config::Config;
 use crate::package::{
-    CategoryName, Manifest, PackageFullName, PackageID, PackageManifest, RepositoryName,
-    SoftPackageRequirement,
+    Arch, CategoryName, Manifest, PackageFullName, PackageID, PackageManifest, PackageName,
+    RepositoryName, SoftPackageRequirement,
 };
 
+use super::manifests;
+
 /// The result of a query to the packages cache
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct QueryResult {
@@ -58,6 +67,33 @@ impl QueryResult {
     }
 }
 
+/// The flat representation of a [`QueryResult`] used when serializing it
+#[derive(Serialize)]
+struct SerializedQueryResult<'a> {
+    repository: &'a RepositoryName,
+    category: &'a CategoryName,
+    name: &'a PackageName,
+    version: &'a Version,
+    description: &'a str,
+}
+
+impl serde::Serialize for QueryResult {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        SerializedQueryResult {
+            repository: self.repository(),
+            category: self.manifest().category(),
+            name: self.manifest().name(),
+            version: self.manifest().version(),
+            description: self.manifest().metadata().description(),
+        }
+        .serialize(serializer)
+    }
+}
+
 /// The strategy to use when looking for packages in this cache.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum AvailablePackagesCacheQueryStrategy {
@@ -72,6 +108,175 @@ pub enum AvailablePackagesCacheQueryStrategy {
     AllMatchesSorted,
 }
 
+/// The way the names of the packages in the cache are compared with the name of the requirement.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum AvailablePackagesCacheQueryNameMatchMode {
+    /// The name of a package must be equal to the name of the requirement
+    Exact,
+
+    /// The name of a package must contain the name of the requirement
+    Contains,
+
+    /// The name of a package must match the given glob pattern (like `python-*`).
+    ///
+    /// The name of the requirement is ignored.
+    Glob(String),
+}
+
+impl Default for AvailablePackagesCacheQueryNameMatchMode {
+    fn default() -> Self {
+        AvailablePackagesCacheQueryNameMatchMode::Exact
+    }
+}
+
+/// Returns the versions of a package that match the given requirement, following the given strategy
+///
+/// Versions that can't be installed on the given architecture, if any, are skipped.
+fn matching_versions(
+    requirement: &SoftPackageRequirement,
+    strategy: AvailablePackagesCacheQueryStrategy,
+    arch: Option<&Arch>,
+    repository: &RepositoryName,
+    package_manifest: &PackageManifest,
+) -> Vec<QueryResult> {
+    let mut results = Vec::new();
+    let mut versions = package_manifest
+        .versions()
+        .iter()
+        .filter(|(_, data)| {
+            arch.map_or(true, |arch| {
+                data.architectures().is_empty() || data.architectures().contains(arch)
+            })
+        })
+        .map(|(version, _)| version)
+        .collect::<Vec<_>>();
+
+    match strategy {
+        AvailablePackagesCacheQueryStrategy::BestMatch => {
+            versions.sort_unstable_by(|a, b| b.cmp(a));
+            let result = versions
+                .iter()
+                .find(|version| requirement.version_requirement().matches(version));
+            if let Some(version) = result {
+                // FIXME: having to ask for a version that we already know exists is meh
+                results.push(QueryResult::from(
+                    repository.clone(),
+                    package_manifest
+                        .get_manifest_for_version((*version).clone())
+                        .unwrap(),
+                ));
+            }
+        }
+        AvailablePackagesCacheQueryStrategy::AllMatchesSorted => {
+            versions.sort_unstable_by(|a, b| b.cmp(a));
+            results.append(
+                &mut versions
+                    .iter()
+                    .filter(|version| requirement.version_requirement().matches(&version))
+                    .map(|version| {
+                        QueryResult::from(
+                            repository.clone(),
+                            package_manifest
+                                .get_manifest_for_version((*version).clone())
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+        AvailablePackagesCacheQueryStrategy::AllMatchesUnsorted => {
+            results.append(
+                &mut versions
+                    .iter()
+                    .filter(|version| requirement.version_requirement().matches(&version))
+                    .map(|version| {
+                        QueryResult::from(
+                            repository.clone(),
+                            package_manifest
+                                .get_manifest_for_version((*version).clone())
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+    results
+}
+
+/// Lists the entries of a directory of the cache.
+///
+/// A directory that doesn't exist is considered empty, as it may have been removed by a concurrent
+/// operation (like a pull) while the query is running. Any other IO error is fatal.
+pub(super) fn cache_entries(path: &Path) -> Result<impl Iterator<Item = String>, Error> {
+    let mut results = Vec::new();
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(results.into_iter()),
+        Err(e) => Err(e).with_context(|_| path.display().to_string())?,
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => Err(e).with_context(|_| path.display().to_string())?,
+        };
+        // Hidden entries are manifests being written, that aren't part of the cache yet, or metadata about the cache
+        if let Ok(name) = entry.file_name().into_string() {
+            if !name.starts_with('.') {
+                results.push(name);
+            }
+        }
+    }
+    Ok(results.into_iter())
+}
+
+/// Lists the packages of the cache whose repository, category and name are accepted by the given filters,
+/// along with the path of their manifest
+pub(super) fn cache_package_paths<R, C, N>(
+    cache_root: &Path,
+    repository_filter: R,
+    category_filter: C,
+    name_filter: N,
+) -> Result<Vec<(RepositoryName, PathBuf)>, Error>
+where
+    R: Fn(&str) -> bool,
+    C: Fn(&str) -> bool,
+    N: Fn(&str) -> bool,
+{
+    let mut package_paths = Vec::new();
+
+    let repositories = cache_entries(cache_root)?
+        .filter(|repo| repository_filter(repo))
+        .map(|name| {
+            RepositoryName::parse(&name).expect("invalid repository name found in the cache")
+        });
+
+    for repo in repositories {
+        let repo_cache_path = cache_root.join(repo.as_str());
+
+        let categories = cache_entries(&repo_cache_path)?
+            .filter(|category| category_filter(category))
+            .map(|name| {
+                CategoryName::parse(&name).expect("invalid category name found in the cache")
+            });
+
+        for category in categories {
+            let category_cache_path = repo_cache_path.join(category.as_str());
+
+            let packages = cache_entries(&category_cache_path)?
+                .filter(|package_name| name_filter(package_name));
+
+            for package in packages {
+                package_paths.push((repo.clone(), category_cache_path.join(package)));
+            }
+        }
+    }
+    Ok(package_paths)
+}
+
 /// Structure representing a query in the [`AvailablePackages`] cache.
 ///
 /// It can be constructed from a [`PackageRequirement`] and a strategy and will look for all
@@ -81,6 +286,9 @@ pub struct AvailablePackagesCacheQuery<'a, 'b> {
     cache_root: &'a Path,
     requirement: &'b SoftPackageRequirement,
     strategy: AvailablePackagesCacheQueryStrategy,
+    name_match_mode: AvailablePackagesCacheQueryNameMatchMode,
+    max_threads: usize,
+    arch: Option<Arch>,
 }
 
 impl<'a, 'b> AvailablePackagesCacheQuery<'a, 'b> {
@@ -93,6 +301,9 @@ impl<'a, 'b> AvailablePackagesCacheQuery<'a, 'b> {
             cache_root,
             requirement,
             strategy: AvailablePackagesCacheQueryStrategy::BestMatch,
+            name_match_mode: AvailablePackagesCacheQueryNameMatchMode::default(),
+            max_threads: num_cpus::get(),
+            arch: None,
         }
     }
 
@@ -103,118 +314,419 @@ impl<'a, 'b> AvailablePackagesCacheQuery<'a, 'b> {
         self
     }
 
-    fn get_cache_entries(path: &Path) -> Result<impl Iterator<Item = String>, Error> {
-        let mut results = Vec::new();
+    /// Sets the way the names of the packages are compared with the name of the requirement.
+    ///
+    /// By default, names must be equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::available::AvailablePackagesCacheQueryNameMatchMode;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, SoftPackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-match-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for name in &["python-requests", "python-six", "cpython", "gcc"] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "dev-lang",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         name,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let names_matching = |name: &str, mode| -> Result<Vec<String>, failure::Error> {
+    ///     let requirement = SoftPackageRequirement::parse(name)?;
+    ///     let results = cache.query(&requirement).set_name_match_mode(mode).perform()?;
+    ///     let mut names: Vec<_> = results.iter().map(|r| r.manifest().name().to_string()).collect();
+    ///     names.sort();
+    ///     Ok(names)
+    /// };
+    ///
+    /// use AvailablePackagesCacheQueryNameMatchMode::*;
+    /// assert_eq!(names_matching("python", Exact)?, Vec::<String>::new());
+    /// assert_eq!(names_matching("cpython", Exact)?, vec!["cpython"]);
+    /// assert_eq!(names_matching("python", Contains)?, vec!["cpython", "python-requests", "python-six"]);
+    /// assert_eq!(names_matching("python", Glob(String::from("python-*")))?, vec!["python-requests", "python-six"]);
+    /// assert_eq!(names_matching("python", Glob(String::from("*c*")))?, vec!["cpython", "gcc"]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_name_match_mode(
+        mut self,
+        name_match_mode: AvailablePackagesCacheQueryNameMatchMode,
+    ) -> Self {
+        self.name_match_mode = name_match_mode;
+        self
+    }
 
-        if path.exists() {
-            for entry in fs::read_dir(path).with_context(|_| path.display().to_string())? {
-                let entry = entry.with_context(|_| path.display().to_string())?;
-                if let Ok(name) = entry.file_name().into_string() {
-                    results.push(name);
-                }
-            }
-        }
-        Ok(results.into_iter())
+    /// Sets the maximum number of threads used to read the manifests of the matching packages.
+    ///
+    /// By default, one thread per CPU is used. The results don't depend on the number of threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::cache::available::{
+    ///     AvailablePackagesCacheQueryNameMatchMode, AvailablePackagesCacheQueryStrategy,
+    /// };
+    /// use libnest::config::Config;
+    /// use libnest::package::SoftPackageRequirement;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-parallel-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// // A cache of a hundred manifests, with several versions each
+    /// for category in 0..4 {
+    ///     let category_path = config.paths().available().join(format!("tests/category-{}", category));
+    ///     fs::create_dir_all(&category_path)?;
+    ///     for package in 0..25 {
+    ///         let name = format!("package-{}", package);
+    ///         fs::write(category_path.join(&name), format!(
+    ///             r#"{{
+    ///                 "name": "{}",
+    ///                 "category": "category-{}",
+    ///                 "repository": "tests",
+    ///                 "metadata": {{
+    ///                     "description": "",
+    ///                     "tags": [],
+    ///                     "maintainer": "nest@raven-os.org",
+    ///                     "licenses": [],
+    ///                     "upstream_url": null
+    ///                 }},
+    ///                 "versions": {{
+    ///                     "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }},
+    ///                     "1.1.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }},
+    ///                     "2.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///                 }}
+    ///             }}"#,
+    ///             name, category,
+    ///         ))?;
+    ///     }
+    /// }
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let requirement = SoftPackageRequirement::parse("package#^1.0")?;
+    ///
+    /// for strategy in &[
+    ///     AvailablePackagesCacheQueryStrategy::BestMatch,
+    ///     AvailablePackagesCacheQueryStrategy::AllMatchesSorted,
+    ///     AvailablePackagesCacheQueryStrategy::AllMatchesUnsorted,
+    /// ] {
+    ///     let query = cache
+    ///         .query(&requirement)
+    ///         .set_strategy(*strategy)
+    ///         .set_name_match_mode(AvailablePackagesCacheQueryNameMatchMode::Contains);
+    ///     let serial = query.clone().set_max_threads(1).perform()?;
+    ///     let parallel = query.clone().set_max_threads(8).perform()?;
+    ///     assert!(serial.len() >= 100);
+    ///     assert_eq!(serial, parallel);
+    /// }
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = max_threads;
+        self
+    }
+
+    /// Sets the architecture the packages must be installable on, or `None` to accept all of them.
+    ///
+    /// By default, the architecture of packages isn't checked. Versions built only for architectures unknown to
+    /// this version of Nest are skipped, unless that exact architecture is given.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{Arch, PackageManifest, SoftPackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-query-arch-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "firmware",
+    ///         "category": "sys-kernel",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "Firmware blobs",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "1.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] },
+    ///             "2.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "architectures": ["x86_64"] },
+    ///             "3.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "architectures": ["loongarch64"] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// cache.update(&manifest)?;
+    ///
+    /// let requirement = SoftPackageRequirement::parse("sys-kernel/firmware")?;
+    /// let best_match = |arch: Option<Arch>| -> Result<String, failure::Error> {
+    ///     let results = cache.query(&requirement).set_arch(arch).perform()?;
+    ///     Ok(results[0].manifest().version().to_string())
+    /// };
+    /// assert_eq!(best_match(None)?, "3.0.0");
+    /// assert_eq!(best_match(Some(Arch::X86_64))?, "2.0.0");
+    /// assert_eq!(best_match(Some(Arch::Aarch64))?, "1.0.0");
+    /// assert_eq!(best_match(Some(Arch::Other(String::from("loongarch64"))))?, "3.0.0");
+    ///
+    /// // The unknown architecture only excludes the version built for it
+    /// if let Some(host) = Arch::host() {
+    ///     assert_ne!(best_match(Some(host))?, "3.0.0");
+    /// }
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn set_arch(mut self, arch: Option<Arch>) -> Self {
+        self.arch = arch;
+        self
     }
 
     /// Perform the query
+    ///
+    /// An empty manifest, which may be left behind by an older version of Nest whose pull was interrupted,
+    /// fails the query with [`CacheErrorKind::EmptyManifest`]: pulling the repository again fixes it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::cache::{CacheError, CacheErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::SoftPackageRequirement;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-empty-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let category_path = config.paths().available().join("tests/sys-devel");
+    /// fs::create_dir_all(&category_path)?;
+    /// fs::write(category_path.join("gcc"), "")?;
+    /// fs::write(category_path.join("clang"), "{")?;
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let kind_of = |requirement: &str| -> Result<CacheErrorKind, failure::Error> {
+    ///     let requirement = SoftPackageRequirement::parse(requirement)?;
+    ///     let error = cache.query(&requirement).perform().unwrap_err();
+    ///     Ok(*error.downcast_ref::<CacheError>().unwrap().kind())
+    /// };
+    ///
+    /// assert_eq!(kind_of("sys-devel/gcc")?, CacheErrorKind::EmptyManifest);
+    /// assert_eq!(kind_of("sys-devel/clang")?, CacheErrorKind::CacheParseError);
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn perform(&self) -> Result<Vec<QueryResult>, Error> {
         let mut results = Vec::new();
 
-        let repositories = Self::get_cache_entries(&self.cache_root)?
-            .filter(|repo| match self.requirement.repository() {
+        let pattern = match &self.name_match_mode {
+            AvailablePackagesCacheQueryNameMatchMode::Glob(pattern) => Some(
+                Pattern::new(pattern)
+                    .with_context(|_| format_err!("invalid package name pattern: {}", pattern))?,
+            ),
+            _ => None,
+        };
+        let name_matches = |package_name: &str| match &self.name_match_mode {
+            AvailablePackagesCacheQueryNameMatchMode::Exact => {
+                self.requirement.name().as_str() == package_name
+            }
+            AvailablePackagesCacheQueryNameMatchMode::Contains => {
+                package_name.contains(self.requirement.name().as_str())
+            }
+            AvailablePackagesCacheQueryNameMatchMode::Glob(_) => pattern
+                .as_ref()
+                .map_or(false, |pattern| pattern.matches(package_name)),
+        };
+
+        let package_paths = cache_package_paths(
+            &self.cache_root,
+            |repo| match self.requirement.repository() {
                 Some(required_repo) => required_repo.as_str() == repo,
                 _ => true,
-            })
-            .map(|name| {
-                RepositoryName::parse(&name).expect("invalid repository name found in the cache")
-            });
+            },
+            |category| match self.requirement.category() {
+                Some(required_category) => required_category.as_str() == category,
+                _ => true,
+            },
+            |package_name| name_matches(package_name),
+        )?;
 
-        for repo in repositories {
-            let repo_cache_path = self.cache_root.join(repo.as_str());
+        // Manifests are read and matched in parallel, but the results are kept in the order of the cache's entries
+        let threads = self.max_threads.min(package_paths.len());
+        if threads <= 1 {
+            for (repository, path) in &package_paths {
+                let package_manifest = manifests::load(path)?;
+                results.append(&mut matching_versions(
+                    self.requirement,
+                    self.strategy,
+                    self.arch.as_ref(),
+                    repository,
+                    &package_manifest,
+                ));
+            }
+        } else {
+            let pool = ThreadPool::new(threads);
+            let (sender, receiver) = channel();
+            let requirement = Arc::new(self.requirement.clone());
+            let n = package_paths.len();
 
-            let categories = Self::get_cache_entries(&repo_cache_path)?
-                .filter(|category| match self.requirement.category() {
-                    Some(required_category) => required_category.as_str() == category,
-                    _ => true,
-                })
-                .map(|name| {
-                    CategoryName::parse(&name).expect("invalid category name found in the cache")
+            for (index, (repository, path)) in package_paths.into_iter().enumerate() {
+                let sender = sender.clone();
+                let requirement = requirement.clone();
+                let strategy = self.strategy;
+                let arch = self.arch.clone();
+                pool.execute(move || {
+                    let results = manifests::load(&path).map(|package_manifest| {
+                        matching_versions(
+                            &requirement,
+                            strategy,
+                            arch.as_ref(),
+                            &repository,
+                            &package_manifest,
+                        )
+                    });
+                    sender
+                        .send((index, results))
+                        .expect("cannot communicate with the querying thread");
                 });
+            }
+            drop(sender);
 
-            for category in categories {
-                let category_cache_path = repo_cache_path.join(category.as_str());
-
-                // TODO: at the moment, we match the package name exactly. This should be configurable.
-                let packages = Self::get_cache_entries(&category_cache_path)?
-                    .filter(|package_name| self.requirement.name().as_str() == package_name);
-
-                for package in packages {
-                    let package_cache_path = category_cache_path.join(package);
-                    let package_manifest = PackageManifest::load_from_cache(package_cache_path)?;
-                    let mut versions = package_manifest.versions().keys().collect::<Vec<_>>();
-
-                    match self.strategy {
-                        AvailablePackagesCacheQueryStrategy::BestMatch => {
-                            versions.sort_unstable_by(|a, b| b.cmp(a));
-                            let result = versions.iter().find(|version| {
-                                self.requirement.version_requirement().matches(version)
-                            });
-                            if let Some(version) = result {
-                                // FIXME: having to ask for a version that we already know exists is meh
-                                results.push(QueryResult::from(
-                                    repo.clone(),
-                                    package_manifest
-                                        .get_manifest_for_version((*version).clone())
-                                        .unwrap(),
-                                ));
-                            }
-                        }
-                        AvailablePackagesCacheQueryStrategy::AllMatchesSorted => {
-                            versions.sort_unstable_by(|a, b| b.cmp(a));
-                            results.append(
-                                &mut versions
-                                    .iter()
-                                    .filter(|version| {
-                                        self.requirement.version_requirement().matches(&version)
-                                    })
-                                    .map(|version| {
-                                        QueryResult::from(
-                                            repo.clone(),
-                                            package_manifest
-                                                .get_manifest_for_version((*version).clone())
-                                                .unwrap(),
-                                        )
-                                    })
-                                    .collect::<Vec<_>>(),
-                            );
-                        }
-                        AvailablePackagesCacheQueryStrategy::AllMatchesUnsorted => {
-                            results.append(
-                                &mut versions
-                                    .iter()
-                                    .filter(|version| {
-                                        self.requirement.version_requirement().matches(&version)
-                                    })
-                                    .map(|version| {
-                                        QueryResult::from(
-                                            repo.clone(),
-                                            package_manifest
-                                                .get_manifest_for_version((*version).clone())
-                                                .unwrap(),
-                                        )
-                                    })
-                                    .collect::<Vec<_>>(),
-                            );
-                        }
-                    }
-                }
+            let mut results_by_package: Vec<_> = receiver.iter().collect();
+            if results_by_package.len() != n {
+                return Err(format_err!("a thread reading the manifests panicked"));
+            }
+            results_by_package.sort_by_key(|(index, _)| *index);
+            for (_, package_results) in results_by_package {
+                results.append(&mut package_results?);
             }
         }
 
         Ok(results)
     }
 
+    /// Perform the query, and returns its results as a JSON array.
+    ///
+    /// Each result is an object made of the `repository`, `category`, `name`, `version` and `description`
+    /// of the package, in the order returned by [`perform`][1].
+    ///
+    /// [1]: #method.perform
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, SoftPackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-json-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "gcc",
+    ///         "category": "sys-devel",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "The GNU Compiler Collection",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "9.1.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// cache.update(&manifest)?;
+    ///
+    /// let json = cache
+    ///     .query(&SoftPackageRequirement::parse("sys-devel/gcc")?)
+    ///     .perform_as_json()?;
+    /// let value: serde_json::Value = serde_json::from_str(&json)?;
+    /// assert_eq!(
+    ///     value,
+    ///     serde_json::json!([{
+    ///         "repository": "tests",
+    ///         "category": "sys-devel",
+    ///         "name": "gcc",
+    ///         "version": "9.1.0",
+    ///         "description": "The GNU Compiler Collection",
+    ///     }])
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn perform_as_json(&self) -> Result<String, Error> {
+        let results = self.perform()?;
+        Ok(serde_json::to_string(&results)?)
+    }
+
     /// Perform the query, and sort the repositories in order of preference
     pub fn perform_and_sort_by_preference(
         &self,
@@ -229,7 +741,13 @@ impl<'a, 'b> AvailablePackagesCacheQuery<'a, 'b> {
         );
 
         self.perform().map(|mut results| {
-            results.sort_by(|a, b| map[a.repository()].cmp(&map[b.repository()]));
+            // Repositories that aren't configured, like the local one, come last
+            let rank = |result: &QueryResult| {
+                map.get(result.repository())
+                    .cloned()
+                    .unwrap_or(usize::max_value())
+            };
+            results.sort_by(|a, b| rank(a).cmp(&rank(b)));
             results
         })
     }
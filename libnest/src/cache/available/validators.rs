@@ -0,0 +1,76 @@
+//! Validators of the responses of the mirrors to pulls.
+//!
+//! They are sent back with the next pull, so a mirror can answer that the manifests didn't change
+//! instead of sending them again.
+
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::MirrorUrl;
+
+/// The validators of a mirror's response to a pull, that is, its `ETag` and `Last-Modified` headers
+#[derive(Serialize, Deserialize, Clone, Default, Eq, PartialEq, Hash, Debug)]
+#[serde(default)]
+pub struct PullValidator {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PullValidator {
+    /// Creates a [`PullValidator`] from the `ETag` and `Last-Modified` headers of a response
+    #[inline]
+    pub fn from(etag: Option<String>, last_modified: Option<String>) -> Self {
+        PullValidator {
+            etag,
+            last_modified,
+        }
+    }
+
+    /// Returns the `ETag` header of the response, if any
+    #[inline]
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_ref().map(String::as_str)
+    }
+
+    /// Returns the `Last-Modified` header of the response, if any
+    #[inline]
+    pub fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_ref().map(String::as_str)
+    }
+
+    /// Returns whether the response had no validator at all
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// The validators of the last responses to a pull of each mirror of a repository
+#[derive(Serialize, Deserialize, Clone, Default, Eq, PartialEq, Debug)]
+pub struct PullValidators(BTreeMap<String, PullValidator>);
+
+impl PullValidators {
+    /// Returns the validator of the last response of the given mirror, if any
+    #[inline]
+    pub fn get(&self, mirror: &MirrorUrl) -> Option<&PullValidator> {
+        self.0.get(mirror.as_str())
+    }
+
+    /// Records the validator of the last response of the given mirror.
+    ///
+    /// An empty validator removes the one previously recorded, if any.
+    pub fn insert(&mut self, mirror: &MirrorUrl, validator: PullValidator) {
+        if validator.is_empty() {
+            self.0.remove(mirror.as_str());
+        } else {
+            self.0.insert(mirror.as_str().to_string(), validator);
+        }
+    }
+
+    /// Forgets the validators of all the mirrors
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
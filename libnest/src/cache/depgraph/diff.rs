@@ -1,6 +1,8 @@
 use std::collections::HashSet;
 
-use crate::transaction::{InstallTransaction, RemoveTransaction, Transaction, UpgradeTransaction};
+use crate::transaction::{
+    DowngradeTransaction, InstallTransaction, RemoveTransaction, Transaction, UpgradeTransaction,
+};
 
 use super::{DependencyGraph, GroupName, NodeKind, NodeName};
 
@@ -144,11 +146,16 @@ impl DependencyGraphDiff {
                     NodeKind::Package { id: id_right, .. },
                 ) = (left_node.kind(), right_node.kind())
                 {
-                    if id_left.version() != id_right.version() {
+                    if id_right.version() > id_left.version() {
                         transactions.push(Transaction::Upgrade(UpgradeTransaction::from(
                             id_left.clone(),
                             id_right.clone(),
                         )));
+                    } else if id_right.version() < id_left.version() {
+                        transactions.push(Transaction::Downgrade(DowngradeTransaction::from(
+                            id_left.clone(),
+                            id_right.clone(),
+                        )));
                     }
                 }
             }
@@ -162,6 +169,82 @@ impl DependencyGraphDiff {
     ///
     /// The resulting transactions are ordered in a way that ensures a valid system state if they
     /// are applied (installations of dependencies come before installations of dependents, etc)
+    ///
+    /// A package whose version changes produces an [`Upgrade`][1] if the new version is more recent,
+    /// and a [`Downgrade`][2] otherwise.
+    ///
+    /// [1]: ../../transaction/enum.Transaction.html#variant.Upgrade
+    /// [2]: ../../transaction/enum.Transaction.html#variant.Downgrade
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{
+    ///     DependencyGraph, DependencyGraphDiff, RequirementKind, RequirementManagementMethod,
+    /// };
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    /// use libnest::transaction::Transaction;
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-downgrade-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "linux",
+    ///         "category": "sys-kernel",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "1.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] },
+    ///             "2.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// let solved = |requirement: &str| -> Result<DependencyGraph, failure::Error> {
+    ///     let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    ///     graph.node_add_requirement(
+    ///         graph.root_id(),
+    ///         RequirementKind::Package { package_req: PackageRequirement::parse(requirement)? },
+    ///         RequirementManagementMethod::Static,
+    ///     );
+    ///     graph.solve(&config)?;
+    ///     Ok(graph)
+    /// };
+    /// let latest = solved("sys-kernel/linux")?;
+    /// let previous = solved("sys-kernel/linux#<2.0.0")?;
+    ///
+    /// match DependencyGraphDiff::new().perform(&latest, &previous).as_slice() {
+    ///     [Transaction::Downgrade(downgrade)] => {
+    ///         assert_eq!(downgrade.old_target().to_string(), "tests::sys-kernel/linux#2.0.0");
+    ///         assert_eq!(downgrade.new_target().to_string(), "tests::sys-kernel/linux#1.0.0");
+    ///     }
+    ///     transactions => panic!("unexpected transactions: {:?}", transactions),
+    /// }
+    /// match DependencyGraphDiff::new().perform(&previous, &latest).as_slice() {
+    ///     [Transaction::Upgrade(_)] => (),
+    ///     transactions => panic!("unexpected transactions: {:?}", transactions),
+    /// }
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn perform<'a, 'b>(
         &self,
         old_graph: &DependencyGraph,
@@ -1,23 +1,91 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{self, Write};
 use std::marker::PhantomData;
 use std::path::Path;
 
-use failure::{format_err, Error, ResultExt};
+use failure::{format_err, Error, Fail, ResultExt};
 use serde_derive::{Deserialize, Serialize};
 use serde_json;
 
 use crate::cache::available::{AvailablePackagesCacheQueryStrategy, QueryResult};
-use crate::config::Config;
+use crate::config::{Config, RepositoryConfig};
 use crate::lock_file::LockFileOwnership;
-use crate::package::{PackageFullName, PackageRequirement};
+use crate::package::{
+    Manifest, PackageFullName, PackageID, PackageRequirement, PackageShortName, RepositoryName,
+    Slot, SoftPackageRequirement,
+};
 
-use super::super::errors::DependencyGraphErrorKind;
+use super::super::errors::{DependencyGraphError, DependencyGraphErrorKind};
 use super::node::{GroupName, Node, NodeID, NodeKind, NodeName, ROOT_ID};
 use super::requirement::{
     Requirement, RequirementID, RequirementKind, RequirementManagementMethod,
 };
+use super::selection::{SelectionManifest, SELECTION_VERSION};
+use super::statistics::GraphStatistics;
+
+/// Returns a key sorting the results of a query by the preference of their repository: by decreasing
+/// priority first, then by order of preference, and finally by name
+fn repository_preference(
+    config: &Config,
+    result: &QueryResult,
+) -> (Reverse<i32>, usize, RepositoryName) {
+    let priority = config
+        .repositories_config()
+        .get(result.repository().as_str())
+        .map_or(0, RepositoryConfig::priority);
+    let rank = config
+        .repositories_order()
+        .iter()
+        .position(|name| name == result.repository())
+        .unwrap_or(usize::max_value());
+    (Reverse(priority), rank, result.repository().clone())
+}
+
+/// Records the failure to solve the given requirement if failures are collected, or returns it otherwise
+fn record_failure(
+    failures: &mut Option<&mut Vec<(RequirementID, Error)>>,
+    requirement_id: RequirementID,
+    error: Error,
+) -> Result<(), Error> {
+    match failures {
+        Some(failures) => {
+            failures.push((requirement_id, error));
+            Ok(())
+        }
+        None => Err(error),
+    }
+}
+
+/// The progress of the solving of a [`DependencyGraph`], reported to a callback each time a node is solved
+struct SolveProgress<'a> {
+    solved: usize,
+    total: usize,
+    callback: &'a mut dyn FnMut(usize, usize),
+}
+
+impl<'a> SolveProgress<'a> {
+    /// Reports that a node was solved, given the number of nodes known so far
+    fn node_solved(&mut self, known_nodes: usize) {
+        self.solved += 1;
+        self.total = self.total.max(known_nodes).max(self.solved);
+        (self.callback)(self.solved, self.total);
+    }
+}
+
+/// A snapshot of the state of a [`DependencyGraph`], used to restore it later on.
+///
+/// It's obtained through [`DependencyGraph::snapshot`] and consumed by [`DependencyGraph::restore`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GraphSnapshot {
+    next_node_id: NodeID,
+    next_requirement_id: RequirementID,
+    nodes: HashMap<NodeID, Node>,
+    requirements: HashMap<RequirementID, Requirement>,
+    node_names: HashMap<NodeName, NodeID>,
+    held: HashSet<PackageID>,
+}
 
 /// The unsolved dependency graph: a serializable collection of [`Node`]s,
 /// linked together with [`Requirement`]s.
@@ -28,6 +96,8 @@ pub struct DependencyGraph<'lock_file> {
     nodes: HashMap<NodeID, Node>,
     requirements: HashMap<RequirementID, Requirement>,
     node_names: HashMap<NodeName, NodeID>,
+    #[serde(default)]
+    held: HashSet<PackageID>,
     #[serde(skip)]
     phantom: PhantomData<&'lock_file LockFileOwnership>,
 }
@@ -54,6 +124,7 @@ impl<'lock_file> DependencyGraph<'lock_file> {
             nodes,
             requirements: HashMap::new(),
             node_names,
+            held: HashSet::new(),
             phantom,
         }
     }
@@ -75,23 +146,190 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         }
     }
 
-    /// Saves the dependency graph back to the cache
+    /// Creates a new graph made of the groups and requirements of the given selection, and holding its held
+    /// packages. The graph isn't solved.
+    pub(crate) fn from_selection(
+        selection: &SelectionManifest,
+        phantom: PhantomData<&'lock_file LockFileOwnership>,
+    ) -> Result<DependencyGraph<'lock_file>, Error> {
+        if selection.version() > SELECTION_VERSION {
+            return Err(DependencyGraphError::from(
+                format_err!("version {}", selection.version())
+                    .context(DependencyGraphErrorKind::UnsupportedSelectionVersion),
+            )
+            .into());
+        }
+
+        let mut graph = DependencyGraph::new(phantom);
+
+        // Groups are all created first, as they may be required by groups coming before them
+        for group in selection.groups().keys() {
+            if *group != GroupName::root_group() {
+                graph.add_group_node(group.clone())?;
+            }
+        }
+
+        for (group, requirements) in selection.groups() {
+            let group_id = graph.get_group_node_id(group)?;
+            for requirement in requirements {
+                graph.node_add_requirement(
+                    group_id,
+                    requirement.clone(),
+                    RequirementManagementMethod::Static,
+                );
+            }
+        }
+
+        graph.held = selection.held().iter().cloned().collect();
+        Ok(graph)
+    }
+
+    /// Saves the dependency graph back to the cache.
+    ///
+    /// The graph is written to a temporary file next to the given path, flushed to the disk and then
+    /// moved over it, so a crash while saving can't leave a truncated graph behind.
     #[inline]
     pub fn save_to_cache<P: AsRef<Path>>(
         &self,
         path: P,
-        _: &LockFileOwnership,
+        lock_ownership: &LockFileOwnership,
     ) -> Result<(), Error> {
+        self.save_to_cache_with(path, lock_ownership, |_| Ok(()))
+    }
+
+    /// Saves the dependency graph back to the cache like [`save_to_cache`][1], calling `before_rename` with
+    /// the path of the temporary file once it's written, right before it's moved over the given path.
+    ///
+    /// If `before_rename` fails, the temporary file is removed and the graph previously saved at the given
+    /// path, if any, is left untouched.
+    ///
+    /// [1]: #method.save_to_cache
+    pub(crate) fn save_to_cache_with<P, F>(
+        &self,
+        path: P,
+        _: &LockFileOwnership,
+        before_rename: F,
+    ) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&Path) -> io::Result<()>,
+    {
         let path = path.as_ref();
 
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).with_context(|_| parent.display().to_string())?;
         }
 
-        let mut file = File::create(path).with_context(|_| path.display().to_string())?;
-        serde_json::to_writer_pretty(&file, self).with_context(|_| path.display().to_string())?;
-        writeln!(file)?;
-        Ok(())
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format_err!("invalid dependency graph path '{}'", path.display()))?;
+        let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+        let res: Result<(), Error> = try {
+            let mut file = File::create(&tmp_path)?;
+            serde_json::to_writer_pretty(&file, self)?;
+            writeln!(file)?;
+            file.sync_all()?;
+            before_rename(&tmp_path)?;
+            fs::rename(&tmp_path, path)?;
+        };
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        Ok(res.with_context(|_| path.display().to_string())?)
+    }
+
+    /// Exports the packages selected by the user, that is, the groups, their static requirements and the held
+    /// packages, leaving the solved nodes out.
+    ///
+    /// The result can be applied on another system using [`Config::apply_selection`].
+    ///
+    /// [`Config::apply_selection`]: ../../config/struct.Config.html#method.apply_selection
+    pub fn export_selection(&self) -> SelectionManifest {
+        let mut groups = BTreeMap::new();
+        for node in self.nodes.values() {
+            if let NodeKind::Group { name } = node.kind() {
+                let mut requirements: Vec<_> = node
+                    .requirements()
+                    .iter()
+                    .map(|requirement_id| &self.requirements[requirement_id])
+                    .filter(|requirement| {
+                        requirement.management_method() == RequirementManagementMethod::Static
+                    })
+                    .map(|requirement| requirement.kind().clone())
+                    .collect();
+                requirements.sort();
+                groups.insert(name.clone(), requirements);
+            }
+        }
+
+        let mut held: Vec<_> = self.held.iter().cloned().collect();
+        held.sort();
+        SelectionManifest::from(groups, held)
+    }
+
+    /// Takes a snapshot of the current state of the graph.
+    ///
+    /// This allows to try risky operations on the graph, like solving or updating it, and go back to the
+    /// previous state using [`restore`] if the result isn't satisfying, without reading the graph from the disk again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::str::FromStr;
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-snapshot-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let original_graph = graph.clone();
+    ///
+    /// let snapshot = graph.snapshot();
+    ///
+    /// let name = GroupName::from_str("@desktop")?;
+    /// graph.add_group_node(name.clone())?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Group { name },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// assert_ne!(graph, original_graph);
+    ///
+    /// graph.restore(snapshot);
+    /// assert_eq!(graph, original_graph);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`restore`]: #method.restore
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot {
+            next_node_id: self.next_node_id,
+            next_requirement_id: self.next_requirement_id,
+            nodes: self.nodes.clone(),
+            requirements: self.requirements.clone(),
+            node_names: self.node_names.clone(),
+            held: self.held.clone(),
+        }
+    }
+
+    /// Restores the graph to the state it was in when the given snapshot was taken
+    pub fn restore(&mut self, snapshot: GraphSnapshot) {
+        self.next_node_id = snapshot.next_node_id;
+        self.next_requirement_id = snapshot.next_requirement_id;
+        self.nodes = snapshot.nodes;
+        self.requirements = snapshot.requirements;
+        self.node_names = snapshot.node_names;
+        self.held = snapshot.held;
     }
 
     /// Returns the ID of the root of the graph
@@ -135,6 +373,8 @@ impl<'lock_file> DependencyGraph<'lock_file> {
     }
 
     /// Returns an iterator over the names of the packages in the graph.
+    ///
+    /// A package installed in several slots is returned once per slot.
     #[inline]
     pub fn packages(&self) -> impl Iterator<Item = &PackageFullName> {
         self.node_names.keys().filter_map(NodeName::package_name)
@@ -146,16 +386,47 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         &self.node_names
     }
 
+    /// Returns the [`NodeID`] of a given group
+    /// If there is no such group, a [`DependencyGraphError`] of kind [`DependencyGraphErrorKind::GroupNotFound`]
+    /// is returned
+    ///
+    /// [`DependencyGraphErrorKind::GroupNotFound`]: ../enum.DependencyGraphErrorKind.html#variant.GroupNotFound
+    pub fn get_group_node_id(&self, name: &GroupName) -> Result<NodeID, Error> {
+        self.node_names
+            .get(&NodeName::Group(name.clone()))
+            .cloned()
+            .ok_or_else(|| {
+                DependencyGraphError::from(
+                    format_err!("{}", **name).context(DependencyGraphErrorKind::GroupNotFound),
+                )
+                .into()
+            })
+    }
+
     /// Returns the [`NodeID`] of a given package
+    /// If the package is installed in several slots, the node of the lowest slot is returned, the default
+    /// slot coming first.
     /// If no such ID is found, a [`DependencyGraphError`] is returned
     pub fn get_package_node_id(&self, name: &PackageFullName) -> Result<NodeID, Error> {
-        let node_name = NodeName::Package(name.clone());
+        self.node_names
+            .iter()
+            .filter(|(node_name, _)| node_name.package_name() == Some(name))
+            .min_by_key(|(node_name, _)| node_name.slot())
+            .map(|(_, node_id)| *node_id)
+            .ok_or_else(|| {
+                format_err!("{}", name)
+                    .context(DependencyGraphErrorKind::UnknownPackage)
+                    .into()
+            })
+    }
 
-        self.node_names.get(&node_name).cloned().ok_or_else(|| {
-            format_err!("{}", name)
-                .context(DependencyGraphErrorKind::UnknownPackage)
-                .into()
-        })
+    /// Returns the IDs of the versions of the given package in the graph, one per slot it's installed in
+    fn installed_versions(&self, name: &PackageFullName) -> Vec<PackageID> {
+        self.node_names
+            .iter()
+            .filter(|(node_name, _)| node_name.package_name() == Some(name))
+            .filter_map(|(_, node_id)| self.nodes[node_id].kind().package().cloned())
+            .collect()
     }
 
     /// Returns a reference to the [`Node`] of a given package
@@ -173,6 +444,792 @@ impl<'lock_file> DependencyGraph<'lock_file> {
             .expect("Invalid node id"))
     }
 
+    /// Holds the given package at its installed version, so that solving or updating the graph never changes it.
+    /// A package installed in several slots is held at its version in each of them.
+    ///
+    /// Requirements that can't be solved without changing the version of a held package fail with a
+    /// [`DependencyGraphError`] of kind [`DependencyGraphErrorKind::HeldPackageConflict`].
+    /// If the package isn't in the graph, a [`DependencyGraphError`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{NodeKind, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageID, PackageManifest, PackageRequirement, Slot};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-hold-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let publish = |versions: &[&str]| -> Result<(), failure::Error> {
+    ///     let versions: Vec<_> = versions
+    ///         .iter()
+    ///         .map(|version| format!(r#""{}": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}"#, version))
+    ///         .collect();
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "linux",
+    ///             "category": "sys-kernel",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{ {} }}
+    ///         }}"#,
+    ///         versions.join(", "),
+    ///     ))?;
+    ///     cache.update(&manifest)
+    /// };
+    ///
+    /// publish(&["1.0.0"])?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package { package_req: PackageRequirement::parse("sys-kernel/linux")? },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    ///
+    /// let name = PackageFullName::parse("tests::sys-kernel/linux")?;
+    /// graph.hold(&name)?;
+    ///
+    /// // A newer version is available, but the held package isn't upgraded
+    /// publish(&["1.0.0", "2.0.0"])?;
+    /// graph.update(&config)?;
+    /// let installed = |graph: &libnest::cache::depgraph::DependencyGraph| {
+    ///     graph.get_package_node(&name).map(|node| node.kind().clone())
+    /// };
+    /// let linux = |version: &str| -> Result<NodeKind, failure::Error> {
+    ///     Ok(NodeKind::Package {
+    ///         id: PackageID::parse(&format!("tests::sys-kernel/linux#{}", version))?,
+    ///         slot: Slot::default(),
+    ///     })
+    /// };
+    /// assert_eq!(installed(&graph)?, linux("1.0.0")?);
+    ///
+    /// // Requiring another version conflicts with the hold
+    /// let mut conflicting_graph = graph.clone();
+    /// conflicting_graph.node_add_requirement(
+    ///     conflicting_graph.root_id(),
+    ///     RequirementKind::Package { package_req: PackageRequirement::parse("sys-kernel/linux#>=2.0.0")? },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// let error = conflicting_graph.solve(&config).unwrap_err();
+    /// let error = error.downcast_ref::<DependencyGraphError>().unwrap();
+    /// assert_eq!(*error.kind(), DependencyGraphErrorKind::HeldPackageConflict);
+    ///
+    /// // Once released, the package is upgraded
+    /// assert!(graph.unhold(&name));
+    /// graph.update(&config)?;
+    /// assert_eq!(installed(&graph)?, linux("2.0.0")?);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Every slot of the package is held:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-hold-slots-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let publish = |versions: &[(&str, &str)]| -> Result<(), failure::Error> {
+    ///     let versions: Vec<_> = versions
+    ///         .iter()
+    ///         .map(|(version, slot)| format!(
+    ///             r#""{}": {{ "slot": "{}", "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}"#,
+    ///             version, slot,
+    ///         ))
+    ///         .collect();
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gtk",
+    ///             "category": "x11-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{ {} }}
+    ///         }}"#,
+    ///         versions.join(", "),
+    ///     ))?;
+    ///     cache.update(&manifest)
+    /// };
+    ///
+    /// // `gtk#2` and `gtk#3` are installed side by side
+    /// publish(&[("2.0.0", "2"), ("3.0.0", "3")])?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// for requirement in &["x11-libs/gtk#^2", "x11-libs/gtk#^3"] {
+    ///     graph.node_add_requirement(
+    ///         graph.root_id(),
+    ///         RequirementKind::Package { package_req: PackageRequirement::parse(requirement)? },
+    ///         RequirementManagementMethod::Static,
+    ///     );
+    /// }
+    /// graph.solve(&config)?;
+    ///
+    /// let name = PackageFullName::parse("tests::x11-libs/gtk")?;
+    /// graph.hold(&name)?;
+    /// assert_eq!(graph.held().len(), 2);
+    ///
+    /// // Newer versions are available in both slots, but none of them is upgraded
+    /// publish(&[("2.0.0", "2"), ("2.1.0", "2"), ("3.0.0", "3"), ("3.1.0", "3")])?;
+    /// graph.update(&config)?;
+    /// let mut installed: Vec<_> = graph
+    ///     .nodes()
+    ///     .values()
+    ///     .filter_map(|node| node.kind().package())
+    ///     .map(|id| id.to_string())
+    ///     .collect();
+    /// installed.sort();
+    /// assert_eq!(installed, vec!["tests::x11-libs/gtk#2.0.0", "tests::x11-libs/gtk#3.0.0"]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn hold(&mut self, name: &PackageFullName) -> Result<(), Error> {
+        // Fails if the package isn't in the graph
+        self.get_package_node_id(name)?;
+
+        let ids = self.installed_versions(name);
+        self.unhold(name);
+        self.held.extend(ids);
+        Ok(())
+    }
+
+    /// Releases the hold on the given package, and returns whether it was held
+    pub fn unhold(&mut self, name: &PackageFullName) -> bool {
+        let len = self.held.len();
+
+        self.held.retain(|id| {
+            let full_name: PackageFullName = id.clone().into();
+            full_name != *name
+        });
+        self.held.len() != len
+    }
+
+    /// Returns the held packages, with the version they are held at
+    #[inline]
+    pub fn held(&self) -> &HashSet<PackageID> {
+        &self.held
+    }
+
+    /// Returns the packages that depend on the given package, directly or not.
+    ///
+    /// Each package is returned once, even if it depends on the given package through several paths,
+    /// and groups (including the root group) are never part of the result.
+    /// If the package isn't in the graph, a [`DependencyGraphError`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-rdeps-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// // A diamond: `app` depends on `liba` and `libb`, which both depend on `libc`
+    /// for (name, dependencies) in &[
+    ///     ("app", r#""sys-libs/liba#*", "sys-libs/libb#*""#),
+    ///     ("liba", r#""sys-libs/libc#*""#),
+    ///     ("libb", r#""sys-libs/libc#*""#),
+    ///     ("libc", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("sys-libs/app")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    ///
+    /// let names = |names: &[&str]| -> Result<Vec<PackageFullName>, failure::Error> {
+    ///     names.iter().map(|name| Ok(PackageFullName::parse(name)?)).collect()
+    /// };
+    /// assert_eq!(
+    ///     graph.reverse_dependencies(&PackageFullName::parse("tests::sys-libs/libc")?)?,
+    ///     names(&["tests::sys-libs/app", "tests::sys-libs/liba", "tests::sys-libs/libb"])?,
+    /// );
+    /// assert_eq!(
+    ///     graph.reverse_dependencies(&PackageFullName::parse("tests::sys-libs/liba")?)?,
+    ///     names(&["tests::sys-libs/app"])?,
+    /// );
+    /// assert!(graph.reverse_dependencies(&PackageFullName::parse("tests::sys-libs/app")?)?.is_empty());
+    /// assert!(graph.reverse_dependencies(&PackageFullName::parse("tests::sys-libs/libd")?).is_err());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reverse_dependencies(
+        &self,
+        name: &PackageFullName,
+    ) -> Result<Vec<PackageFullName>, Error> {
+        let node_id = self.get_package_node_id(name)?;
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![node_id];
+        let mut dependents = Vec::new();
+
+        visited.insert(node_id);
+        while let Some(node_id) = to_visit.pop() {
+            for requirement_id in self.nodes[&node_id].dependents() {
+                let parent_id = self.requirements[requirement_id].fulfilled_node_id();
+
+                if visited.insert(parent_id) {
+                    if let NodeKind::Package { id, .. } = self.nodes[&parent_id].kind() {
+                        dependents.push(id.clone().into());
+                    }
+                    to_visit.push(parent_id);
+                }
+            }
+        }
+
+        dependents.sort();
+        Ok(dependents)
+    }
+
+    /// Returns every path going from the root group down to the given package, following the requirements of each node,
+    /// that is, the reasons why the package is installed. Each path is a list of node names, starting with the root group
+    /// and ending with the package. The result is sorted.
+    ///
+    /// A path never goes through the same node twice, so cyclic dependencies don't lead to an infinite number of paths.
+    /// If the package isn't in the graph, a [`DependencyGraphError`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-explain-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// // `libc` is needed by both `liba` and `libb`, which are needed by `app`
+    /// for (name, dependencies) in &[
+    ///     ("app", r#""sys-libs/liba#*", "sys-libs/libb#*""#),
+    ///     ("liba", r#""sys-libs/libc#*""#),
+    ///     ("libb", r#""sys-libs/libc#*""#),
+    ///     ("libc", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("sys-libs/app")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    ///
+    /// let explain = |name: &str| -> Result<Vec<String>, failure::Error> {
+    ///     Ok(graph
+    ///         .explain(&PackageFullName::parse(name)?)?
+    ///         .iter()
+    ///         .map(|path| path.iter().map(ToString::to_string).collect::<Vec<_>>().join(" -> "))
+    ///         .collect())
+    /// };
+    /// assert_eq!(
+    ///     explain("tests::sys-libs/libc")?,
+    ///     vec![
+    ///         "@root -> tests::sys-libs/app -> tests::sys-libs/liba -> tests::sys-libs/libc",
+    ///         "@root -> tests::sys-libs/app -> tests::sys-libs/libb -> tests::sys-libs/libc",
+    ///     ],
+    /// );
+    /// assert_eq!(explain("tests::sys-libs/app")?, vec!["@root -> tests::sys-libs/app"]);
+    /// assert!(graph.explain(&PackageFullName::parse("tests::sys-libs/libd")?).is_err());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn explain(&self, name: &PackageFullName) -> Result<Vec<Vec<NodeName>>, Error> {
+        let target_id = self.get_package_node_id(name)?;
+        let mut paths = Vec::new();
+        let mut path = vec![ROOT_ID];
+
+        self.explain_rec(target_id, &mut path, &mut paths);
+
+        let mut paths: Vec<Vec<NodeName>> = paths
+            .into_iter()
+            .map(|path| {
+                path.into_iter()
+                    .map(|node_id| NodeName::from(self.nodes[&node_id].kind().clone()))
+                    .collect()
+            })
+            .collect();
+        paths.sort_by_cached_key(|path| path.iter().map(ToString::to_string).collect::<Vec<_>>());
+        Ok(paths)
+    }
+
+    /// Extends the given path (going from the root group to its last node) in every possible way, and records those
+    /// reaching the target node
+    fn explain_rec(&self, target_id: NodeID, path: &mut Vec<NodeID>, paths: &mut Vec<Vec<NodeID>>) {
+        let node_id = *path.last().expect("empty path");
+
+        if node_id == target_id {
+            paths.push(path.clone());
+            return;
+        }
+
+        for requirement_id in self.nodes[&node_id].requirements() {
+            if let Some(child_id) = self.requirements[requirement_id].fulfilling_node_id() {
+                if !path.contains(child_id) {
+                    path.push(*child_id);
+                    self.explain_rec(target_id, path, paths);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns the installed packages that are only kept because of automatic requirements of the root group,
+    /// or because of packages that are themselves orphans.
+    ///
+    /// Packages explicitly required by the user, their dependencies and the members of user-created groups
+    /// are never orphans. The result is sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-orphans-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (name, dependencies) in &[
+    ///     ("app", r#""sys-libs/lib#*""#),
+    ///     ("lib", ""),
+    ///     ("oldlib", r#""sys-libs/olddep#*""#),
+    ///     ("olddep", ""),
+    ///     ("tool", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let requirement_on = |name: &str| -> Result<RequirementKind, failure::Error> {
+    ///     Ok(RequirementKind::Package { package_req: PackageRequirement::parse(name)? })
+    /// };
+    ///
+    /// // `app` was installed manually, and pulled `lib` automatically
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/app")?, RequirementManagementMethod::Static);
+    ///
+    /// // `oldlib` (and its dependency `olddep`) were pulled automatically, but nothing uses them anymore
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/oldlib")?, RequirementManagementMethod::Auto);
+    ///
+    /// // `tool` is a member of a user-created group
+    /// let group_name: GroupName = "@devel".parse()?;
+    /// let group_id = graph.add_group_node(group_name.clone())?;
+    /// graph.node_add_requirement(graph.root_id(), RequirementKind::Group { name: group_name }, RequirementManagementMethod::Static);
+    /// graph.node_add_requirement(group_id, requirement_on("sys-libs/tool")?, RequirementManagementMethod::Auto);
+    ///
+    /// graph.solve(&config)?;
+    ///
+    /// assert_eq!(
+    ///     graph.orphans(),
+    ///     vec![
+    ///         PackageID::parse("tests::sys-libs/olddep#1.0.0")?,
+    ///         PackageID::parse("tests::sys-libs/oldlib#1.0.0")?,
+    ///     ],
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn orphans(&self) -> Vec<PackageID> {
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![ROOT_ID];
+
+        visited.insert(ROOT_ID);
+        while let Some(node_id) = to_visit.pop() {
+            for requirement_id in self.nodes[&node_id].requirements() {
+                let requirement = &self.requirements[requirement_id];
+
+                // Automatic requirements of the root group don't keep packages installed
+                if node_id == ROOT_ID
+                    && requirement.management_method() == RequirementManagementMethod::Auto
+                {
+                    continue;
+                }
+
+                if let Some(child_id) = requirement.fulfilling_node_id() {
+                    if visited.insert(*child_id) {
+                        to_visit.push(*child_id);
+                    }
+                }
+            }
+        }
+
+        let mut orphans: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|(node_id, _)| !visited.contains(node_id))
+            .filter_map(|(_, node)| node.kind().package().cloned())
+            .collect();
+        orphans.sort();
+        orphans
+    }
+
+    /// Computes a summary of the content of the graph.
+    ///
+    /// Packages fulfilling at least one static requirement are counted as explicitly installed, and all the
+    /// others as pulled automatically. Orphans are counted the same way they are listed by [`orphans`][1].
+    ///
+    /// [1]: #method.orphans
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-statistics-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (name, dependencies) in &[
+    ///     ("app", r#""sys-libs/lib#*""#),
+    ///     ("lib", ""),
+    ///     ("oldlib", ""),
+    ///     ("tool", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let requirement_on = |name: &str| -> Result<RequirementKind, failure::Error> {
+    ///     Ok(RequirementKind::Package { package_req: PackageRequirement::parse(name)? })
+    /// };
+    ///
+    /// // `app` was installed manually and pulled `lib`, `oldlib` isn't used anymore
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/app")?, RequirementManagementMethod::Static);
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/oldlib")?, RequirementManagementMethod::Auto);
+    ///
+    /// // `tool` is explicitly installed in a user-created group
+    /// let group_name: GroupName = "@devel".parse()?;
+    /// let group_id = graph.add_group_node(group_name.clone())?;
+    /// graph.node_add_requirement(graph.root_id(), RequirementKind::Group { name: group_name }, RequirementManagementMethod::Static);
+    /// graph.node_add_requirement(group_id, requirement_on("sys-libs/tool")?, RequirementManagementMethod::Static);
+    ///
+    /// graph.solve(&config)?;
+    ///
+    /// let statistics = graph.statistics();
+    /// assert_eq!(statistics.explicit_packages(), 2);
+    /// assert_eq!(statistics.auto_packages(), 2);
+    /// assert_eq!(statistics.groups(), 1);
+    /// assert_eq!(statistics.requirements(), 5);
+    /// assert_eq!(statistics.orphans(), 1);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn statistics(&self) -> GraphStatistics {
+        let explicit: HashSet<_> = self
+            .requirements
+            .values()
+            .filter(|requirement| {
+                requirement.management_method() == RequirementManagementMethod::Static
+            })
+            .filter_map(|requirement| *requirement.fulfilling_node_id())
+            .filter(|node_id| {
+                self.nodes
+                    .get(node_id)
+                    .map_or(false, |node| node.kind().package().is_some())
+            })
+            .collect();
+        let packages = self
+            .nodes
+            .values()
+            .filter(|node| node.kind().package().is_some())
+            .count();
+        let groups = self
+            .nodes
+            .iter()
+            .filter(|(node_id, node)| **node_id != ROOT_ID && node.kind().group().is_some())
+            .count();
+
+        GraphStatistics::from(
+            explicit.len(),
+            packages - explicit.len(),
+            groups,
+            self.requirements.len(),
+            self.orphans().len(),
+        )
+    }
+
+    /// Exports the graph in the DOT format, so it can be rendered using Graphviz.
+    ///
+    /// Groups are rendered as boxes and packages as ellipses. Each requirement is an edge going from the node
+    /// holding it to the node fulfilling it, or a dashed edge to a synthetic `unsolved` node if it isn't solved yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-dot-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (name, dependencies) in &[("app", r#""sys-libs/lib#*""#), ("lib", "")] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let requirement_on = |name: &str| -> Result<RequirementKind, failure::Error> {
+    ///     Ok(RequirementKind::Package { package_req: PackageRequirement::parse(name)? })
+    /// };
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/app")?, RequirementManagementMethod::Static);
+    /// graph.solve(&config)?;
+    /// graph.node_add_requirement(graph.root_id(), requirement_on("sys-libs/tool")?, RequirementManagementMethod::Static);
+    ///
+    /// let dot = graph.to_dot();
+    /// assert!(dot.starts_with("digraph depgraph {\n"));
+    /// assert!(dot.contains(r#"n0 [label="@root", shape=box];"#));
+    /// assert!(dot.contains(r#"n1 [label="tests::sys-libs/app#1.0.0", shape=ellipse];"#));
+    /// assert!(dot.contains(r#"n2 [label="tests::sys-libs/lib#1.0.0", shape=ellipse];"#));
+    /// assert!(dot.contains(r#"n0 -> n1 [label="sys-libs/app#*"];"#));
+    /// assert!(dot.contains(r#"n1 -> n2 [label="sys-libs/lib#*"];"#));
+    /// assert!(dot.contains(r#"n0 -> unsolved [label="sys-libs/tool#*", style=dashed];"#));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let escape = |label: String| label.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph depgraph {\n");
+
+        let mut node_ids: Vec<_> = self.nodes.keys().collect();
+        node_ids.sort();
+        for node_id in node_ids {
+            let node = &self.nodes[node_id];
+            let shape = match node.kind() {
+                NodeKind::Group { .. } => "box",
+                NodeKind::Package { .. } => "ellipse",
+            };
+            dot += &format!(
+                "    n{} [label=\"{}\", shape={}];\n",
+                node_id,
+                escape(node.to_string()),
+                shape
+            );
+        }
+
+        let mut requirement_ids: Vec<_> = self.requirements.keys().collect();
+        requirement_ids.sort();
+        if self
+            .requirements
+            .values()
+            .any(|requirement| requirement.fulfilling_node_id().is_none())
+        {
+            dot += "    unsolved [label=\"unsolved\", shape=plaintext];\n";
+        }
+        for requirement_id in requirement_ids {
+            let requirement = &self.requirements[requirement_id];
+            let label = escape(requirement.kind().to_string());
+
+            dot += &match requirement.fulfilling_node_id() {
+                Some(fulfilling_id) => format!(
+                    "    n{} -> n{} [label=\"{}\"];\n",
+                    requirement.fulfilled_node_id(),
+                    fulfilling_id,
+                    label
+                ),
+                None => format!(
+                    "    n{} -> unsolved [label=\"{}\", style=dashed];\n",
+                    requirement.fulfilled_node_id(),
+                    label
+                ),
+            };
+        }
+
+        dot += "}\n";
+        dot
+    }
+
     /// Adds a given requirement as a dependency for a given node
     pub fn node_add_requirement(
         &mut self,
@@ -196,6 +1253,198 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         requirement_id
     }
 
+    /// Adds a requirement on a package to the given group, and returns the ID of the new requirement.
+    ///
+    /// The requirement isn't solved until the graph is. If there is no such group, a [`DependencyGraphError`] of kind
+    /// [`DependencyGraphErrorKind::GroupNotFound`] is returned.
+    ///
+    /// [`DependencyGraphErrorKind::GroupNotFound`]: ../enum.DependencyGraphErrorKind.html#variant.GroupNotFound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::PackageRequirement;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-group-requirement-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    ///
+    /// let requirement = PackageRequirement::parse("sys-apps/coreutils")?;
+    /// let requirement_id = graph.add_requirement_to_group(
+    ///     &GroupName::root_group(),
+    ///     requirement.clone(),
+    ///     RequirementManagementMethod::Static,
+    /// )?;
+    /// assert!(graph.nodes()[&graph.root_id()].requirements().contains(&requirement_id));
+    /// assert_eq!(
+    ///     *graph.requirements()[&requirement_id].kind(),
+    ///     RequirementKind::Package { package_req: requirement.clone() },
+    /// );
+    ///
+    /// let error = graph
+    ///     .add_requirement_to_group(&"@missing".parse()?, requirement, RequirementManagementMethod::Static)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     *error.downcast_ref::<DependencyGraphError>().unwrap().kind(),
+    ///     DependencyGraphErrorKind::GroupNotFound,
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_requirement_to_group(
+        &mut self,
+        group: &GroupName,
+        requirement: PackageRequirement,
+        management_method: RequirementManagementMethod,
+    ) -> Result<RequirementID, Error> {
+        let group_id = self.get_group_node_id(group)?;
+
+        Ok(self.node_add_requirement(
+            group_id,
+            RequirementKind::Package {
+                package_req: requirement,
+            },
+            management_method,
+        ))
+    }
+
+    /// Adds requirements on packages to the given groups, and solves the graph once they are all added.
+    ///
+    /// Solving all the requirements at once resolves the dependencies they share together, and detects the
+    /// conflicts between the requested packages before any of them is added to the graph. Returns the IDs of
+    /// the new requirements, in the same order as the given requirements.
+    ///
+    /// If a group doesn't exist, or if the graph can't be solved, the graph is left untouched and the error
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-add-requirements-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for (name, dependencies) in &[
+    ///     ("glibc", ""),
+    ///     ("coreutils", r#""tests::sys-apps/glibc#^1.0.0""#),
+    ///     ("bash", r#""tests::sys-apps/glibc#^1.0.0""#),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-apps",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let requirement_ids = graph.add_requirements(
+    ///     &config,
+    ///     vec![
+    ///         (GroupName::root_group(), PackageRequirement::parse("sys-apps/coreutils")?),
+    ///         (GroupName::root_group(), PackageRequirement::parse("sys-apps/bash")?),
+    ///     ],
+    ///     RequirementManagementMethod::Static,
+    /// )?;
+    /// assert_eq!(requirement_ids.len(), 2);
+    ///
+    /// let mut installed: Vec<_> = graph
+    ///     .nodes()
+    ///     .values()
+    ///     .filter_map(|node| node.kind().package())
+    ///     .map(|id| id.to_string())
+    ///     .collect();
+    /// installed.sort();
+    /// assert_eq!(
+    ///     installed,
+    ///     vec!["tests::sys-apps/bash#1.0.0", "tests::sys-apps/coreutils#1.0.0", "tests::sys-apps/glibc#1.0.0"],
+    /// );
+    ///
+    /// // Nothing is added if one of the requirements can't be solved
+    /// let before = graph.clone();
+    /// assert!(graph
+    ///     .add_requirements(
+    ///         &config,
+    ///         vec![
+    ///             (GroupName::root_group(), PackageRequirement::parse("sys-apps/glibc")?),
+    ///             (GroupName::root_group(), PackageRequirement::parse("sys-apps/emacs")?),
+    ///         ],
+    ///         RequirementManagementMethod::Static,
+    ///     )
+    ///     .is_err());
+    /// assert_eq!(graph, before);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_requirements(
+        &mut self,
+        config: &Config,
+        requirements: Vec<(GroupName, PackageRequirement)>,
+        management_method: RequirementManagementMethod,
+    ) -> Result<Vec<RequirementID>, Error> {
+        let requirements = requirements
+            .into_iter()
+            .map(|(group, requirement)| Ok((self.get_group_node_id(&group)?, requirement)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let snapshot = self.snapshot();
+        let requirement_ids = requirements
+            .into_iter()
+            .map(|(group_id, requirement)| {
+                self.node_add_requirement(
+                    group_id,
+                    RequirementKind::Package {
+                        package_req: requirement,
+                    },
+                    management_method,
+                )
+            })
+            .collect();
+
+        if let Err(e) = self.solve(config) {
+            self.restore(snapshot);
+            return Err(e);
+        }
+        Ok(requirement_ids)
+    }
+
     /// Tests by value if a group has a specific requirement
     pub fn node_has_requirement(&self, node: &Node, value: &RequirementKind) -> bool {
         for requirement_id in node.requirements() {
@@ -298,7 +1547,8 @@ impl<'lock_file> DependencyGraph<'lock_file> {
 
     /// Creates a new node with the given package
     pub fn add_package_node(&mut self, package: QueryResult) -> Result<NodeID, Error> {
-        let node_name = NodeName::Package(package.full_name());
+        let slot = package.manifest().slot().clone();
+        let node_name = NodeName::Package(package.full_name(), slot.clone());
 
         if self.node_names.contains_key(&node_name) {
             Err(format_err!("{}", &node_name)
@@ -307,8 +1557,13 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         } else {
             let node_id = self.next_node_id();
 
-            self.nodes
-                .insert(node_id, Node::from(NodeKind::Package { id: package.id() }));
+            self.nodes.insert(
+                node_id,
+                Node::from(NodeKind::Package {
+                    id: package.id(),
+                    slot,
+                }),
+            );
 
             for dependency in package.manifest().dependencies() {
                 let kind = RequirementKind::Package {
@@ -317,6 +1572,13 @@ impl<'lock_file> DependencyGraph<'lock_file> {
                 self.node_add_requirement(node_id, kind, RequirementManagementMethod::Auto);
             }
 
+            for dependency in package.manifest().optional_dependencies() {
+                let kind = RequirementKind::Package {
+                    package_req: dependency.clone(),
+                };
+                self.node_add_requirement(node_id, kind, RequirementManagementMethod::Optional);
+            }
+
             self.node_names.insert(node_name, node_id);
             Ok(node_id)
         }
@@ -344,6 +1606,226 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         }
     }
 
+    /// Returns the kinds of the requirements held by the given group, sorted.
+    ///
+    /// If there is no such group, a [`DependencyGraphError`] of kind [`DependencyGraphErrorKind::GroupNotFound`]
+    /// is returned.
+    ///
+    /// [`DependencyGraphErrorKind::GroupNotFound`]: ../enum.DependencyGraphErrorKind.html#variant.GroupNotFound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-group-members-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "nginx",
+    ///         "category": "www-servers",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "1.17.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// // @web holds a package and a nested group
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let web: GroupName = "@web".parse()?;
+    /// let php: GroupName = "@php".parse()?;
+    /// for group in &[&web, &php] {
+    ///     graph.add_group_node((*group).clone())?;
+    /// }
+    /// graph.node_add_requirement(graph.root_id(), RequirementKind::Group { name: web.clone() }, RequirementManagementMethod::Static);
+    /// let web_id = graph.get_group_node_id(&web)?;
+    /// graph.node_add_requirement(web_id, RequirementKind::Group { name: php.clone() }, RequirementManagementMethod::Static);
+    /// let nginx = PackageRequirement::parse("www-servers/nginx")?;
+    /// graph.add_requirement_to_group(&web, nginx.clone(), RequirementManagementMethod::Static)?;
+    /// graph.solve(&config)?;
+    ///
+    /// assert_eq!(
+    ///     graph.group_members(&web)?,
+    ///     vec![
+    ///         RequirementKind::Group { name: php.clone() },
+    ///         RequirementKind::Package { package_req: nginx },
+    ///     ],
+    /// );
+    /// assert_eq!(
+    ///     graph.group_package_members(&web)?,
+    ///     vec![PackageID::parse("tests::www-servers/nginx#1.17.0")?],
+    /// );
+    /// assert!(graph.group_members(&php)?.is_empty());
+    ///
+    /// let error = graph.group_members(&"@missing".parse()?).unwrap_err();
+    /// assert_eq!(
+    ///     *error.downcast_ref::<DependencyGraphError>().unwrap().kind(),
+    ///     DependencyGraphErrorKind::GroupNotFound,
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn group_members(&self, group: &GroupName) -> Result<Vec<RequirementKind>, Error> {
+        let group_id = self.get_group_node_id(group)?;
+
+        let mut members: Vec<_> = self.nodes[&group_id]
+            .requirements()
+            .iter()
+            .map(|requirement_id| self.requirements[requirement_id].kind().clone())
+            .collect();
+        members.sort();
+        Ok(members)
+    }
+
+    /// Returns the packages fulfilling the package requirements held by the given group, sorted.
+    ///
+    /// Packages required by nested groups and requirements that aren't solved yet are left out.
+    /// If there is no such group, a [`DependencyGraphError`] of kind [`DependencyGraphErrorKind::GroupNotFound`]
+    /// is returned.
+    ///
+    /// [`DependencyGraphErrorKind::GroupNotFound`]: ../enum.DependencyGraphErrorKind.html#variant.GroupNotFound
+    pub fn group_package_members(&self, group: &GroupName) -> Result<Vec<PackageID>, Error> {
+        let group_id = self.get_group_node_id(group)?;
+
+        let mut members: Vec<_> = self.nodes[&group_id]
+            .requirements()
+            .iter()
+            .filter_map(|requirement_id| *self.requirements[requirement_id].fulfilling_node_id())
+            .filter_map(|node_id| self.nodes[&node_id].kind().package().cloned())
+            .collect();
+        members.sort();
+        members.dedup();
+        Ok(members)
+    }
+
+    /// Renames the given group, keeping all the requirements of the group and on the group.
+    ///
+    /// If there is no group named `old`, a [`DependencyGraphError`] of kind [`DependencyGraphErrorKind::GroupNotFound`]
+    /// is returned, and if there is already a group named `new`, one of kind
+    /// [`DependencyGraphErrorKind::GroupAlreadyExists`] is returned. The root group can't be renamed.
+    ///
+    /// [`DependencyGraphErrorKind::GroupNotFound`]: ../enum.DependencyGraphErrorKind.html#variant.GroupNotFound
+    /// [`DependencyGraphErrorKind::GroupAlreadyExists`]: ../enum.DependencyGraphErrorKind.html#variant.GroupAlreadyExists
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::PackageRequirement;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-rename-group-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    ///
+    /// let web: GroupName = "@web".parse()?;
+    /// let webservers: GroupName = "@webservers".parse()?;
+    /// let group_id = graph.add_group_node(web.clone())?;
+    /// let nginx_id = graph.add_requirement_to_group(
+    ///     &web,
+    ///     PackageRequirement::parse("www-servers/nginx")?,
+    ///     RequirementManagementMethod::Static,
+    /// )?;
+    /// let web_id = graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Group { name: web.clone() },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    ///
+    /// graph.rename_group(&web, &webservers)?;
+    ///
+    /// // The group keeps its requirements, and the requirement on it follows the new name
+    /// assert!(graph.nodes()[&group_id].requirements().contains(&nginx_id));
+    /// assert_eq!(
+    ///     *graph.requirements()[&web_id].kind(),
+    ///     RequirementKind::Group { name: webservers.clone() },
+    /// );
+    /// assert!(graph.groups().any(|group| *group == webservers));
+    /// assert!(!graph.groups().any(|group| *group == web));
+    /// graph.add_requirement_to_group(
+    ///     &webservers,
+    ///     PackageRequirement::parse("www-servers/apache")?,
+    ///     RequirementManagementMethod::Static,
+    /// )?;
+    ///
+    /// let kind = |error: failure::Error| *error.downcast_ref::<DependencyGraphError>().unwrap().kind();
+    /// assert_eq!(kind(graph.rename_group(&web, &webservers).unwrap_err()), DependencyGraphErrorKind::GroupNotFound);
+    /// graph.add_group_node(web.clone())?;
+    /// assert_eq!(
+    ///     kind(graph.rename_group(&webservers, &web).unwrap_err()),
+    ///     DependencyGraphErrorKind::GroupAlreadyExists,
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rename_group(&mut self, old: &GroupName, new: &GroupName) -> Result<(), Error> {
+        let old_name = NodeName::Group(old.clone());
+        let new_name = NodeName::Group(new.clone());
+
+        let group_id = self.get_group_node_id(old)?;
+        if group_id == ROOT_ID {
+            return Err(DependencyGraphError::from(
+                format_err!("{}", **old).context(DependencyGraphErrorKind::RootGroupRename),
+            )
+            .into());
+        }
+        if self.node_names.contains_key(&new_name) {
+            return Err(DependencyGraphError::from(
+                format_err!("{}", **new).context(DependencyGraphErrorKind::GroupAlreadyExists),
+            )
+            .into());
+        }
+
+        self.node_names.remove(&old_name);
+        self.node_names.insert(new_name, group_id);
+        *self
+            .nodes
+            .get_mut(&group_id)
+            .expect("invalid node id")
+            .kind_mut() = NodeKind::Group { name: new.clone() };
+
+        // Requirements on the group designate it by its name
+        for requirement in self.requirements.values_mut() {
+            if let RequirementKind::Group { name } = requirement.kind_mut() {
+                if name == old {
+                    *name = new.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Removes a node from the dependency graph, and all requirements linked from/to it
     pub fn remove_node(&mut self, node_id: NodeID) {
         let dependents = self
@@ -403,20 +1885,141 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         }
 
         // Remove the node from the node table and the groups/packages tables
-        match self.nodes[&node_id].kind() {
-            NodeKind::Group { name } => {
-                self.node_names.remove(&NodeName::Group(name.clone()));
-            }
-            NodeKind::Package { id } => {
-                self.node_names
-                    .remove(&NodeName::Package(id.clone().into()));
-            }
-        }
+        self.node_names
+            .remove(&NodeName::from(self.nodes[&node_id].kind().clone()));
 
         // Remove the node from the nodes table
         self.nodes.remove(&node_id);
     }
 
+    /// Removes the given package from the dependency graph, along with its dependencies that aren't needed
+    /// anymore once it's removed, and returns the sorted list of all the removed packages.
+    ///
+    /// The requirements of groups (including the root group) fulfilled by the package are removed too.
+    /// If other packages depend on it, a [`DependencyGraphError`] of kind
+    /// [`DependencyGraphErrorKind::PackageStillRequired`] is returned and the graph is left untouched.
+    ///
+    /// [`DependencyGraphErrorKind::PackageStillRequired`]: ../enum.DependencyGraphErrorKind.html#variant.PackageStillRequired
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageID, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-cascade-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// // `app` is the only user of `libapp`, but shares `libshared` with `tool`
+    /// for (name, dependencies) in &[
+    ///     ("app", r#""sys-libs/libapp#*", "sys-libs/libshared#*""#),
+    ///     ("tool", r#""sys-libs/libshared#*""#),
+    ///     ("libapp", ""),
+    ///     ("libshared", ""),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// for name in &["sys-libs/app", "sys-libs/tool"] {
+    ///     graph.node_add_requirement(
+    ///         graph.root_id(),
+    ///         RequirementKind::Package { package_req: PackageRequirement::parse(name)? },
+    ///         RequirementManagementMethod::Static,
+    ///     );
+    /// }
+    /// graph.solve(&config)?;
+    ///
+    /// // A package other packages depend on can't be removed
+    /// let error = graph.remove_package_cascade(&PackageFullName::parse("tests::sys-libs/libshared")?).unwrap_err();
+    /// assert_eq!(
+    ///     *error.downcast_ref::<DependencyGraphError>().unwrap().kind(),
+    ///     DependencyGraphErrorKind::PackageStillRequired,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     graph.remove_package_cascade(&PackageFullName::parse("tests::sys-libs/app")?)?,
+    ///     vec![PackageID::parse("tests::sys-libs/app#1.0.0")?, PackageID::parse("tests::sys-libs/libapp#1.0.0")?],
+    /// );
+    /// let mut remaining: Vec<_> = graph.packages().map(|name| name.to_string()).collect();
+    /// remaining.sort();
+    /// assert_eq!(remaining, vec!["tests::sys-libs/libshared", "tests::sys-libs/tool"]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_package_cascade(
+        &mut self,
+        name: &PackageFullName,
+    ) -> Result<Vec<PackageID>, Error> {
+        let node_id = self.get_package_node_id(name)?;
+
+        let mut dependents: Vec<_> = self.nodes[&node_id]
+            .dependents()
+            .iter()
+            .map(|requirement_id| self.requirements[requirement_id].fulfilled_node_id())
+            .filter_map(|parent_id| self.nodes[&parent_id].kind().package())
+            .map(ToString::to_string)
+            .collect();
+        if !dependents.is_empty() {
+            dependents.sort();
+            Err(format_err!(
+                "{} is required by {}",
+                name,
+                dependents.join(", ")
+            ))
+            .context(DependencyGraphErrorKind::PackageStillRequired)
+            .map_err(DependencyGraphError::from)?;
+        }
+
+        let packages = |graph: &Self| -> HashSet<PackageID> {
+            graph
+                .nodes
+                .values()
+                .filter_map(|node| node.kind().package().cloned())
+                .collect()
+        };
+        let before = packages(self);
+
+        self.remove_node(node_id);
+        self.remove_orphan_nodes();
+
+        let after = packages(self);
+        let mut removed: Vec<_> = before.difference(&after).cloned().collect();
+        removed.sort();
+        Ok(removed)
+    }
+
     fn remove_orphans_rec(&self, marks: &mut HashSet<NodeID>, node_id: NodeID) {
         if !marks.contains(&node_id) {
             marks.insert(node_id);
@@ -449,18 +2052,23 @@ impl<'lock_file> DependencyGraph<'lock_file> {
             .for_each(|node_id| self.remove_node(node_id));
     }
 
-    fn find_package_node_matching_name(&self, requirement: &PackageRequirement) -> Option<NodeID> {
+    /// Returns the nodes of the packages named like the given requirement, along with their slot
+    fn find_package_nodes_matching_name(
+        &self,
+        requirement: &PackageRequirement,
+    ) -> Vec<(Slot, NodeID)> {
         self.node_names
             .iter()
-            .find(|(node_name, _)| {
-                if let Some(full_name) = node_name.package_name() {
-                    requirement.name() == full_name.name()
-                        && requirement.category() == full_name.category()
-                } else {
-                    false
+            .filter_map(|(node_name, node_id)| match node_name {
+                NodeName::Package(full_name, slot)
+                    if requirement.name() == full_name.name()
+                        && requirement.category() == full_name.category() =>
+                {
+                    Some((slot.clone(), *node_id))
                 }
+                _ => None,
             })
-            .map(|(_, id)| *id)
+            .collect()
     }
 
     fn solve_package_requirement(
@@ -468,86 +2076,226 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         config: &Config,
         requirement: PackageRequirement,
     ) -> Result<NodeID, Error> {
-        // The list of requirements the package must fulfill.
-        let mut requirements = Vec::new();
-        let node_id_opt = self.find_package_node_matching_name(&requirement);
-
-        // Test whether a package with the same PackageFullName is already within the dependency graph
-        if let Some(package_node_id) = node_id_opt {
-            let node = &self.nodes[&package_node_id];
+        let nodes = self.find_package_nodes_matching_name(&requirement);
 
-            // Since a version of the package is already in the graph, test whether it matches the new requirement
-            if let NodeKind::Package { id } = node.kind() {
+        // Test whether a version of the package already within the dependency graph, in any slot,
+        // matches the new requirement
+        for (_, node_id) in &nodes {
+            if let Some(id) = self.nodes[node_id].kind().package() {
                 if requirement.matches(id) {
                     // If that's the case, we can stop here, as the requirement is already fulfilled
-                    return Ok(package_node_id);
+                    return Ok(*node_id);
                 }
             }
+        }
 
-            // At this point, a version of the package is already in the graph, but it does not match the new requirement.
-            // Therefore, the version of the package is going to change in order to solve the new requirement.
-            //
-            // However, the old requirements on the installed version of the package should be preserved,
-            // thus we add them to the requirements to fulfill.
+        let mut available_packages = config
+            .available_packages_cache_internal(self.phantom)
+            .query(&requirement.clone().any_version().into())
+            .set_strategy(AvailablePackagesCacheQueryStrategy::AllMatchesSorted)
+            .set_arch(config.install().arch())
+            .perform()?;
+
+        // Newest versions first. When the same version is available in multiple repositories, the one with
+        // the highest priority comes first, falling back to the order of preference of the repositories and
+        // then to their name.
+        available_packages.sort_by(|a, b| {
+            b.manifest()
+                .version()
+                .cmp(a.manifest().version())
+                .then_with(|| {
+                    repository_preference(config, a).cmp(&repository_preference(config, b))
+                })
+        });
+
+        // Nothing is named like the requirement: it may be a virtual package, provided by other packages
+        if nodes.is_empty() && available_packages.is_empty() {
+            return self.solve_provided_requirement(config, &requirement);
+        }
 
-            let requirement_kinds = node
-                .dependents()
+        // At this point, no version of the package in the graph matches the new requirement.
+        // The version solving it replaces the one in the same slot, if any, while versions in other slots
+        // are left untouched.
+        //
+        // However, the old requirements on the replaced version of the package should be preserved,
+        // thus we add them to the requirements to fulfill, along with the new requirement.
+        let requirements_in_slot = |slot: &Slot| -> Vec<PackageRequirement> {
+            nodes
                 .iter()
-                .map(|requirement_id| &self.requirements[requirement_id])
-                .map(|requirement| requirement.kind());
-
-            for requirement_kind in requirement_kinds {
-                if let RequirementKind::Package { package_req } = requirement_kind {
-                    requirements.push(package_req.clone());
-                }
-            }
-        }
+                .filter(|(node_slot, _)| node_slot == slot)
+                .flat_map(|(_, node_id)| self.nodes[node_id].dependents().iter())
+                .filter_map(
+                    |requirement_id| match self.requirements[requirement_id].kind() {
+                        RequirementKind::Package { package_req } => Some(package_req.clone()),
+                        _ => None,
+                    },
+                )
+                // Requirements on a virtual package provided by this one don't restrict its version
+                .filter(|package_req| {
+                    package_req.category() == requirement.category()
+                        && package_req.name() == requirement.name()
+                })
+                .chain(std::iter::once(requirement.clone()))
+                .collect()
+        };
 
-        // We add the new requirement to the requirements to fulfill
-        requirements.push(requirement.clone());
+        // A held package must keep the version it's held at within its slot.
+        // If the held version isn't available anymore, its slot is unknown, so it applies to all of them.
+        let held: Vec<(PackageID, Option<Slot>)> = self
+            .held
+            .iter()
+            .filter(|id| requirement.clone().any_version().matches(id))
+            .map(|id| {
+                let slot = available_packages
+                    .iter()
+                    .find(|package| package.id() == *id)
+                    .map(|package| package.manifest().slot().clone());
+                (id.clone(), slot)
+            })
+            .collect();
 
-        // Look for the newest version matching all the requirements
-        let find_matching_packages = || -> Result<Option<QueryResult>, Error> {
-            let available_packages = config
-                .available_packages_cache_internal(self.phantom)
-                .query(&requirement.clone().any_version().into())
-                .set_strategy(AvailablePackagesCacheQueryStrategy::AllMatchesSorted)
-                .perform_and_sort_by_preference(config);
+        // Look for the newest version matching all the requirements of its slot
+        let package = available_packages
+            .into_iter()
+            .find(|package| {
+                let id = package.id();
+                let slot = package.manifest().slot();
 
-            for package in available_packages? {
-                let is_valid = requirements
+                requirements_in_slot(slot)
                     .iter()
-                    .all(|requirement| requirement.matches(&package.id()));
-                if is_valid {
-                    return Ok(Some(package));
-                }
-            }
-            Ok(None)
-        };
-
-        let package = find_matching_packages()?.ok_or_else(|| {
-            format_err!("{}", requirement)
-                .context(DependencyGraphErrorKind::RequirementSolvingError)
-        })?;
+                    .all(|requirement| requirement.matches(&id))
+                    && held.iter().all(|(held_id, held_slot)| {
+                        *held_id == id || held_slot.as_ref().map_or(false, |held| held != slot)
+                    })
+            })
+            .ok_or_else(|| match held.first() {
+                // The held version is the only one that could be used, but it doesn't fit
+                Some((held, _)) => DependencyGraphError::from(
+                    format_err!("{} (held at {})", requirement, held)
+                        .context(DependencyGraphErrorKind::HeldPackageConflict),
+                )
+                .into(),
+                None => Error::from(
+                    format_err!("{}", requirement)
+                        .context(DependencyGraphErrorKind::RequirementSolvingError),
+                ),
+            })?;
+
+        // If a version of the package is already in the same slot, replace it if it's different
+        let slot = package.manifest().slot().clone();
+        let node_id_opt = nodes
+            .iter()
+            .find(|(node_slot, _)| *node_slot == slot)
+            .map(|(_, node_id)| *node_id);
 
-        // If the new version is different from the old one, remove the old one
         if let Some(node_id) = node_id_opt {
             let node = self.nodes.get_mut(&node_id).expect("invalid node id");
-            let id = package.id();
+            let kind = NodeKind::Package {
+                id: package.id(),
+                slot,
+            };
 
-            if (*node.kind() != NodeKind::Package { id: id.clone() }) {
-                *node.kind_mut() = NodeKind::Package { id };
+            if *node.kind() != kind {
+                *node.kind_mut() = kind;
                 node.requirements_mut().clear();
-                Ok(node_id)
-            } else {
-                Ok(node_id)
             }
+            Ok(node_id)
         } else {
             let node_id = self.add_package_node(package)?;
             Ok(node_id)
         }
     }
 
+    /// Returns the manifest of the given package from the cache of available packages, if it's still there
+    fn package_manifest(&self, config: &Config, id: &PackageID) -> Result<Option<Manifest>, Error> {
+        Ok(config
+            .available_packages_cache_internal(self.phantom)
+            .query(&SoftPackageRequirement::from_id(id))
+            .set_strategy(AvailablePackagesCacheQueryStrategy::AllMatchesUnsorted)
+            .perform()?
+            .into_iter()
+            .find(|result| result.id() == *id)
+            .map(|result| result.manifest().clone()))
+    }
+
+    /// Solves a requirement on a virtual package, that is, a package that doesn't exist on its own
+    /// but is provided by other packages.
+    ///
+    /// A package of the graph providing it is used in priority. Otherwise, the available providers are
+    /// sorted by the priority of their repository, then by version, and the first one is added to the graph.
+    ///
+    /// As providers are named differently than the virtual package, the version requirement applies to
+    /// their own version.
+    fn solve_provided_requirement(
+        &mut self,
+        config: &Config,
+        requirement: &PackageRequirement,
+    ) -> Result<NodeID, Error> {
+        let name =
+            PackageShortName::from(requirement.category().clone(), requirement.name().clone());
+        let provides = |manifest: &Manifest, id: &PackageID| {
+            manifest.provides().contains(&name)
+                && requirement
+                    .repository()
+                    .as_ref()
+                    .map_or(true, |repository| repository == id.repository())
+                && requirement.version_requirement().matches(id.version())
+        };
+
+        let mut packages: Vec<_> = self
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| node.kind().package().map(|id| (id.clone(), *node_id)))
+            .collect();
+        packages.sort();
+        for (id, node_id) in packages {
+            if let Some(manifest) = self.package_manifest(config, &id)? {
+                if provides(&manifest, &id) {
+                    return Ok(node_id);
+                }
+            }
+        }
+
+        let mut providers: Vec<_> = config
+            .available_packages_cache_internal(self.phantom)
+            .query_providers(&name)?
+            .into_iter()
+            .filter(|provider| provides(provider.manifest(), &provider.id()))
+            .filter(|provider| {
+                config
+                    .install()
+                    .arch()
+                    .map_or(true, |arch| provider.manifest().supports_arch(&arch))
+            })
+            .collect();
+        providers.sort_by(|a, b| {
+            let (a_preference, b_preference) = (
+                repository_preference(config, a),
+                repository_preference(config, b),
+            );
+            a_preference
+                .0
+                .cmp(&b_preference.0)
+                .then_with(|| b.manifest().version().cmp(a.manifest().version()))
+                .then_with(|| a_preference.cmp(&b_preference))
+                .then_with(|| a.full_name().cmp(&b.full_name()))
+        });
+
+        let provider = match providers.first() {
+            Some(provider) => provider.id(),
+            None => {
+                // Nothing provides it either, the name may be mistyped
+                let error = config
+                    .available_packages_cache_internal(self.phantom)
+                    .no_package_found(&requirement.clone().into());
+                return Err(Error::from(DependencyGraphError::from(
+                    error.context(DependencyGraphErrorKind::RequirementSolvingError),
+                )));
+            }
+        };
+        self.solve_package_requirement(config, PackageRequirement::from_id(&provider))
+    }
+
     /// Solves the requirement with the given ID
     pub fn solve_requirement(
         &mut self,
@@ -584,46 +2332,1068 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         Ok(())
     }
 
+    /// Solves the requirements of the given node, and then those of the nodes fulfilling them.
+    ///
+    /// `path` holds the nodes leading from the root to the given node: finding one of them among
+    /// the nodes fulfilling the requirements means there is a dependency cycle. A node depending on
+    /// itself isn't considered as a cycle though.
     fn solve_node(
         &mut self,
         config: &Config,
         node_id: NodeID,
         visited_nodes: &mut HashSet<NodeID>,
+        path: &mut Vec<NodeID>,
+        progress: &mut SolveProgress,
+        mut failures: Option<&mut Vec<(RequirementID, Error)>>,
     ) -> Result<(), Error> {
         let requirements = self.nodes[&node_id].requirements().clone();
 
-        // Solve all requirements
+        // Solve all requirements. Optional ones are skipped unless the configuration opts in, and are left
+        // unsolved if they can't be solved.
         for requirement_id in &requirements {
-            self.solve_requirement(config, *requirement_id)?;
+            let optional = self.requirements[requirement_id].management_method()
+                == RequirementManagementMethod::Optional;
+            if optional && !config.install().optional_dependencies() {
+                continue;
+            }
+            if let Err(e) = self.solve_requirement(config, *requirement_id) {
+                if !optional {
+                    record_failure(&mut failures, *requirement_id, e)?;
+                }
+            }
         }
 
-        // Repeat for each requirement's fulfilling node
+        // The nodes of the graph are those that were already solved before, and are likely to be solved again.
+        // Newly discovered nodes are added to the graph as their requirements are solved, growing the estimate.
+        progress.node_solved(self.nodes.len().max(visited_nodes.len() + 1));
+
+        path.push(node_id);
+
+        // Repeat for each requirement's fulfilling node. Requirements can only be left unsolved when failures
+        // are recorded instead of aborting.
         for requirement_id in &requirements {
-            let node_id = self.requirements[&requirement_id]
-                .fulfilling_node_id()
-                .expect("expected a fulfilling node after solving the dependent node");
-            if !visited_nodes.contains(&node_id) {
-                visited_nodes.insert(node_id);
-                self.solve_node(config, node_id, visited_nodes)?;
+            let child_id = match self.requirements[&requirement_id].fulfilling_node_id() {
+                Some(child_id) => *child_id,
+                None => continue,
+            };
+
+            if child_id != node_id {
+                if let Some(position) = path.iter().position(|id| *id == child_id) {
+                    let cycle = path[position..]
+                        .iter()
+                        .chain(std::iter::once(&child_id))
+                        .map(|id| self.nodes[id].to_string())
+                        .collect::<Vec<_>>();
+
+                    record_failure(
+                        &mut failures,
+                        *requirement_id,
+                        DependencyGraphError::from(
+                            format_err!("{}", cycle.join(" -> "))
+                                .context(DependencyGraphErrorKind::CyclicDependency),
+                        )
+                        .into(),
+                    )?;
+                    continue;
+                }
+            }
+
+            if !visited_nodes.contains(&child_id) {
+                visited_nodes.insert(child_id);
+                self.solve_node(
+                    config,
+                    child_id,
+                    visited_nodes,
+                    path,
+                    progress,
+                    failures.as_mut().map(|failures| &mut **failures),
+                )?;
             }
         }
+
+        path.pop();
         Ok(())
     }
 
     /// Solves the graph (attempts to fulfill every requirement)
+    ///
+    /// If some packages depend on each other, directly or not, a [`DependencyGraphError`] of kind
+    /// [`DependencyGraphErrorKind::CyclicDependency`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-cycle-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// // `chicken` and `egg` depend on each other
+    /// for (name, dependency) in &[("chicken", "egg"), ("egg", "chicken")] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": ["sys-libs/{}#*"] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependency,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("sys-libs/chicken")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    ///
+    /// let error = graph.solve(&config).unwrap_err();
+    /// let error = error.downcast_ref::<DependencyGraphError>().unwrap();
+    /// assert_eq!(*error.kind(), DependencyGraphErrorKind::CyclicDependency);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Versions of a package in different slots are installed side by side, while a version replaces the one
+    /// of the same slot:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-slots-solve-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let publish = |versions: &[(&str, &str)]| -> Result<(), failure::Error> {
+    ///     let versions: Vec<_> = versions
+    ///         .iter()
+    ///         .map(|(version, slot)| format!(
+    ///             r#""{}": {{ "slot": "{}", "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}"#,
+    ///             version, slot,
+    ///         ))
+    ///         .collect();
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gtk",
+    ///             "category": "x11-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{ {} }}
+    ///         }}"#,
+    ///         versions.join(", "),
+    ///     ))?;
+    ///     cache.update(&manifest)
+    /// };
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let require = |graph: &mut libnest::cache::depgraph::DependencyGraph, requirement: &str| {
+    ///     PackageRequirement::parse(requirement).map(|package_req| {
+    ///         graph.node_add_requirement(
+    ///             graph.root_id(),
+    ///             RequirementKind::Package { package_req },
+    ///             RequirementManagementMethod::Static,
+    ///         );
+    ///     })
+    /// };
+    /// let installed = |graph: &libnest::cache::depgraph::DependencyGraph| {
+    ///     let mut names: Vec<_> = graph
+    ///         .node_names()
+    ///         .iter()
+    ///         .filter(|(name, _)| name.package_name().is_some())
+    ///         .map(|(name, id)| format!("{} -> {}", name, graph.nodes()[id]))
+    ///         .collect();
+    ///     names.sort();
+    ///     names
+    /// };
+    ///
+    /// // `gtk#2` and `gtk#3` coexist
+    /// publish(&[("2.0.0", "2"), ("3.0.0", "3")])?;
+    /// require(&mut graph, "x11-libs/gtk#^2")?;
+    /// require(&mut graph, "x11-libs/gtk#^3")?;
+    /// graph.solve(&config)?;
+    /// assert_eq!(
+    ///     installed(&graph),
+    ///     vec![
+    ///         "tests::x11-libs/gtk@2 -> tests::x11-libs/gtk#2.0.0",
+    ///         "tests::x11-libs/gtk@3 -> tests::x11-libs/gtk#3.0.0",
+    ///     ],
+    /// );
+    ///
+    /// // `gtk#2.1.0` replaces `gtk#2.0.0`, and leaves `gtk#3` alone
+    /// publish(&[("2.0.0", "2"), ("2.1.0", "2"), ("3.0.0", "3")])?;
+    /// require(&mut graph, "x11-libs/gtk#>=2.1.0,<3.0.0")?;
+    /// graph.solve(&config)?;
+    /// assert_eq!(
+    ///     installed(&graph),
+    ///     vec![
+    ///         "tests::x11-libs/gtk@2 -> tests::x11-libs/gtk#2.1.0",
+    ///         "tests::x11-libs/gtk@3 -> tests::x11-libs/gtk#3.0.0",
+    ///     ],
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// When the same version is available in multiple repositories, the one with the highest priority is
+    /// used, even if it comes later in `repositories_order`:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config = toml::from_str(
+    ///     r#"
+    ///         repositories_order = ["stable", "beta"]
+    ///
+    ///         [repositories.stable]
+    ///         mirrors = []
+    ///
+    ///         [repositories.beta]
+    ///         mirrors = []
+    ///         priority = 10
+    ///     "#,
+    /// )?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-priority-solve-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for repository in &["stable", "beta"] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gcc",
+    ///             "category": "sys-devel",
+    ///             "repository": "{}",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "9.1.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         repository,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let root_id = graph.root_id();
+    /// graph.node_add_requirement(
+    ///     root_id,
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("sys-devel/gcc")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    ///
+    /// let installed: Vec<_> = graph
+    ///     .nodes()
+    ///     .values()
+    ///     .filter_map(|node| node.kind().package())
+    ///     .map(|id| id.to_string())
+    ///     .collect();
+    /// assert_eq!(installed, vec!["beta::sys-devel/gcc#9.1.0"]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Solving fails if a package of the resulting graph conflicts with another one:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::{DependencyGraphError, DependencyGraphErrorKind};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-conflicts-solve-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for (name, conflicts) in &[
+    ///     ("postfix", r#"["mail-mta/sendmail"]"#),
+    ///     ("sendmail", "[]"),
+    ///     ("mutt", "[]"),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "mail-mta",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{
+    ///                     "wrap_date": "2019-05-27T16:34:15Z",
+    ///                     "dependencies": [],
+    ///                     "conflicts": {}
+    ///                 }}
+    ///             }}
+    ///         }}"#,
+    ///         name, conflicts,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let graph_requiring = |names: &[&str]| -> Result<_, failure::Error> {
+    ///     let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    ///     let root_id = graph.root_id();
+    ///     for name in names {
+    ///         graph.node_add_requirement(
+    ///             root_id,
+    ///             RequirementKind::Package {
+    ///                 package_req: PackageRequirement::parse(&format!("mail-mta/{}", name))?,
+    ///             },
+    ///             RequirementManagementMethod::Static,
+    ///         );
+    ///     }
+    ///     Ok(graph)
+    /// };
+    ///
+    /// // Packages without any conflict between them are solved fine
+    /// graph_requiring(&["postfix", "mutt"])?.solve(&config)?;
+    ///
+    /// // `postfix` and `sendmail` can't be installed together
+    /// let mut graph = graph_requiring(&["postfix", "sendmail"])?;
+    /// let error = graph.solve(&config).unwrap_err();
+    /// let error = error.downcast_ref::<DependencyGraphError>().unwrap();
+    /// match *error.kind() {
+    ///     DependencyGraphErrorKind::Conflict { a, b } => {
+    ///         assert_eq!(graph.nodes()[&a].to_string(), "tests::mail-mta/postfix#1.0.0");
+    ///         assert_eq!(graph.nodes()[&b].to_string(), "tests::mail-mta/sendmail#1.0.0");
+    ///     }
+    ///     kind => panic!("unexpected error: {}", kind),
+    /// }
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// A requirement on a virtual package is fulfilled by a package providing it. When there are several
+    /// providers, the one from the repository with the highest priority is used, then the newest one:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-provides-solve-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for (name, version) in &[("gcc", "9.1.0"), ("clang", "8.0.0")] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-devel",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "{}": {{
+    ///                     "wrap_date": "2019-05-27T16:34:15Z",
+    ///                     "dependencies": [],
+    ///                     "provides": ["virtual/cc"]
+    ///                 }}
+    ///             }}
+    ///         }}"#,
+    ///         name, version,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let root_id = graph.root_id();
+    /// graph.node_add_requirement(
+    ///     root_id,
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("virtual/cc")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    ///
+    /// let installed: Vec<_> = graph
+    ///     .nodes()
+    ///     .values()
+    ///     .filter_map(|node| node.kind().package())
+    ///     .map(|id| id.to_string())
+    ///     .collect();
+    /// assert_eq!(installed, vec!["tests::sys-devel/gcc#9.1.0"]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Optional dependencies are only solved if the [configuration][1] opts in for them, and are skipped if they
+    /// can't be solved:
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-optional-solve-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `vim` optionally depends on `vim-plugins`, which is available, and on `vim-extras`, which isn't
+    /// for (name, optional_dependencies) in &[
+    ///     ("vim", r#"["tests::app-editors/vim-plugins", "tests::app-editors/vim-extras"]"#),
+    ///     ("vim-plugins", "[]"),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "app-editors",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{
+    ///                     "wrap_date": "2019-05-27T16:34:15Z",
+    ///                     "dependencies": [],
+    ///                     "optional_dependencies": {}
+    ///                 }}
+    ///             }}
+    ///         }}"#,
+    ///         name, optional_dependencies,
+    ///     ))?;
+    ///     config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    /// }
+    ///
+    /// let solved_packages = |config: &Config| -> Result<Vec<String>, failure::Error> {
+    ///     let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    ///     let root_id = graph.root_id();
+    ///     graph.node_add_requirement(
+    ///         root_id,
+    ///         RequirementKind::Package {
+    ///             package_req: PackageRequirement::parse("app-editors/vim")?,
+    ///         },
+    ///         RequirementManagementMethod::Static,
+    ///     );
+    ///     graph.solve(config)?;
+    ///     assert!(graph.unsolved_requirements().is_empty());
+    ///
+    ///     let mut packages: Vec<_> = graph
+    ///         .nodes()
+    ///         .values()
+    ///         .filter_map(|node| node.kind().package())
+    ///         .map(|id| id.to_string())
+    ///         .collect();
+    ///     packages.sort();
+    ///     Ok(packages)
+    /// };
+    ///
+    /// // By default, optional dependencies are left out
+    /// assert_eq!(solved_packages(&config)?, vec!["tests::app-editors/vim#1.0.0"]);
+    ///
+    /// // Once opted in, the available ones are installed
+    /// *config.install_mut().optional_dependencies_mut() = true;
+    /// assert_eq!(
+    ///     solved_packages(&config)?,
+    ///     vec!["tests::app-editors/vim#1.0.0", "tests::app-editors/vim-plugins#1.0.0"],
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [1]: ../../config/struct.InstallConfig.html#method.optional_dependencies
     pub fn solve(&mut self, config: &Config) -> Result<(), Error> {
-        self.solve_node(config, ROOT_ID, &mut HashSet::new())?;
+        self.solve_with_progress(config, |_, _| ())
+    }
+
+    /// Solves the graph like [`solve`][1], calling `progress` each time a node is solved with the number of
+    /// nodes solved so far and an estimate of the total number of nodes to solve.
+    ///
+    /// As nodes are discovered while the graph is solved, the estimate starts with the number of nodes of the
+    /// graph, and grows as new packages are required. It never decreases while the nodes are solved, and is
+    /// never lower than the number of solved nodes. Once the graph is solved, `progress` is called a last time
+    /// with both numbers equal to the number of solved nodes, so that a progress bar ends up full.
+    ///
+    /// [1]: #method.solve
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-solve-progress-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for (name, dependencies) in &[
+    ///     ("glibc", ""),
+    ///     ("ncurses", r#""sys-libs/glibc#*""#),
+    ///     ("bash", r#""sys-libs/glibc#*", "sys-libs/ncurses#*""#),
+    /// ] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [{}] }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package { package_req: PackageRequirement::parse("sys-libs/bash")? },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    ///
+    /// let mut reports = Vec::new();
+    /// graph.solve_with_progress(&config, |solved, total| reports.push((solved, total)))?;
+    ///
+    /// // The root group and each of the three packages are solved, and the last report is complete
+    /// assert!(reports.len() >= 4);
+    /// assert_eq!(reports.last(), Some(&(4, 4)));
+    /// assert!(reports.iter().all(|(solved, total)| solved <= total));
+    /// assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn solve_with_progress<F>(&mut self, config: &Config, mut progress: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        let mut solve_progress = SolveProgress {
+            solved: 0,
+            total: self.nodes.len(),
+            callback: &mut progress,
+        };
+        self.solve_node(
+            config,
+            ROOT_ID,
+            &mut HashSet::new(),
+            &mut Vec::new(),
+            &mut solve_progress,
+            None,
+        )?;
+        let solved = solve_progress.solved;
+
         self.remove_orphan_nodes();
+        progress(solved, solved);
+        self.check_conflicts(config)
+    }
+
+    /// Solves the graph like [`solve`][1], but attempts to solve every requirement instead of stopping at the
+    /// first one that can't be, and returns the requirements that couldn't be solved along with the reason why.
+    ///
+    /// The requirements that couldn't be solved are left unsolved in the graph, and the nodes they would have
+    /// brought aren't solved either. The conflicts between the packages of the graph are still checked once
+    /// it's solved, returning an error if there is any.
+    ///
+    /// [1]: #method.solve
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-best-effort-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "coreutils",
+    ///         "category": "sys-apps",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "8.31.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let mut require = |requirement: &str| -> Result<_, failure::Error> {
+    ///     Ok(graph.node_add_requirement(
+    ///         graph.root_id(),
+    ///         RequirementKind::Package { package_req: PackageRequirement::parse(requirement)? },
+    ///         RequirementManagementMethod::Static,
+    ///     ))
+    /// };
+    /// let coreutils = require("sys-apps/coreutils")?;
+    /// let emacs = require("app-editors/emacs")?;
+    /// let old_coreutils = require("sys-apps/coreutils#^9.0.0")?;
+    ///
+    /// // Both unsatisfiable requirements are reported, and the other one is solved anyway
+    /// let failures = graph.solve_best_effort(&config)?;
+    /// let mut failed: Vec<_> = failures.iter().map(|(requirement_id, _)| *requirement_id).collect();
+    /// failed.sort();
+    /// assert_eq!(failed, vec![emacs, old_coreutils]);
+    /// assert!(graph.requirements()[&coreutils].fulfilling_node_id().is_some());
+    ///
+    /// let unsolved: Vec<_> = graph
+    ///     .unsolved_requirements()
+    ///     .into_iter()
+    ///     .map(|(requirement_id, _)| requirement_id)
+    ///     .collect();
+    /// assert_eq!(unsolved, vec![emacs, old_coreutils]);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn solve_best_effort(
+        &mut self,
+        config: &Config,
+    ) -> Result<Vec<(RequirementID, Error)>, Error> {
+        let mut failures = Vec::new();
+        let mut progress = |_, _| ();
+        let mut solve_progress = SolveProgress {
+            solved: 0,
+            total: self.nodes.len(),
+            callback: &mut progress,
+        };
+        self.solve_node(
+            config,
+            ROOT_ID,
+            &mut HashSet::new(),
+            &mut Vec::new(),
+            &mut solve_progress,
+            Some(&mut failures),
+        )?;
+
+        self.remove_orphan_nodes();
+        self.check_conflicts(config)?;
+        Ok(failures)
+    }
+
+    /// Returns the requirements that aren't fulfilled by any node, along with their kind, sorted by ID.
+    ///
+    /// Optional requirements aren't returned, as they are allowed to be left unsolved.
+    pub fn unsolved_requirements(&self) -> Vec<(RequirementID, RequirementKind)> {
+        let mut unsolved: Vec<_> = self
+            .requirements
+            .iter()
+            .filter(|(_, requirement)| {
+                requirement.fulfilling_node_id().is_none()
+                    && requirement.management_method() != RequirementManagementMethod::Optional
+            })
+            .map(|(requirement_id, requirement)| (*requirement_id, requirement.kind().clone()))
+            .collect();
+        unsolved.sort_by_key(|(requirement_id, _)| *requirement_id);
+        unsolved
+    }
+
+    /// Checks that the graph is consistent, returning a [`DependencyGraphError`] of kind
+    /// [`DependencyGraphErrorKind::CorruptedGraph`] describing the first violation found otherwise.
+    ///
+    /// The graph is consistent if:
+    /// - the root group exists
+    /// - every requirement held or fulfilled by a node exists, and links back to that node
+    /// - every requirement is held by a live node, and is fulfilled by a live node if it's solved
+    /// - every name maps to a live node of the same name, and every node has a name
+    /// - the IDs of new nodes and requirements don't collide with existing ones
+    ///
+    /// [`DependencyGraphErrorKind::CorruptedGraph`]: ../enum.DependencyGraphErrorKind.html#variant.CorruptedGraph
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{DependencyGraph, NodeKind, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::DependencyGraphErrorKind;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-check-invariants-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "coreutils",
+    ///         "category": "sys-apps",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "8.31.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Package { package_req: PackageRequirement::parse("sys-apps/coreutils")? },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.solve(&config)?;
+    /// graph.check_invariants()?;
+    ///
+    /// let name = PackageFullName::parse("tests::sys-apps/coreutils")?;
+    /// let node_id = graph.get_package_node_id(&name)?;
+    /// let violation = |corrupt: &dyn Fn(&mut DependencyGraph)| {
+    ///     let mut graph = graph.clone();
+    ///     corrupt(&mut graph);
+    ///     let error = graph.check_invariants().unwrap_err();
+    ///     assert_eq!(*error.kind(), DependencyGraphErrorKind::CorruptedGraph);
+    ///     failure::Fail::cause(&error).unwrap().to_string()
+    /// };
+    ///
+    /// // A node holds a requirement that doesn't exist
+    /// let message = violation(&|graph| {
+    ///     graph.get_package_node_mut(&name).unwrap().requirements_mut().insert(42);
+    /// });
+    /// assert_eq!(message, format!("node {} holds requirement 42, which doesn't exist", node_id));
+    ///
+    /// // A node is fulfilled by a requirement that doesn't exist
+    /// let message = violation(&|graph| {
+    ///     graph.get_package_node_mut(&name).unwrap().dependents_mut().insert(42);
+    /// });
+    /// assert_eq!(message, format!("node {} is fulfilled by requirement 42, which doesn't exist", node_id));
+    ///
+    /// // A node isn't what its name says
+    /// let message = violation(&|graph| {
+    ///     *graph.get_package_node_mut(&name).unwrap().kind_mut() = NodeKind::Group { name: "@other".parse().unwrap() };
+    /// });
+    /// assert_eq!(message, format!("node {} (@other) has no name", node_id));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), DependencyGraphError> {
+        let corrupted = |message: String| {
+            Err(DependencyGraphError::from(
+                format_err!("{}", message).context(DependencyGraphErrorKind::CorruptedGraph),
+            ))
+        };
+
+        if !self.nodes.contains_key(&ROOT_ID) {
+            return corrupted(format!("the root group (node {}) doesn't exist", ROOT_ID));
+        }
+
+        let mut node_ids: Vec<_> = self.nodes.keys().collect();
+        node_ids.sort();
+        for node_id in node_ids {
+            let node = &self.nodes[node_id];
+            if *node_id >= self.next_node_id {
+                return corrupted(format!(
+                    "node {} collides with the next node ID ({})",
+                    node_id, self.next_node_id
+                ));
+            }
+
+            let mut requirement_ids: Vec<_> = node.requirements().iter().collect();
+            requirement_ids.sort();
+            for requirement_id in requirement_ids {
+                match self.requirements.get(requirement_id) {
+                    None => {
+                        return corrupted(format!(
+                            "node {} holds requirement {}, which doesn't exist",
+                            node_id, requirement_id
+                        ))
+                    }
+                    Some(requirement) if requirement.fulfilled_node_id() != *node_id => {
+                        return corrupted(format!(
+                            "node {} holds requirement {}, which belongs to node {}",
+                            node_id,
+                            requirement_id,
+                            requirement.fulfilled_node_id()
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+
+            let mut dependent_ids: Vec<_> = node.dependents().iter().collect();
+            dependent_ids.sort();
+            for requirement_id in dependent_ids {
+                match self.requirements.get(requirement_id) {
+                    None => {
+                        return corrupted(format!(
+                            "node {} is fulfilled by requirement {}, which doesn't exist",
+                            node_id, requirement_id
+                        ))
+                    }
+                    Some(requirement) if *requirement.fulfilling_node_id() != Some(*node_id) => {
+                        return corrupted(format!(
+                            "node {} is fulfilled by requirement {}, which isn't fulfilled by it",
+                            node_id, requirement_id
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+
+            let name = NodeName::from(node.kind().clone());
+            if self.node_names.get(&name) != Some(node_id) {
+                return corrupted(format!("node {} ({}) has no name", node_id, name));
+            }
+        }
+
+        let mut requirement_ids: Vec<_> = self.requirements.keys().collect();
+        requirement_ids.sort();
+        for requirement_id in requirement_ids {
+            let requirement = &self.requirements[requirement_id];
+            if *requirement_id >= self.next_requirement_id {
+                return corrupted(format!(
+                    "requirement {} collides with the next requirement ID ({})",
+                    requirement_id, self.next_requirement_id
+                ));
+            }
+
+            let fulfilled_id = requirement.fulfilled_node_id();
+            match self.nodes.get(&fulfilled_id) {
+                Some(node) if node.requirements().contains(requirement_id) => (),
+                Some(_) => {
+                    return corrupted(format!(
+                        "requirement {} belongs to node {}, which doesn't hold it",
+                        requirement_id, fulfilled_id
+                    ))
+                }
+                None => {
+                    return corrupted(format!(
+                        "requirement {} belongs to node {}, which doesn't exist",
+                        requirement_id, fulfilled_id
+                    ))
+                }
+            }
+
+            if let Some(fulfilling_id) = requirement.fulfilling_node_id() {
+                match self.nodes.get(fulfilling_id) {
+                    Some(node) if node.dependents().contains(requirement_id) => (),
+                    Some(_) => {
+                        return corrupted(format!(
+                            "requirement {} is fulfilled by node {}, which doesn't list it",
+                            requirement_id, fulfilling_id
+                        ))
+                    }
+                    None => {
+                        return corrupted(format!(
+                            "requirement {} is fulfilled by node {}, which doesn't exist",
+                            requirement_id, fulfilling_id
+                        ))
+                    }
+                }
+            }
+        }
+
+        let mut names: Vec<_> = self.node_names.iter().collect();
+        names.sort_by_key(|(name, _)| name.to_string());
+        for (name, node_id) in names {
+            match self.nodes.get(node_id) {
+                None => {
+                    return corrupted(format!(
+                        "{} is the name of node {}, which doesn't exist",
+                        name, node_id
+                    ))
+                }
+                Some(node) if NodeName::from(node.kind().clone()) != *name => {
+                    return corrupted(format!(
+                        "{} is the name of node {}, which is named {}",
+                        name,
+                        node_id,
+                        NodeName::from(node.kind().clone())
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no package of the graph conflicts with another one, returning a [`DependencyGraphError`]
+    /// of kind [`DependencyGraphErrorKind::Conflict`] otherwise.
+    ///
+    /// A package conflicts with another one if any of its conflict requirements matches the other package.
+    /// Packages whose manifest isn't in the cache of available packages anymore don't have any conflict.
+    fn check_conflicts(&self, config: &Config) -> Result<(), Error> {
+        let cache = config.available_packages_cache_internal(self.phantom);
+
+        // Gather the packages of the graph and the conflicts of those that have some in a single pass
+        let mut packages = Vec::new();
+        let mut conflicts = Vec::new();
+        for (node_id, node) in &self.nodes {
+            if let Some(id) = node.kind().package() {
+                let requirements = cache.package_conflicts(id)?;
+                if !requirements.is_empty() {
+                    conflicts.push((id, *node_id, requirements));
+                }
+                packages.push((id, *node_id));
+            }
+        }
+        packages.sort();
+        conflicts.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+        for (id, node_id, requirements) in &conflicts {
+            let conflicting = packages.iter().find(|(other, _)| {
+                other != id && requirements.iter().any(|req| req.matches(other))
+            });
+
+            if let Some((other, other_node_id)) = conflicting {
+                return Err(DependencyGraphError::from(
+                    format_err!("{} conflicts with {}", id, other).context(
+                        DependencyGraphErrorKind::Conflict {
+                            a: *node_id,
+                            b: *other_node_id,
+                        },
+                    ),
+                )
+                .into());
+            }
+        }
         Ok(())
     }
 
     /// Updates the graph by removing automatic requirements, and solving again
     pub fn update(&mut self, config: &Config) -> Result<(), Error> {
-        // First, remove auto requirements. Static requirements against packages are set as unsolved.
+        // First, remove auto and optional requirements. Static requirements against packages are set as unsolved.
         let mut marks = HashSet::new();
         for (requirement_id, requirement) in &mut self.requirements {
             match requirement.management_method() {
-                RequirementManagementMethod::Auto => {
+                RequirementManagementMethod::Auto | RequirementManagementMethod::Optional => {
                     marks.insert(*requirement_id);
                 }
                 RequirementManagementMethod::Static => {
@@ -648,4 +3418,158 @@ impl<'lock_file> DependencyGraph<'lock_file> {
         // Solve the graph
         self.solve(config)
     }
+
+    /// Updates the graph like [`update`], but keeps the given packages at their current version in every slot
+    /// they are installed in, as if they were held for the duration of the update.
+    ///
+    /// The dependencies of the ignored packages are still updated. Ignored packages that aren't in the graph
+    /// are skipped.
+    ///
+    /// [`update`]: #method.update
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::collections::HashSet;
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-update-ignored-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let publish = |name: &str, versions: &[&str]| -> Result<(), failure::Error> {
+    ///     let versions: Vec<_> = versions
+    ///         .iter()
+    ///         .map(|version| format!(r#""{}": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}"#, version))
+    ///         .collect();
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-apps",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{ {} }}
+    ///         }}"#,
+    ///         name,
+    ///         versions.join(", "),
+    ///     ))?;
+    ///     cache.update(&manifest)
+    /// };
+    ///
+    /// publish("systemd", &["1.0.0"])?;
+    /// publish("coreutils", &["1.0.0"])?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// for name in &["systemd", "coreutils"] {
+    ///     graph.node_add_requirement(
+    ///         graph.root_id(),
+    ///         RequirementKind::Package { package_req: PackageRequirement::parse(&format!("sys-apps/{}", name))? },
+    ///         RequirementManagementMethod::Static,
+    ///     );
+    /// }
+    /// graph.solve(&config)?;
+    ///
+    /// // Newer versions of both packages are available, but systemd is left out of this update
+    /// publish("systemd", &["1.0.0", "2.0.0"])?;
+    /// publish("coreutils", &["1.0.0", "2.0.0"])?;
+    /// let systemd = PackageFullName::parse("tests::sys-apps/systemd")?;
+    /// let ignored: HashSet<_> = vec![systemd.clone()].into_iter().collect();
+    /// graph.update_with_ignored(&config, &ignored)?;
+    ///
+    /// let mut installed: Vec<_> = graph
+    ///     .nodes()
+    ///     .values()
+    ///     .filter_map(|node| node.kind().package())
+    ///     .map(|id| id.to_string())
+    ///     .collect();
+    /// installed.sort();
+    /// assert_eq!(installed, vec!["tests::sys-apps/coreutils#2.0.0", "tests::sys-apps/systemd#1.0.0"]);
+    ///
+    /// // The package isn't held once the update is over
+    /// assert!(graph.held().is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_with_ignored(
+        &mut self,
+        config: &Config,
+        ignored: &HashSet<PackageFullName>,
+    ) -> Result<(), Error> {
+        let held = self.held.clone();
+        for name in ignored {
+            let ids = self.installed_versions(name);
+            self.held.extend(ids);
+        }
+
+        let res = self.update(config);
+        self.held = held;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+
+    use crate::cache::depgraph::GroupName;
+    use crate::config::Config;
+
+    #[test]
+    fn interrupted_save_leaves_the_saved_graph_intact() -> Result<(), failure::Error> {
+        let mut config: Config = toml::from_str("")?;
+        let root =
+            std::env::temp_dir().join(format!("nest-test-atomic-save-{}", std::process::id()));
+        *config.paths_mut() = config.paths().chroot(&root);
+
+        let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+        let path = config.paths().depgraph();
+        let mut graph = config.dependency_graph(&lock_file_ownership)?;
+        graph.save_to_cache(path, &lock_file_ownership)?;
+        let saved = fs::read_to_string(path)?;
+
+        // Simulate a crash right before the new graph replaces the saved one
+        let group_name: GroupName = "@devel".parse()?;
+        graph.add_group_node(group_name.clone())?;
+        let mut written_tmp_path = None;
+        let res = graph.save_to_cache_with(path, &lock_file_ownership, |tmp_path| {
+            assert_eq!(tmp_path.parent(), path.parent());
+            assert!(tmp_path.is_file());
+            written_tmp_path = Some(tmp_path.to_path_buf());
+            Err(io::Error::new(io::ErrorKind::Other, "simulated crash"))
+        });
+        assert!(res.is_err());
+
+        // The saved graph is intact, and no temporary file is left behind
+        assert_eq!(fs::read_to_string(path)?, saved);
+        assert!(!written_tmp_path.unwrap().exists());
+        assert!(config
+            .dependency_graph(&lock_file_ownership)?
+            .get_group_node_id(&group_name)
+            .is_err());
+
+        graph.save_to_cache(path, &lock_file_ownership)?;
+        assert!(config
+            .dependency_graph(&lock_file_ownership)?
+            .get_group_node_id(&group_name)
+            .is_ok());
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
 }
@@ -4,10 +4,14 @@ mod diff;
 mod graph;
 mod node;
 mod requirement;
+mod selection;
+mod statistics;
 
 pub use self::diff::DependencyGraphDiff;
-pub use self::graph::DependencyGraph;
+pub use self::graph::{DependencyGraph, GraphSnapshot};
 pub use self::node::{GroupName, NodeID, NodeKind, NodeName};
 pub use self::requirement::{
     Requirement, RequirementID, RequirementKind, RequirementManagementMethod,
 };
+pub use self::selection::SelectionManifest;
+pub use self::statistics::GraphStatistics;
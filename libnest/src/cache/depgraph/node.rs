@@ -6,7 +6,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::package::{PackageFullName, PackageID};
+use crate::package::{PackageFullName, PackageID, Slot};
 
 use super::super::errors::{GroupNameError, GroupNameErrorKind};
 use super::RequirementID;
@@ -68,6 +68,9 @@ pub enum NodeKind {
     Package {
         /// The [`PackageID`] of this node.
         id: PackageID,
+        /// The [`Slot`] of the package. Versions of a package in different slots have different nodes.
+        #[serde(default)]
+        slot: Slot,
     },
 }
 
@@ -83,12 +86,21 @@ impl NodeKind {
 
     /// Retrieves the [`PackageID`] if the node kind describes a package
     pub fn package(&self) -> Option<&PackageID> {
-        if let Self::Package { id } = self {
+        if let Self::Package { id, .. } = self {
             Some(id)
         } else {
             None
         }
     }
+
+    /// Retrieves the [`Slot`] if the node kind describes a package
+    pub fn slot(&self) -> Option<&Slot> {
+        if let Self::Package { slot, .. } = self {
+            Some(slot)
+        } else {
+            None
+        }
+    }
 }
 
 /// A node of the dependency graph.
@@ -167,14 +179,17 @@ impl std::fmt::Display for Node {
     }
 }
 
-/// The name of a node (that is, the [`GroupName`] or the [`PackageFullName`] for this node)
+/// The name of a node (that is, the [`GroupName`] or the [`PackageFullName`] and [`Slot`] for this node)
+///
+/// Packages in the default slot are named after their [`PackageFullName`], while the others have their
+/// slot appended to it, after an `@` (like `stable::x11-libs/gtk@3`).
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum NodeName {
     /// The node name describes a group
     Group(GroupName),
 
-    /// The node name describes a package
-    Package(PackageFullName),
+    /// The node name describes a package in a given slot
+    Package(PackageFullName, Slot),
 }
 
 impl NodeName {
@@ -189,19 +204,33 @@ impl NodeName {
 
     /// Retrieves the [`PackageFullName`] if the node name describes a package
     pub fn package_name(&self) -> Option<&PackageFullName> {
-        if let NodeName::Package(full_name) = self {
+        if let NodeName::Package(full_name, _) = self {
             Some(full_name)
         } else {
             None
         }
     }
+
+    /// Retrieves the [`Slot`] if the node name describes a package
+    pub fn slot(&self) -> Option<&Slot> {
+        if let NodeName::Package(_, slot) = self {
+            Some(slot)
+        } else {
+            None
+        }
+    }
 }
 
 impl std::fmt::Display for NodeName {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
             NodeName::Group(group_name) => f.write_str(group_name.as_str()),
-            NodeName::Package(full_name) => f.write_fmt(format_args!("{}", full_name)),
+            NodeName::Package(full_name, slot) if slot.is_empty() => {
+                f.write_fmt(format_args!("{}", full_name))
+            }
+            NodeName::Package(full_name, slot) => {
+                f.write_fmt(format_args!("{}@{}", full_name, slot))
+            }
         }
     }
 }
@@ -210,7 +239,7 @@ impl From<NodeKind> for NodeName {
     fn from(kind: NodeKind) -> Self {
         match kind {
             NodeKind::Group { name } => NodeName::Group(name),
-            NodeKind::Package { id } => NodeName::Package(id.into()),
+            NodeKind::Package { id, slot } => NodeName::Package(id.into(), slot),
         }
     }
 }
@@ -223,7 +252,7 @@ impl From<GroupName> for NodeName {
 
 impl From<PackageFullName> for NodeName {
     fn from(full_name: PackageFullName) -> Self {
-        NodeName::Package(full_name)
+        NodeName::Package(full_name, Slot::default())
     }
 }
 
@@ -234,7 +263,7 @@ impl serde::Serialize for NodeName {
     {
         match self {
             NodeName::Group(name) => serializer.serialize_str(name),
-            NodeName::Package(full_name) => full_name.serialize(serializer),
+            NodeName::Package(..) => self.to_string().serialize(serializer),
         }
     }
 }
@@ -255,16 +284,24 @@ impl<'de> serde::de::Visitor<'de> for NodeNameDeserializeVisitor {
         E: serde::de::Error,
     {
         match value.chars().next() {
-            Some('@') => GroupName::from_str(value).map(NodeName::Group).map_err(|_| {
-                E::custom(
-                    "the group's name doesn't follow the convention `@name`",
-                )
-            }),
-            _ => PackageFullName::from_str(value).map(NodeName::Package).map_err(|_| {
-                E::custom(
-                    "the package's full name doesn't follow the convention `repository::category/name`",
-                )
-            }),
+            Some('@') => GroupName::from_str(value)
+                .map(NodeName::Group)
+                .map_err(|_| E::custom("the group's name doesn't follow the convention `@name`")),
+            _ => {
+                let (full_name, slot) = match value.rfind('@') {
+                    Some(pos) => (&value[..pos], Slot::parse(&value[pos + 1..])),
+                    None => (value, Ok(Slot::default())),
+                };
+                let slot = slot.map_err(|_| E::custom("the slot value isn't valid"))?;
+
+                PackageFullName::from_str(full_name)
+                    .map(|full_name| NodeName::Package(full_name, slot))
+                    .map_err(|_| {
+                        E::custom(
+                            "the package's full name doesn't follow the convention `repository::category/name`",
+                        )
+                    })
+            }
         }
     }
 }
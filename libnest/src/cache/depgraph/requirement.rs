@@ -65,6 +65,12 @@ impl Requirement {
         &self.kind
     }
 
+    /// Returns a mutable reference to the kind of this requirement
+    #[inline]
+    pub fn kind_mut(&mut self) -> &mut RequirementKind {
+        &mut self.kind
+    }
+
     /// Returns the requirement method for this requirement
     #[inline]
     pub fn management_method(&self) -> RequirementManagementMethod {
@@ -98,4 +104,8 @@ pub enum RequirementManagementMethod {
 
     /// Static
     Static,
+
+    /// Optional: the requirement is only solved if the configuration opts in for optional dependencies,
+    /// and is left unsolved if it can't be
+    Optional,
 }
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::package::PackageID;
+
+use super::{GroupName, RequirementKind};
+
+/// The version of the format of [`SelectionManifest`]s written by this version of Nest
+pub(crate) const SELECTION_VERSION: u32 = 1;
+
+/// The packages selected by the user, in a portable format.
+///
+/// Unlike a [`DependencyGraph`], it doesn't hold any solved node: only the groups, the static requirements they
+/// hold and the held packages. It's obtained through [`DependencyGraph::export_selection`], and used to select
+/// the same packages on another system with [`Config::apply_selection`].
+///
+/// [`DependencyGraph`]: struct.DependencyGraph.html
+/// [`DependencyGraph::export_selection`]: struct.DependencyGraph.html#method.export_selection
+/// [`Config::apply_selection`]: ../../config/struct.Config.html#method.apply_selection
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct SelectionManifest {
+    version: u32,
+    groups: BTreeMap<GroupName, Vec<RequirementKind>>,
+    #[serde(default)]
+    held: Vec<PackageID>,
+}
+
+impl SelectionManifest {
+    pub(crate) fn from(
+        groups: BTreeMap<GroupName, Vec<RequirementKind>>,
+        held: Vec<PackageID>,
+    ) -> Self {
+        SelectionManifest {
+            version: SELECTION_VERSION,
+            groups,
+            held,
+        }
+    }
+
+    /// Returns the version of the format of the selection
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the groups of the selection, including the root group, along with the requirements they hold
+    #[inline]
+    pub fn groups(&self) -> &BTreeMap<GroupName, Vec<RequirementKind>> {
+        &self.groups
+    }
+
+    /// Returns the held packages of the selection, with the version they are held at
+    #[inline]
+    pub fn held(&self) -> &[PackageID] {
+        &self.held
+    }
+}
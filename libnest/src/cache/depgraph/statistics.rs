@@ -0,0 +1,64 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// A summary of the content of a [`DependencyGraph`], obtained through [`DependencyGraph::statistics`].
+///
+/// [`DependencyGraph`]: struct.DependencyGraph.html
+/// [`DependencyGraph::statistics`]: struct.DependencyGraph.html#method.statistics
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct GraphStatistics {
+    explicit_packages: usize,
+    auto_packages: usize,
+    groups: usize,
+    requirements: usize,
+    orphans: usize,
+}
+
+impl GraphStatistics {
+    pub(crate) fn from(
+        explicit_packages: usize,
+        auto_packages: usize,
+        groups: usize,
+        requirements: usize,
+        orphans: usize,
+    ) -> Self {
+        GraphStatistics {
+            explicit_packages,
+            auto_packages,
+            groups,
+            requirements,
+            orphans,
+        }
+    }
+
+    /// Returns the number of packages fulfilling a static requirement, which were explicitly installed by the user
+    #[inline]
+    pub fn explicit_packages(&self) -> usize {
+        self.explicit_packages
+    }
+
+    /// Returns the number of packages that were only pulled automatically, like dependencies
+    #[inline]
+    pub fn auto_packages(&self) -> usize {
+        self.auto_packages
+    }
+
+    /// Returns the number of groups, not counting the root group
+    #[inline]
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /// Returns the total number of requirements, solved or not
+    #[inline]
+    pub fn requirements(&self) -> usize {
+        self.requirements
+    }
+
+    /// Returns the number of [orphan packages][1]
+    ///
+    /// [1]: struct.DependencyGraph.html#method.orphans
+    #[inline]
+    pub fn orphans(&self) -> usize {
+        self.orphans
+    }
+}
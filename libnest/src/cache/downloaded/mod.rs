@@ -1,15 +1,14 @@
 //! Module to query and manipulate the cache of downloaded packages
 
+use std::collections::HashSet;
 use std::fs;
+use std::io;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
-use data_encoding::HEXUPPER;
-use sha2::{Digest, Sha256};
-
 use crate::cache::{CacheError, CacheErrorKind};
 use crate::lock_file::LockFileOwnership;
-use crate::package::{NPFExplorationError, NPFExplorer, PackageID};
+use crate::package::{HashAlgorithm, Manifest, NPFExplorationError, NPFExplorer, PackageID};
 
 /// Structure representing the cache of downloaded packages
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -42,27 +41,164 @@ impl<'cache_root, 'lock_file> DownloadedPackages<'cache_root, 'lock_file> {
         self.package_path(package).exists()
     }
 
-    /// Checks whether a given package has already been downloaded and matches a given hash
-    pub fn has_package_matching_hash(
+    /// Returns the sub-directories of the given directory, along with their names, or nothing if it doesn't exist
+    fn sub_directories(path: &Path) -> Result<Vec<(PathBuf, String)>, io::Error> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut directories = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Ok(name) = entry.file_name().into_string() {
+                    directories.push((entry.path(), name));
+                }
+            }
+        }
+        Ok(directories)
+    }
+
+    /// Removes the given directory if it's empty
+    fn remove_dir_if_empty(path: &Path) -> Result<(), io::Error> {
+        if fs::read_dir(path)?.next().is_none() {
+            fs::remove_dir(path)?;
+        }
+        Ok(())
+    }
+
+    /// Computes the hash of a downloaded package with the given algorithm, or `None` if it hasn't been downloaded
+    fn package_hash(
         &self,
         package: &PackageID,
-        hash: &str,
-    ) -> Result<bool, CacheError> {
+        algorithm: HashAlgorithm,
+    ) -> Result<Option<String>, CacheError> {
         let package_path = self.package_path(package);
 
         if !package_path.exists() {
-            return Ok(false);
+            return Ok(None);
         }
 
         fs::File::open(package_path)
-            .and_then(|mut file| {
-                let mut sha256 = Sha256::default();
-                std::io::copy(&mut file, &mut sha256)
-                    .map(|_| HEXUPPER.encode(sha256.result().as_ref()) == hash)
-            })
+            .and_then(|file| algorithm.digest(file).map(Some))
             .map_err(|_| CacheError::from(CacheErrorKind::CacheLoadError))
     }
 
+    /// Checks whether a given package has already been downloaded and matches a given hash, computed with
+    /// the given algorithm
+    pub fn has_package_matching_hash(
+        &self,
+        package: &PackageID,
+        hash: &str,
+        algorithm: HashAlgorithm,
+    ) -> Result<bool, CacheError> {
+        Ok(self
+            .package_hash(package, algorithm)?
+            .map_or(false, |local| local == hash))
+    }
+
+    /// Checks whether a given package has already been downloaded and matches the hash carried by its manifest.
+    ///
+    /// Unlike a hash issued by the server alongside the package, the hash of the manifest comes from the
+    /// repository's metadata, so a mirror can't tamper with both at once.
+    ///
+    /// Returns `false` if the package hasn't been downloaded, if it doesn't match the hash, or if the
+    /// manifest doesn't carry any hash.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::{Manifest, PackageManifest, RepositoryName};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-verify-hash-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let manifest_with = |hash: &str| -> Result<Manifest, failure::Error> {
+    ///     let package_manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "hello",
+    ///             "category": "sys-apps",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] {} }}
+    ///             }}
+    ///         }}"#,
+    ///         hash,
+    ///     ))?;
+    ///     let manifest = package_manifest.iter_manifests().next().unwrap();
+    ///     Ok(manifest)
+    /// };
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let downloaded = config.downloaded_packages_cache(&lock_file_ownership);
+    /// let manifest = manifest_with(
+    ///     r#", "hash": "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824""#,
+    /// )?;
+    /// let id = manifest.id(RepositoryName::parse("tests")?);
+    ///
+    /// // The package hasn't been downloaded yet
+    /// assert!(!downloaded.verify_against_manifest(&id, &manifest)?);
+    ///
+    /// let npf_path = config.paths().downloaded().join("tests/sys-apps/hello/hello-1.0.0.nest");
+    /// fs::create_dir_all(npf_path.parent().unwrap())?;
+    /// fs::write(&npf_path, "hello")?;
+    ///
+    /// // The hash matches
+    /// assert!(downloaded.verify_against_manifest(&id, &manifest)?);
+    ///
+    /// // The hash doesn't match
+    /// fs::write(&npf_path, "tampered")?;
+    /// assert!(!downloaded.verify_against_manifest(&id, &manifest)?);
+    ///
+    /// // The manifest doesn't carry any hash
+    /// fs::write(&npf_path, "hello")?;
+    /// assert!(!downloaded.verify_against_manifest(&id, &manifest_with("")?)?);
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_against_manifest(
+        &self,
+        package: &PackageID,
+        manifest: &Manifest,
+    ) -> Result<bool, CacheError> {
+        match manifest.hash() {
+            Some(expected) => Ok(self
+                .package_hash(package, manifest.hash_algorithm())?
+                .map_or(false, |local| local.eq_ignore_ascii_case(expected))),
+            None => Ok(false),
+        }
+    }
+
+    /// Copies the NPF at the given path into the cache, as the downloaded file of the given package
+    pub fn import_package(
+        &self,
+        package: &PackageID,
+        npf_path: &Path,
+    ) -> Result<(), std::io::Error> {
+        let path = self.package_path(package);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(npf_path, path)?;
+        Ok(())
+    }
+
     /// Opens a downloaded package for exploration
     pub fn explore_package(&self, package: &PackageID) -> Result<NPFExplorer, NPFExplorationError> {
         NPFExplorer::from(self.package_path(package))
@@ -74,4 +210,112 @@ impl<'cache_root, 'lock_file> DownloadedPackages<'cache_root, 'lock_file> {
 
         fs::remove_file(&path)
     }
+
+    /// Removes all the downloaded packages that aren't part of `keep`, and returns the removed ones.
+    ///
+    /// The directories of repositories, categories and packages that end up empty are removed too.
+    /// Files that don't look like downloaded packages are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::collections::HashSet;
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::PackageID;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-gc-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let downloaded = config.downloaded_packages_cache(&lock_file_ownership);
+    ///
+    /// for path in &[
+    ///     "stable/sys-apps/hello/hello-1.0.0.nest",
+    ///     "stable/sys-apps/hello/hello-1.1.0.nest",
+    ///     "stable/sys-libs/libc/libc-2.29.0.nest",
+    ///     "beta/sys-apps/hello/hello-2.0.0.nest",
+    /// ] {
+    ///     let path = config.paths().downloaded().join(path);
+    ///     fs::create_dir_all(path.parent().unwrap())?;
+    ///     fs::write(&path, "")?;
+    /// }
+    ///
+    /// let keep: HashSet<_> = vec![PackageID::parse("stable::sys-apps/hello#1.1.0")?].into_iter().collect();
+    /// let mut removed = downloaded.garbage_collect(&keep)?;
+    /// removed.sort();
+    /// assert_eq!(
+    ///     removed,
+    ///     vec![
+    ///         PackageID::parse("beta::sys-apps/hello#2.0.0")?,
+    ///         PackageID::parse("stable::sys-apps/hello#1.0.0")?,
+    ///         PackageID::parse("stable::sys-libs/libc#2.29.0")?,
+    ///     ],
+    /// );
+    ///
+    /// // Kept packages survive, and empty directories are removed
+    /// let downloaded_path = config.paths().downloaded();
+    /// assert!(downloaded.has_package(&PackageID::parse("stable::sys-apps/hello#1.1.0")?));
+    /// assert!(!downloaded_path.join("stable/sys-apps/hello/hello-1.0.0.nest").exists());
+    /// assert!(!downloaded_path.join("stable/sys-libs").exists());
+    /// assert!(!downloaded_path.join("beta").exists());
+    ///
+    /// // Nothing is left to collect
+    /// assert!(downloaded.garbage_collect(&keep)?.is_empty());
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn garbage_collect(&self, keep: &HashSet<PackageID>) -> Result<Vec<PackageID>, io::Error> {
+        let mut removed = Vec::new();
+
+        for (repository_path, repository) in Self::sub_directories(self.cache_root)? {
+            for (category_path, category) in Self::sub_directories(&repository_path)? {
+                for (name_path, name) in Self::sub_directories(&category_path)? {
+                    for entry in fs::read_dir(&name_path)? {
+                        let path = entry?.path();
+
+                        // Retrieve the version out of the file name, which looks like `name-version.nest`
+                        let prefix = format!("{}-", name);
+                        let file_name = path
+                            .file_name()
+                            .and_then(|file_name| file_name.to_str())
+                            .unwrap_or_default();
+                        let version = if file_name.starts_with(&prefix)
+                            && file_name.ends_with(".nest")
+                            && file_name.len() > prefix.len() + ".nest".len()
+                        {
+                            Some(&file_name[prefix.len()..file_name.len() - ".nest".len()])
+                        } else {
+                            None
+                        };
+                        let id = version.and_then(|version| {
+                            PackageID::parse(&format!(
+                                "{}::{}/{}#{}",
+                                repository, category, name, version
+                            ))
+                            .ok()
+                        });
+
+                        if let Some(id) = id {
+                            if !keep.contains(&id) {
+                                fs::remove_file(&path)?;
+                                removed.push(id);
+                            }
+                        }
+                    }
+                    Self::remove_dir_if_empty(&name_path)?;
+                }
+                Self::remove_dir_if_empty(&category_path)?;
+            }
+            Self::remove_dir_if_empty(&repository_path)?;
+        }
+
+        Ok(removed)
+    }
 }
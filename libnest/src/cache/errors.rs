@@ -2,6 +2,10 @@
 
 use failure::{Context, Fail};
 
+use crate::package::PackageShortName;
+
+use super::depgraph::NodeID;
+
 /// Error type for cache-related errors
 #[derive(Debug)]
 pub struct CacheError {
@@ -26,10 +30,49 @@ pub enum CacheErrorKind {
     /// Some data could not be cleared from the cache
     #[fail(display = "unable to clear data from the cache")]
     CacheClearError,
+
+    /// A manifest of the cache is empty, usually because a pull was interrupted while writing it
+    #[fail(display = "the manifest is empty, the repository must be pulled again")]
+    EmptyManifest,
 }
 
 use_as_error!(CacheError, CacheErrorKind);
 
+/// Error type for errors related to a query of the cache of available packages
+#[derive(Debug)]
+pub struct QueryError {
+    inner: Context<QueryErrorKind>,
+}
+
+/// Error kind describing a kind of error related to a query of the cache of available packages
+#[derive(Clone, Eq, PartialEq, Debug, Fail)]
+pub enum QueryErrorKind {
+    /// No package matches the given requirement
+    #[fail(display = "no package found for requirement '{}'", requirement)]
+    NoPackageFound {
+        /// The requirement matching no package
+        requirement: String,
+
+        /// The names of the packages named closely to the requirement, the closest first
+        suggestions: Vec<PackageShortName>,
+    },
+}
+
+use_as_error!(QueryError, QueryErrorKind);
+
+impl QueryError {
+    /// Returns an advice to the user on how to solve the error, if there is any
+    pub fn advice(&self) -> Option<String> {
+        match self.kind() {
+            QueryErrorKind::NoPackageFound { suggestions, .. } if !suggestions.is_empty() => {
+                let suggestions: Vec<_> = suggestions.iter().map(ToString::to_string).collect();
+                Some(format!("did you mean {}?", suggestions.join(", ")))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Error type for errors related to group names
 #[derive(Debug)]
 pub struct GroupNameError {
@@ -74,6 +117,41 @@ pub enum DependencyGraphErrorKind {
     /// The given group cannot be found
     #[fail(display = "group not found")]
     GroupNotFound,
+
+    /// The root group can't be renamed
+    #[fail(display = "the root group can't be renamed")]
+    RootGroupRename,
+
+    /// Some nodes depend on each other, directly or not
+    #[fail(display = "cyclic dependency")]
+    CyclicDependency,
+
+    /// The given requirement can't be solved without changing the version of a held package
+    #[fail(display = "the requirement conflicts with a held package")]
+    HeldPackageConflict,
+
+    /// Two packages of the graph can't be installed alongside each other: the package of node `a` conflicts
+    /// with the one of node `b`
+    #[fail(display = "conflicting packages")]
+    Conflict {
+        /// The node of the package whose conflicts match the other one
+        a: NodeID,
+
+        /// The node of the package matched by the conflicts of the other one
+        b: NodeID,
+    },
+
+    /// The given package can't be removed because other packages depend on it
+    #[fail(display = "the package is required by other packages")]
+    PackageStillRequired,
+
+    /// The nodes, requirements and names of the graph don't match each other
+    #[fail(display = "the dependency graph is corrupted")]
+    CorruptedGraph,
+
+    /// The selection was exported by a newer version of Nest, using a format this one doesn't support
+    #[fail(display = "unsupported selection format")]
+    UnsupportedSelectionVersion,
 }
 
 use_as_error!(DependencyGraphError, DependencyGraphErrorKind);
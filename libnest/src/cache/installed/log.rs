@@ -1,12 +1,17 @@
 //! Module to query and manipulate the log files for installed packages
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use serde_derive::{Deserialize, Serialize};
 use tar::EntryType;
 
+use crate::chroot::Chroot;
+use crate::package::{HashAlgorithm, PackageID};
+
+use super::normalize;
+
 /// Enumeration representing the different installable file types
 #[derive(Serialize, Deserialize, Copy, Clone, Ord, PartialOrd, PartialEq, Eq, Hash, Debug)]
 pub enum FileType {
@@ -84,17 +89,99 @@ impl From<EntryType> for FileType {
     }
 }
 
+/// The state of an installed file, compared to the state it was in when it was installed
+#[derive(Serialize, Deserialize, Copy, Clone, Ord, PartialOrd, PartialEq, Eq, Hash, Debug)]
+pub enum FileStatus {
+    /// The file wasn't modified since it was installed
+    Intact,
+
+    /// The file was modified since it was installed
+    Modified,
+
+    /// The file was removed since it was installed
+    Missing,
+}
+
 /// Structure representing a file entry in a log
 #[derive(Serialize, Deserialize, Clone, Ord, PartialOrd, PartialEq, Eq, Hash, Debug)]
 pub struct FileLogEntry {
     path: PathBuf,
     file_type: FileType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    displaced_owner: Option<PackageID>,
 }
 
 impl FileLogEntry {
     /// Creates a new entry given a path and a file type
     pub fn new(path: PathBuf, file_type: FileType) -> Self {
-        FileLogEntry { path, file_type }
+        FileLogEntry {
+            path,
+            file_type,
+            sha256: None,
+            displaced_owner: None,
+        }
+    }
+
+    /// Sets the SHA256 hash of the content of the file when it was installed, as an uppercase hexadecimal string
+    pub fn with_sha256(mut self, sha256: String) -> Self {
+        self.sha256 = Some(sha256);
+        self
+    }
+
+    /// Returns the SHA256 hash of the content of the file when it was installed, if it was recorded
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_ref().map(String::as_str)
+    }
+
+    /// Sets the package that owned the file before it was forcibly overwritten by the package of this log
+    pub fn with_displaced_owner(mut self, owner: PackageID) -> Self {
+        self.displaced_owner = Some(owner);
+        self
+    }
+
+    /// Returns the package that owned the file before it was forcibly overwritten by the package of this log, if any
+    pub fn displaced_owner(&self) -> Option<&PackageID> {
+        self.displaced_owner.as_ref()
+    }
+
+    /// Checks the state of the file installed in the given root folder against this entry.
+    ///
+    /// A file is considered modified if its type changed, or if its content doesn't match the recorded hash.
+    /// Files whose hash wasn't recorded are only checked for existence.
+    pub fn verify<P: AsRef<Path>>(&self, root: P) -> Result<FileStatus, std::io::Error> {
+        let path = root.as_ref().with_content(&self.path);
+
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(FileStatus::Missing)
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Directories may be replaced by symlinks to directories, like when the installation is merged
+        let same_type = match self.file_type {
+            FileType::Directory => metadata.is_dir() || fs::metadata(&path)?.is_dir(),
+            FileType::File => metadata.is_file(),
+            FileType::Symlink => metadata.file_type().is_symlink(),
+            _ => true,
+        };
+        if !same_type {
+            return Ok(FileStatus::Modified);
+        }
+
+        match &self.sha256 {
+            Some(expected) if self.file_type.is_file() => {
+                if HashAlgorithm::Sha256.digest(File::open(&path)?)? == *expected {
+                    Ok(FileStatus::Intact)
+                } else {
+                    Ok(FileStatus::Modified)
+                }
+            }
+            _ => Ok(FileStatus::Intact),
+        }
     }
 
     /// Returns a reference over the path for this entry
@@ -151,4 +238,148 @@ impl Log {
     pub fn files(&self) -> &[FileLogEntry] {
         &self.files
     }
+
+    /// Removes the entries of the given path that aren't directories, returning the first of them, if any.
+    ///
+    /// Paths are compared once normalized.
+    pub(crate) fn remove_file(&mut self, path: &Path) -> Option<FileLogEntry> {
+        let path = normalize(path);
+
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .files
+            .drain(..)
+            .partition(|file| !file.file_type().is_dir() && normalize(file.path()) == path);
+        self.files = kept;
+        removed.into_iter().next()
+    }
+
+    /// Adds the given entry at the end of the log
+    pub(crate) fn add_file(&mut self, entry: FileLogEntry) {
+        self.files.push(entry);
+    }
+
+    /// Checks the state of all the files of the log, installed in the given root folder.
+    ///
+    /// Returns the entries of the files that aren't [`FileStatus::Intact`], along with their status.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use std::path::PathBuf;
+    /// use libnest::cache::installed::log::{FileLogEntry, FileStatus, FileType, Log};
+    ///
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-verify-{}", std::process::id()));
+    /// fs::create_dir_all(root.join("usr/bin"))?;
+    /// fs::write(root.join("usr/bin/intact"), "intact")?;
+    /// fs::write(root.join("usr/bin/modified"), "modified")?;
+    ///
+    /// // SHA256 of "intact" and "original"
+    /// let log = Log::new(vec![
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/intact"), FileType::File)
+    ///         .with_sha256(String::from("E6D7DDD8F414A22D8935148498C32CE0ACDCF5C0C71DB2455033F9BE0A6CBC0A")),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/modified"), FileType::File)
+    ///         .with_sha256(String::from("0682C5F2076F099C34CFDD15A9E063849ED437A49677E6FCC5B4198C76575BE5")),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/missing"), FileType::File),
+    /// ]);
+    ///
+    /// let altered = log.verify(&root)?;
+    /// let altered = altered
+    ///     .iter()
+    ///     .map(|(entry, status)| (entry.path().to_str().unwrap(), *status))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     altered,
+    ///     vec![("/usr/bin/modified", FileStatus::Modified), ("/usr/bin/missing", FileStatus::Missing)]
+    /// );
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify<P: AsRef<Path>>(
+        &self,
+        root: P,
+    ) -> Result<Vec<(&FileLogEntry, FileStatus)>, std::io::Error> {
+        let mut altered = Vec::new();
+        for entry in &self.files {
+            let status = entry.verify(root.as_ref())?;
+            if status != FileStatus::Intact {
+                altered.push((entry, status));
+            }
+        }
+        Ok(altered)
+    }
+
+    /// Returns the space occupied on disk by the files of the log, installed in the given root folder, in bytes.
+    ///
+    /// Only regular files are counted: directories, symlinks and hard links (whose content is already counted
+    /// with the file they link to) are skipped. Files that went missing count as zero, use
+    /// [`installed_size_with_missing`] to know which ones they are.
+    ///
+    /// [`installed_size_with_missing`]: #method.installed_size_with_missing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use std::path::PathBuf;
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    ///
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-installed-size-{}", std::process::id()));
+    /// fs::create_dir_all(root.join("usr/bin"))?;
+    /// fs::write(root.join("usr/bin/hello"), vec![0; 1000])?;
+    /// fs::write(root.join("usr/bin/world"), vec![0; 24])?;
+    /// std::os::unix::fs::symlink("hello", root.join("usr/bin/hi"))?;
+    ///
+    /// let log = Log::new(vec![
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/hello"), FileType::File),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/world"), FileType::File),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/hi"), FileType::Symlink),
+    ///     FileLogEntry::new(PathBuf::from("/usr/bin/missing"), FileType::File),
+    /// ]);
+    ///
+    /// assert_eq!(log.installed_size(&root)?, 1024);
+    ///
+    /// let (size, missing) = log.installed_size_with_missing(&root)?;
+    /// assert_eq!(size, 1024);
+    /// assert_eq!(
+    ///     missing.iter().map(|entry| entry.path()).collect::<Vec<_>>(),
+    ///     vec![PathBuf::from("/usr/bin/missing")],
+    /// );
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn installed_size<P: AsRef<Path>>(&self, root: P) -> Result<u64, std::io::Error> {
+        self.installed_size_with_missing(root).map(|(size, _)| size)
+    }
+
+    /// Same as [`installed_size`], but also returns the entries of the regular files that went missing.
+    ///
+    /// [`installed_size`]: #method.installed_size
+    pub fn installed_size_with_missing<P: AsRef<Path>>(
+        &self,
+        root: P,
+    ) -> Result<(u64, Vec<&FileLogEntry>), std::io::Error> {
+        let mut size = 0;
+        let mut missing = Vec::new();
+        for entry in self.files.iter().filter(|entry| entry.file_type.is_file()) {
+            let path = root.as_ref().with_content(&entry.path);
+            match fs::symlink_metadata(&path) {
+                Ok(ref metadata) if metadata.is_file() => size += metadata.len(),
+                Ok(_) => (),
+                Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => missing.push(entry),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((size, missing))
+    }
 }
@@ -2,14 +2,15 @@
 
 pub mod log;
 
+use std::collections::HashMap;
 use std::fs;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 use crate::lock_file::LockFileOwnership;
-use crate::package::PackageID;
+use crate::package::{PackageID, Slot};
 
-use self::log::Log;
+use self::log::{FileLogEntry, FileStatus, Log};
 
 /// Structure representing the cache of installed packages
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -29,25 +30,70 @@ impl<'cache_root, 'lock_file> InstalledPackages<'cache_root, 'lock_file> {
         }
     }
 
-    /// Loads the log of installed files for a given package
-    pub fn package_log(&self, package: &PackageID) -> Result<Log, std::io::Error> {
-        let path = self
-            .cache_root
+    /// Returns the path of the folder holding the logs of all the versions of a given package and slot.
+    ///
+    /// The slot's folder is prefixed by an `@`, so the default (empty) slot is stored in a folder named `@`.
+    fn package_slot_dir(&self, package: &PackageID, slot: &Slot) -> PathBuf {
+        self.cache_root
             .join(package.repository().as_str())
             .join(package.category().as_str())
             .join(package.name().as_str())
+            .join(format!("@{}", slot))
+    }
+
+    /// Loads the log of installed files for a given package and slot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::PathBuf;
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-slots-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// // Two slots of the same version of a package are stored independently
+    /// let id = PackageID::parse("stable::sys-devel/gcc#1.0.0")?;
+    /// let log_for = |path: &str| Log::new(vec![FileLogEntry::new(PathBuf::from(path), FileType::File)]);
+    /// installed.save_package_log(&id, &Slot::parse("1")?, &log_for("/usr/bin/gcc-1"))?;
+    /// installed.save_package_log(&id, &Slot::parse("2")?, &log_for("/usr/bin/gcc-2"))?;
+    ///
+    /// assert_eq!(installed.package_log(&id, &Slot::parse("1")?)?, log_for("/usr/bin/gcc-1"));
+    /// assert_eq!(installed.package_log(&id, &Slot::parse("2")?)?, log_for("/usr/bin/gcc-2"));
+    ///
+    /// installed.remove_package_log(&id, &Slot::parse("1")?)?;
+    /// assert!(installed.package_log(&id, &Slot::parse("1")?).is_err());
+    /// assert_eq!(installed.package_log(&id, &Slot::parse("2")?)?, log_for("/usr/bin/gcc-2"));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn package_log(&self, package: &PackageID, slot: &Slot) -> Result<Log, std::io::Error> {
+        let path = self
+            .package_slot_dir(package, slot)
             .join(package.version().to_string());
 
         Log::load_from_file(path)
     }
 
-    /// Saves the log of installed files for a given package
-    pub fn save_package_log(&self, package: &PackageID, log: &Log) -> Result<(), std::io::Error> {
-        let log_dir = self
-            .cache_root
-            .join(package.repository().as_str())
-            .join(package.category().as_str())
-            .join(package.name().as_str());
+    /// Saves the log of installed files for a given package and slot
+    pub fn save_package_log(
+        &self,
+        package: &PackageID,
+        slot: &Slot,
+        log: &Log,
+    ) -> Result<(), std::io::Error> {
+        let log_dir = self.package_slot_dir(package, slot);
         fs::create_dir_all(&log_dir)?;
 
         let path = log_dir.join(package.version().to_string());
@@ -55,15 +101,577 @@ impl<'cache_root, 'lock_file> InstalledPackages<'cache_root, 'lock_file> {
         log.save_to_file(path)
     }
 
-    /// Removes the log of installed files for a given package
-    pub fn remove_package_log(&self, package: &PackageID) -> Result<(), std::io::Error> {
+    /// Removes the log of installed files for a given package and slot
+    pub fn remove_package_log(
+        &self,
+        package: &PackageID,
+        slot: &Slot,
+    ) -> Result<(), std::io::Error> {
         let path = self
-            .cache_root
-            .join(package.repository().as_str())
-            .join(package.category().as_str())
-            .join(package.name().as_str())
+            .package_slot_dir(package, slot)
             .join(package.version().to_string());
 
         fs::remove_file(&path)
     }
+
+    /// Lists the packages that have a log in this cache, along with their slot.
+    ///
+    /// Entries that don't follow the layout of the cache are ignored.
+    pub fn packages(&self) -> Result<Vec<(PackageID, Slot)>, std::io::Error> {
+        fn name_of(path: &Path) -> &str {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+        }
+
+        let mut packages = Vec::new();
+        for repository_path in read_dir_or_empty(self.cache_root)? {
+            for category_path in read_dir_or_empty(&repository_path)? {
+                for package_path in read_dir_or_empty(&category_path)? {
+                    for slot_path in read_dir_or_empty(&package_path)? {
+                        if !name_of(&slot_path).starts_with('@') {
+                            continue;
+                        }
+                        let slot = Slot::parse(&name_of(&slot_path)[1..]);
+
+                        for version_path in read_dir_or_empty(&slot_path)? {
+                            let id = PackageID::parse(&format!(
+                                "{}::{}/{}#{}",
+                                name_of(&repository_path),
+                                name_of(&category_path),
+                                name_of(&package_path),
+                                name_of(&version_path),
+                            ));
+                            if let (Ok(id), Ok(slot)) = (id, slot.clone()) {
+                                packages.push((id, slot));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(packages)
+    }
+
+    /// Returns an iterator over the [`PackageID`]s of the packages that have a log in this cache, sorted.
+    ///
+    /// A version installed in several slots is only returned once. Like with [`packages`][1], entries that
+    /// don't follow the layout of the cache are skipped.
+    ///
+    /// [1]: #method.packages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::cache::installed::log::Log;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-iter-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// for (id, slot) in &[
+    ///     ("stable::sys-devel/gcc#9.1.0", ""),
+    ///     ("stable::sys-devel/binutils#2.32.0", ""),
+    ///     ("stable::x11-libs/gtk#2.24.0", "2"),
+    ///     ("stable::x11-libs/gtk#3.24.0", "3"),
+    ///     ("beta::sys-apps/coreutils#8.31.0", ""),
+    /// ] {
+    ///     installed.save_package_log(&PackageID::parse(id)?, &Slot::parse(slot)?, &Log::new(Vec::new()))?;
+    /// }
+    ///
+    /// // Malformed entries are skipped
+    /// let cache = config.paths().installed();
+    /// fs::write(cache.join("stable/sys-devel/README"), "not a package")?;
+    /// fs::write(cache.join("stable/sys-devel/gcc/@/not-a-version"), "{}")?;
+    /// fs::create_dir_all(cache.join("stable/sys-devel/gcc/not-a-slot"))?;
+    /// fs::create_dir_all(cache.join("Invalid Repository/sys-apps/bash/@/5.0.0"))?;
+    ///
+    /// let ids: Vec<_> = installed.iter()?.map(|id| id.to_string()).collect();
+    /// assert_eq!(
+    ///     ids,
+    ///     vec![
+    ///         "beta::sys-apps/coreutils#8.31.0",
+    ///         "stable::sys-devel/binutils#2.32.0",
+    ///         "stable::sys-devel/gcc#9.1.0",
+    ///         "stable::x11-libs/gtk#2.24.0",
+    ///         "stable::x11-libs/gtk#3.24.0",
+    ///     ],
+    /// );
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(&self) -> Result<impl Iterator<Item = PackageID>, std::io::Error> {
+        let mut ids: Vec<_> = self.packages()?.into_iter().map(|(id, _)| id).collect();
+        ids.sort();
+        ids.dedup();
+        Ok(ids.into_iter())
+    }
+
+    /// Returns the files installed by the given package, in the order they were logged.
+    ///
+    /// Like in the logs, the paths are absolute within the root of the installation. If the package is
+    /// installed in several slots, the files of all of them are returned. An error of kind
+    /// [`NotFound`][1] is returned if the package isn't installed.
+    ///
+    /// [1]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.NotFound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::io::ErrorKind;
+    /// use std::path::PathBuf;
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-package-files-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// let gcc = PackageID::parse("stable::sys-devel/gcc#1.0.0")?;
+    /// installed.save_package_log(
+    ///     &gcc,
+    ///     &Slot::default(),
+    ///     &Log::new(vec![
+    ///         FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    ///         FileLogEntry::new(PathBuf::from("/usr/bin/gcc"), FileType::File),
+    ///     ]),
+    /// )?;
+    ///
+    /// assert_eq!(
+    ///     installed.package_files(&gcc)?,
+    ///     vec![PathBuf::from("/usr/bin"), PathBuf::from("/usr/bin/gcc")],
+    /// );
+    ///
+    /// let error = installed
+    ///     .package_files(&PackageID::parse("stable::sys-devel/gcc#2.0.0")?)
+    ///     .unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::NotFound);
+    /// assert_eq!(error.to_string(), "stable::sys-devel/gcc#2.0.0 is not installed");
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn package_files(&self, id: &PackageID) -> Result<Vec<PathBuf>, std::io::Error> {
+        let mut files = Vec::new();
+        for log in self.installed_package_logs(id)? {
+            files.extend(log.files().iter().map(|file| file.path().to_path_buf()));
+        }
+        Ok(files)
+    }
+
+    /// Checks the files installed by the given package against the root of the installation, and returns the
+    /// status of each of them, in the order of the package's log.
+    ///
+    /// Files whose hash was recorded when they were installed are [`Modified`] if their content changed, and all files
+    /// are [`Missing`] if they don't exist anymore. Like for [`package_files`][1], [`NotFound`][2] is returned if
+    /// the package isn't installed.
+    ///
+    /// [`Modified`]: log/enum.FileStatus.html#variant.Modified
+    /// [`Missing`]: log/enum.FileStatus.html#variant.Missing
+    /// [1]: #method.package_files
+    /// [2]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.NotFound
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::cache::installed::log::FileStatus;
+    /// use libnest::config::Config;
+    /// use libnest::transaction::{InstallTransaction, NoopObserver};
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-verify-package-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `hello` is a downloaded package shipping three files
+    /// # let hello = download_npf(&config, &lock_file_ownership, "tests::sys-apps/hello#1.0.0", "", &[], &[
+    /// #     ("usr/bin/hello", 0o755, "hello"),
+    /// #     ("usr/bin/hi", 0o755, "hello"),
+    /// #     ("usr/bin/hey", 0o755, "hello"),
+    /// # ])?;
+    /// InstallTransaction::from(hello.clone()).extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    ///
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    /// assert!(installed
+    ///     .verify_package(&hello, config.paths().root())?
+    ///     .iter()
+    ///     .all(|status| *status == FileStatus::Intact));
+    ///
+    /// // Alter one file and delete another one behind Nest's back
+    /// fs::write(root.join("usr/bin/hi"), "howdy")?;
+    /// fs::remove_file(root.join("usr/bin/hey"))?;
+    ///
+    /// let files = installed.package_files(&hello)?;
+    /// let statuses = installed.verify_package(&hello, config.paths().root())?;
+    /// let status_of = |path: &str| statuses[files.iter().position(|file| file.to_str() == Some(path)).unwrap()];
+    /// assert_eq!(status_of("/usr/bin/hello"), FileStatus::Intact);
+    /// assert_eq!(status_of("/usr/bin/hi"), FileStatus::Modified);
+    /// assert_eq!(status_of("/usr/bin/hey"), FileStatus::Missing);
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_package<P: AsRef<Path>>(
+        &self,
+        id: &PackageID,
+        root: P,
+    ) -> Result<Vec<FileStatus>, std::io::Error> {
+        let mut statuses = Vec::new();
+        for log in self.installed_package_logs(id)? {
+            for file in log.files() {
+                statuses.push(file.verify(root.as_ref())?);
+            }
+        }
+        Ok(statuses)
+    }
+
+    /// Loads the logs of all the slots of the given installed package, or fails with [`NotFound`][1] if it isn't
+    /// installed.
+    ///
+    /// [1]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.NotFound
+    fn installed_package_logs(&self, id: &PackageID) -> Result<Vec<Log>, std::io::Error> {
+        let mut packages = self.packages()?;
+        packages.retain(|(installed, _)| installed == id);
+        packages.sort();
+
+        if packages.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not installed", id),
+            ));
+        }
+
+        packages
+            .iter()
+            .map(|(id, slot)| self.package_log(id, slot))
+            .collect()
+    }
+
+    /// Looks for the installed package owning the given file, according to the logs of this cache.
+    ///
+    /// The path is absolute within the root of the installation. It's normalized before the
+    /// comparison (so trailing slashes, `.` and `..` components don't matter), but symlinks aren't
+    /// resolved. If several packages claim the given path, the one with the lowest [`PackageID`]
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-owner-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// let log_for = |paths: &[(&str, FileType)]| {
+    ///     Log::new(paths.iter().map(|(path, ty)| FileLogEntry::new(PathBuf::from(path), *ty)).collect())
+    /// };
+    /// let gcc = PackageID::parse("stable::sys-devel/gcc#1.0.0")?;
+    /// let binutils = PackageID::parse("stable::sys-devel/binutils#1.0.0")?;
+    /// installed.save_package_log(
+    ///     &gcc,
+    ///     &Slot::default(),
+    ///     &log_for(&[("/usr/bin", FileType::Directory), ("/usr/bin/gcc", FileType::File)]),
+    /// )?;
+    /// installed.save_package_log(
+    ///     &binutils,
+    ///     &Slot::default(),
+    ///     &log_for(&[("/usr/bin", FileType::Directory), ("/usr/bin/ld", FileType::File)]),
+    /// )?;
+    ///
+    /// assert_eq!(installed.find_owner(Path::new("/usr/bin/gcc"))?, Some(gcc));
+    /// assert_eq!(installed.find_owner(Path::new("/usr/lib/../bin/./ld"))?, Some(binutils.clone()));
+    ///
+    /// // Both packages claim `/usr/bin`: the lowest ID is returned
+    /// assert_eq!(installed.find_owner(Path::new("/usr/bin/"))?, Some(binutils));
+    /// assert_eq!(installed.find_owner(Path::new("/usr/bin/cc"))?, None);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_owner(&self, path: &Path) -> Result<Option<PackageID>, std::io::Error> {
+        let path = normalize(path);
+
+        let mut packages = self.packages()?;
+        packages.sort();
+
+        for (id, slot) in packages {
+            let log = self.package_log(&id, &slot)?;
+            if log
+                .files()
+                .iter()
+                .any(|file| normalize(file.path()) == path)
+            {
+                return Ok(Some(id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the files owned by the installed packages, mapped to their owner, leaving out directories
+    /// (as they can be shared by several packages) and the files of any version of the given package.
+    ///
+    /// The paths are normalized. If several packages claim the same file, the lowest [`PackageID`] is kept.
+    pub(crate) fn file_owners(
+        &self,
+        installing: &PackageID,
+    ) -> Result<HashMap<PathBuf, PackageID>, std::io::Error> {
+        let mut packages = self.packages()?;
+        packages.sort();
+
+        let mut owners = HashMap::new();
+        for (id, slot) in packages {
+            if id.repository() == installing.repository()
+                && id.category() == installing.category()
+                && id.name() == installing.name()
+            {
+                continue;
+            }
+
+            let log = self.package_log(&id, &slot)?;
+            for file in log.files().iter().filter(|file| !file.file_type().is_dir()) {
+                owners
+                    .entry(normalize(file.path()))
+                    .or_insert_with(|| id.clone());
+            }
+        }
+        Ok(owners)
+    }
+
+    /// Looks for an installed package, other than the one being installed, that already owns the given file.
+    ///
+    /// Directories can be shared by several packages, so they are never considered as conflicting.
+    /// Files owned by any version of the package being installed aren't conflicting either, so that
+    /// it can be upgraded. The path is normalized like with [`find_owner`][1].
+    ///
+    /// [1]: #method.find_owner
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-conflict-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// let busybox = PackageID::parse("stable::sys-apps/busybox#1.0.0")?;
+    /// installed.save_package_log(
+    ///     &busybox,
+    ///     &Slot::default(),
+    ///     &Log::new(vec![
+    ///         FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    ///         FileLogEntry::new(PathBuf::from("/usr/bin/ls"), FileType::File),
+    ///     ]),
+    /// )?;
+    ///
+    /// // Another package can't install a file that is already owned
+    /// let coreutils = PackageID::parse("stable::sys-apps/coreutils#1.0.0")?;
+    /// assert_eq!(
+    ///     installed.conflicting_owner(Path::new("/usr/bin/ls"), &coreutils)?,
+    ///     Some(busybox.clone()),
+    /// );
+    ///
+    /// // Directories are shared, and unowned files are free to use
+    /// assert_eq!(installed.conflicting_owner(Path::new("/usr/bin"), &coreutils)?, None);
+    /// assert_eq!(installed.conflicting_owner(Path::new("/usr/bin/cp"), &coreutils)?, None);
+    ///
+    /// // A new version of the owner can install the file again
+    /// let busybox_upgrade = PackageID::parse("stable::sys-apps/busybox#2.0.0")?;
+    /// assert_eq!(installed.conflicting_owner(Path::new("/usr/bin/ls"), &busybox_upgrade)?, None);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn conflicting_owner(
+        &self,
+        path: &Path,
+        installing: &PackageID,
+    ) -> Result<Option<PackageID>, std::io::Error> {
+        Ok(self.file_owners(installing)?.remove(&normalize(path)))
+    }
+
+    /// Removes the claim of the given package over the given file from its log, in all the slots it's
+    /// installed in, like when the file was forcibly overwritten by another package.
+    ///
+    /// Directories are shared by several packages, so they are never removed from the log. The path is
+    /// normalized like with [`find_owner`][1].
+    ///
+    /// Returns the entry of the file that was removed from the log, if any.
+    ///
+    /// [1]: #method.find_owner
+    pub fn release_file(
+        &self,
+        owner: &PackageID,
+        path: &Path,
+    ) -> Result<Option<FileLogEntry>, std::io::Error> {
+        let mut released = None;
+        for (id, slot) in self.packages()? {
+            if id == *owner {
+                let mut log = self.package_log(&id, &slot)?;
+                if let Some(entry) = log.remove_file(path) {
+                    self.save_package_log(&id, &slot, &log)?;
+                    released = released.or(Some(entry));
+                }
+            }
+        }
+        Ok(released)
+    }
+
+    /// Gives back the given file to the given package, in all the slots it's installed in, like when the
+    /// package that took it over with [`release_file`][1] is removed.
+    ///
+    /// Returns whether the package is still installed to claim it.
+    ///
+    /// [1]: #method.release_file
+    pub(crate) fn claim_file(
+        &self,
+        owner: &PackageID,
+        entry: &FileLogEntry,
+    ) -> Result<bool, std::io::Error> {
+        let mut claimed = false;
+        for (id, slot) in self.packages()? {
+            if id == *owner {
+                let mut log = self.package_log(&id, &slot)?;
+                log.add_file(entry.clone());
+                self.save_package_log(&id, &slot, &log)?;
+                claimed = true;
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Moves the logs stored using the legacy layout, which didn't include the slot of packages, to
+    /// the current layout.
+    ///
+    /// Legacy logs are stored at `repository/category/name/version`, and are moved to the
+    /// default slot, at `repository/category/name/@/version`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-migrate-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// // Create a log using the legacy layout
+    /// let legacy_dir = config.paths().installed().join("stable/sys-devel/gcc");
+    /// fs::create_dir_all(&legacy_dir)?;
+    /// fs::write(legacy_dir.join("1.0.0"), r#"{ "files": [] }"#)?;
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    /// installed.migrate_legacy_logs()?;
+    ///
+    /// let id = PackageID::parse("stable::sys-devel/gcc#1.0.0")?;
+    /// assert!(installed.package_log(&id, &Slot::default())?.files().is_empty());
+    /// assert!(!legacy_dir.join("1.0.0").exists());
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn migrate_legacy_logs(&self) -> Result<(), std::io::Error> {
+        for repository in read_dir_or_empty(self.cache_root)? {
+            for category in read_dir_or_empty(&repository)? {
+                for package in read_dir_or_empty(&category)? {
+                    let slot_dir = package.join(format!("@{}", Slot::default()));
+
+                    for entry in read_dir_or_empty(&package)? {
+                        if entry.is_file() {
+                            fs::create_dir_all(&slot_dir)?;
+                            if let Some(version) = entry.file_name() {
+                                fs::rename(&entry, slot_dir.join(version))?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lexically normalizes an absolute path, removing `.` and `..` components.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => (),
+        }
+    }
+    normalized
+}
+
+/// Returns the paths of the entries of the given directory, or nothing if it doesn't exist or isn't a directory.
+fn read_dir_or_empty(path: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    if path.exists() && !path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    match fs::read_dir(path) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect(),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
 }
@@ -1,10 +1,17 @@
 //! Chroot-like path.
 //!
-//! This module isn't provided for any kind of sandboxing or any security-related feature, but as
-//! a simple way to prevent going "upper" than a folder by using `../` and as a path-beautifier.
+//! This module isn't provided for any kind of sandboxing, but as a simple way to prevent going "upper"
+//! than a folder by using `../` or symlinks, and as a path-beautifier.
 
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
 use std::path::{Component, Path, PathBuf};
 
+/// The maximum number of symlinks followed when canonicalizing a path, like the kernel does
+const MAX_SYMLINKS: usize = 40;
+
 /// The Chroot trait provides two functions to interact with [`Path`][1]-like structs:
 /// the first one if the current path is the root and the other the content, like in `/this/is/root + /this/is/content = /this/is/root/this/is/content`
 /// or the other one, when the current path is the content and the other one is the root.
@@ -19,6 +26,50 @@ pub trait Chroot {
     fn with_content<P: AsRef<Path>>(&self, p: P) -> PathBuf;
     /// Returns a PathBuf using the given path as the root-base and the current path as the content.
     fn with_root<P: AsRef<Path>>(&self, p: P) -> PathBuf;
+
+    /// Returns the canonical form of the current path, as seen from within the given root, joined to it.
+    ///
+    /// Unlike [`with_root`][1], the symlinks found along the path are resolved. Their targets are
+    /// interpreted within the root too, so neither `..` components nor symlinks (absolute or not) can lead
+    /// above the root: they are clamped to it, like they would be by a `chroot(2)`. This makes it safe to
+    /// use with paths coming from untrusted packages.
+    ///
+    /// The components that don't exist are kept as they are, so the path of a file about to be created can
+    /// be canonicalized.
+    ///
+    /// [1]: #tymethod.with_root
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # fn main() -> std::io::Result<()> {
+    /// use std::fs;
+    /// use std::os::unix::fs::symlink;
+    /// use std::path::Path;
+    /// use libnest::chroot::Chroot;
+    ///
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-canonicalize-{}", std::process::id()));
+    /// fs::create_dir_all(root.join("usr/lib"))?;
+    /// symlink("/usr/lib", root.join("lib"))?;
+    /// symlink("../../../../..", root.join("usr/up"))?;
+    /// symlink("/etc", root.join("usr/etc"))?;
+    ///
+    /// // `..` components can't go above the root
+    /// assert_eq!(Path::new("../../etc/passwd").canonicalize_within(&root)?, root.join("etc/passwd"));
+    /// assert_eq!(Path::new("/usr/../../lib64").canonicalize_within(&root)?, root.join("lib64"));
+    ///
+    /// // Absolute symlinks are resolved within the root
+    /// assert_eq!(Path::new("/lib/libc.so").canonicalize_within(&root)?, root.join("usr/lib/libc.so"));
+    /// assert_eq!(Path::new("/usr/etc/shadow").canonicalize_within(&root)?, root.join("etc/shadow"));
+    ///
+    /// // Relative symlinks can't escape either
+    /// assert_eq!(Path::new("/usr/up/etc/shadow").canonicalize_within(&root)?, root.join("etc/shadow"));
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn canonicalize_within<P: AsRef<Path>>(&self, root: P) -> io::Result<PathBuf>;
 }
 
 impl Chroot for PathBuf {
@@ -29,6 +80,10 @@ impl Chroot for PathBuf {
     fn with_root<P: AsRef<Path>>(&self, p: P) -> PathBuf {
         self.as_path().with_root(p)
     }
+
+    fn canonicalize_within<P: AsRef<Path>>(&self, root: P) -> io::Result<PathBuf> {
+        self.as_path().canonicalize_within(root)
+    }
 }
 
 impl Chroot for Path {
@@ -61,4 +116,73 @@ impl Chroot for Path {
         assert!(!out.has_root());
         p.as_ref().join(out)
     }
+
+    fn canonicalize_within<P: AsRef<Path>>(&self, root: P) -> io::Result<PathBuf> {
+        let root = root.as_ref();
+        let mut resolved = PathBuf::new();
+        let mut pending: VecDeque<OsString> = components_of(self);
+        let mut symlinks = 0;
+
+        while let Some(component) = pending.pop_front() {
+            if component == ".." {
+                resolved.pop();
+                continue;
+            }
+
+            let candidate = resolved.join(&component);
+            let path = root.join(&candidate);
+            match fs::symlink_metadata(&path) {
+                Ok(ref metadata) if metadata.file_type().is_symlink() => {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("{:?}: too many levels of symbolic links", self),
+                        ));
+                    }
+
+                    // The target replaces the symlink, starting over from the root if it's absolute
+                    let target = fs::read_link(&path)?;
+                    if target.has_root() {
+                        resolved.clear();
+                    }
+                    for component in components_of(&target).into_iter().rev() {
+                        pending.push_front(component);
+                    }
+                }
+                Ok(_) => resolved = candidate,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => resolved = candidate,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(root.join(resolved))
+    }
+}
+
+/// Returns where the entry of the given path is extracted within the given root.
+///
+/// Symlinks in the directories leading to the entry are resolved within the root, so that an entry can't be
+/// extracted outside of it through an existing symlink. The entry itself isn't resolved, as it replaces the
+/// file at its path. Returns [`None`] for entries without a name, like the root itself.
+pub(crate) fn extraction_path(root: &Path, entry_path: &Path) -> io::Result<Option<PathBuf>> {
+    let name = match entry_path.file_name() {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let parent = entry_path
+        .parent()
+        .unwrap_or_else(|| Path::new("/"))
+        .canonicalize_within(root)?;
+    Ok(Some(parent.join(name)))
+}
+
+/// Returns the normal and parent components of the given path, leaving out the root and current ones
+fn components_of(path: &Path) -> VecDeque<OsString> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            _ => None,
+        })
+        .collect()
 }
@@ -22,6 +22,42 @@ pub enum ConfigErrorKind {
     /// The data in the configuration file is invalid
     #[fail(display = "invalid configuration file")]
     InvalidConfigFile,
+
+    /// The configuration is inconsistent, like a repository without any mirror
+    #[fail(display = "invalid configuration")]
+    InvalidConfig,
+
+    /// A repository is defined more than once across the configuration file and the repositories directory
+    #[fail(display = "duplicate repository")]
+    DuplicateRepository,
 }
 
 use_as_error!(ConfigError, ConfigErrorKind);
+
+/// Error type for errors related to the mirror list of a repository
+#[derive(Debug)]
+pub struct MirrorListError {
+    inner: Context<MirrorListErrorKind>,
+}
+
+/// Error kind describing a kind of error related to the mirror list of a repository
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum MirrorListErrorKind {
+    /// The mirror list could not be fetched from its URL
+    #[fail(display = "unable to fetch the mirror list")]
+    MirrorListFetchError,
+
+    /// The mirror list isn't a list of URLs, one per line
+    #[fail(display = "invalid mirror list")]
+    InvalidMirrorList,
+
+    /// The cached mirror list could not be loaded
+    #[fail(display = "unable to load the cached mirror list")]
+    MirrorListLoadError,
+
+    /// The mirror list could not be cached
+    #[fail(display = "unable to cache the mirror list")]
+    MirrorListWriteError,
+}
+
+use_as_error!(MirrorListError, MirrorListErrorKind);
@@ -0,0 +1,150 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::package::Arch;
+
+/// Structure holding the configuration of package installations. It's a sub member of [`Config`][1].
+///
+/// [1]: struct.Config.html
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[serde(default)]
+pub struct InstallConfig {
+    run_scripts: bool,
+    optional_dependencies: bool,
+    arch: Option<Arch>,
+}
+
+impl InstallConfig {
+    /// Returns whether the `pre-install.sh` and `post-install.sh` scripts of packages are executed when installing them.
+    ///
+    /// This is disabled by default, as these scripts run with the privileges of Nest. If the `post-install.sh`
+    /// script of a package fails, the files of the package are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::transaction::{InstallErrorKind, InstallTransaction, NoopObserver};
+    ///
+    /// let mut config: Config = toml::from_str("[install]\nrun_scripts = true")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-run-scripts-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `hello` is a downloaded package with a post-install script
+    /// # let hello = download_npf(&config, &lock_file_ownership, "tests::sys-apps/hello#1.0.0", "",
+    /// #     &[("post-install.sh", "exit 0")], &[("usr/bin/hello", 0o755, "hello")])?;
+    ///
+    /// // There is no shell in the root to run the script, so it fails and the package is rolled back
+    /// let error = InstallTransaction::from(hello.clone())
+    ///     .extract(&config, &lock_file_ownership, &mut NoopObserver)
+    ///     .unwrap_err();
+    /// assert!(match error.kind() {
+    ///     InstallErrorKind::PostInstallScriptFailure(_) => true,
+    ///     _ => false,
+    /// });
+    /// assert!(!root.join("usr/bin/hello").exists());
+    /// assert!(config.installed_packages_cache(&lock_file_ownership).iter()?.all(|id| id != hello));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn run_scripts(&self) -> bool {
+        self.run_scripts
+    }
+
+    /// Returns a mutable reference over whether the installation scripts of packages are executed
+    #[inline]
+    pub fn run_scripts_mut(&mut self) -> &mut bool {
+        &mut self.run_scripts
+    }
+
+    /// Returns whether the optional dependencies of packages are installed along with them.
+    ///
+    /// This is disabled by default. Optional dependencies that can't be solved are skipped instead of failing
+    /// the solving of the dependency graph.
+    #[inline]
+    pub fn optional_dependencies(&self) -> bool {
+        self.optional_dependencies
+    }
+
+    /// Returns a mutable reference over whether the optional dependencies of packages are installed
+    #[inline]
+    pub fn optional_dependencies_mut(&mut self) -> &mut bool {
+        &mut self.optional_dependencies
+    }
+
+    /// Returns the architecture packages are installed for.
+    ///
+    /// It defaults to the [architecture Nest is running on][1], and packages that can't be installed on it are
+    /// ignored when solving the dependency graph. `None` is returned if the architecture isn't configured and
+    /// the one Nest is running on isn't supported, in which case packages aren't filtered.
+    ///
+    /// [1]: ../package/enum.Arch.html#method.host
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{Arch, PackageManifest, PackageRequirement};
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-arch-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// // `steam` is only built for x86_64
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "steam",
+    ///         "category": "games-util",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "A game launcher",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "1.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "architectures": ["x86_64"] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    /// let requirement = PackageRequirement::parse("games-util/steam")?;
+    ///
+    /// *config.install_mut().arch_mut() = Some(Arch::X86_64);
+    /// let packages = config.resolve_dependencies(&requirement, &lock_file_ownership)?;
+    /// assert_eq!(packages.len(), 1);
+    ///
+    /// *config.install_mut().arch_mut() = Some(Arch::Aarch64);
+    /// assert!(config.resolve_dependencies(&requirement, &lock_file_ownership).is_err());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn arch(&self) -> Option<Arch> {
+        self.arch.clone().or_else(Arch::host)
+    }
+
+    /// Returns a mutable reference over the configured architecture packages are installed for
+    #[inline]
+    pub fn arch_mut(&mut self) -> &mut Option<Arch> {
+        &mut self.arch
+    }
+}
@@ -13,32 +13,55 @@
 //! It also provides a way to load a `Config` from a TOML file.
 
 pub mod errors;
+mod install;
+mod network;
 mod paths;
 mod repository;
+mod ui;
 
 pub use self::errors::*;
+pub use self::install::InstallConfig;
+pub use self::network::{NetworkConfig, ProxyConfig};
 pub use self::paths::ConfigPaths;
-pub use self::repository::{MirrorUrl, RepositoryConfig};
+pub use self::repository::{parse_mirror_list, Credentials, MirrorUrl, RepositoryConfig};
+pub use self::ui::{ColorTheme, ColorsConfig, LogLevel, UiConfig};
 
 use failure::*;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
 use std::marker::PhantomData;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
 use toml;
 
-use crate::cache::available::AvailablePackages;
-use crate::cache::depgraph::DependencyGraph;
+use crate::cache::available::{mirror_list_path, AvailablePackages};
+use crate::cache::depgraph::{
+    DependencyGraph, DependencyGraphDiff, GraphSnapshot, GroupName, RequirementKind,
+    RequirementManagementMethod, SelectionManifest,
+};
 use crate::cache::downloaded::DownloadedPackages;
 use crate::cache::installed::InstalledPackages;
-use crate::lock_file::LockFileOwnership;
-use crate::package::RepositoryName;
+use crate::lock_file::{LockFileOwnership, LockFileReadOwnership, SharedLockFileOwnership};
+use crate::package::{NPFExplorer, PackageID, PackageRequirement, RepositoryName};
 use crate::repository::Repository;
+use crate::transaction::{
+    displaced_files_root, Journal, NoopObserver, Transaction, TransactionError,
+    TransactionErrorKind,
+};
+
+/// The name of the synthetic repository holding the packages installed from a local NPF, see
+/// [`Config::install_local_package`].
+///
+/// No repository of the configuration can have this name, see [`Config::validate`].
+pub const LOCAL_REPOSITORY: &str = "local";
+
+/// The schemes of the URLs of the mirrors Nest is able to download from
+const SUPPORTED_MIRROR_SCHEMES: &[&str] = &["http", "https", "file"];
 
 lazy_static! {
     static ref NEST_PATH_CONFIG: &'static Path = Path::new("/etc/nest/config.toml");
@@ -69,6 +92,25 @@ pub struct Config {
     repositories: HashMap<String, RepositoryConfig>,
     #[serde(default)]
     repositories_order: Vec<RepositoryName>,
+    #[serde(default)]
+    ui: UiConfig,
+    #[serde(default)]
+    network: NetworkConfig,
+    #[serde(default)]
+    install: InstallConfig,
+    #[serde(default)]
+    max_cache_age: Option<u64>,
+    #[serde(default)]
+    manifest_checksums: bool,
+}
+
+/// The content of a file of the repositories directory
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+struct RepositoriesFile {
+    #[serde(default)]
+    repositories: HashMap<String, RepositoryConfig>,
+    #[serde(default)]
+    repositories_order: Vec<RepositoryName>,
 }
 
 impl Config {
@@ -92,6 +134,18 @@ impl Config {
 
     /// Loads the configuration file located at the given path
     ///
+    /// The paths of the configuration can be overridden using environment variables, as described by
+    /// [`ConfigPaths::apply_env_overrides`][2].
+    ///
+    /// Once the file is parsed, the repositories described by each `*.toml` file of the
+    /// [repositories directory][1] are added to it, in alphabetical order of the files. Each of these files
+    /// may also append repositories to `repositories_order`. A repository can't be defined twice.
+    ///
+    /// The resulting configuration is then checked using [`Config::validate`].
+    ///
+    /// [1]: struct.ConfigPaths.html#method.repositories_dir
+    /// [2]: struct.ConfigPaths.html#method.apply_env_overrides
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -103,6 +157,66 @@ impl Config {
     /// let config = Config::load_from("./config.toml")?;
     /// # Ok(()) }
     /// ```
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::{Config, ConfigErrorKind};
+    ///
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-repos-d-{}", std::process::id()));
+    /// let repositories_dir = root.join("repos.d");
+    /// fs::create_dir_all(&repositories_dir)?;
+    /// fs::write(
+    ///     root.join("config.toml"),
+    ///     format!(
+    ///         r#"
+    ///         repositories_order = ["stable"]
+    ///
+    ///         [paths]
+    ///         repositories_dir = "{}"
+    ///
+    ///         [repositories.stable]
+    ///         mirrors = ["https://stable.raven-os.org"]
+    ///         "#,
+    ///         repositories_dir.display(),
+    ///     ),
+    /// )?;
+    ///
+    /// // An empty repositories directory doesn't change anything
+    /// let config = Config::load_from(root.join("config.toml"))?;
+    /// assert_eq!(config.repositories_config().len(), 1);
+    ///
+    /// // Repositories of the directory are merged into the configuration
+    /// fs::write(
+    ///     repositories_dir.join("beta.toml"),
+    ///     r#"
+    ///     repositories_order = ["beta"]
+    ///
+    ///     [repositories.beta]
+    ///     mirrors = ["https://beta.raven-os.org"]
+    ///     "#,
+    /// )?;
+    /// let config = Config::load_from(root.join("config.toml"))?;
+    /// assert_eq!(config.repositories_config().len(), 2);
+    /// let order: Vec<_> = config.repositories_order().iter().map(|name| name.as_str()).collect();
+    /// assert_eq!(order, vec!["stable", "beta"]);
+    ///
+    /// // A repository can't be defined twice
+    /// fs::write(
+    ///     repositories_dir.join("stable.toml"),
+    ///     r#"
+    ///     [repositories.stable]
+    ///     mirrors = ["https://mirror.example.org"]
+    ///     "#,
+    /// )?;
+    /// let error = Config::load_from(root.join("config.toml")).unwrap_err();
+    /// assert_eq!(*error.kind(), ConfigErrorKind::DuplicateRepository);
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     #[inline]
     pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
         let path = path.as_ref();
@@ -120,19 +234,288 @@ impl Config {
             .context(path.display().to_string())
             .context(ConfigErrorKind::ConfigLoadError)?;
 
-        let config: Config = toml::from_str(&s)
+        let mut config: Config = toml::from_str(&s)
             .context(path.display().to_string())
             .context(ConfigErrorKind::ConfigParseError)?;
 
-        if !config
-            .repositories_order()
-            .iter()
-            .all(|x| config.repositories_config().contains_key(x.deref()))
-        {
-            Err(ConfigErrorKind::InvalidConfigFile.into())
-        } else {
-            Ok(config)
+        config.paths.apply_env_overrides();
+        config.load_repositories_dir()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the configuration is consistent, returning a [`ConfigError`] of kind
+    /// [`ConfigErrorKind::InvalidConfig`] describing the first issue found otherwise.
+    ///
+    /// Every repository of `repositories_order` must be defined, and every repository must have at least
+    /// one mirror, whose URL must be usable as a base to build the URLs of the repository's resources.
+    /// Mirrors are reached either through `http`, `https` or `file` URLs. No repository can be named after the
+    /// [`LOCAL_REPOSITORY`]. The maximum age of the cache, if any, must fit in a number of seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::{Config, ConfigErrorKind};
+    ///
+    /// let config: Config = toml::from_str(
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = ["https://stable.raven-os.org"]
+    ///     "#,
+    /// )?;
+    /// assert!(config.validate().is_ok());
+    ///
+    /// // Mirrors can also be local directories
+    /// let config: Config = toml::from_str(
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = ["file:///srv/mirrors/stable/"]
+    ///     "#,
+    /// )?;
+    /// assert!(config.validate().is_ok());
+    ///
+    /// let invalid_configs = [
+    ///     // A repository of `repositories_order` isn't defined
+    ///     r#"
+    ///     repositories_order = ["stable", "beta"]
+    ///     [repositories.stable]
+    ///     mirrors = ["https://stable.raven-os.org"]
+    ///     "#,
+    ///     // A repository doesn't have any mirror
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = []
+    ///     "#,
+    ///     // A mirror's URL can't be used as a base
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = ["mailto:mirrors@raven-os.org"]
+    ///     "#,
+    ///     // A mirror's URL uses an unsupported scheme
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = ["ftp://stable.raven-os.org"]
+    ///     "#,
+    ///     // The maximum age of the cache can't be represented in seconds
+    ///     r#"
+    ///     max_cache_age = 1000000000000000
+    ///     "#,
+    ///     // The name of the repository of local packages is reserved
+    ///     r#"
+    ///     repositories_order = ["local"]
+    ///     [repositories.local]
+    ///     mirrors = ["file:///srv/mirrors/local/"]
+    ///     "#,
+    /// ];
+    /// for invalid_config in invalid_configs.iter() {
+    ///     let config: Config = toml::from_str(invalid_config)?;
+    ///     let error = config.validate().unwrap_err();
+    ///     assert_eq!(*error.kind(), ConfigErrorKind::InvalidConfig);
+    /// }
+    ///
+    /// // The configuration is validated when it's loaded
+    /// let path = std::env::temp_dir().join(format!("nest-doctest-validate-{}.toml", std::process::id()));
+    /// std::fs::write(&path, invalid_configs[0])?;
+    /// let error = Config::load_from(&path).unwrap_err();
+    /// assert_eq!(*error.kind(), ConfigErrorKind::InvalidConfig);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for name in &self.repositories_order {
+            if !self.repositories.contains_key(name.deref()) {
+                Err(format_err!(
+                    "repository '{}' is listed in `repositories_order` but isn't defined",
+                    name.deref()
+                ))
+                .context(ConfigErrorKind::InvalidConfig)?;
+            }
+        }
+
+        if let Some(days) = self.max_cache_age {
+            if self.max_cache_age_secs().is_none() {
+                Err(format_err!("`max_cache_age` is too large: {} days", days))
+                    .context(ConfigErrorKind::InvalidConfig)?;
+            }
+        }
+
+        // Check the repositories in alphabetical order, so the reported issue doesn't change between runs
+        let mut names: Vec<_> = self.repositories.keys().collect();
+        names.sort();
+        for name in names {
+            if name == LOCAL_REPOSITORY {
+                Err(format_err!(
+                    "repository '{}' has a name reserved for the packages installed from a local NPF",
+                    name
+                ))
+                .context(ConfigErrorKind::InvalidConfig)?;
+            }
+
+            let repository = &self.repositories[name];
+            if repository.mirrors().is_empty() && repository.mirrorlist_url().is_none() {
+                Err(format_err!("repository '{}' doesn't have any mirror", name))
+                    .context(ConfigErrorKind::InvalidConfig)?;
+            }
+            for mirror in repository
+                .mirrors()
+                .iter()
+                .chain(repository.mirrorlist_url())
+            {
+                if mirror.cannot_be_a_base() {
+                    Err(format_err!(
+                        "repository '{}' has an invalid mirror: {}",
+                        name,
+                        mirror.as_str()
+                    ))
+                    .context(ConfigErrorKind::InvalidConfig)?;
+                }
+                if !SUPPORTED_MIRROR_SCHEMES.contains(&mirror.scheme()) {
+                    Err(format_err!(
+                        "repository '{}' has a mirror with an unsupported scheme '{}' (expected one of {}): {}",
+                        name,
+                        mirror.scheme(),
+                        SUPPORTED_MIRROR_SCHEMES.join(", "),
+                        mirror.as_str()
+                    ))
+                    .context(ConfigErrorKind::InvalidConfig)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the mirrors of the cached mirror list of each repository having a mirror list URL to its mirrors, after those
+    /// of the configuration.
+    ///
+    /// Repositories whose mirror list wasn't fetched yet are left untouched. Mirror lists are fetched and cached
+    /// through [`AvailablePackages::save_mirror_list`][1].
+    ///
+    /// [1]: ../cache/available/struct.AvailablePackages.html#method.save_mirror_list
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::{parse_mirror_list, Config};
+    ///
+    /// let mut config: Config = toml::from_str(
+    ///     r#"
+    ///     repositories_order = ["stable"]
+    ///     [repositories.stable]
+    ///     mirrors = ["https://stable.raven-os.org"]
+    ///     mirrorlist_url = "https://raven-os.org/mirrorlist"
+    ///     "#,
+    /// )?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-mirror-list-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let mirrors = |config: &Config| -> Vec<String> {
+    ///     config.repositories()[0].config().mirrors().iter().map(|mirror| mirror.to_string()).collect()
+    /// };
+    ///
+    /// // The mirror list wasn't fetched yet
+    /// config.load_mirror_lists()?;
+    /// assert_eq!(mirrors(&config), vec!["https://stable.raven-os.org/"]);
+    ///
+    /// // Once it's fetched and cached, its mirrors are used along with those of the configuration
+    /// {
+    ///     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///     let fetched = parse_mirror_list("https://mirror1.raven-os.org\nhttps://stable.raven-os.org\nhttps://mirror2.raven-os.org\n")?;
+    ///     config
+    ///         .available_packages_cache(&lock_file_ownership)
+    ///         .save_mirror_list(&config.repositories()[0], &fetched)?;
+    /// }
+    /// config.load_mirror_lists()?;
+    /// assert_eq!(
+    ///     mirrors(&config),
+    ///     vec!["https://stable.raven-os.org/", "https://mirror1.raven-os.org/", "https://mirror2.raven-os.org/"],
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_mirror_lists(&mut self) -> Result<(), MirrorListError> {
+        let mut mirror_lists = Vec::new();
+
+        for (name, repository) in &self.repositories {
+            if repository.mirrorlist_url().is_none() {
+                continue;
+            }
+
+            let path = mirror_list_path(self.paths.available(), name);
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => Err(e)
+                    .context(path.display().to_string())
+                    .context(MirrorListErrorKind::MirrorListLoadError)?,
+            };
+            mirror_lists.push((name.clone(), parse_mirror_list(&content)?));
         }
+
+        for (name, mirrors) in mirror_lists {
+            if let Some(repository) = self.repositories.get_mut(&name) {
+                repository.extend_mirrors(mirrors);
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the repositories of each file of the repositories directory into this configuration
+    fn load_repositories_dir(&mut self) -> Result<(), ConfigError> {
+        let dir = self.paths.repositories_dir().to_path_buf();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => Err(e)
+                .context(dir.display().to_string())
+                .context(ConfigErrorKind::ConfigLoadError)?,
+        };
+
+        let mut paths = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .context(dir.display().to_string())
+            .context(ConfigErrorKind::ConfigLoadError)?;
+        paths.retain(|path| path.extension().map_or(false, |ext| ext == "toml") && path.is_file());
+        paths.sort();
+
+        for path in paths {
+            let s = fs::read_to_string(&path)
+                .context(path.display().to_string())
+                .context(ConfigErrorKind::ConfigLoadError)?;
+            let repositories: RepositoriesFile = toml::from_str(&s)
+                .context(path.display().to_string())
+                .context(ConfigErrorKind::ConfigParseError)?;
+
+            for (name, repository) in repositories.repositories {
+                if self.repositories.contains_key(&name) {
+                    Err(format_err!(
+                        "{}: repository '{}' is already defined",
+                        path.display(),
+                        name
+                    ))
+                    .context(ConfigErrorKind::DuplicateRepository)?;
+                }
+                self.repositories.insert(name, repository);
+            }
+            self.repositories_order
+                .extend(repositories.repositories_order);
+        }
+        Ok(())
     }
 
     /// Returns a reference to an intermediate structure holding all important paths that are used by `libnest`.
@@ -173,21 +556,170 @@ impl Config {
         &self.repositories_order
     }
 
+    /// Returns a reference to an intermediate structure holding the configuration of the user interface
+    #[inline]
+    pub fn ui(&self) -> &UiConfig {
+        &self.ui
+    }
+
+    /// Returns a mutable reference to an intermediate structure holding the configuration of the user interface
+    #[inline]
+    pub fn ui_mut(&mut self) -> &mut UiConfig {
+        &mut self.ui
+    }
+
+    /// Returns a reference to an intermediate structure holding the configuration of the network operations
+    #[inline]
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    /// Returns a mutable reference to an intermediate structure holding the configuration of the network operations
+    #[inline]
+    pub fn network_mut(&mut self) -> &mut NetworkConfig {
+        &mut self.network
+    }
+
+    /// Returns a reference to an intermediate structure holding the configuration of package installations
+    #[inline]
+    pub fn install(&self) -> &InstallConfig {
+        &self.install
+    }
+
+    /// Returns a mutable reference to an intermediate structure holding the configuration of package installations
+    #[inline]
+    pub fn install_mut(&mut self) -> &mut InstallConfig {
+        &mut self.install
+    }
+
+    /// Returns the maximum age of the cache of available packages, in days, after which the repositories should be
+    /// pulled again, if any
+    #[inline]
+    pub fn max_cache_age(&self) -> Option<u64> {
+        self.max_cache_age
+    }
+
+    /// Returns a mutable reference over the maximum age of the cache of available packages, in days
+    #[inline]
+    pub fn max_cache_age_mut(&mut self) -> &mut Option<u64> {
+        &mut self.max_cache_age
+    }
+
+    /// Returns the maximum age of the cache of available packages in seconds, or `None` if there is none or if it
+    /// overflows
+    fn max_cache_age_secs(&self) -> Option<u64> {
+        self.max_cache_age?.checked_mul(24 * 3600)
+    }
+
+    /// Returns whether the checksum of the manifests of the cache of available packages is recorded when they are
+    /// written, so that [`AvailablePackages::verify_integrity`] can detect their corruption.
+    ///
+    /// It's disabled by default, as it doubles the number of files of the cache.
+    #[inline]
+    pub fn manifest_checksums(&self) -> bool {
+        self.manifest_checksums
+    }
+
+    /// Returns a mutable reference over whether the checksum of the manifests of the cache of available packages is
+    /// recorded
+    #[inline]
+    pub fn manifest_checksums_mut(&mut self) -> &mut bool {
+        &mut self.manifest_checksums
+    }
+
+    /// Returns the sorted names of the repositories whose cache is older than the maximum age given by the configuration,
+    /// or that were never pulled.
+    ///
+    /// If there is no maximum age, or if it's too large to be represented, no repository is ever stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate chrono;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use chrono::{Duration, Utc};
+    /// use libnest::config::Config;
+    /// use libnest::package::RepositoryName;
+    ///
+    /// let mut config: Config = toml::from_str(
+    ///     r#"
+    ///     max_cache_age = 30
+    ///     repositories_order = ["stable", "beta", "unstable"]
+    ///     [repositories.stable]
+    ///     mirrors = []
+    ///     [repositories.beta]
+    ///     mirrors = []
+    ///     [repositories.unstable]
+    ///     mirrors = []
+    ///     "#,
+    /// )?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-stale-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// for repository in config.repositories() {
+    ///     match repository.name() {
+    ///         "stable" => cache.save_last_pull(&repository, Utc::now() - Duration::days(2))?,
+    ///         "beta" => cache.save_last_pull(&repository, Utc::now() - Duration::days(40))?,
+    ///         _ => (), // `unstable` was never pulled
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(
+    ///     config.stale_repositories(&lock_file_ownership),
+    ///     vec![RepositoryName::parse("beta")?, RepositoryName::parse("unstable")?],
+    /// );
+    ///
+    /// *config.max_cache_age_mut() = None;
+    /// assert!(config.stale_repositories(&lock_file_ownership).is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stale_repositories<L: LockFileReadOwnership>(
+        &self,
+        lock_file_ownership: &L,
+    ) -> Vec<RepositoryName> {
+        let max_age = match self.max_cache_age_secs() {
+            Some(secs) => Duration::from_secs(secs),
+            None => return Vec::new(),
+        };
+
+        let mut stale: Vec<_> = self
+            .repositories()
+            .into_iter()
+            .filter(|repository| {
+                repository
+                    .cache_age(self, lock_file_ownership)
+                    .map_or(true, |age| age > max_age)
+            })
+            .filter_map(|repository| RepositoryName::parse(repository.name()).ok())
+            .collect();
+        stale.sort();
+        stale
+    }
+
     pub(crate) fn available_packages_cache_internal<'a, 'b>(
         &'b self,
         phantom: PhantomData<&'a LockFileOwnership>,
     ) -> AvailablePackages<'b, 'a> {
         AvailablePackages::from(self.paths().available(), phantom)
+            .with_checksums(self.manifest_checksums)
     }
 
-    /// Returns a handle over the cache containing available packages
-    pub fn available_packages_cache<'a, 'b>(
+    /// Returns a handle over the cache containing available packages.
+    ///
+    /// It can be read with a shared ownership over the lock file, but writing to it requires the exclusive one.
+    pub fn available_packages_cache<'a, 'b, L: LockFileReadOwnership>(
         &'b self,
-        _: &'a LockFileOwnership,
-    ) -> AvailablePackages<'b, 'a> {
-        let phantom: PhantomData<&'a LockFileOwnership> = PhantomData;
-
-        self.available_packages_cache_internal(phantom)
+        _: &'a L,
+    ) -> AvailablePackages<'b, 'a, L> {
+        AvailablePackages::from(self.paths().available(), PhantomData)
+            .with_checksums(self.manifest_checksums)
     }
 
     fn dependency_graph_internal<'a>(
@@ -198,10 +730,12 @@ impl Config {
         DependencyGraph::load_from_cache(path, phantom)
     }
 
-    /// Returns a handle over the dependency graph, or an error if it could not be loaded
-    pub fn dependency_graph<'a>(
+    /// Returns a handle over the dependency graph, or an error if it could not be loaded.
+    ///
+    /// It can be loaded with a shared ownership over the lock file, but saving it back requires the exclusive one.
+    pub fn dependency_graph<'a, L: LockFileReadOwnership>(
         &self,
-        _: &'a LockFileOwnership,
+        _: &'a L,
     ) -> Result<DependencyGraph<'a>, Error> {
         let phantom: PhantomData<&'a LockFileOwnership> = PhantomData;
 
@@ -222,6 +756,649 @@ impl Config {
         }
     }
 
+    /// Writes the dependency graph captured by the given snapshot back to the disk, replacing the current one.
+    ///
+    /// Along with [`DependencyGraph::snapshot`][1], this allows frontends to undo changes made to the dependency
+    /// graph, like an update whose result isn't satisfying.
+    ///
+    /// [1]: ../cache/depgraph/struct.DependencyGraph.html#method.snapshot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::str::FromStr;
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-restore-graph-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let original_graph = graph.clone();
+    /// let snapshot = graph.snapshot();
+    ///
+    /// // Change the graph and save it
+    /// let name = GroupName::from_str("@desktop")?;
+    /// graph.add_group_node(name.clone())?;
+    /// graph.node_add_requirement(
+    ///     graph.root_id(),
+    ///     RequirementKind::Group { name },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    /// assert_ne!(config.dependency_graph(&lock_file_ownership)?, original_graph);
+    ///
+    /// // Undo the changes
+    /// config.restore_graph(snapshot, &lock_file_ownership)?;
+    /// assert_eq!(config.dependency_graph(&lock_file_ownership)?, original_graph);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn restore_graph(
+        &self,
+        snapshot: GraphSnapshot,
+        lock_ownership: &LockFileOwnership,
+    ) -> Result<(), Error> {
+        let mut graph = DependencyGraph::new(PhantomData);
+        graph.restore(snapshot);
+        graph.save_to_cache(self.paths.depgraph(), lock_ownership)
+    }
+
+    /// Returns every package that would be installed to fulfill the given requirement on an empty system, that is,
+    /// the package matching it and all of its dependencies, sorted.
+    ///
+    /// The requirement is solved in a throwaway dependency graph: neither the dependency graph nor the scratch one
+    /// are modified, and nothing is written to the disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// # use libnest::config::Config;
+    /// # use libnest::package::PackageManifest;
+    /// use libnest::package::PackageRequirement;
+    ///
+    /// # let mut config: Config =
+    /// #     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-resolve-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `app` depends on `libfoo`, which depends on `glibc`
+    /// # let cache = config.available_packages_cache(&lock_file_ownership);
+    /// # for (name, dependencies) in &[
+    /// #     ("app", r#"["tests::sys-libs/libfoo#^1.0.0"]"#),
+    /// #     ("libfoo", r#"["tests::sys-libs/glibc#^1.0.0"]"#),
+    /// #     ("glibc", "[]"),
+    /// # ] {
+    /// #     let manifest: PackageManifest = serde_json::from_str(&format!(
+    /// #         r#"{{
+    /// #             "name": "{}",
+    /// #             "category": "sys-libs",
+    /// #             "repository": "tests",
+    /// #             "metadata": {{
+    /// #                 "description": "",
+    /// #                 "tags": [],
+    /// #                 "maintainer": "nest@raven-os.org",
+    /// #                 "licenses": [],
+    /// #                 "upstream_url": null
+    /// #             }},
+    /// #             "versions": {{
+    /// #                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": {} }}
+    /// #             }}
+    /// #         }}"#,
+    /// #         name, dependencies,
+    /// #     ))?;
+    /// #     cache.update(&manifest)?;
+    /// # }
+    /// let packages = config.resolve_dependencies(
+    ///     &PackageRequirement::parse("sys-libs/app")?,
+    ///     &lock_file_ownership,
+    /// )?;
+    /// let packages: Vec<_> = packages.iter().map(ToString::to_string).collect();
+    /// assert_eq!(
+    ///     packages,
+    ///     vec![
+    ///         "tests::sys-libs/app#1.0.0",
+    ///         "tests::sys-libs/glibc#1.0.0",
+    ///         "tests::sys-libs/libfoo#1.0.0",
+    ///     ],
+    /// );
+    ///
+    /// // The dependency graph wasn't touched
+    /// assert!(!config.paths().depgraph().exists());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_dependencies(
+        &self,
+        requirement: &PackageRequirement,
+        _: &LockFileOwnership,
+    ) -> Result<Vec<PackageID>, Error> {
+        let mut graph = DependencyGraph::new(PhantomData);
+        graph.node_add_requirement(
+            graph.root_id(),
+            RequirementKind::Package {
+                package_req: requirement.clone(),
+            },
+            RequirementManagementMethod::Static,
+        );
+        graph.solve(self)?;
+
+        let mut packages: Vec<_> = graph
+            .nodes()
+            .values()
+            .filter_map(|node| node.kind().package())
+            .cloned()
+            .collect();
+        packages.sort();
+        Ok(packages)
+    }
+
+    /// Rebuilds a scratch dependency graph selecting the packages of the given selection, solves it and saves it.
+    ///
+    /// The current dependency graph is left untouched: the returned graph can be merged into it like any other
+    /// scratch graph.
+    /// If the selection was exported by a newer version of Nest, a [`DependencyGraphError`] of kind
+    /// [`DependencyGraphErrorKind::UnsupportedSelectionVersion`] is returned.
+    ///
+    /// [`DependencyGraphError`]: ../cache/struct.DependencyGraphError.html
+    /// [`DependencyGraphErrorKind::UnsupportedSelectionVersion`]: ../cache/enum.DependencyGraphErrorKind.html#variant.UnsupportedSelectionVersion
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod, SelectionManifest};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    ///
+    /// // Sets up a system whose repository contains a few packages
+    /// let system = |name: &str| -> Result<Config, failure::Error> {
+    ///     let mut config: Config =
+    ///         toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    ///     let root = std::env::temp_dir().join(format!("nest-doctest-selection-{}-{}", name, std::process::id()));
+    ///     *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    ///     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///     let cache = config.available_packages_cache(&lock_file_ownership);
+    ///     for (name, dependencies) in &[("nginx", r#"["tests::dev-libs/openssl#^1.1.0"]"#), ("openssl", "[]"), ("vim", "[]")] {
+    ///         let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///             r#"{{
+    ///                 "name": "{}",
+    ///                 "category": "dev-libs",
+    ///                 "repository": "tests",
+    ///                 "metadata": {{
+    ///                     "description": "",
+    ///                     "tags": [],
+    ///                     "maintainer": "nest@raven-os.org",
+    ///                     "licenses": [],
+    ///                     "upstream_url": null
+    ///                 }},
+    ///                 "versions": {{
+    ///                     "1.1.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": {} }}
+    ///                 }}
+    ///             }}"#,
+    ///             name, dependencies,
+    ///         ))?;
+    ///         cache.update(&manifest)?;
+    ///     }
+    ///     Ok(config)
+    /// };
+    /// let installed = |graph: &libnest::cache::depgraph::DependencyGraph| {
+    ///     let mut packages: Vec<_> = graph
+    ///         .nodes()
+    ///         .values()
+    ///         .filter_map(|node| node.kind().package())
+    ///         .map(|id| id.to_string())
+    ///         .collect();
+    ///     packages.sort();
+    ///     packages
+    /// };
+    ///
+    /// // On the first system, vim is required directly, and nginx through a group
+    /// let config = system("first")?;
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let web: GroupName = "@web".parse()?;
+    /// graph.add_group_node(web.clone())?;
+    /// graph.node_add_requirement(graph.root_id(), RequirementKind::Group { name: web.clone() }, RequirementManagementMethod::Static);
+    /// graph.add_requirement_to_group(&web, PackageRequirement::parse("dev-libs/nginx")?, RequirementManagementMethod::Static)?;
+    /// graph.add_requirement_to_group(&GroupName::root_group(), PackageRequirement::parse("dev-libs/vim")?, RequirementManagementMethod::Static)?;
+    /// graph.solve(&config)?;
+    ///
+    /// let exported = serde_json::to_string(&graph.export_selection())?;
+    ///
+    /// // The second system ends up with the same packages
+    /// let other_config = system("second")?;
+    /// let other_lock_file_ownership = other_config.acquire_lock_file_ownership(true)?;
+    /// let selection: SelectionManifest = serde_json::from_str(&exported)?;
+    /// let other_graph = other_config.apply_selection(&selection, &other_lock_file_ownership)?;
+    ///
+    /// assert_eq!(
+    ///     installed(&other_graph),
+    ///     vec!["tests::dev-libs/nginx#1.1.0", "tests::dev-libs/openssl#1.1.0", "tests::dev-libs/vim#1.1.0"],
+    /// );
+    /// assert_eq!(installed(&other_graph), installed(&graph));
+    /// assert_eq!(other_graph.export_selection(), graph.export_selection());
+    /// assert_eq!(other_config.scratch_dependency_graph(&other_lock_file_ownership)?, other_graph);
+    /// # std::fs::remove_dir_all(config.paths().root())?;
+    /// # std::fs::remove_dir_all(other_config.paths().root())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_selection<'a>(
+        &self,
+        selection: &SelectionManifest,
+        lock_ownership: &'a LockFileOwnership,
+    ) -> Result<DependencyGraph<'a>, Error> {
+        let phantom: PhantomData<&'a LockFileOwnership> = PhantomData;
+
+        let mut graph = DependencyGraph::from_selection(selection, phantom)?;
+        graph.solve(self)?;
+        graph.save_to_cache(self.paths.scratch_depgraph(), lock_ownership)?;
+        Ok(graph)
+    }
+
+    /// Requires the package of the NPF at the given path in the given dependency graph, even though it doesn't have
+    /// to belong to any repository.
+    ///
+    /// The package is recorded as part of the synthetic [`LOCAL_REPOSITORY`]: its manifest is added to the cache of
+    /// available packages and its NPF is copied to the cache of downloaded packages. It's then required by the root
+    /// group of the graph, replacing any other local version of the same package, and its dependencies are solved
+    /// against the repositories like any other package.
+    ///
+    /// Nothing is installed: the transactions to apply are found by diffing the graph, and are then downloaded and
+    /// performed like any others. The [`PackageID`] of the package is returned. See [`install_local_package`][1] to
+    /// install the package right away.
+    ///
+    /// [1]: #method.install_local_package
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::cache::depgraph::DependencyGraphDiff;
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageID};
+    /// use libnest::transaction::{NoopObserver, Transaction};
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-local-package-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// // `npf_with` builds an NPF of `hello` with the given dependencies, that isn't part of any repository
+    /// # let npf_with = |dependencies: &str| -> Result<std::path::PathBuf, failure::Error> {
+    /// #     let path = root.join("build/hello-1.0.0.nest");
+    /// #     write_npf(&path, "local::sys-apps/hello#1.0.0", &format!("dependencies = {}", dependencies), &[],
+    /// #         &[("usr/bin/hello", 0o755, "hello")])?;
+    /// #     Ok(path)
+    /// # };
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let original_graph = config.dependency_graph(&lock_file_ownership)?;
+    ///
+    /// // Dependencies are still solved against the repositories
+    /// let mut graph = original_graph.clone();
+    /// let npf = npf_with(r#"["stable::sys-libs/glibc#^2.0.0"]"#)?;
+    /// assert!(config.require_local_package(&npf, &mut graph, &lock_file_ownership).is_err());
+    ///
+    /// let mut graph = original_graph.clone();
+    /// let npf = npf_with("[]")?;
+    /// let hello = config.require_local_package(&npf, &mut graph, &lock_file_ownership)?;
+    /// assert_eq!(hello, PackageID::parse("local::sys-apps/hello#1.0.0")?);
+    /// assert!(graph.get_package_node(&PackageFullName::parse("local::sys-apps/hello")?).is_ok());
+    ///
+    /// // Nothing is installed until the transactions are performed
+    /// assert!(!root.join("usr/bin/hello").exists());
+    /// let transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
+    /// assert_eq!(transactions.len(), 1);
+    /// for transaction in &transactions {
+    ///     if let Transaction::Install(install) = transaction {
+    ///         install.extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    ///     }
+    /// }
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/hello"))?, "hello");
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn require_local_package(
+        &self,
+        path: &Path,
+        graph: &mut DependencyGraph,
+        lock_ownership: &LockFileOwnership,
+    ) -> Result<PackageID, Error> {
+        let npf_explorer = NPFExplorer::from(path).with_context(|_| path.display().to_string())?;
+        let repository = RepositoryName::parse(LOCAL_REPOSITORY)?;
+        let manifest = npf_explorer.manifest();
+        let id = manifest.id(repository.clone());
+
+        // The NPF can't carry its own hash, so the one of its manifest is meaningless
+        let mut package_manifest = manifest.package_manifest(repository);
+        for version_data in package_manifest.versions_mut().values_mut() {
+            *version_data.hash_mut() = None;
+        }
+
+        // Keep the other local versions of the package, in case they are still installed
+        self.available_packages_cache(lock_ownership)
+            .register(package_manifest)?;
+
+        self.downloaded_packages_cache(lock_ownership)
+            .import_package(&id, path)
+            .with_context(|_| path.display().to_string())?;
+
+        // Replace the requirements on other local versions of the same package
+        let root_id = graph.root_id();
+        for requirement in graph.group_members(&GroupName::root_group())? {
+            let is_same_package = match &requirement {
+                RequirementKind::Package { package_req } => {
+                    package_req.repository().as_ref() == Some(id.repository())
+                        && package_req.category() == id.category()
+                        && package_req.name() == id.name()
+                }
+                _ => false,
+            };
+            if is_same_package {
+                graph.node_remove_requirement(root_id, requirement);
+            }
+        }
+        graph.add_requirements(
+            self,
+            vec![(GroupName::root_group(), PackageRequirement::from_id(&id))],
+            RequirementManagementMethod::Static,
+        )?;
+        Ok(id)
+    }
+
+    /// Installs the package of the NPF at the given path, even though it doesn't have to belong to any repository.
+    ///
+    /// The package is [required in the dependency graph][1] as part of the synthetic [`LOCAL_REPOSITORY`], and the
+    /// transactions leading to the new graph are then performed through a [`Journal`], so that an interrupted
+    /// installation can be resumed. The dependencies of the package are solved against the repositories, but this
+    /// function doesn't download anything: their NPFs must already be in the cache of downloaded packages.
+    ///
+    /// [1]: #method.require_local_package
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::package::PackageID;
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-install-local-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// // `hello-1.0.0.nest` is an NPF that isn't part of any repository
+    /// let npf = root.join("build/hello-1.0.0.nest");
+    /// # write_npf(&npf, "local::sys-apps/hello#1.0.0", "", &[], &[("usr/bin/hello", 0o755, "hello")])?;
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// config.install_local_package(&npf, &lock_file_ownership)?;
+    ///
+    /// let installed: Vec<_> = config.installed_packages_cache(&lock_file_ownership).iter()?.collect();
+    /// assert_eq!(installed, vec![PackageID::parse("local::sys-apps/hello#1.0.0")?]);
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/hello"))?, "hello");
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn install_local_package(
+        &self,
+        path: &Path,
+        lock_ownership: &LockFileOwnership,
+    ) -> Result<(), Error> {
+        let original_graph = self.dependency_graph(lock_ownership)?;
+        let mut graph = original_graph.clone();
+        self.require_local_package(path, &mut graph, lock_ownership)?;
+
+        let transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
+
+        // Nothing is downloaded here, so the transactions can only be performed if all their NPFs are available
+        let downloaded_packages = self.downloaded_packages_cache(lock_ownership);
+        for transaction in &transactions {
+            let download = match transaction {
+                Transaction::Install(install) => install.associated_download(),
+                Transaction::Upgrade(upgrade) => upgrade.associated_download(),
+                Transaction::Downgrade(downgrade) => downgrade.associated_download(),
+                Transaction::Reinstall(reinstall) => reinstall.associated_download(),
+                _ => continue,
+            };
+            if !downloaded_packages.has_package(download.target()) {
+                return Err(format_err!(
+                    "{}: the package isn't downloaded",
+                    download.target()
+                ));
+            }
+        }
+
+        let mut journal = Journal::new(&transactions, graph);
+        self.write_journal(&journal, lock_ownership)?;
+        for transaction in journal.pending() {
+            match transaction {
+                Transaction::Install(install) => {
+                    install.extract(self, lock_ownership, &mut NoopObserver)?
+                }
+                Transaction::Upgrade(upgrade) => {
+                    upgrade.perform(self, lock_ownership, &mut NoopObserver)?
+                }
+                Transaction::Downgrade(downgrade) => {
+                    downgrade.perform(self, lock_ownership, &mut NoopObserver)?
+                }
+                Transaction::Reinstall(reinstall) => {
+                    reinstall.perform(self, lock_ownership, &mut NoopObserver)?
+                }
+                Transaction::Remove(remove) => {
+                    remove.perform(self, lock_ownership, &mut NoopObserver)?
+                }
+                Transaction::Pull(_) => (),
+            }
+            journal.mark_completed();
+            self.write_journal(&journal, lock_ownership)?;
+        }
+
+        journal
+            .graph()
+            .save_to_cache(self.paths.depgraph(), lock_ownership)?;
+        self.clear_journal(lock_ownership)?;
+        Ok(())
+    }
+
+    /// Returns the transactions required to go from the current dependency graph to the given one,
+    /// without applying them.
+    ///
+    /// The current dependency graph is read without acquiring the lock file, and nothing is written,
+    /// which makes it suitable for a dry run. The transactions are ordered like those of
+    /// [`DependencyGraphDiff::perform`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::cache::depgraph::{RequirementKind, RequirementManagementMethod};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageManifest, PackageRequirement};
+    /// use libnest::transaction::Transaction;
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-plan-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// // `app` depends on `lib`
+    /// for (name, dependencies) in &[("app", r#"["sys-libs/lib#*"]"#), ("lib", "[]")] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-libs",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": {} }}
+    ///             }}
+    ///         }}"#,
+    ///         name, dependencies,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let mut target = config.dependency_graph(&lock_file_ownership)?;
+    /// target.node_add_requirement(
+    ///     target.root_id(),
+    ///     RequirementKind::Package {
+    ///         package_req: PackageRequirement::parse("sys-libs/app")?,
+    ///     },
+    ///     RequirementManagementMethod::Static,
+    /// );
+    /// target.solve(&config)?;
+    ///
+    /// let transactions = config.plan_transaction(&target)?;
+    /// let installed: Vec<_> = transactions
+    ///     .iter()
+    ///     .map(|transaction| match transaction {
+    ///         Transaction::Install(install) => install.target().to_string(),
+    ///         _ => panic!("unexpected transaction"),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(installed, vec!["tests::sys-libs/lib#1.0.0", "tests::sys-libs/app#1.0.0"]);
+    ///
+    /// // Nothing was written
+    /// assert!(!config.paths().depgraph().exists());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn plan_transaction<'a, 'b>(
+        &self,
+        target: &DependencyGraph,
+    ) -> Result<Vec<Transaction<'a, 'b>>, Error> {
+        let phantom = PhantomData;
+        let current = self.dependency_graph_internal(self.paths.depgraph(), phantom)?;
+
+        Ok(DependencyGraphDiff::new().perform(&current, target))
+    }
+
+    /// Writes the given journal to the disk, replacing the previous one if any.
+    ///
+    /// See [`Journal`] for an example.
+    pub fn write_journal(&self, journal: &Journal, _: &LockFileOwnership) -> Result<(), Error> {
+        let path = self.paths.journal();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|_| parent.display().to_string())?;
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format_err!("invalid journal path '{}'", path.display()))?;
+        let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+        let res: Result<(), Error> = try {
+            let mut file = File::create(&tmp_path)?;
+            serde_json::to_writer(&file, journal)?;
+            writeln!(file)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)?;
+        };
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        Ok(res.with_context(|_| path.display().to_string())?)
+    }
+
+    /// Loads the journal left by an interrupted run, or returns [`None`] if there is none.
+    ///
+    /// If the journal can't be parsed, it is moved next to its original location with a `.unparsable`
+    /// extension, so that it doesn't get in the way of the next runs, and an error of kind
+    /// [`TransactionErrorKind::UnparsableJournal`] is returned.
+    ///
+    /// See [`Journal`] for an example.
+    ///
+    /// [`TransactionErrorKind::UnparsableJournal`]: ../transaction/enum.TransactionErrorKind.html#variant.UnparsableJournal
+    pub fn load_journal<'a>(&self, _: &'a LockFileOwnership) -> Result<Option<Journal<'a>>, Error> {
+        let path = self.paths.journal();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(path).with_context(|_| path.display().to_string())?;
+        match serde_json::from_reader(&file) {
+            Ok(journal) => Ok(Some(journal)),
+            Err(e) => {
+                let set_aside = path.with_extension("unparsable");
+                fs::rename(path, &set_aside).with_context(|_| path.display().to_string())?;
+                Err(TransactionError::from(
+                    format_err!("{}: {}", path.display(), e)
+                        .context(TransactionErrorKind::UnparsableJournal { set_aside }),
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Removes the journal from the disk, if any, along with the files kept aside to roll back the forced
+    /// installations it recorded.
+    ///
+    /// See [`Journal`] for an example.
+    pub fn clear_journal(&self, _: &LockFileOwnership) -> Result<(), Error> {
+        let path = self.paths.journal();
+
+        if path.exists() {
+            fs::remove_file(path).with_context(|_| path.display().to_string())?;
+        }
+
+        let displaced_files = displaced_files_root(&self.paths);
+        if displaced_files.exists() {
+            fs::remove_dir_all(&displaced_files)
+                .with_context(|_| displaced_files.display().to_string())?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn installed_packages_cache_internal<'a, 'b>(
         &'b self,
         phantom: PhantomData<&'a LockFileOwnership>,
@@ -256,7 +1433,215 @@ impl Config {
         self.downloaded_packages_cache_internal(phantom)
     }
 
-    /// Acquire the ownership over Nest's lock file
+    /// Moves the caches of Nest, the dependency graphs and the journal to the given directory, and updates the paths
+    /// of this configuration accordingly.
+    ///
+    /// Each of them keeps its file name: the cache of available packages is moved to `<new_root>/available`, the
+    /// dependency graph to `<new_root>/depgraph`, and so on. Entries are renamed, and only copied when the new
+    /// directory is on another file system: the copied entries are only removed from their old location once all of
+    /// them were moved successfully. If any of them can't be moved, those already moved are put back, and the paths of
+    /// the configuration are left untouched. The entries that can't be put back, if any, are listed in the error.
+    ///
+    /// The configuration file isn't rewritten, so the new paths must be saved there to be used by the next instances of
+    /// Nest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use std::path::PathBuf;
+    /// use libnest::cache::depgraph::{GroupName, RequirementKind, RequirementManagementMethod};
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageFullName, PackageID, PackageManifest, Slot};
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-relocate-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // Populate the store
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "gcc",
+    ///         "category": "sys-devel",
+    ///         "repository": "stable",
+    ///         "metadata": {
+    ///             "description": "The GNU Compiler Collection",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "9.1.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// let gcc = PackageID::parse("stable::sys-devel/gcc#9.1.0")?;
+    /// let npf = root.join("gcc-9.1.0.nest");
+    /// fs::write(&npf, "not really an NPF")?;
+    /// config.downloaded_packages_cache(&lock_file_ownership).import_package(&gcc, &npf)?;
+    ///
+    /// let log = Log::new(vec![FileLogEntry::new(PathBuf::from("/usr/bin/gcc"), FileType::File)]);
+    /// config.installed_packages_cache(&lock_file_ownership).save_package_log(&gcc, &Slot::default(), &log)?;
+    ///
+    /// let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    /// let name: GroupName = "@devel".parse()?;
+    /// graph.add_group_node(name.clone())?;
+    /// graph.node_add_requirement(graph.root_id(), RequirementKind::Group { name }, RequirementManagementMethod::Static);
+    /// graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    ///
+    /// let old_available = config.paths().available().to_path_buf();
+    /// let old_depgraph = config.paths().depgraph().to_path_buf();
+    ///
+    /// // Move it to another directory
+    /// let new_root = std::env::temp_dir().join(format!("nest-doctest-relocated-{}", std::process::id()));
+    /// config.relocate_store(&new_root, &lock_file_ownership)?;
+    ///
+    /// assert_eq!(config.paths().available(), new_root.join("available"));
+    /// assert_eq!(config.paths().depgraph(), new_root.join("depgraph"));
+    /// assert!(!old_available.exists());
+    /// assert!(!old_depgraph.exists());
+    ///
+    /// let available = config.available_packages_cache(&lock_file_ownership);
+    /// assert_eq!(available.package_manifest(&PackageFullName::parse("stable::sys-devel/gcc")?)?, Some(manifest));
+    /// assert!(config.downloaded_packages_cache(&lock_file_ownership).has_package(&gcc));
+    /// assert_eq!(
+    ///     config.installed_packages_cache(&lock_file_ownership).package_log(&gcc, &Slot::default())?,
+    ///     log,
+    /// );
+    /// assert_eq!(config.dependency_graph(&lock_file_ownership)?, graph);
+    /// # fs::remove_dir_all(&root)?;
+    /// # fs::remove_dir_all(&new_root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn relocate_store(&mut self, new_root: &Path, _: &LockFileOwnership) -> Result<(), Error> {
+        let mut paths = self.paths.clone();
+        let entries: [fn(&mut ConfigPaths) -> &mut PathBuf; 6] = [
+            ConfigPaths::available_mut,
+            ConfigPaths::downloaded_mut,
+            ConfigPaths::installed_mut,
+            ConfigPaths::depgraph_mut,
+            ConfigPaths::scratch_depgraph_mut,
+            ConfigPaths::journal_mut,
+        ];
+
+        let mut moves = Vec::new();
+        for entry in entries.iter() {
+            let path = entry(&mut paths);
+            let name = path
+                .file_name()
+                .ok_or_else(|| format_err!("{} has no file name", path.display()))?;
+            let new_path = new_root.join(name);
+            moves.push((path.clone(), new_path.clone()));
+            *path = new_path;
+        }
+
+        let mut moved = Vec::new();
+        let res: Result<(), Error> = try {
+            fs::create_dir_all(new_root).with_context(|_| new_root.display().to_string())?;
+            for (from, to) in &moves {
+                if fs::symlink_metadata(from).is_err() {
+                    continue;
+                }
+                if fs::symlink_metadata(to).is_ok() {
+                    Err(format_err!("{} already exists", to.display()))?;
+                }
+
+                // Renaming fails across file systems, in which case the entry is copied instead
+                let renamed = match fs::rename(from, to) {
+                    Err(ref e) if e.raw_os_error() == Some(libc::EXDEV) => false,
+                    rename => {
+                        rename
+                            .with_context(|_| format!("{} to {}", from.display(), to.display()))?;
+                        true
+                    }
+                };
+                if !renamed {
+                    if let Err(e) = copy_recursively(from, to) {
+                        // A partial copy is removed, unless that fails too
+                        let cleanup = match fs::symlink_metadata(to) {
+                            Ok(_) => remove_recursively(to),
+                            Err(_) => Ok(()),
+                        };
+                        if let Err(cleanup_error) = cleanup {
+                            Err(e).with_context(|_| {
+                                format!(
+                                    "{} to {}, leaving a partial copy behind ({})",
+                                    from.display(),
+                                    to.display(),
+                                    cleanup_error
+                                )
+                            })?;
+                        } else {
+                            Err(e).with_context(|_| {
+                                format!("{} to {}", from.display(), to.display())
+                            })?;
+                        }
+                    }
+                }
+                moved.push((from, to, renamed));
+            }
+        };
+
+        if let Err(e) = res {
+            // The entries that can't be moved back are reported, as the store is left split between both locations
+            let mut stranded = Vec::new();
+            for (from, to, renamed) in moved.into_iter().rev() {
+                let rollback = if renamed {
+                    fs::rename(to, from)
+                } else {
+                    remove_recursively(to)
+                };
+                if let Err(rollback_error) = rollback {
+                    stranded.push(format!("{} ({})", to.display(), rollback_error));
+                }
+            }
+            if !stranded.is_empty() {
+                return Err(e
+                    .context(format_err!(
+                        "unable to relocate the store, and unable to roll back {}",
+                        stranded.join(", ")
+                    ))
+                    .into());
+            }
+            return Err(e.context("unable to relocate the store").into());
+        }
+
+        // Everything was moved: the store is used from its new location, and the old copies can go
+        self.paths = paths;
+        for (from, _, renamed) in moved {
+            if !renamed {
+                remove_recursively(from).with_context(|_| from.display().to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Acquire the shared ownership over Nest's lock file, allowing to read Nest's caches alongside other
+    /// instances of Nest doing the same.
+    ///
+    /// See [`SharedLockFileOwnership`] for an example.
+    pub fn acquire_lock_file_ownership_shared(
+        &self,
+        should_wait: bool,
+    ) -> Result<SharedLockFileOwnership, Error> {
+        Ok(
+            SharedLockFileOwnership::acquire(self.paths.lock_file(), should_wait)
+                .with_context(|_| format_err!("unable to acquire lock file"))?,
+        )
+    }
+
+    /// Acquire the exclusive ownership over Nest's lock file
     pub fn acquire_lock_file_ownership(
         &self,
         should_wait: bool,
@@ -267,3 +1652,31 @@ impl Config {
         )
     }
 }
+
+/// Copies the given file, directory or symlink to the given path, along with the content of directories
+fn copy_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+
+    if metadata.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)
+    } else if metadata.is_dir() {
+        fs::create_dir(to)?;
+        fs::set_permissions(to, metadata.permissions())?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Removes the given file, directory or symlink, along with the content of directories
+fn remove_recursively(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
@@ -0,0 +1,489 @@
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::Credentials;
+
+/// Structure holding the configuration of the proxy used for network operations.
+///
+/// The host may contain a scheme (like `socks5://`); plain HTTP is assumed otherwise.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ProxyConfig {
+    host: String,
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    credentials: Option<Credentials>,
+}
+
+impl ProxyConfig {
+    /// Creates a new [`ProxyConfig`] from a host, without any port or credentials
+    #[inline]
+    pub fn new(host: String) -> ProxyConfig {
+        ProxyConfig {
+            host,
+            port: None,
+            credentials: None,
+        }
+    }
+
+    /// Returns the host of the proxy
+    #[inline]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns a mutable reference over the host of the proxy
+    #[inline]
+    pub fn host_mut(&mut self) -> &mut String {
+        &mut self.host
+    }
+
+    /// Returns the port of the proxy, if it isn't the default one
+    #[inline]
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Returns a mutable reference over the port of the proxy
+    #[inline]
+    pub fn port_mut(&mut self) -> &mut Option<u16> {
+        &mut self.port
+    }
+
+    /// Returns the credentials used to authenticate against the proxy, if any
+    #[inline]
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    /// Returns a mutable reference over the credentials used to authenticate against the proxy
+    #[inline]
+    pub fn credentials_mut(&mut self) -> &mut Option<Credentials> {
+        &mut self.credentials
+    }
+}
+
+/// Structure holding the configuration of the network operations, like downloads. It's a sub member of [`Config`][1].
+///
+/// [1]: struct.Config.html
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    max_connections_per_host: Option<usize>,
+    max_concurrent_downloads: Option<usize>,
+    proxy: Option<ProxyConfig>,
+    mirror_ping_timeout: Option<u64>,
+    max_download_speed: Option<u64>,
+    pull_retries: Option<u32>,
+    pull_retry_delay: Option<u64>,
+    connect_timeout: Option<u64>,
+    low_speed_limit: Option<u32>,
+    low_speed_time: Option<u64>,
+}
+
+impl NetworkConfig {
+    /// Returns the maximum number of concurrent transfers targeting the same host, if there is one
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("max_connections_per_host = 2")?;
+    /// assert_eq!(network.max_connections_per_host(), Some(2));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.max_connections_per_host(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn max_connections_per_host(&self) -> Option<usize> {
+        self.max_connections_per_host
+    }
+
+    /// Returns a mutable reference over the maximum number of concurrent transfers targeting the same host
+    #[inline]
+    pub fn max_connections_per_host_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_connections_per_host
+    }
+
+    /// Returns the maximum number of downloads performed at the same time, if there is one.
+    ///
+    /// Frontends default to one download per CPU. A limit of 1 makes downloads fully serial.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("max_concurrent_downloads = 2")?;
+    /// assert_eq!(network.max_concurrent_downloads(), Some(2));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.max_concurrent_downloads(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn max_concurrent_downloads(&self) -> Option<usize> {
+        self.max_concurrent_downloads
+    }
+
+    /// Returns a mutable reference over the maximum number of downloads performed at the same time
+    #[inline]
+    pub fn max_concurrent_downloads_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_concurrent_downloads
+    }
+
+    /// Returns how long to wait for a mirror to answer when ranking the mirrors of a repository by latency.
+    ///
+    /// It's given in milliseconds in the configuration, and defaults to one second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("mirror_ping_timeout = 250")?;
+    /// assert_eq!(network.mirror_ping_timeout(), Duration::from_millis(250));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.mirror_ping_timeout(), Duration::from_secs(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn mirror_ping_timeout(&self) -> Duration {
+        Duration::from_millis(self.mirror_ping_timeout.unwrap_or(1000))
+    }
+
+    /// Returns a mutable reference over the time to wait for a mirror to answer when ranking mirrors, in milliseconds
+    #[inline]
+    pub fn mirror_ping_timeout_mut(&mut self) -> &mut Option<u64> {
+        &mut self.mirror_ping_timeout
+    }
+
+    /// Returns the maximum speed of each download in bytes per second, if there is one.
+    ///
+    /// The limit applies to each connection on its own: as several packages may be downloaded at the same time,
+    /// the total bandwidth used can be higher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("max_download_speed = 1048576")?;
+    /// assert_eq!(network.max_download_speed(), Some(1_048_576));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.max_download_speed(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn max_download_speed(&self) -> Option<u64> {
+        self.max_download_speed
+    }
+
+    /// Returns a mutable reference over the maximum speed of each download, in bytes per second
+    #[inline]
+    pub fn max_download_speed_mut(&mut self) -> &mut Option<u64> {
+        &mut self.max_download_speed
+    }
+
+    /// Returns how many times pulling a repository is retried after a download error, before giving up.
+    ///
+    /// Defaults to 3.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("pull_retries = 5")?;
+    /// assert_eq!(network.pull_retries(), 5);
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.pull_retries(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn pull_retries(&self) -> u32 {
+        self.pull_retries.unwrap_or(3)
+    }
+
+    /// Returns a mutable reference over the number of times pulling a repository is retried
+    #[inline]
+    pub fn pull_retries_mut(&mut self) -> &mut Option<u32> {
+        &mut self.pull_retries
+    }
+
+    /// Returns the time to wait before the first retry of a failed pull, which is doubled for each
+    /// subsequent retry.
+    ///
+    /// It's given in milliseconds in the configuration, and defaults to one second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("pull_retry_delay = 250")?;
+    /// assert_eq!(network.pull_retry_delay(), Duration::from_millis(250));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.pull_retry_delay(), Duration::from_secs(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn pull_retry_delay(&self) -> Duration {
+        Duration::from_millis(self.pull_retry_delay.unwrap_or(1000))
+    }
+
+    /// Returns a mutable reference over the time to wait before the first retry of a failed pull, in milliseconds
+    #[inline]
+    pub fn pull_retry_delay_mut(&mut self) -> &mut Option<u64> {
+        &mut self.pull_retry_delay
+    }
+
+    /// Returns how long to wait for the connection to a mirror to be established before giving up on it.
+    ///
+    /// It's given in milliseconds in the configuration, and defaults to 30 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("connect_timeout = 5000")?;
+    /// assert_eq!(network.connect_timeout(), Duration::from_secs(5));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.connect_timeout(), Duration::from_secs(30));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout.unwrap_or(30_000))
+    }
+
+    /// Returns a mutable reference over the time to wait for the connection to a mirror, in milliseconds
+    #[inline]
+    pub fn connect_timeout_mut(&mut self) -> &mut Option<u64> {
+        &mut self.connect_timeout
+    }
+
+    /// Returns the speed, in bytes per second, below which a transfer is considered stalled.
+    ///
+    /// A transfer staying below this speed for [`low_speed_time`][1] is aborted, so that a hung mirror
+    /// can't block an operation forever. Defaults to 1 byte per second.
+    ///
+    /// [1]: #method.low_speed_time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("low_speed_limit = 1024\nlow_speed_time = 10000")?;
+    /// assert_eq!(network.low_speed_limit(), 1024);
+    /// assert_eq!(network.low_speed_time(), Duration::from_secs(10));
+    ///
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// assert_eq!(network.low_speed_limit(), 1);
+    /// assert_eq!(network.low_speed_time(), Duration::from_secs(30));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn low_speed_limit(&self) -> u32 {
+        self.low_speed_limit.unwrap_or(1)
+    }
+
+    /// Returns a mutable reference over the speed below which a transfer is considered stalled, in bytes per second
+    #[inline]
+    pub fn low_speed_limit_mut(&mut self) -> &mut Option<u32> {
+        &mut self.low_speed_limit
+    }
+
+    /// Returns how long a transfer can stay below the [`low_speed_limit`][1] before being aborted.
+    ///
+    /// It's given in milliseconds in the configuration, and defaults to 30 seconds.
+    ///
+    /// [1]: #method.low_speed_limit
+    #[inline]
+    pub fn low_speed_time(&self) -> Duration {
+        Duration::from_millis(self.low_speed_time.unwrap_or(30_000))
+    }
+
+    /// Returns a mutable reference over the time a transfer can stay below the low speed limit, in milliseconds
+    #[inline]
+    pub fn low_speed_time_mut(&mut self) -> &mut Option<u64> {
+        &mut self.low_speed_time
+    }
+
+    /// Performs the given operation, retrying it with an exponential backoff as long as it fails,
+    /// up to [`pull_retries`][1] times.
+    ///
+    /// Before each retry, `on_retry` is called with the number of the upcoming retry (starting at 1),
+    /// the time that will be waited before it and the error that caused it, so that frontends can
+    /// reflect it. The error of the last attempt is returned if all of them failed.
+    ///
+    /// [1]: #method.pull_retries
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str("pull_retries = 3\npull_retry_delay = 10")?;
+    ///
+    /// // A mirror that fails twice, then succeeds
+    /// let mut attempts = 0;
+    /// let mut retries = Vec::new();
+    /// let result = network.retry_with_backoff(
+    ///     || {
+    ///         attempts += 1;
+    ///         if attempts <= 2 {
+    ///             Err("connection reset")
+    ///         } else {
+    ///             Ok("manifests")
+    ///         }
+    ///     },
+    ///     |retry, delay, _| retries.push((retry, delay)),
+    /// );
+    /// assert_eq!(result, Ok("manifests"));
+    /// assert_eq!(
+    ///     retries,
+    ///     vec![(1, Duration::from_millis(10)), (2, Duration::from_millis(20))],
+    /// );
+    ///
+    /// // A mirror that always fails
+    /// let result: Result<(), _> = network.retry_with_backoff(|| Err("connection reset"), |_, _, _| ());
+    /// assert_eq!(result, Err("connection reset"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn retry_with_backoff<T, E, F, R>(&self, mut operation: F, mut on_retry: R) -> Result<T, E>
+    where
+        F: FnMut() -> Result<T, E>,
+        R: FnMut(u32, Duration, &E),
+    {
+        let mut delay = self.pull_retry_delay();
+        let mut retry = 0;
+
+        loop {
+            match operation() {
+                Err(ref e) if retry < self.pull_retries() => {
+                    retry += 1;
+                    on_retry(retry, delay, e);
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the proxy configured for network operations, if any
+    #[inline]
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    /// Returns a mutable reference over the proxy configured for network operations
+    #[inline]
+    pub fn proxy_mut(&mut self) -> &mut Option<ProxyConfig> {
+        &mut self.proxy
+    }
+
+    /// Returns the proxy to use to reach a URL of the given scheme (like `http` or `https`).
+    ///
+    /// The configured proxy is used in priority. Otherwise, the `<scheme>_proxy` environment variable
+    /// (or its uppercase variant) is used, if it's set and not empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::NetworkConfig;
+    ///
+    /// let network: NetworkConfig = toml::from_str(
+    ///     r#"
+    ///     [proxy]
+    ///     host = "proxy.example.org"
+    ///     port = 3128
+    ///     credentials = { username = "alice", password = "s3cr3t" }
+    ///     "#,
+    /// )?;
+    /// let proxy = network.proxy_for("https").unwrap();
+    /// assert_eq!(proxy.host(), "proxy.example.org");
+    /// assert_eq!(proxy.port(), Some(3128));
+    /// assert_eq!(proxy.credentials().unwrap().username(), "alice");
+    ///
+    /// // Without any proxy in the configuration, the environment is used
+    /// let network: NetworkConfig = toml::from_str("")?;
+    /// std::env::remove_var("HTTPS_PROXY");
+    /// std::env::set_var("https_proxy", "http://env-proxy.example.org:8080");
+    /// assert_eq!(network.proxy_for("https").unwrap().host(), "http://env-proxy.example.org:8080");
+    ///
+    /// std::env::set_var("https_proxy", "");
+    /// assert!(network.proxy_for("https").is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn proxy_for(&self, scheme: &str) -> Option<ProxyConfig> {
+        if let Some(proxy) = &self.proxy {
+            return Some(proxy.clone());
+        }
+
+        let variable = format!("{}_proxy", scheme.to_lowercase());
+        env::var(&variable)
+            .or_else(|_| env::var(variable.to_uppercase()))
+            .ok()
+            .filter(|host| !host.is_empty())
+            .map(ProxyConfig::new)
+    }
+}
@@ -1,6 +1,7 @@
 use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
 
+use std::env;
 use std::path::{Path, PathBuf};
 
 lazy_static! {
@@ -10,7 +11,12 @@ lazy_static! {
     static ref NEST_PATH_INSTALLED: &'static Path = Path::new("/var/nest/installed/");
     static ref NEST_PATH_DEPGRAPH: &'static Path = Path::new("/var/nest/depgraph");
     static ref NEST_PATH_SCRATCH_DEPGRAPH: &'static Path = Path::new("/var/nest/scratch_depgraph");
+    static ref NEST_PATH_JOURNAL: &'static Path = Path::new("/var/nest/journal");
     static ref NEST_PATH_LOCKFILE: &'static Path = Path::new("/var/lock/nest.lock");
+    static ref NEST_PATH_REPOSITORIES_DIR: &'static Path = Path::new("/etc/nest/repos.d/");
+    static ref NEST_PATH_TRANSACTION_LOG: &'static Path =
+        Path::new("/var/log/nest/transactions.log");
+    static ref NEST_PATH_NETRC: &'static Path = Path::new("/etc/nest/netrc");
 }
 
 /// A structure holding all important paths for libnest. It's a sub member of [`Config`][1].
@@ -25,7 +31,11 @@ pub struct ConfigPaths {
     installed: PathBuf,
     depgraph: PathBuf,
     scratch_depgraph: PathBuf,
+    journal: PathBuf,
     lockfile_path: PathBuf,
+    repositories_dir: PathBuf,
+    transaction_log: PathBuf,
+    netrc: PathBuf,
 }
 
 impl ConfigPaths {
@@ -38,7 +48,11 @@ impl ConfigPaths {
             installed: PathBuf::from(*NEST_PATH_INSTALLED),
             depgraph: PathBuf::from(*NEST_PATH_DEPGRAPH),
             scratch_depgraph: PathBuf::from(*NEST_PATH_SCRATCH_DEPGRAPH),
+            journal: PathBuf::from(*NEST_PATH_JOURNAL),
             lockfile_path: PathBuf::from(*NEST_PATH_LOCKFILE),
+            repositories_dir: PathBuf::from(*NEST_PATH_REPOSITORIES_DIR),
+            transaction_log: PathBuf::from(*NEST_PATH_TRANSACTION_LOG),
+            netrc: PathBuf::from(*NEST_PATH_NETRC),
         }
     }
 
@@ -60,7 +74,11 @@ impl ConfigPaths {
     /// assert_eq!(paths.downloaded(), Path::new("/chroot/var/nest/downloaded"));
     /// assert_eq!(paths.installed(), Path::new("/chroot/var/nest/installed"));
     /// assert_eq!(paths.depgraph(), Path::new("/chroot/var/nest/depgraph"));
+    /// assert_eq!(paths.journal(), Path::new("/chroot/var/nest/journal"));
     /// assert_eq!(paths.lock_file(), Path::new("/chroot/var/lock/nest.lock"));
+    /// assert_eq!(paths.repositories_dir(), Path::new("/chroot/etc/nest/repos.d"));
+    /// assert_eq!(paths.transaction_log(), Path::new("/chroot/var/log/nest/transactions.log"));
+    /// assert_eq!(paths.netrc(), Path::new("/chroot/etc/nest/netrc"));
     /// # Ok(())
     /// # }
     /// ```
@@ -75,7 +93,104 @@ impl ConfigPaths {
             installed: self.installed.with_root(root.as_ref()),
             depgraph: self.depgraph.with_root(root.as_ref()),
             scratch_depgraph: self.scratch_depgraph.with_root(root.as_ref()),
+            journal: self.journal.with_root(root.as_ref()),
             lockfile_path: self.lockfile_path.with_root(root.as_ref()),
+            repositories_dir: self.repositories_dir.with_root(root.as_ref()),
+            transaction_log: self.transaction_log.with_root(root.as_ref()),
+            netrc: self.netrc.with_root(root.as_ref()),
+        }
+    }
+
+    /// Overrides the paths for which an environment variable is set, leaving the others untouched.
+    ///
+    /// The following variables are consulted, empty ones being ignored:
+    ///
+    /// | Variable                 | Path                   |
+    /// |--------------------------|------------------------|
+    /// | `NEST_ROOT`              | [`root`]               |
+    /// | `NEST_CACHE`             | [`available`]          |
+    /// | `NEST_DOWNLOADED`        | [`downloaded`]         |
+    /// | `NEST_INSTALLED`         | [`installed`]          |
+    /// | `NEST_DEPGRAPH`          | [`depgraph`]           |
+    /// | `NEST_SCRATCH_DEPGRAPH`  | [`scratch_depgraph`]   |
+    /// | `NEST_JOURNAL`           | [`journal`]            |
+    /// | `NEST_LOCKFILE`          | [`lock_file`]          |
+    /// | `NEST_REPOSITORIES_DIR`  | [`repositories_dir`]   |
+    /// | `NEST_TRANSACTION_LOG`   | [`transaction_log`]    |
+    /// | `NEST_NETRC`             | [`netrc`]              |
+    ///
+    /// This is done by [`Config::load_from`][1] right after the configuration file is parsed.
+    ///
+    /// [`root`]: #method.root
+    /// [`available`]: #method.available
+    /// [`downloaded`]: #method.downloaded
+    /// [`installed`]: #method.installed
+    /// [`depgraph`]: #method.depgraph
+    /// [`scratch_depgraph`]: #method.scratch_depgraph
+    /// [`journal`]: #method.journal
+    /// [`lock_file`]: #method.lock_file
+    /// [`repositories_dir`]: #method.repositories_dir
+    /// [`transaction_log`]: #method.transaction_log
+    /// [`netrc`]: #method.netrc
+    /// [1]: struct.Config.html#method.load_from
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::{Config, ConfigPaths};
+    /// use std::path::Path;
+    ///
+    /// std::env::set_var("NEST_CACHE", "/tmp/ci/available");
+    /// std::env::set_var("NEST_DEPGRAPH", "/tmp/ci/depgraph");
+    /// std::env::set_var("NEST_LOCKFILE", "");
+    ///
+    /// let mut paths = ConfigPaths::default();
+    /// paths.apply_env_overrides();
+    /// assert_eq!(paths.available(), Path::new("/tmp/ci/available"));
+    /// assert_eq!(paths.depgraph(), Path::new("/tmp/ci/depgraph"));
+    ///
+    /// // Paths without a variable, or with an empty one, are left untouched
+    /// assert_eq!(paths.installed(), Path::new("/var/nest/installed"));
+    /// assert_eq!(paths.lock_file(), Path::new("/var/lock/nest.lock"));
+    ///
+    /// // Variables win over the configuration file
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-env-{}", std::process::id()));
+    /// std::fs::create_dir_all(&root)?;
+    /// std::fs::write(
+    ///     root.join("config.toml"),
+    ///     "[paths]\ninstalled = \"/from/file/installed\"\ndepgraph = \"/from/file/depgraph\"\n",
+    /// )?;
+    /// let config = Config::load_from(root.join("config.toml"))?;
+    /// assert_eq!(config.paths().installed(), Path::new("/from/file/installed"));
+    /// assert_eq!(config.paths().depgraph(), Path::new("/tmp/ci/depgraph"));
+    /// assert_eq!(config.paths().available(), Path::new("/tmp/ci/available"));
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_env_overrides(&mut self) {
+        let mut overrides = [
+            ("NEST_ROOT", &mut self.root),
+            ("NEST_CACHE", &mut self.available),
+            ("NEST_DOWNLOADED", &mut self.downloaded),
+            ("NEST_INSTALLED", &mut self.installed),
+            ("NEST_DEPGRAPH", &mut self.depgraph),
+            ("NEST_SCRATCH_DEPGRAPH", &mut self.scratch_depgraph),
+            ("NEST_JOURNAL", &mut self.journal),
+            ("NEST_LOCKFILE", &mut self.lockfile_path),
+            ("NEST_REPOSITORIES_DIR", &mut self.repositories_dir),
+            ("NEST_TRANSACTION_LOG", &mut self.transaction_log),
+            ("NEST_NETRC", &mut self.netrc),
+        ];
+
+        for (var, path) in overrides.iter_mut() {
+            match env::var_os(var) {
+                Some(ref value) if !value.is_empty() => **path = PathBuf::from(value),
+                _ => (),
+            }
         }
     }
 
@@ -335,6 +450,49 @@ impl ConfigPaths {
         &mut self.scratch_depgraph
     }
 
+    /// Returns a reference to the file's path where the journal of the transactions being processed is stored
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::Path;
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let paths = ConfigPaths::default();
+    /// assert_eq!(paths.journal(), Path::new("/var/nest/journal"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn journal(&self) -> &Path {
+        &self.journal
+    }
+
+    /// Returns a mutable reference to the file's path where the journal of the transactions being processed is stored
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let mut paths = ConfigPaths::default();
+    /// *paths.journal_mut() = PathBuf::from("/tmp/journal");
+    /// assert_eq!(paths.journal(), Path::new("/tmp/journal"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn journal_mut(&mut self) -> &mut PathBuf {
+        &mut self.journal
+    }
+
     /// Returns a reference to the file's path where the lock file is stored
     ///
     /// # Examples
@@ -377,6 +535,135 @@ impl ConfigPaths {
     pub fn lock_file_mut(&mut self) -> &mut PathBuf {
         &mut self.lockfile_path
     }
+
+    /// Returns a reference to the path of the directory holding additional repositories, one file each
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::Path;
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let paths = ConfigPaths::default();
+    /// assert_eq!(paths.repositories_dir(), Path::new("/etc/nest/repos.d"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn repositories_dir(&self) -> &Path {
+        &self.repositories_dir
+    }
+
+    /// Returns a mutable reference to the path of the directory holding additional repositories, one file each
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let mut paths = ConfigPaths::default();
+    /// *paths.repositories_dir_mut() = PathBuf::from("/tmp/repos.d");
+    /// assert_eq!(paths.repositories_dir(), Path::new("/tmp/repos.d"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn repositories_dir_mut(&mut self) -> &mut PathBuf {
+        &mut self.repositories_dir
+    }
+
+    /// Returns a reference to the file's path where the files created and removed by transactions are logged
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::Path;
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let paths = ConfigPaths::default();
+    /// assert_eq!(paths.transaction_log(), Path::new("/var/log/nest/transactions.log"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn transaction_log(&self) -> &Path {
+        &self.transaction_log
+    }
+
+    /// Returns a mutable reference to the file's path where the files created and removed by transactions are logged
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let mut paths = ConfigPaths::default();
+    /// *paths.transaction_log_mut() = PathBuf::from("/tmp/transactions.log");
+    /// assert_eq!(paths.transaction_log(), Path::new("/tmp/transactions.log"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn transaction_log_mut(&mut self) -> &mut PathBuf {
+        &mut self.transaction_log
+    }
+
+    /// Returns a reference to the path of the netrc file holding the credentials of private mirrors
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::Path;
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let paths = ConfigPaths::default();
+    /// assert_eq!(paths.netrc(), Path::new("/etc/nest/netrc"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn netrc(&self) -> &Path {
+        &self.netrc
+    }
+
+    /// Returns a mutable reference to the path of the netrc file holding the credentials of private mirrors
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::{Path, PathBuf};
+    /// use libnest::config::ConfigPaths;
+    ///
+    /// let mut paths = ConfigPaths::default();
+    /// *paths.netrc_mut() = PathBuf::from("/tmp/netrc");
+    /// assert_eq!(paths.netrc(), Path::new("/tmp/netrc"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn netrc_mut(&mut self) -> &mut PathBuf {
+        &mut self.netrc
+    }
 }
 
 impl Default for ConfigPaths {
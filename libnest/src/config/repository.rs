@@ -1,13 +1,240 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use failure::{format_err, ResultExt};
 use serde_derive::{Deserialize, Serialize};
-use url_serde::SerdeUrl;
+use url::Url;
+use url_serde::{Serde, SerdeUrl};
+
+use super::{MirrorListError, MirrorListErrorKind};
+
+/// The maximum number of mirrors pinged at the same time
+const MAX_PING_THREADS: usize = 8;
 
 /// Represents the URL pointing to a repository mirror
 pub type MirrorUrl = SerdeUrl;
 
+/// The credentials used to authenticate against the mirrors of a private repository.
+///
+/// The password can either be given inline, or read from a file (so the configuration file itself
+/// doesn't have to hold any secret).
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+pub struct Credentials {
+    username: String,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    password_file: Option<PathBuf>,
+}
+
+impl Credentials {
+    /// Creates new credentials from a username and a password
+    #[inline]
+    pub fn new(username: String, password: String) -> Credentials {
+        Credentials {
+            username,
+            password: Some(password),
+            password_file: None,
+        }
+    }
+
+    /// Looks for the credentials of the given host in a netrc file.
+    ///
+    /// If the host has no entry of its own, the `default` entry is used if there is one.
+    /// A missing file is considered empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Credentials;
+    ///
+    /// let path = std::env::temp_dir().join(format!("nest-doctest-netrc-{}", std::process::id()));
+    /// std::fs::write(
+    ///     &path,
+    ///     "machine private.raven-os.org login alice password s3cr3t\ndefault login anonymous password guest\n",
+    /// )?;
+    ///
+    /// let credentials = Credentials::from_netrc(&path, "private.raven-os.org")?.unwrap();
+    /// assert_eq!(credentials.username(), "alice");
+    /// assert_eq!(credentials.password()?, Some(String::from("s3cr3t")));
+    ///
+    /// // The password is never printed
+    /// assert!(!format!("{:?}", credentials).contains("s3cr3t"));
+    ///
+    /// let credentials = Credentials::from_netrc(&path, "stable.raven-os.org")?.unwrap();
+    /// assert_eq!(credentials.username(), "anonymous");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_netrc<P: AsRef<Path>>(
+        path: P,
+        host: &str,
+    ) -> Result<Option<Credentials>, std::io::Error> {
+        let content = match fs::read_to_string(path.as_ref()) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut found = None;
+        let mut default = None;
+        let mut current: Option<(bool, Option<String>, Option<String>)> = None;
+        let mut tokens = content.split_whitespace();
+
+        // Stores the entry being parsed, if it's relevant
+        let mut flush = |entry: Option<(bool, Option<String>, Option<String>)>| {
+            if let Some((is_default, Some(username), password)) = entry {
+                let credentials = Credentials {
+                    username,
+                    password,
+                    password_file: None,
+                };
+                if is_default {
+                    default.get_or_insert(credentials);
+                } else {
+                    found.get_or_insert(credentials);
+                }
+            }
+        };
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" => {
+                    flush(current.take());
+                    if tokens.next() == Some(host) {
+                        current = Some((false, None, None));
+                    }
+                }
+                "default" => {
+                    flush(current.take());
+                    current = Some((true, None, None));
+                }
+                "login" => {
+                    let value = tokens.next().map(String::from);
+                    if let Some(entry) = &mut current {
+                        entry.1 = value;
+                    }
+                }
+                "password" => {
+                    let value = tokens.next().map(String::from);
+                    if let Some(entry) = &mut current {
+                        entry.2 = value;
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                _ => (),
+            }
+        }
+        flush(current.take());
+        drop(flush);
+
+        Ok(found.or(default))
+    }
+
+    /// Returns the username
+    #[inline]
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns the path of the file holding the password, if any
+    #[inline]
+    pub fn password_file(&self) -> Option<&Path> {
+        self.password_file.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns the password, reading it from the password file if it isn't given inline.
+    ///
+    /// Trailing whitespaces are stripped from the content of the password file.
+    pub fn password(&self) -> Result<Option<String>, std::io::Error> {
+        if let Some(password) = &self.password {
+            Ok(Some(password.clone()))
+        } else if let Some(path) = &self.password_file {
+            Ok(Some(fs::read_to_string(path)?.trim_end().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Never print the password, so it doesn't end up in logs
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("password_file", &self.password_file)
+            .finish()
+    }
+}
+
+/// Parses a mirror list, that is, the URLs of the mirrors of a repository, one per line.
+///
+/// Empty lines and lines starting with a `#` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::{parse_mirror_list, MirrorListErrorKind};
+///
+/// let mirrors = parse_mirror_list(
+///     "# Mirrors of the stable repository\nhttps://mirror1.raven-os.org\n\n  https://mirror2.raven-os.org/stable  \n",
+/// )?;
+/// let mirrors: Vec<_> = mirrors.iter().map(|mirror| mirror.as_str()).collect();
+/// assert_eq!(mirrors, vec!["https://mirror1.raven-os.org/", "https://mirror2.raven-os.org/stable"]);
+///
+/// let error = parse_mirror_list("https://mirror1.raven-os.org\n<html>").unwrap_err();
+/// assert_eq!(*error.kind(), MirrorListErrorKind::InvalidMirrorList);
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_mirror_list(content: &str) -> Result<Vec<MirrorUrl>, MirrorListError> {
+    let mut mirrors = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let url = Url::parse(line)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| format_err!("line {}: invalid mirror '{}'", index + 1, line))
+            .context(MirrorListErrorKind::InvalidMirrorList)?;
+        mirrors.push(Serde(url));
+    }
+    Ok(mirrors)
+}
+
 /// Structure holding all the configuration for a single repository: mirrors, proxy, etc...
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Default)]
 pub struct RepositoryConfig {
+    #[serde(default)]
     mirrors: Vec<MirrorUrl>,
+    #[serde(default)]
+    mirrorlist_url: Option<MirrorUrl>,
+    #[serde(default)]
+    credentials: Option<Credentials>,
+    #[serde(default)]
+    pubkey: Option<PathBuf>,
+    #[serde(default)]
+    priority: i32,
 }
 
 impl RepositoryConfig {
@@ -16,6 +243,10 @@ impl RepositoryConfig {
     pub fn new() -> RepositoryConfig {
         RepositoryConfig {
             mirrors: Vec::new(),
+            mirrorlist_url: None,
+            credentials: None,
+            pubkey: None,
+            priority: 0,
         }
     }
 
@@ -32,4 +263,237 @@ impl RepositoryConfig {
     pub fn mirrors_mut(&mut self) -> &mut Vec<MirrorUrl> {
         &mut self.mirrors
     }
+
+    /// Returns the URL of the mirror list of this repository, if any.
+    ///
+    /// The mirrors it lists are fetched when the repository is pulled, and added to the mirrors of the configuration
+    /// when it's loaded.
+    #[inline]
+    pub fn mirrorlist_url(&self) -> Option<&MirrorUrl> {
+        self.mirrorlist_url.as_ref()
+    }
+
+    /// Returns a mutable reference over the URL of the mirror list of this repository
+    #[inline]
+    pub fn mirrorlist_url_mut(&mut self) -> &mut Option<MirrorUrl> {
+        &mut self.mirrorlist_url
+    }
+
+    /// Adds the given mirrors after those of this repository, skipping those it already has
+    pub fn extend_mirrors<I>(&mut self, mirrors: I)
+    where
+        I: IntoIterator<Item = MirrorUrl>,
+    {
+        for mirror in mirrors {
+            if !self.mirrors.contains(&mirror) {
+                self.mirrors.push(mirror);
+            }
+        }
+    }
+
+    /// Returns the mirrors of this repository, sorted from the fastest to respond to the slowest.
+    ///
+    /// Each mirror is pinged once, depending on its scheme:
+    /// - `http` mirrors are sent a `HEAD` request on their base URL
+    /// - `file` mirrors are checked for the existence of their directory
+    /// - other mirrors, like `https` ones, only have a connection established with them
+    ///
+    /// Mirrors that don't respond within the given timeout are put last, in the order of the configuration.
+    ///
+    /// Use [`Repository::mirrors_by_latency`][1] to compute the ranking only once per run.
+    ///
+    /// [1]: ../repository/struct.Repository.html#method.mirrors_by_latency
+    #[inline]
+    pub fn mirrors_by_latency(&self, timeout: Duration) -> Vec<MirrorUrl> {
+        self.mirrors_by_latency_with(timeout, ping_mirror)
+    }
+
+    /// Returns the mirrors of this repository, sorted from the fastest to respond to the slowest like
+    /// [`mirrors_by_latency`][1], but measures the time taken by each mirror to respond with the given function.
+    ///
+    /// The function is given the time left before the timeout, and returns `None` for the mirrors that didn't respond
+    /// within it. The mirrors are pinged by a bounded number of threads, and those that aren't ranked once the timeout
+    /// is over, even if they're still resolving their host name, are put last.
+    ///
+    /// [1]: #method.mirrors_by_latency
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::time::Duration;
+    /// use libnest::config::{MirrorUrl, RepositoryConfig};
+    ///
+    /// fn ping(mirror: &MirrorUrl, _: Duration) -> Option<Duration> {
+    ///     match mirror.host_str() {
+    ///         Some("fast.raven-os.org") => Some(Duration::from_millis(10)),
+    ///         Some("slow.raven-os.org") => Some(Duration::from_millis(300)),
+    ///         Some("hanging.raven-os.org") => loop {
+    ///             std::thread::park();
+    ///         },
+    ///         _ => None,
+    ///     }
+    /// }
+    ///
+    /// let repository: RepositoryConfig = toml::from_str(
+    ///     r#"mirrors = [
+    ///         "http://hanging.raven-os.org",
+    ///         "http://dead.raven-os.org",
+    ///         "http://slow.raven-os.org",
+    ///         "http://fast.raven-os.org",
+    ///     ]"#,
+    /// )?;
+    ///
+    /// let mirrors = repository.mirrors_by_latency_with(Duration::from_millis(500), ping);
+    /// let hosts: Vec<_> = mirrors.iter().map(|mirror| mirror.host_str().unwrap()).collect();
+    /// assert_eq!(
+    ///     hosts,
+    ///     vec!["fast.raven-os.org", "slow.raven-os.org", "hanging.raven-os.org", "dead.raven-os.org"]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mirrors_by_latency_with(
+        &self,
+        timeout: Duration,
+        ping: fn(&MirrorUrl, Duration) -> Option<Duration>,
+    ) -> Vec<MirrorUrl> {
+        if self.mirrors.len() <= 1 {
+            return self.mirrors.clone();
+        }
+
+        let deadline = Instant::now() + timeout;
+        let queue = Arc::new(Mutex::new(
+            self.mirrors
+                .iter()
+                .cloned()
+                .enumerate()
+                .rev()
+                .collect::<Vec<_>>(),
+        ));
+        let (sender, receiver) = channel();
+        for _ in 0..self.mirrors.len().min(MAX_PING_THREADS) {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().ok().and_then(|mut queue| queue.pop());
+                let (index, mirror) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                let latency = deadline
+                    .checked_duration_since(Instant::now())
+                    .and_then(|remaining| ping(&mirror, remaining));
+
+                // The ranking may already be over, in which case nobody is listening anymore
+                if sender.send((index, latency)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Mirrors that didn't answer before the deadline (like those stuck resolving their host) are left unranked
+        let mut latencies = vec![None; self.mirrors.len()];
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match receiver.recv_timeout(remaining) {
+                Ok((index, latency)) => latencies[index] = latency,
+                Err(_) => break,
+            }
+        }
+
+        let mut ranking: Vec<_> = latencies.into_iter().enumerate().collect();
+        ranking.sort_by_key(|(index, latency)| (latency.is_none(), *latency, *index));
+        ranking
+            .into_iter()
+            .map(|(index, _)| self.mirrors[index].clone())
+            .collect()
+    }
+
+    /// Returns the credentials used to authenticate against the mirrors of this repository, if any
+    #[inline]
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    /// Returns a mutable reference over the credentials used to authenticate against the mirrors of this repository
+    #[inline]
+    pub fn credentials_mut(&mut self) -> &mut Option<Credentials> {
+        &mut self.credentials
+    }
+
+    /// Returns the path of the public key used to verify the signature of the manifests pulled from this repository, if any.
+    ///
+    /// It's relative to the root of the system Nest is managing, like the other paths of the configuration.
+    #[inline]
+    pub fn pubkey(&self) -> Option<&Path> {
+        self.pubkey.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Returns a mutable reference over the path of the public key used to verify the signature of the manifests
+    #[inline]
+    pub fn pubkey_mut(&mut self) -> &mut Option<PathBuf> {
+        &mut self.pubkey
+    }
+
+    /// Returns the priority of this repository.
+    ///
+    /// When the same version of a package is available in multiple repositories, the one with the
+    /// highest priority is used. Defaults to `0`.
+    #[inline]
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Returns a mutable reference over the priority of this repository
+    #[inline]
+    pub fn priority_mut(&mut self) -> &mut i32 {
+        &mut self.priority
+    }
+}
+
+/// Measures the time taken by a mirror to respond, or returns `None` if it didn't respond within the given timeout
+fn ping_mirror(mirror: &MirrorUrl, timeout: Duration) -> Option<Duration> {
+    let start = Instant::now();
+
+    // Local mirrors respond as long as their directory exists
+    if mirror.scheme() == "file" {
+        return mirror
+            .to_file_path()
+            .ok()
+            .filter(|path| path.is_dir())
+            .map(|_| start.elapsed());
+    }
+
+    let host = mirror.host_str()?;
+    let address = (host, mirror.port_or_known_default()?)
+        .to_socket_addrs()
+        .ok()?
+        .next()?;
+
+    // Resolving the host may have taken some of the time given to the mirror
+    let remaining = timeout.checked_sub(start.elapsed())?;
+    let mut stream = TcpStream::connect_timeout(&address, remaining).ok()?;
+    if mirror.scheme() == "http" {
+        let remaining = timeout.checked_sub(start.elapsed())?;
+        stream.set_read_timeout(Some(remaining)).ok()?;
+        stream.set_write_timeout(Some(remaining)).ok()?;
+        write!(
+            stream,
+            "HEAD {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            mirror.path(),
+            host
+        )
+        .ok()?;
+
+        // Any answer will do, as long as the mirror is alive
+        if stream.read(&mut [0; 1]).ok()? == 0 {
+            return None;
+        }
+    }
+
+    Some(start.elapsed()).filter(|latency| *latency <= timeout)
 }
@@ -0,0 +1,217 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// The color theme used by frontends when printing to a terminal.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ColorTheme {
+    /// The default theme, where each element has its own color
+    #[serde(rename = "default")]
+    Default,
+
+    /// A theme without any color
+    #[serde(rename = "mono")]
+    Mono,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        ColorTheme::Default
+    }
+}
+
+/// Structure holding the colors used to highlight each kind of element printed by a frontend.
+///
+/// Each color is given by its name (like `"green"` or `"bright blue"`). If a color is omitted,
+/// the frontend's default color for this element is used.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[serde(default)]
+pub struct ColorsConfig {
+    theme: ColorTheme,
+    install: Option<String>,
+    remove: Option<String>,
+    upgrade: Option<String>,
+    downgrade: Option<String>,
+    pull: Option<String>,
+    warning: Option<String>,
+}
+
+impl ColorsConfig {
+    /// Returns the color theme
+    #[inline]
+    pub fn theme(&self) -> ColorTheme {
+        self.theme
+    }
+
+    /// Returns a mutable reference over the color theme
+    #[inline]
+    pub fn theme_mut(&mut self) -> &mut ColorTheme {
+        &mut self.theme
+    }
+
+    /// Returns the name of the color used for installations, if one was configured
+    #[inline]
+    pub fn install(&self) -> Option<&str> {
+        self.install.as_ref().map(String::as_str)
+    }
+
+    /// Returns the name of the color used for removals, if one was configured
+    #[inline]
+    pub fn remove(&self) -> Option<&str> {
+        self.remove.as_ref().map(String::as_str)
+    }
+
+    /// Returns the name of the color used for upgrades, if one was configured
+    #[inline]
+    pub fn upgrade(&self) -> Option<&str> {
+        self.upgrade.as_ref().map(String::as_str)
+    }
+
+    /// Returns the name of the color used for downgrades, if one was configured
+    #[inline]
+    pub fn downgrade(&self) -> Option<&str> {
+        self.downgrade.as_ref().map(String::as_str)
+    }
+
+    /// Returns the name of the color used for pulls, if one was configured
+    #[inline]
+    pub fn pull(&self) -> Option<&str> {
+        self.pull.as_ref().map(String::as_str)
+    }
+
+    /// Returns the name of the color used for warnings, if one was configured
+    #[inline]
+    pub fn warning(&self) -> Option<&str> {
+        self.warning.as_ref().map(String::as_str)
+    }
+}
+
+/// The level of the events reported by libnest, from the most to the least important.
+///
+/// Frontends report the events whose level is at most the [configured one][1], which defaults to
+/// [`LogLevel::Warn`].
+///
+/// [1]: struct.UiConfig.html#method.log_level
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// use libnest::config::LogLevel;
+///
+/// assert!(LogLevel::Error < LogLevel::Warn);
+/// assert_eq!(LogLevel::default(), LogLevel::Warn);
+/// assert_eq!(LogLevel::Warn.raised(1), LogLevel::Info);
+/// assert_eq!(LogLevel::Warn.raised(5), LogLevel::Debug);
+/// assert_eq!(LogLevel::Info.to_string(), "info");
+/// ```
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Errors, that make an operation fail
+    Error,
+
+    /// Warnings, about something that may need the attention of the user
+    Warn,
+
+    /// Informational events, like a mirror that failed and was replaced by the next one
+    Info,
+
+    /// Details useful to debug Nest
+    Debug,
+}
+
+impl LogLevel {
+    /// Returns the level that is the given number of steps more verbose than this one, stopping at
+    /// [`LogLevel::Debug`]
+    pub fn raised(self, steps: u64) -> LogLevel {
+        (0..steps).fold(self, |level, _| match level {
+            LogLevel::Error => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Info,
+            LogLevel::Info | LogLevel::Debug => LogLevel::Debug,
+        })
+    }
+}
+
+impl Default for LogLevel {
+    fn default() -> Self {
+        LogLevel::Warn
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LogLevel::Error => write!(f, "error"),
+            LogLevel::Warn => write!(f, "warn"),
+            LogLevel::Info => write!(f, "info"),
+            LogLevel::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+/// Structure holding the configuration of the user interface of frontends. It's a sub member of [`Config`][1].
+///
+/// [1]: struct.Config.html
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[serde(default)]
+pub struct UiConfig {
+    colors: ColorsConfig,
+    log_level: LogLevel,
+}
+
+impl UiConfig {
+    /// Returns a reference over the colors configuration
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::{ColorTheme, UiConfig};
+    ///
+    /// let ui: UiConfig = toml::from_str("[colors]\ntheme = \"mono\"\ninstall = \"blue\"")?;
+    /// assert_eq!(ui.colors().theme(), ColorTheme::Mono);
+    /// assert_eq!(ui.colors().install(), Some("blue"));
+    /// assert_eq!(ui.colors().remove(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn colors(&self) -> &ColorsConfig {
+        &self.colors
+    }
+
+    /// Returns a mutable reference over the colors configuration
+    #[inline]
+    pub fn colors_mut(&mut self) -> &mut ColorsConfig {
+        &mut self.colors
+    }
+
+    /// Returns the level of the least important events that are reported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::{LogLevel, UiConfig};
+    ///
+    /// assert_eq!(UiConfig::default().log_level(), LogLevel::Warn);
+    ///
+    /// let ui: UiConfig = toml::from_str("log_level = \"info\"")?;
+    /// assert_eq!(ui.log_level(), LogLevel::Info);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn log_level(&self) -> LogLevel {
+        self.log_level
+    }
+
+    /// Returns a mutable reference over the level of the least important events that are reported
+    #[inline]
+    pub fn log_level_mut(&mut self) -> &mut LogLevel {
+        &mut self.log_level
+    }
+}
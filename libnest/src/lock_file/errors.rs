@@ -0,0 +1,41 @@
+//! Errors that can be returned by the lock_file module
+
+use failure::{Context, Fail};
+
+/// Error type for errors related to the lock file
+#[derive(Debug)]
+pub struct LockError {
+    inner: Context<LockErrorKind>,
+}
+
+/// Error kind describing a kind of error related to the lock file
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum LockErrorKind {
+    /// The lock file is held by another instance of Nest that is still running
+    #[fail(
+        display = "the lock file is held by another instance of nest (pid {})",
+        pid
+    )]
+    HeldBy {
+        /// The PID of the process holding the lock file
+        pid: u32,
+    },
+
+    /// The lock file is shared by other instances of Nest that are reading the caches
+    #[fail(display = "the lock file is held by other instances of nest reading the caches")]
+    HeldByReaders,
+
+    /// The lock file is held, but the instance of Nest that acquired it isn't running anymore.
+    ///
+    /// This usually means a process spawned by that instance inherited the lock file.
+    #[fail(
+        display = "the lock file is held, but the instance of nest that acquired it (pid {}) isn't running anymore",
+        pid
+    )]
+    Stale {
+        /// The PID of the process that acquired the lock file
+        pid: u32,
+    },
+}
+
+use_as_error!(LockError, LockErrorKind);
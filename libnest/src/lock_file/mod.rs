@@ -1,13 +1,85 @@
 //! Simple file-based locking to prevent race conditions when running multiple instances of Nest
 
-use std::fs::{self, File};
+pub mod errors;
+
+pub use self::errors::*;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
 use std::ops::Drop;
 use std::path::Path;
+use std::process;
 
 use failure::{Error, ResultExt};
 use fs2::FileExt;
 
-/// A handle representing ownership over Nest's lock file
+/// A marker trait for the ownerships over Nest's lock file that allow to read Nest's caches.
+///
+/// It's implemented by both [`LockFileOwnership`], which is exclusive, and [`SharedLockFileOwnership`], so
+/// read-only operations accept either of them while those writing to the caches require the exclusive one.
+///
+/// It's sealed, so that no other type can pose as an ownership over the lock file.
+pub trait LockFileReadOwnership: sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::LockFileOwnership {}
+    impl Sealed for super::SharedLockFileOwnership {}
+}
+
+/// A handle representing exclusive ownership over Nest's lock file
+///
+/// While owned, the lock file contains the PID and the start time of the owning process, so that
+/// other instances of Nest can tell who is holding it.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use std::fs;
+/// use libnest::config::Config;
+/// use libnest::lock_file::{LockError, LockErrorKind};
+///
+/// let mut config: Config = toml::from_str("")?;
+/// let root = std::env::temp_dir().join(format!("nest-doctest-lock-{}", std::process::id()));
+/// *config.paths_mut() = config.paths().chroot(&root);
+///
+/// let lock_error_kind = |error: failure::Error| {
+///     error
+///         .iter_chain()
+///         .find_map(|cause| cause.downcast_ref::<LockError>())
+///         .map(|error| *error.kind())
+/// };
+///
+/// let lock_file_ownership = config.acquire_lock_file_ownership(false)?;
+///
+/// // The lock file is held by this process
+/// let error = config.acquire_lock_file_ownership(false).unwrap_err();
+/// assert_eq!(
+///     lock_error_kind(error),
+///     Some(LockErrorKind::HeldBy { pid: std::process::id() }),
+/// );
+///
+/// // Simulate a lock file acquired by a process that doesn't exist anymore
+/// fs::write(config.paths().lock_file(), "4194304 0\n")?;
+/// let error = config.acquire_lock_file_ownership(false).unwrap_err();
+/// assert_eq!(
+///     lock_error_kind(error),
+///     Some(LockErrorKind::Stale { pid: 4_194_304 }),
+/// );
+///
+/// // Once released, the lock file can be acquired again
+/// drop(lock_file_ownership);
+/// let lock_file_ownership = config.acquire_lock_file_ownership(false)?;
+/// # drop(lock_file_ownership);
+/// # fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug)]
 pub struct LockFileOwnership {
     lock_file: File,
@@ -18,25 +90,183 @@ impl LockFileOwnership {
         if let Some(parent_path) = path.parent() {
             fs::create_dir_all(&parent_path).with_context(|_| parent_path.display().to_string())?;
         }
-        let f = File::create(path)?;
+
+        // The file must not be truncated before the lock is acquired, as it holds the PID of the current owner
+        let mut f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
 
         if should_wait {
             f.lock_exclusive()?;
-        } else {
-            f.try_lock_exclusive()?;
+        } else if let Err(e) = f.try_lock_exclusive() {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                if let Some(error) = Self::owner_error(path) {
+                    return Err(error.into());
+                }
+            }
+            return Err(e.into());
+        }
+
+        f.set_len(0)?;
+        f.seek(SeekFrom::Start(0))?;
+        match process_start_time(process::id()) {
+            Some(start_time) => writeln!(f, "{} {}", process::id(), start_time)?,
+            None => writeln!(f, "{}", process::id())?,
         }
         Ok(LockFileOwnership { lock_file: f })
     }
 
+    /// Describes the owner of the lock file, or returns `None` if it couldn't be read
+    fn owner_error(path: &Path) -> Option<LockError> {
+        let content = fs::read_to_string(path).ok()?;
+
+        // Only the exclusive owner writes its PID
+        if content.trim().is_empty() {
+            return Some(LockError::from(LockErrorKind::HeldByReaders));
+        }
+
+        let mut fields = content.split_whitespace();
+        let pid = fields.next()?.parse::<u32>().ok()?;
+        let start_time = fields.next().and_then(|field| field.parse::<u64>().ok());
+
+        // Compare the start times too, in case the PID was reused by an unrelated process
+        let is_running = match (process_start_time(pid), start_time) {
+            (Some(current), Some(recorded)) => current == recorded,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        if is_running {
+            Some(LockError::from(LockErrorKind::HeldBy { pid }))
+        } else {
+            Some(LockError::from(LockErrorKind::Stale { pid }))
+        }
+    }
+
     fn release(&mut self) {
+        // The PID is removed first, so that a contended lock file without any is known to be shared
+        let _ = self.lock_file.set_len(0);
         self.lock_file
             .unlock()
             .expect("unable to release the lock file");
     }
 }
 
+impl LockFileReadOwnership for LockFileOwnership {}
+
 impl Drop for LockFileOwnership {
     fn drop(&mut self) {
         self.release()
     }
 }
+
+/// A handle representing shared ownership over Nest's lock file, allowing to read Nest's caches.
+///
+/// Any number of instances of Nest can hold a shared ownership at the same time, but not while another
+/// one holds the [exclusive ownership][1] over the lock file, which is needed to write to the caches.
+///
+/// [1]: struct.LockFileOwnership.html
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::Config;
+/// use libnest::lock_file::{LockError, LockErrorKind};
+///
+/// let mut config: Config = toml::from_str("")?;
+/// let root = std::env::temp_dir().join(format!("nest-doctest-shared-lock-{}", std::process::id()));
+/// *config.paths_mut() = config.paths().chroot(&root);
+///
+/// let lock_error_kind = |error: failure::Error| {
+///     error
+///         .iter_chain()
+///         .find_map(|cause| cause.downcast_ref::<LockError>())
+///         .map(|error| *error.kind())
+/// };
+///
+/// // Shared ownerships coexist, and allow to read the caches
+/// let first = config.acquire_lock_file_ownership_shared(false)?;
+/// let second = config.acquire_lock_file_ownership_shared(false)?;
+/// assert!(config.available_packages_cache(&first).search(&["hello"])?.is_empty());
+/// assert!(config.dependency_graph(&second)?.packages().next().is_none());
+///
+/// // The exclusive ownership can't be acquired while the lock file is shared
+/// let error = config.acquire_lock_file_ownership(false).unwrap_err();
+/// assert_eq!(lock_error_kind(error), Some(LockErrorKind::HeldByReaders));
+///
+/// // Nor can a shared ownership be acquired while the lock file is exclusively owned
+/// drop(first);
+/// drop(second);
+/// let lock_file_ownership = config.acquire_lock_file_ownership(false)?;
+/// let error = config.acquire_lock_file_ownership_shared(false).unwrap_err();
+/// assert_eq!(
+///     lock_error_kind(error),
+///     Some(LockErrorKind::HeldBy { pid: std::process::id() }),
+/// );
+///
+/// drop(lock_file_ownership);
+/// let shared_lock_file_ownership = config.acquire_lock_file_ownership_shared(false)?;
+/// # drop(shared_lock_file_ownership);
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SharedLockFileOwnership {
+    lock_file: File,
+}
+
+impl SharedLockFileOwnership {
+    pub(crate) fn acquire(path: &Path, should_wait: bool) -> Result<Self, Error> {
+        if let Some(parent_path) = path.parent() {
+            fs::create_dir_all(&parent_path).with_context(|_| parent_path.display().to_string())?;
+        }
+
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+
+        if should_wait {
+            FileExt::lock_shared(&f)?;
+        } else if let Err(e) = FileExt::try_lock_shared(&f) {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                if let Some(error) = LockFileOwnership::owner_error(path) {
+                    return Err(error.into());
+                }
+            }
+            return Err(e.into());
+        }
+        Ok(SharedLockFileOwnership { lock_file: f })
+    }
+}
+
+impl LockFileReadOwnership for SharedLockFileOwnership {}
+
+impl Drop for SharedLockFileOwnership {
+    fn drop(&mut self) {
+        self.lock_file
+            .unlock()
+            .expect("unable to release the lock file");
+    }
+}
+
+/// Returns the start time of a running process, in clock ticks since boot, or `None` if it isn't running
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // The name of the process may contain spaces and parentheses, so the fields are read after its last ')'.
+    // The start time is the 22nd field, and the first field after the name is the 3rd.
+    stat.get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,91 @@
+use std::env::consts::ARCH;
+use std::fmt::{self, Display, Formatter};
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The processor architectures packages can be built for
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// use libnest::package::Arch;
+///
+/// assert_eq!(Arch::X86_64.to_string(), "x86_64");
+/// if cfg!(target_arch = "x86_64") {
+///     assert_eq!(Arch::host(), Some(Arch::X86_64));
+/// }
+///
+/// // Architectures unknown to this version of Nest are kept as-is
+/// let arch = Arch::from(String::from("mips64"));
+/// assert_eq!(arch, Arch::Other(String::from("mips64")));
+/// assert_eq!(arch.to_string(), "mips64");
+/// ```
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Arch {
+    /// 64-bit x86
+    X86_64,
+
+    /// 32-bit x86
+    I686,
+
+    /// 64-bit ARM
+    Aarch64,
+
+    /// 32-bit ARM
+    Armv7,
+
+    /// 64-bit RISC-V
+    Riscv64,
+
+    /// An architecture unknown to this version of Nest, which is never the one it is running on
+    Other(String),
+}
+
+impl Arch {
+    /// Returns the architecture Nest is running on, or `None` if it isn't one of the supported ones
+    pub fn host() -> Option<Arch> {
+        match ARCH {
+            "x86_64" => Some(Arch::X86_64),
+            "x86" => Some(Arch::I686),
+            "aarch64" => Some(Arch::Aarch64),
+            "arm" => Some(Arch::Armv7),
+            "riscv64" => Some(Arch::Riscv64),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Arch::X86_64 => write!(f, "x86_64"),
+            Arch::I686 => write!(f, "i686"),
+            Arch::Aarch64 => write!(f, "aarch64"),
+            Arch::Armv7 => write!(f, "armv7"),
+            Arch::Riscv64 => write!(f, "riscv64"),
+            Arch::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<String> for Arch {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "x86_64" => Arch::X86_64,
+            "i686" => Arch::I686,
+            "aarch64" => Arch::Aarch64,
+            "armv7" => Arch::Armv7,
+            "riscv64" => Arch::Riscv64,
+            _ => Arch::Other(name),
+        }
+    }
+}
+
+impl From<Arch> for String {
+    #[inline]
+    fn from(arch: Arch) -> Self {
+        arch.to_string()
+    }
+}
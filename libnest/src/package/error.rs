@@ -193,11 +193,45 @@ pub struct TagParseError(pub String);
 #[fail(display = "{}: invalid license", 0)]
 pub struct LicenseParseError(pub String);
 
+/// Strong type to represent an error message related to the parsing of a package's upstream URL
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Fail)]
+#[fail(display = "{}: invalid upstream URL", _0)]
+pub struct UpstreamURLParseError(pub String);
+
+/// Strong type to represent an error message related to the parsing of a version requirement
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Fail)]
+#[fail(display = "{}: invalid version requirement", _0)]
+pub struct VersionRequirementParseError(pub String);
+
 /// Strong type to represent an error message related to the parsing of a package slot
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Fail)]
 #[fail(display = "{}: invalid slot", 0)]
 pub struct SlotParseError(pub String);
 
+/// Type for errors related to the merge of two [`PackageManifest`]s
+#[derive(Debug)]
+pub struct PackageManifestMergeError {
+    inner: Context<PackageManifestMergeErrorKind>,
+}
+
+/// Kind for errors related to the merge of two [`PackageManifest`]s
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Fail)]
+pub enum PackageManifestMergeErrorKind {
+    /// The two manifests describe different packages
+    #[fail(display = "cannot merge the manifests of {} and {}", _0, _1)]
+    DifferentPackages(super::PackageFullName, super::PackageFullName),
+
+    /// The two manifests describe the same package, but with different metadata
+    #[fail(display = "the manifests of {} have conflicting metadata", _0)]
+    ConflictingMetadata(super::PackageFullName),
+
+    /// The two manifests describe the same version of a package, but with different data
+    #[fail(display = "the manifests of {} have conflicting data", _0)]
+    ConflictingVersion(super::PackageID),
+}
+
+use_as_error!(PackageManifestMergeError, PackageManifestMergeErrorKind);
+
 /// Type for errors related to the exploration of an NPF file
 #[derive(Debug)]
 pub struct NPFExplorationError {
@@ -219,6 +253,14 @@ pub enum NPFExplorationErrorKind {
     #[fail(display = "invalid manifest.toml")]
     InvalidManifest,
 
+    /// The data archive of an effective package could not be found in its NPF
+    #[fail(display = "the data archive could not be found")]
+    MissingDataArchive,
+
+    /// The data archive of an NPF is compressed using an unknown format
+    #[fail(display = "unsupported compression format for the data archive")]
+    UnsupportedCompression,
+
     /// A requested file could not be found in the NPF
     #[fail(display = "the requested file not found in the NPF: {:?}", _0)]
     FileNotFound(std::path::PathBuf),
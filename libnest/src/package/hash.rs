@@ -0,0 +1,72 @@
+use std::io::{self, Read, Write};
+
+use data_encoding::HEXUPPER;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+/// The hash algorithms that can be used to check the integrity of a package's NPF
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # fn main() -> std::io::Result<()> {
+/// use libnest::package::HashAlgorithm;
+///
+/// assert_eq!(HashAlgorithm::default(), HashAlgorithm::Sha256);
+/// assert_eq!(
+///     HashAlgorithm::Sha256.digest(&b"abc"[..])?,
+///     "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD",
+/// );
+/// assert_eq!(
+///     HashAlgorithm::Sha512.digest(&b"abc"[..])?,
+///     "DDAF35A193617ABACC417349AE20413112E6FA4E89A97EA20A9EEEE64B55D39A\
+///      2192992A274FC1A836BA3C23A3FEEBBD454D4423643CE80E2A9AC94FA54CA49F",
+/// );
+/// assert_eq!(
+///     HashAlgorithm::Blake3.digest(&b"abc"[..])?,
+///     "6437B3AC38465133FFB63B75273A8DB548C558465D79DB03FD359C6CD5BD9D85",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// SHA-256
+    Sha256,
+
+    /// SHA-512
+    Sha512,
+
+    /// BLAKE3, with its default 256-bit output
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Computes the hash of all the data of the given reader, and returns it as an uppercase hexadecimal string
+    pub fn digest<R: Read>(self, mut reader: R) -> io::Result<String> {
+        match self {
+            HashAlgorithm::Sha256 => digest_with::<Sha256, _>(reader),
+            HashAlgorithm::Sha512 => digest_with::<Sha512, _>(reader),
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                io::copy(&mut reader, &mut hasher)?;
+                Ok(HEXUPPER.encode(hasher.finalize().as_bytes()))
+            }
+        }
+    }
+}
+
+impl Default for HashAlgorithm {
+    #[inline]
+    fn default() -> Self {
+        HashAlgorithm::Sha256
+    }
+}
+
+fn digest_with<D: Digest + Write + Default, R: Read>(mut reader: R) -> io::Result<String> {
+    let mut hasher = D::default();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(HEXUPPER.encode(hasher.result().as_ref()))
+}
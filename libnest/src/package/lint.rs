@@ -0,0 +1,44 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, Utc};
+
+use super::UpstreamURL;
+
+/// A warning about a [`Manifest`] that is valid but shouldn't be published as is, returned by
+/// [`Manifest::lint`].
+///
+/// [`Manifest`]: struct.Manifest.html
+/// [`Manifest::lint`]: struct.Manifest.html#method.lint
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LintWarning {
+    /// The package has no maintainer
+    MissingMaintainer,
+
+    /// The package has no upstream URL
+    MissingUpstreamURL,
+
+    /// The upstream URL of the package isn't an HTTP or HTTPS URL
+    UnsupportedUpstreamURL(UpstreamURL),
+
+    /// The package was wrapped at a date that is still in the future
+    FutureWrapDate(DateTime<Utc>),
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LintWarning::MissingMaintainer => write!(f, "the package has no maintainer"),
+            LintWarning::MissingUpstreamURL => write!(f, "the package has no upstream URL"),
+            LintWarning::UnsupportedUpstreamURL(url) => {
+                write!(
+                    f,
+                    "the upstream URL \"{}\" isn't an HTTP or HTTPS URL",
+                    url.as_str()
+                )
+            }
+            LintWarning::FutureWrapDate(date) => {
+                write!(f, "the wrap date {} is in the future", date)
+            }
+        }
+    }
+}
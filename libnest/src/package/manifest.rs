@@ -3,23 +3,35 @@ use std::convert::TryFrom;
 use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
-use failure::{Error, ResultExt};
+use failure::{format_err, Error, ResultExt};
 use lazy_static::lazy_static;
 use regex::Regex;
 use semver::Version;
 use serde::de::Visitor;
 use serde_derive::{Deserialize, Serialize};
 
-use super::error::SlotParseError;
+use crate::cache::{CacheError, CacheErrorKind};
+
+use super::arch::Arch;
+use super::error::{PackageManifestMergeError, PackageManifestMergeErrorKind, SlotParseError};
+use super::hash::HashAlgorithm;
+use super::lint::LintWarning;
+use super::npf::Compression;
 use super::Metadata;
 use super::{
     CategoryName, PackageFullName, PackageID, PackageName, PackageRequirement, PackageShortName,
     RepositoryName,
 };
 
+lazy_static! {
+    /// The version of Nest that is running, compared to the minimum Nest version of manifests
+    static ref NEST_VERSION: Version =
+        Version::parse(env!("CARGO_PKG_VERSION")).expect("the version of Nest is invalid");
+}
+
 /// A manifest that aggregates all versions of a package in one, compact structure.
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct PackageManifest {
@@ -49,12 +61,30 @@ impl PackageManifest {
         }
     }
 
-    #[inline]
+    /// Loads the manifest stored at the given path of the available packages cache
+    ///
+    /// An empty file fails with [`CacheErrorKind::EmptyManifest`], so that it can be told apart from a
+    /// manifest with a malformed content, which fails with [`CacheErrorKind::CacheParseError`].
     pub(crate) fn load_from_cache<P: AsRef<Path>>(cache_path: P) -> Result<Self, Error> {
-        let file =
-            File::open(cache_path.as_ref()).context(cache_path.as_ref().display().to_string())?;
+        let cache_path = cache_path.as_ref();
+        let file = File::open(cache_path).context(cache_path.display().to_string())?;
+
+        if file
+            .metadata()
+            .context(cache_path.display().to_string())?
+            .len()
+            == 0
+        {
+            return Err(CacheError::from(
+                format_err!("{}", cache_path.display()).context(CacheErrorKind::EmptyManifest),
+            )
+            .into());
+        }
 
-        Ok(serde_json::from_reader(&file).context(cache_path.as_ref().display().to_string())?)
+        Ok(serde_json::from_reader(&file)
+            .context(cache_path.display().to_string())
+            .context(CacheErrorKind::CacheParseError)
+            .map_err(CacheError::from)?)
     }
 
     /// Returns a reference over the name of the package
@@ -134,6 +164,155 @@ impl PackageManifest {
         )
     }
 
+    /// Merges another [`PackageManifest`] of the same package into this one.
+    ///
+    /// This is used when the manifests of a package are split across multiple sources: the versions of
+    /// both manifests are united. Merging fails, leaving this manifest untouched, if the two manifests
+    /// describe different packages, have different metadata, or hold different data for the same version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::package::{PackageID, PackageManifest, PackageManifestMergeErrorKind};
+    ///
+    /// let manifest_with = |version: &str, wrap_date: &str| -> Result<PackageManifest, failure::Error> {
+    ///     Ok(serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "gcc",
+    ///             "category": "sys-devel",
+    ///             "repository": "stable",
+    ///             "metadata": {{
+    ///                 "description": "The GNU Compiler Collection",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "{}": {{ "wrap_date": "{}", "dependencies": [] }}
+    ///             }}
+    ///         }}"#,
+    ///         version, wrap_date,
+    ///     ))?)
+    /// };
+    ///
+    /// // Manifests with disjoint versions are merged
+    /// let mut manifest = manifest_with("1.0.0", "2019-05-27T16:34:15Z")?;
+    /// manifest.merge(manifest_with("2.0.0", "2019-05-27T16:34:15Z")?)?;
+    /// assert_eq!(manifest.versions().len(), 2);
+    ///
+    /// // The same version is allowed twice, as long as both copies are the same
+    /// manifest.merge(manifest_with("2.0.0", "2019-05-27T16:34:15Z")?)?;
+    /// assert_eq!(manifest.versions().len(), 2);
+    ///
+    /// // Conflicting versions are refused
+    /// let error = manifest.merge(manifest_with("2.0.0", "2019-06-01T00:00:00Z")?).unwrap_err();
+    /// assert_eq!(
+    ///     *error.kind(),
+    ///     PackageManifestMergeErrorKind::ConflictingVersion(PackageID::parse("stable::sys-devel/gcc#2.0.0")?),
+    /// );
+    /// assert_eq!(manifest.versions().len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(&mut self, other: PackageManifest) -> Result<(), PackageManifestMergeError> {
+        if self.full_name() != other.full_name() {
+            return Err(PackageManifestMergeErrorKind::DifferentPackages(
+                self.full_name(),
+                other.full_name(),
+            )
+            .into());
+        }
+        if self.metadata != other.metadata {
+            return Err(
+                PackageManifestMergeErrorKind::ConflictingMetadata(self.full_name()).into(),
+            );
+        }
+
+        // Check all versions before merging anything, so the manifest is left untouched on error
+        for (version, version_data) in &other.versions {
+            if let Some(data) = self.versions.get(version) {
+                if data != version_data {
+                    let id = PackageID::from(
+                        self.repository.clone(),
+                        self.category.clone(),
+                        self.name.clone(),
+                        version.clone(),
+                    );
+                    return Err(PackageManifestMergeErrorKind::ConflictingVersion(id).into());
+                }
+            }
+        }
+
+        self.versions.extend(other.versions);
+        Ok(())
+    }
+
+    /// Returns the changelog entries of the versions crossed when going from the version `from` to the version `to`,
+    /// sorted by ascending version.
+    ///
+    /// Only the versions greater than `from` and lower than or equal to `to` are considered, and those without a
+    /// changelog are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate semver;
+    /// # extern crate serde_json;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::package::PackageManifest;
+    /// use semver::Version;
+    ///
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "gcc",
+    ///         "category": "sys-devel",
+    ///         "repository": "stable",
+    ///         "metadata": {
+    ///             "description": "The GNU Compiler Collection",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "0.9.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Beta" },
+    ///             "1.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] },
+    ///             "1.1.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Faster" },
+    ///             "1.2.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Smaller" },
+    ///             "1.3.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Unreleased" }
+    ///         }
+    ///     }"#,
+    /// )?;
+    ///
+    /// let entries = manifest.changelog_between(&Version::parse("1.0.0")?, &Version::parse("1.2.0")?);
+    /// assert_eq!(
+    ///     entries,
+    ///     vec![
+    ///         (&Version::parse("1.1.0")?, "Faster"),
+    ///         (&Version::parse("1.2.0")?, "Smaller"),
+    ///     ],
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn changelog_between(&self, from: &Version, to: &Version) -> Vec<(&Version, &str)> {
+        let mut entries: Vec<_> = self
+            .versions
+            .iter()
+            .filter(|(version, _)| *version > from && *version <= to)
+            .filter_map(|(version, data)| data.changelog().map(|changelog| (version, changelog)))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+
     /// Regenerates the [`Manifest`] of this [`PackageManifest`] for the given [`Version`].
     pub fn get_manifest_for_version(&self, version: Version) -> Option<Manifest> {
         self.versions.get(&version).map(|version_data| {
@@ -174,6 +353,30 @@ pub struct Manifest {
     kind: Kind,
     wrap_date: DateTime<Utc>,
     dependencies: HashSet<PackageRequirement>,
+    #[serde(default)]
+    optional_dependencies: HashSet<PackageRequirement>,
+    #[serde(default)]
+    conflicts: HashSet<PackageRequirement>,
+    #[serde(default)]
+    provides: HashSet<PackageShortName>,
+    #[serde(default, alias = "sha256")]
+    hash: Option<String>,
+    #[serde(default)]
+    hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    download_size: Option<u64>,
+    #[serde(default)]
+    installed_size: Option<u64>,
+    #[serde(default)]
+    compression: Option<Compression>,
+    #[serde(default)]
+    min_nest_version: Option<Version>,
+    #[serde(default)]
+    config_files: HashSet<PathBuf>,
+    #[serde(default)]
+    changelog: Option<String>,
+    #[serde(default)]
+    architectures: HashSet<Arch>,
 }
 
 impl Manifest {
@@ -195,6 +398,18 @@ impl Manifest {
             kind: version_data.kind,
             wrap_date: version_data.wrap_date,
             dependencies: version_data.dependencies,
+            optional_dependencies: version_data.optional_dependencies,
+            conflicts: version_data.conflicts,
+            provides: version_data.provides,
+            hash: version_data.hash,
+            hash_algorithm: version_data.hash_algorithm,
+            download_size: version_data.download_size,
+            installed_size: version_data.installed_size,
+            compression: version_data.compression,
+            min_nest_version: version_data.min_nest_version,
+            config_files: version_data.config_files,
+            changelog: version_data.changelog,
+            architectures: version_data.architectures,
         }
     }
 
@@ -294,6 +509,227 @@ impl Manifest {
         &mut self.dependencies
     }
 
+    /// Returns a reference over the package's optional dependencies, which are only installed along with it
+    /// if the [configuration][1] allows it
+    ///
+    /// [1]: ../config/struct.InstallConfig.html#method.optional_dependencies
+    #[inline]
+    pub fn optional_dependencies(&self) -> &HashSet<PackageRequirement> {
+        &self.optional_dependencies
+    }
+
+    /// Returns a mutable reference over the package's optional dependencies
+    #[inline]
+    pub fn optional_dependencies_mut(&mut self) -> &mut HashSet<PackageRequirement> {
+        &mut self.optional_dependencies
+    }
+
+    /// Returns a reference over the requirements matching the packages that can't be installed alongside this one
+    #[inline]
+    pub fn conflicts(&self) -> &HashSet<PackageRequirement> {
+        &self.conflicts
+    }
+
+    /// Returns a mutable reference over the requirements matching the packages that can't be installed alongside this one
+    #[inline]
+    pub fn conflicts_mut(&mut self) -> &mut HashSet<PackageRequirement> {
+        &mut self.conflicts
+    }
+
+    /// Returns a reference over the names of the virtual packages this package provides.
+    ///
+    /// A requirement on one of them can be fulfilled by this package.
+    #[inline]
+    pub fn provides(&self) -> &HashSet<PackageShortName> {
+        &self.provides
+    }
+
+    /// Returns a mutable reference over the names of the virtual packages this package provides
+    #[inline]
+    pub fn provides_mut(&mut self) -> &mut HashSet<PackageShortName> {
+        &mut self.provides
+    }
+
+    /// Returns the expected hash of the package's NPF, if the manifest carries one.
+    ///
+    /// It's computed with the algorithm given by [`hash_algorithm`], SHA256 by default. It's read from
+    /// either the `hash` field or, for older manifests, the `sha256` one.
+    ///
+    /// [`hash_algorithm`]: #method.hash_algorithm
+    #[inline]
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_ref().map(String::as_str)
+    }
+
+    /// Returns a mutable reference over the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_mut(&mut self) -> &mut Option<String> {
+        &mut self.hash
+    }
+
+    /// Returns the algorithm of the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Returns a mutable reference over the algorithm of the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_algorithm_mut(&mut self) -> &mut HashAlgorithm {
+        &mut self.hash_algorithm
+    }
+
+    /// Returns the size of the package's NPF in bytes, if the manifest carries it
+    #[inline]
+    pub fn download_size(&self) -> Option<u64> {
+        self.download_size
+    }
+
+    /// Returns a mutable reference over the size of the package's NPF in bytes
+    #[inline]
+    pub fn download_size_mut(&mut self) -> &mut Option<u64> {
+        &mut self.download_size
+    }
+
+    /// Returns the size of the package's files once installed in bytes, if the manifest carries it
+    #[inline]
+    pub fn installed_size(&self) -> Option<u64> {
+        self.installed_size
+    }
+
+    /// Returns a mutable reference over the size of the package's files once installed in bytes
+    #[inline]
+    pub fn installed_size_mut(&mut self) -> &mut Option<u64> {
+        &mut self.installed_size
+    }
+
+    /// Returns the compression format of the data archive of the package's NPF, if the manifest carries it.
+    ///
+    /// It's only a hint, used when the format can't be guessed from the content of the archive.
+    #[inline]
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Returns a mutable reference over the compression format of the data archive of the package's NPF
+    #[inline]
+    pub fn compression_mut(&mut self) -> &mut Option<Compression> {
+        &mut self.compression
+    }
+
+    /// Returns the oldest version of Nest able to install the package, if the manifest carries it
+    #[inline]
+    pub fn min_nest_version(&self) -> Option<&Version> {
+        self.min_nest_version.as_ref()
+    }
+
+    /// Returns a mutable reference over the oldest version of Nest able to install the package
+    #[inline]
+    pub fn min_nest_version_mut(&mut self) -> &mut Option<Version> {
+        &mut self.min_nest_version
+    }
+
+    /// Returns a reference over the configuration files of the package, as absolute paths within the installation root.
+    ///
+    /// When upgrading the package, those modified by the user are kept and the new version is written next to them,
+    /// with a `.nest-new` extension.
+    #[inline]
+    pub fn config_files(&self) -> &HashSet<PathBuf> {
+        &self.config_files
+    }
+
+    /// Returns a mutable reference over the configuration files of the package
+    #[inline]
+    pub fn config_files_mut(&mut self) -> &mut HashSet<PathBuf> {
+        &mut self.config_files
+    }
+
+    /// Returns the changes brought by this version of the package, if the manifest carries them
+    #[inline]
+    pub fn changelog(&self) -> Option<&str> {
+        self.changelog.as_ref().map(String::as_str)
+    }
+
+    /// Returns a mutable reference over the changes brought by this version of the package
+    #[inline]
+    pub fn changelog_mut(&mut self) -> &mut Option<String> {
+        &mut self.changelog
+    }
+
+    /// Returns a reference over the architectures the package can be installed on.
+    ///
+    /// An empty set means the package can be installed on any architecture.
+    #[inline]
+    pub fn architectures(&self) -> &HashSet<Arch> {
+        &self.architectures
+    }
+
+    /// Returns a mutable reference over the architectures the package can be installed on
+    #[inline]
+    pub fn architectures_mut(&mut self) -> &mut HashSet<Arch> {
+        &mut self.architectures
+    }
+
+    /// Returns whether the running version of Nest is recent enough to install the package, according to its
+    /// [minimum Nest version][1].
+    ///
+    /// Packages whose manifest doesn't carry a minimum Nest version are supported by all versions.
+    ///
+    /// [1]: #method.min_nest_version
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate semver;
+    /// # extern crate serde_json;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::package::Manifest;
+    /// use semver::Version;
+    ///
+    /// let mut manifest: Manifest = serde_json::from_value(serde_json::json!({
+    ///     "name": "hello",
+    ///     "category": "sys-apps",
+    ///     "version": "1.0.0",
+    ///     "wrap_date": "2019-05-27T16:34:15Z",
+    ///     "dependencies": [],
+    ///     "metadata": {
+    ///         "description": "Prints hello",
+    ///         "tags": [],
+    ///         "maintainer": "nest@raven-os.org",
+    ///         "licenses": [],
+    ///     },
+    /// }))?;
+    ///
+    /// // Without a minimum version, the package is supported
+    /// assert_eq!(manifest.min_nest_version(), None);
+    /// assert!(manifest.is_supported_by_running_nest());
+    ///
+    /// *manifest.min_nest_version_mut() = Some(Version::parse("0.0.1")?);
+    /// assert!(manifest.is_supported_by_running_nest());
+    ///
+    /// *manifest.min_nest_version_mut() = Some(Version::parse("99.0.0")?);
+    /// assert!(!manifest.is_supported_by_running_nest());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_supported_by_running_nest(&self) -> bool {
+        match &self.min_nest_version {
+            Some(min_nest_version) => *min_nest_version <= *NEST_VERSION,
+            None => true,
+        }
+    }
+
+    /// Returns whether the package can be installed on the given architecture, according to its
+    /// [architectures][1].
+    ///
+    /// [1]: #method.architectures
+    #[inline]
+    pub fn supports_arch(&self, arch: &Arch) -> bool {
+        self.architectures.is_empty() || self.architectures.contains(arch)
+    }
+
     /// Generates the [`PackageShortName`] of this package
     pub fn short_name(&self) -> PackageShortName {
         PackageShortName::from(self.category().clone(), self.name().clone())
@@ -319,6 +755,121 @@ impl Manifest {
             self.name().clone(),
         )
     }
+
+    /// Generates a [`PackageManifest`] holding only this version of the package, given its missing piece: the
+    /// [`RepositoryName`].
+    pub fn package_manifest(&self, repository_name: RepositoryName) -> PackageManifest {
+        let mut package_manifest = PackageManifest::new(
+            self.name.clone(),
+            self.category.clone(),
+            repository_name,
+            self.metadata.clone(),
+        );
+        package_manifest.versions.insert(
+            self.version.clone(),
+            VersionData {
+                slot: self.slot.clone(),
+                kind: self.kind,
+                wrap_date: self.wrap_date,
+                dependencies: self.dependencies.clone(),
+                optional_dependencies: self.optional_dependencies.clone(),
+                conflicts: self.conflicts.clone(),
+                provides: self.provides.clone(),
+                hash: self.hash.clone(),
+                hash_algorithm: self.hash_algorithm,
+                download_size: self.download_size,
+                installed_size: self.installed_size,
+                compression: self.compression,
+                min_nest_version: self.min_nest_version.clone(),
+                config_files: self.config_files.clone(),
+                changelog: self.changelog.clone(),
+                architectures: self.architectures.clone(),
+            },
+        );
+        package_manifest
+    }
+
+    /// Checks the manifest against the rules a package should follow before being published, returning a
+    /// [`LintWarning`] for each broken rule.
+    ///
+    /// The manifest must have a maintainer, an HTTP or HTTPS upstream URL and a wrap date that isn't in the future. Its
+    /// name, category and dependencies aren't checked, as they are already validated when the manifest is parsed.
+    /// Unlike parsing errors, these warnings don't prevent the manifest from being used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::package::{LintWarning, Manifest};
+    ///
+    /// let manifest: Manifest = toml::from_str(
+    ///     r#"
+    ///     name = "gcc"
+    ///     category = "sys-devel"
+    ///     version = "9.1.0"
+    ///     wrap_date = "2019-05-27T16:34:15Z"
+    ///     dependencies = ["stable::sys-libs/glibc#^2.29.0"]
+    ///
+    ///     [metadata]
+    ///     description = "The GNU Compiler Collection"
+    ///     tags = []
+    ///     maintainer = "Raven <nest@raven-os.org>"
+    ///     licenses = ["gpl_v3"]
+    ///     upstream_url = "https://gcc.gnu.org/"
+    ///     "#,
+    /// )?;
+    /// assert!(manifest.lint().is_empty());
+    ///
+    /// let manifest: Manifest = toml::from_str(
+    ///     r#"
+    ///     name = "gcc"
+    ///     category = "sys-devel"
+    ///     version = "9.1.0"
+    ///     wrap_date = "2999-01-01T00:00:00Z"
+    ///     dependencies = []
+    ///
+    ///     [metadata]
+    ///     description = "The GNU Compiler Collection"
+    ///     tags = []
+    ///     maintainer = ""
+    ///     licenses = ["gpl_v3"]
+    ///     upstream_url = "ftp://gcc.gnu.org/pub/gcc/"
+    ///     "#,
+    /// )?;
+    /// let warnings = manifest.lint();
+    /// assert_eq!(warnings.len(), 3);
+    /// assert_eq!(warnings[0], LintWarning::MissingMaintainer);
+    /// match &warnings[1] {
+    ///     LintWarning::UnsupportedUpstreamURL(url) => assert_eq!(url.scheme(), "ftp"),
+    ///     warning => panic!("unexpected warning: {}", warning),
+    /// }
+    /// assert_eq!(warnings[2], LintWarning::FutureWrapDate(*manifest.wrap_date()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lint(&self) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+
+        if self.metadata().maintainer().trim().is_empty() {
+            warnings.push(LintWarning::MissingMaintainer);
+        }
+        match self.metadata().upstream_url() {
+            Some(url) if url.scheme() != "http" && url.scheme() != "https" => {
+                warnings.push(LintWarning::UnsupportedUpstreamURL(url.clone()))
+            }
+            Some(_) => (),
+            None => warnings.push(LintWarning::MissingUpstreamURL),
+        }
+
+        if *self.wrap_date() > Utc::now() {
+            warnings.push(LintWarning::FutureWrapDate(*self.wrap_date()));
+        }
+
+        warnings
+    }
 }
 
 /// A container holding that differs from one version to another of the same package.
@@ -330,6 +881,30 @@ pub struct VersionData {
     kind: Kind,
     wrap_date: DateTime<Utc>,
     dependencies: HashSet<PackageRequirement>,
+    #[serde(default)]
+    optional_dependencies: HashSet<PackageRequirement>,
+    #[serde(default)]
+    conflicts: HashSet<PackageRequirement>,
+    #[serde(default)]
+    provides: HashSet<PackageShortName>,
+    #[serde(default, alias = "sha256")]
+    hash: Option<String>,
+    #[serde(default)]
+    hash_algorithm: HashAlgorithm,
+    #[serde(default)]
+    download_size: Option<u64>,
+    #[serde(default)]
+    installed_size: Option<u64>,
+    #[serde(default)]
+    compression: Option<Compression>,
+    #[serde(default)]
+    min_nest_version: Option<Version>,
+    #[serde(default)]
+    config_files: HashSet<PathBuf>,
+    #[serde(default)]
+    changelog: Option<String>,
+    #[serde(default)]
+    architectures: HashSet<Arch>,
 }
 
 impl VersionData {
@@ -346,6 +921,18 @@ impl VersionData {
             kind,
             wrap_date,
             dependencies,
+            optional_dependencies: HashSet::new(),
+            conflicts: HashSet::new(),
+            provides: HashSet::new(),
+            hash: None,
+            hash_algorithm: HashAlgorithm::default(),
+            download_size: None,
+            installed_size: None,
+            compression: None,
+            min_nest_version: None,
+            config_files: HashSet::new(),
+            changelog: None,
+            architectures: HashSet::new(),
         }
     }
 
@@ -396,6 +983,167 @@ impl VersionData {
     pub fn dependencies_mut(&mut self) -> &mut HashSet<PackageRequirement> {
         &mut self.dependencies
     }
+
+    /// Returns a reference over the package's optional dependencies, which are only installed along with it
+    /// if the [configuration][1] allows it
+    ///
+    /// [1]: ../config/struct.InstallConfig.html#method.optional_dependencies
+    #[inline]
+    pub fn optional_dependencies(&self) -> &HashSet<PackageRequirement> {
+        &self.optional_dependencies
+    }
+
+    /// Returns a mutable reference over the package's optional dependencies
+    #[inline]
+    pub fn optional_dependencies_mut(&mut self) -> &mut HashSet<PackageRequirement> {
+        &mut self.optional_dependencies
+    }
+
+    /// Returns a reference over the requirements matching the packages that can't be installed alongside this one
+    #[inline]
+    pub fn conflicts(&self) -> &HashSet<PackageRequirement> {
+        &self.conflicts
+    }
+
+    /// Returns a mutable reference over the requirements matching the packages that can't be installed alongside this one
+    #[inline]
+    pub fn conflicts_mut(&mut self) -> &mut HashSet<PackageRequirement> {
+        &mut self.conflicts
+    }
+
+    /// Returns a reference over the names of the virtual packages this package provides.
+    ///
+    /// A requirement on one of them can be fulfilled by this package.
+    #[inline]
+    pub fn provides(&self) -> &HashSet<PackageShortName> {
+        &self.provides
+    }
+
+    /// Returns a mutable reference over the names of the virtual packages this package provides
+    #[inline]
+    pub fn provides_mut(&mut self) -> &mut HashSet<PackageShortName> {
+        &mut self.provides
+    }
+
+    /// Returns the expected hash of the package's NPF, if the manifest carries one.
+    ///
+    /// It's computed with the algorithm given by [`hash_algorithm`], SHA256 by default. It's read from
+    /// either the `hash` field or, for older manifests, the `sha256` one.
+    ///
+    /// [`hash_algorithm`]: #method.hash_algorithm
+    #[inline]
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_ref().map(String::as_str)
+    }
+
+    /// Returns a mutable reference over the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_mut(&mut self) -> &mut Option<String> {
+        &mut self.hash
+    }
+
+    /// Returns the algorithm of the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Returns a mutable reference over the algorithm of the expected hash of the package's NPF
+    #[inline]
+    pub fn hash_algorithm_mut(&mut self) -> &mut HashAlgorithm {
+        &mut self.hash_algorithm
+    }
+
+    /// Returns the size of the package's NPF in bytes, if the manifest carries it
+    #[inline]
+    pub fn download_size(&self) -> Option<u64> {
+        self.download_size
+    }
+
+    /// Returns a mutable reference over the size of the package's NPF in bytes
+    #[inline]
+    pub fn download_size_mut(&mut self) -> &mut Option<u64> {
+        &mut self.download_size
+    }
+
+    /// Returns the size of the package's files once installed in bytes, if the manifest carries it
+    #[inline]
+    pub fn installed_size(&self) -> Option<u64> {
+        self.installed_size
+    }
+
+    /// Returns a mutable reference over the size of the package's files once installed in bytes
+    #[inline]
+    pub fn installed_size_mut(&mut self) -> &mut Option<u64> {
+        &mut self.installed_size
+    }
+
+    /// Returns the compression format of the data archive of the package's NPF, if the manifest carries it.
+    ///
+    /// It's only a hint, used when the format can't be guessed from the content of the archive.
+    #[inline]
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Returns a mutable reference over the compression format of the data archive of the package's NPF
+    #[inline]
+    pub fn compression_mut(&mut self) -> &mut Option<Compression> {
+        &mut self.compression
+    }
+
+    /// Returns the oldest version of Nest able to install the package, if the manifest carries it
+    #[inline]
+    pub fn min_nest_version(&self) -> Option<&Version> {
+        self.min_nest_version.as_ref()
+    }
+
+    /// Returns a mutable reference over the oldest version of Nest able to install the package
+    #[inline]
+    pub fn min_nest_version_mut(&mut self) -> &mut Option<Version> {
+        &mut self.min_nest_version
+    }
+
+    /// Returns a reference over the configuration files of the package, as absolute paths within the installation root.
+    ///
+    /// When upgrading the package, those modified by the user are kept and the new version is written next to them,
+    /// with a `.nest-new` extension.
+    #[inline]
+    pub fn config_files(&self) -> &HashSet<PathBuf> {
+        &self.config_files
+    }
+
+    /// Returns a mutable reference over the configuration files of the package
+    #[inline]
+    pub fn config_files_mut(&mut self) -> &mut HashSet<PathBuf> {
+        &mut self.config_files
+    }
+
+    /// Returns the changes brought by this version of the package, if the manifest carries them
+    #[inline]
+    pub fn changelog(&self) -> Option<&str> {
+        self.changelog.as_ref().map(String::as_str)
+    }
+
+    /// Returns a mutable reference over the changes brought by this version of the package
+    #[inline]
+    pub fn changelog_mut(&mut self) -> &mut Option<String> {
+        &mut self.changelog
+    }
+
+    /// Returns a reference over the architectures the package can be installed on.
+    ///
+    /// An empty set means the package can be installed on any architecture.
+    #[inline]
+    pub fn architectures(&self) -> &HashSet<Arch> {
+        &self.architectures
+    }
+
+    /// Returns a mutable reference over the architectures the package can be installed on
+    #[inline]
+    pub fn architectures_mut(&mut self) -> &mut HashSet<Arch> {
+        &mut self.architectures
+    }
 }
 
 /// A package's kind.
@@ -2,11 +2,12 @@ use std::convert::TryFrom;
 
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::de::Visitor;
+use serde::de::{Error as _, Visitor};
+use serde::{Deserialize, Deserializer};
 use serde_derive::{Deserialize, Serialize};
 use url_serde::SerdeUrl;
 
-use super::error::{LicenseParseError, TagParseError};
+use super::error::{LicenseParseError, TagParseError, UpstreamURLParseError};
 
 /// A package's metadata, like its description, tags, maintainer etc.
 #[derive(Default, Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
@@ -15,6 +16,7 @@ pub struct Metadata {
     tags: Vec<Tag>,
     maintainer: Maintainer,
     licenses: Vec<License>,
+    #[serde(default, deserialize_with = "deserialize_upstream_url")]
     upstream_url: Option<UpstreamURL>,
 }
 
@@ -49,6 +51,43 @@ impl Metadata {
         &mut self.maintainer
     }
 
+    /// Returns the name of the maintainer of the package, if any.
+    ///
+    /// The maintainer usually follows the `Name <email>` format, but the name or the email address may be
+    /// given alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// use libnest::package::Metadata;
+    ///
+    /// let mut metadata = Metadata::default();
+    /// *metadata.maintainer_mut() = "Jane Doe <jane@example.org>".to_string();
+    /// assert_eq!(metadata.maintainer_name(), Some("Jane Doe"));
+    /// assert_eq!(metadata.maintainer_email(), Some("jane@example.org"));
+    ///
+    /// *metadata.maintainer_mut() = "Jane Doe".to_string();
+    /// assert_eq!(metadata.maintainer_name(), Some("Jane Doe"));
+    /// assert_eq!(metadata.maintainer_email(), None);
+    ///
+    /// *metadata.maintainer_mut() = "nest@raven-os.org".to_string();
+    /// assert_eq!(metadata.maintainer_name(), None);
+    /// assert_eq!(metadata.maintainer_email(), Some("nest@raven-os.org"));
+    /// ```
+    pub fn maintainer_name(&self) -> Option<&str> {
+        split_maintainer(&self.maintainer).0
+    }
+
+    /// Returns the email address of the maintainer of the package, if any.
+    ///
+    /// See [`maintainer_name`][1] for the format of the maintainer.
+    ///
+    /// [1]: #method.maintainer_name
+    pub fn maintainer_email(&self) -> Option<&str> {
+        split_maintainer(&self.maintainer).1
+    }
+
     /// Returns a reference over the list of licenses of the package
     pub fn licenses(&self) -> &Vec<License> {
         &self.licenses
@@ -60,6 +99,36 @@ impl Metadata {
     }
 
     /// Returns a reference over the upstream_url of the package
+    ///
+    /// The upstream URL must be a well-formed URL with a host, otherwise the metadata can't be deserialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate serde_json;
+    /// use libnest::package::Metadata;
+    ///
+    /// let metadata_with = |upstream_url: &str| {
+    ///     serde_json::from_str::<Metadata>(&format!(
+    ///         r#"{{
+    ///             "description": "",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": {}
+    ///         }}"#,
+    ///         upstream_url,
+    ///     ))
+    /// };
+    ///
+    /// let metadata = metadata_with(r#""https://www.gnu.org/software/gcc/""#).unwrap();
+    /// assert_eq!(metadata.upstream_url().as_ref().unwrap().host_str(), Some("www.gnu.org"));
+    /// assert!(metadata_with("null").unwrap().upstream_url().is_none());
+    ///
+    /// assert!(metadata_with(r#""www.gnu.org""#).is_err());
+    /// assert!(metadata_with(r#""mailto:gcc@gnu.org""#).is_err());
+    /// ```
     pub fn upstream_url(&self) -> &Option<UpstreamURL> {
         &self.upstream_url
     }
@@ -76,6 +145,36 @@ pub type Maintainer = String;
 /// An URL pointing to the upstream source of the package, usually its home page.
 pub type UpstreamURL = SerdeUrl;
 
+/// Splits a maintainer into its name and email address, each of them being optional
+fn split_maintainer(maintainer: &str) -> (Option<&str>, Option<&str>) {
+    fn non_empty(value: &str) -> Option<&str> {
+        Some(value.trim()).filter(|value| !value.is_empty())
+    }
+
+    let value = maintainer.trim();
+    match value.rfind('<') {
+        Some(start) if value.ends_with('>') => (
+            non_empty(&value[..start]),
+            non_empty(&value[start + 1..value.len() - 1]),
+        ),
+        _ if value.contains('@') && !value.contains(char::is_whitespace) => (None, Some(value)),
+        _ => (non_empty(value), None),
+    }
+}
+
+/// Deserializes an upstream URL, refusing those that don't have a host
+fn deserialize_upstream_url<'de, D>(deserializer: D) -> Result<Option<UpstreamURL>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<UpstreamURL>::deserialize(deserializer)? {
+        Some(url) if !url.has_host() => Err(D::Error::custom(UpstreamURLParseError(
+            url.as_str().to_string(),
+        ))),
+        url => Ok(url),
+    }
+}
+
 /// A Tag describing a package.
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Tag(String);
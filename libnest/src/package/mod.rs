@@ -68,21 +68,29 @@ macro_rules! strong_name_impl {
     };
 }
 
+mod arch;
 mod error;
+mod hash;
 mod identification;
+mod lint;
 mod manifest;
 mod metadata;
 mod npf;
 mod requirement;
 
+pub use arch::Arch;
 pub use error::*;
+pub use hash::HashAlgorithm;
 pub use identification::{
     CategoryName, PackageFullName, PackageID, PackageName, PackageShortName, RepositoryName,
 };
-pub use manifest::{Kind, Manifest, PackageManifest, VersionData};
+pub use lint::LintWarning;
+pub use manifest::{Kind, Manifest, PackageManifest, Slot, VersionData};
 pub use metadata::{License, Maintainer, Metadata, Tag, UpstreamURL};
-pub use npf::{NPFExplorer, NPFFile};
-pub use requirement::{HardPackageRequirement, PackageRequirement, SoftPackageRequirement};
+pub use npf::{Compression, NPFExplorer, NPFFile};
+pub use requirement::{
+    HardPackageRequirement, PackageRequirement, SoftPackageRequirement, VersionRequirement,
+};
 
 lazy_static::lazy_static! {
     /// A regular expression to match and parse a package's string representation
@@ -1,15 +1,63 @@
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{self, Read};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
+use flate2::read::GzDecoder;
+use serde_derive::{Deserialize, Serialize};
 use tar::Archive;
 use toml;
+use xz2::read::XzDecoder;
 
 use super::error::{NPFExplorationError, NPFExplorationErrorKind};
+use super::hash::HashAlgorithm;
 use super::manifest::{Kind::Effective, Manifest};
+use crate::cache::installed::log::{FileLogEntry, Log};
+use crate::chroot::{extraction_path, Chroot};
 use crate::transaction::InstructionsExecutor;
 
+/// The names the data archive of an NPF may have, one for each supported compression format
+const DATA_ARCHIVE_NAMES: &[&str] = &["data.tar.gz", "data.tar.xz", "data.tar.zst"];
+
+/// The compression formats supported for the data archive of an NPF
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// The archive is compressed using gzip
+    Gzip,
+
+    /// The archive is compressed using xz
+    Xz,
+
+    /// The archive is compressed using Zstandard
+    Zstd,
+}
+
+impl Compression {
+    /// Guesses the compression format of some data from its first bytes, returning `None` if they don't
+    /// match any of the supported formats
+    pub fn sniff(header: &[u8]) -> Option<Compression> {
+        if header.starts_with(&[0x1F, 0x8B]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if header.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Wraps the given reader into one decompressing its content
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(GzDecoder::new(reader)),
+            Compression::Xz => Box::new(XzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+}
+
 /// Structure representing a handle over a file contained in an NPF
 #[derive(Debug)]
 pub struct NPFFile<'explorer> {
@@ -38,6 +86,7 @@ impl<'explorer> NPFFile<'explorer> {
 pub struct NPFExplorer {
     manifest: Manifest,
     path: PathBuf,
+    data_archive: Option<(&'static str, Compression)>,
 }
 
 impl NPFExplorer {
@@ -54,6 +103,39 @@ impl NPFExplorer {
         Ok(toml::from_str(&content).map_err(|_| NPFExplorationErrorKind::InvalidManifest)?)
     }
 
+    /// Finds the name and the compression format of the data archive, if there is one.
+    ///
+    /// The format is guessed from the content of the archive, falling back to the hint given by the
+    /// manifest if it can't be.
+    fn load_data_archive(
+        path: &Path,
+        manifest: &Manifest,
+    ) -> Result<Option<(&'static str, Compression)>, NPFExplorationError> {
+        let name = match Self::find_data_archive(path) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let data_path = path.join(name);
+
+        let mut header = Vec::with_capacity(6);
+        File::open(&data_path)
+            .and_then(|file| file.take(6).read_to_end(&mut header))
+            .map_err(|_| NPFExplorationErrorKind::FileIOError(data_path.clone()))?;
+
+        Compression::sniff(&header)
+            .or_else(|| manifest.compression())
+            .map(|compression| Some((name, compression)))
+            .ok_or_else(|| NPFExplorationErrorKind::UnsupportedCompression.into())
+    }
+
+    /// Returns the name of the data archive of the NPF extracted at the given path, if it has one
+    fn find_data_archive(path: &Path) -> Option<&'static str> {
+        DATA_ARCHIVE_NAMES
+            .iter()
+            .cloned()
+            .find(|name| path.join(name).is_file())
+    }
+
     fn gen_tmp_filename<P: AsRef<Path>>(base_dir: P) -> PathBuf {
         use rand::distributions::Alphanumeric;
         use rand::{thread_rng, Rng};
@@ -87,9 +169,23 @@ impl NPFExplorer {
             })
             .map_err(|_| NPFExplorationErrorKind::UnpackError)?;
 
-        let manifest = Self::load_manifest(&path)?;
+        let res: Result<_, NPFExplorationError> = try {
+            let manifest = Self::load_manifest(&path)?;
+            let data_archive = Self::load_data_archive(&path, &manifest)?;
+            (manifest, data_archive)
+        };
 
-        Ok(Self { path, manifest })
+        match res {
+            Ok((manifest, data_archive)) => Ok(Self {
+                path,
+                manifest,
+                data_archive,
+            }),
+            Err(e) => {
+                let _ = fs::remove_dir_all(&path);
+                Err(e)
+            }
+        }
     }
 
     /// Create an NPFExplorer from a path to an NPF archive
@@ -123,22 +219,308 @@ impl NPFExplorer {
         self.open_file("manifest.toml")
     }
 
-    /// Retrieves a handle over the NPF's data.tar.gz
+    /// Returns the compression format of the NPF's data archive, or `None` if it doesn't have any
+    pub fn data_compression(&self) -> Option<Compression> {
+        self.data_archive.map(|(_, compression)| compression)
+    }
+
+    /// Retrieves a handle over the NPF's data archive (`data.tar.gz`, `data.tar.xz` or `data.tar.zst`)
     pub fn open_data(&self) -> Result<Option<NPFFile>, NPFExplorationError> {
-        self.open_file("data.tar.gz").map_or_else(
-            |e| match e.kind() {
-                NPFExplorationErrorKind::FileNotFound(_) if self.manifest.kind() != Effective => {
-                    Ok(None)
-                }
-                _ => Err(e),
-            },
-            |o| Ok(Some(o)),
-        )
+        match self.data_archive {
+            Some((name, _)) => self.open_file(name).map(Some),
+            None if self.manifest.kind() != Effective => Ok(None),
+            None => Err(NPFExplorationErrorKind::MissingDataArchive.into()),
+        }
     }
 
-    /// Retrieves a handle over the NPF's instructions.sh, if one exists
-    pub fn open_instructions(&self) -> Result<Option<NPFFile>, NPFExplorationError> {
-        self.open_file("instructions.sh").map_or_else(
+    /// Retrieves the name of the NPF's data archive and a reader over its decompressed content
+    fn open_data_archive(
+        &self,
+    ) -> Result<Option<(&'static str, Box<dyn Read>)>, NPFExplorationError> {
+        let (data, (name, compression)) = match (self.open_data()?, self.data_archive) {
+            (Some(data), Some(data_archive)) => (data, data_archive),
+            _ => return Ok(None),
+        };
+
+        let decoder = compression
+            .decoder(data.file)
+            .map_err(|_| NPFExplorationErrorKind::UnsupportedCompression)?;
+        Ok(Some((name, decoder)))
+    }
+
+    /// Retrieves a reader over the decompressed content of the NPF's data archive, which is a tarball
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate xz2;
+    /// # extern crate zstd;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs::{self, File};
+    /// use std::io::Write;
+    /// use std::path::PathBuf;
+    /// use flate2::write::GzEncoder;
+    /// use xz2::write::XzEncoder;
+    /// use libnest::package::{Compression, NPFExplorationErrorKind, NPFExplorer};
+    ///
+    /// let dir = std::env::temp_dir().join(format!("nest-doctest-compression-{}", std::process::id()));
+    /// fs::create_dir_all(&dir)?;
+    ///
+    /// // A tarball holding a single file
+    /// let mut tarball = tar::Builder::new(Vec::new());
+    /// let mut header = tar::Header::new_gnu();
+    /// header.set_size(6);
+    /// header.set_mode(0o644);
+    /// header.set_cksum();
+    /// tarball.append_data(&mut header, "usr/share/hello", &b"hello\n"[..])?;
+    /// let tarball = tarball.into_inner()?;
+    ///
+    /// // Builds an NPF whose data archive has the given name and content
+    /// let build_npf = |data_name: &str, data: &[u8], hint: &str| -> Result<PathBuf, failure::Error> {
+    ///     fs::write(
+    ///         dir.join("manifest.toml"),
+    ///         format!(
+    ///             r#"
+    ///             name = "hello"
+    ///             category = "sys-apps"
+    ///             version = "1.0.0"
+    ///             kind = "effective"
+    ///             wrap_date = "2019-05-27T16:34:15Z"
+    ///             dependencies = []
+    ///             {}
+    ///
+    ///             [metadata]
+    ///             description = "Says hello"
+    ///             tags = []
+    ///             maintainer = "nest@raven-os.org"
+    ///             licenses = []
+    ///             "#,
+    ///             hint,
+    ///         ),
+    ///     )?;
+    ///     fs::write(dir.join(data_name), data)?;
+    ///
+    ///     let npf_path = dir.join("hello-1.0.0.nest");
+    ///     let mut npf = tar::Builder::new(File::create(&npf_path)?);
+    ///     npf.append_path_with_name(dir.join("manifest.toml"), "manifest.toml")?;
+    ///     npf.append_path_with_name(dir.join(data_name), data_name)?;
+    ///     npf.finish()?;
+    ///     fs::remove_file(dir.join(data_name))?;
+    ///     Ok(npf_path)
+    /// };
+    ///
+    /// let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    /// gzip.write_all(&tarball)?;
+    /// let mut xz = XzEncoder::new(Vec::new(), 6);
+    /// xz.write_all(&tarball)?;
+    /// let fixtures = vec![
+    ///     ("data.tar.gz", gzip.finish()?, Compression::Gzip),
+    ///     ("data.tar.xz", xz.finish()?, Compression::Xz),
+    ///     ("data.tar.zst", zstd::encode_all(&tarball[..], 3)?, Compression::Zstd),
+    /// ];
+    ///
+    /// // The format is guessed from the content of the data archive
+    /// for (data_name, data, compression) in fixtures {
+    ///     let explorer = NPFExplorer::open_at(build_npf(data_name, &data, "")?, &dir)?;
+    ///     assert_eq!(explorer.data_compression(), Some(compression));
+    ///     assert_eq!(explorer.list_files()?, vec![PathBuf::from("/usr/share/hello")]);
+    /// }
+    ///
+    /// // An unknown format is refused...
+    /// let npf_path = build_npf("data.tar.gz", b"not an archive", "")?;
+    /// let error = NPFExplorer::open_at(&npf_path, &dir).unwrap_err();
+    /// assert_eq!(*error.kind(), NPFExplorationErrorKind::UnsupportedCompression);
+    ///
+    /// // ...unless the manifest gives a hint
+    /// let npf_path = build_npf("data.tar.gz", b"not an archive", r#"compression = "gzip""#)?;
+    /// let explorer = NPFExplorer::open_at(&npf_path, &dir)?;
+    /// assert_eq!(explorer.data_compression(), Some(Compression::Gzip));
+    /// # drop(explorer);
+    ///
+    /// // Errors name the data archive actually found
+    /// let npf_path = build_npf("data.tar.xz", b"not an archive", r#"compression = "xz""#)?;
+    /// let error = NPFExplorer::open_at(&npf_path, &dir)?.list_files().unwrap_err();
+    /// assert_eq!(*error.kind(), NPFExplorationErrorKind::FileIOError(PathBuf::from("data.tar.xz")));
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_data_decoder(&self) -> Result<Option<Box<dyn Read>>, NPFExplorationError> {
+        Ok(self.open_data_archive()?.map(|(_, decoder)| decoder))
+    }
+
+    /// Lists the paths of all the files contained in the NPF's data archive, without extracting them.
+    ///
+    /// The paths are absolute within the installation root, like those of the logs of installed packages.
+    /// The archive is read as a stream, so its content is never loaded in memory as a whole.
+    /// Packages without any data have no files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::path::PathBuf;
+    /// use libnest::package::NPFExplorer;
+    ///
+    /// # let dir = std::env::temp_dir().join(format!("nest-doctest-list-files-{}", std::process::id()));
+    /// // `hello-1.0.0.nest` ships a script and its documentation
+    /// # write_npf(&dir.join("hello-1.0.0.nest"), "tests::sys-apps/hello#1.0.0", "", &[],
+    /// #     &[("usr/bin/hello", 0o755, "#!/bin/sh\necho hello\n"), ("usr/share/doc/hello/README", 0o644, "")])?;
+    /// let explorer = NPFExplorer::open_at(dir.join("hello-1.0.0.nest"), &dir)?;
+    /// assert_eq!(
+    ///     explorer.list_files()?,
+    ///     vec![PathBuf::from("/usr/bin/hello"), PathBuf::from("/usr/share/doc/hello/README")],
+    /// );
+    /// # drop(explorer);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_files(&self) -> Result<Vec<PathBuf>, NPFExplorationError> {
+        let (name, data) = match self.open_data_archive()? {
+            Some(data_archive) => data_archive,
+            None => return Ok(Vec::new()),
+        };
+        let invalid_data = |_| NPFExplorationErrorKind::FileIOError(PathBuf::from(name));
+
+        let mut archive = Archive::new(data);
+        let mut files = Vec::new();
+        for entry in archive.entries().map_err(invalid_data)? {
+            let entry = entry.map_err(invalid_data)?;
+            let path = entry.path().map_err(invalid_data)?;
+            files.push(Path::new("/").with_content(&path));
+        }
+        Ok(files)
+    }
+
+    /// Extracts the content of the NPF's data archive within the given root, and returns the [`Log`] of the
+    /// extracted files.
+    ///
+    /// The archive is read as a stream and each entry is written straight to the disk, so the content of
+    /// the package is never loaded in memory as a whole. Parent directories are created as needed and the
+    /// mode of each entry is preserved. The observer is called with the absolute path (within the root) of
+    /// each entry once it's extracted.
+    ///
+    /// Unlike an installation, no check is made against the files that already exist within the root.
+    /// Packages without any data have an empty log.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use std::os::unix::fs::PermissionsExt;
+    /// use std::path::PathBuf;
+    /// use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::package::{HashAlgorithm, NPFExplorer};
+    ///
+    /// # let dir = std::env::temp_dir().join(format!("nest-doctest-extract-to-{}", std::process::id()));
+    /// // `hello-1.0.0.nest` ships the `/usr/bin` directory, an executable script and its documentation
+    /// # write_npf(&dir.join("hello-1.0.0.nest"), "tests::sys-apps/hello#1.0.0", "", &[], &[
+    /// #     ("usr/bin/", 0o755, ""),
+    /// #     ("usr/bin/hello", 0o755, "#!/bin/sh\necho hello\n"),
+    /// #     ("usr/share/doc/hello/README", 0o644, "Prints hello"),
+    /// # ])?;
+    /// let explorer = NPFExplorer::open_at(dir.join("hello-1.0.0.nest"), &dir)?;
+    /// let root = dir.join("root");
+    /// let mut extracted = Vec::new();
+    /// let log = explorer.extract_to(&root, |path| extracted.push(path.to_path_buf()))?;
+    ///
+    /// let file = |path: &str, content: &str| -> std::io::Result<FileLogEntry> {
+    ///     Ok(FileLogEntry::new(PathBuf::from(path), FileType::File)
+    ///         .with_sha256(HashAlgorithm::Sha256.digest(content.as_bytes())?))
+    /// };
+    /// assert_eq!(
+    ///     log,
+    ///     Log::new(vec![
+    ///         FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    ///         file("/usr/bin/hello", "#!/bin/sh\necho hello\n")?,
+    ///         file("/usr/share/doc/hello/README", "Prints hello")?,
+    ///     ]),
+    /// );
+    /// assert_eq!(
+    ///     extracted,
+    ///     vec![
+    ///         PathBuf::from("/usr/bin"),
+    ///         PathBuf::from("/usr/bin/hello"),
+    ///         PathBuf::from("/usr/share/doc/hello/README"),
+    ///     ],
+    /// );
+    /// assert_eq!(fs::read_to_string(root.join("usr/share/doc/hello/README"))?, "Prints hello");
+    /// assert_eq!(fs::metadata(root.join("usr/bin/hello"))?.permissions().mode() & 0o777, 0o755);
+    /// # drop(explorer);
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_to<F: FnMut(&Path)>(
+        &self,
+        root: &Path,
+        mut observer: F,
+    ) -> Result<Log, NPFExplorationError> {
+        let (name, data) = match self.open_data_archive()? {
+            Some(data_archive) => data_archive,
+            None => return Ok(Log::new(Vec::new())),
+        };
+        let invalid_data = |_| NPFExplorationErrorKind::FileIOError(PathBuf::from(name));
+
+        let mut archive = Archive::new(data);
+        let mut files = Vec::new();
+        for entry in archive.entries().map_err(invalid_data)? {
+            let mut entry = entry.map_err(invalid_data)?;
+            let entry_path = entry.path().map_err(invalid_data)?.to_path_buf();
+            let entry_type = entry.header().entry_type();
+            let abs_path = Path::new("/").with_content(&entry_path);
+            let io_error = |_| NPFExplorationErrorKind::FileIOError(abs_path.clone());
+
+            let rel_path = match extraction_path(root, &entry_path).map_err(io_error)? {
+                Some(rel_path) => rel_path,
+                None => continue,
+            };
+            if let Some(parent) = rel_path.parent() {
+                fs::create_dir_all(parent).map_err(io_error)?;
+            }
+
+            // Hard links target another entry, which is looked for within the root by tar itself
+            if entry_type.is_hard_link() {
+                entry.unpack_in(root).map_err(io_error)?;
+            } else {
+                entry.unpack(&rel_path).map_err(io_error)?;
+            }
+
+            // Regular files are hashed once written, reading them back as a stream
+            let mut log_entry = FileLogEntry::new(abs_path.clone(), entry_type.into());
+            if entry_type.is_file() {
+                let sha256 = File::open(&rel_path)
+                    .and_then(|file| HashAlgorithm::Sha256.digest(file))
+                    .map_err(io_error)?;
+                log_entry = log_entry.with_sha256(sha256);
+            }
+            files.push(log_entry);
+            observer(&abs_path);
+        }
+        Ok(Log::new(files))
+    }
+
+    fn open_optional_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Option<NPFFile>, NPFExplorationError> {
+        self.open_file(path).map_or_else(
             |e| match e.kind() {
                 NPFExplorationErrorKind::FileNotFound(_) => Ok(None),
                 _ => Err(e),
@@ -147,21 +529,52 @@ impl NPFExplorer {
         )
     }
 
-    /// Loads the NPF's instructions.sh file for execution, if one exists
-    pub fn load_instructions(&self) -> Result<Option<InstructionsExecutor>, NPFExplorationError> {
-        let mut file = self.open_instructions()?;
+    fn load_script<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Option<InstructionsExecutor>, NPFExplorationError> {
+        let path = path.as_ref();
+        let mut file = self.open_optional_file(path)?;
 
         if let Some(file) = &mut file {
-            let executor =
-                InstructionsExecutor::from_script_file(file.file_mut()).map_err(|_| {
-                    NPFExplorationErrorKind::FileIOError(PathBuf::from("instructions.sh"))
-                })?;
+            let executor = InstructionsExecutor::from_script_file(file.file_mut())
+                .map_err(|_| NPFExplorationErrorKind::FileIOError(path.to_path_buf()))?;
 
             Ok(Some(executor))
         } else {
             Ok(None)
         }
     }
+
+    /// Retrieves a handle over the NPF's instructions.sh, if one exists
+    pub fn open_instructions(&self) -> Result<Option<NPFFile>, NPFExplorationError> {
+        self.open_optional_file("instructions.sh")
+    }
+
+    /// Loads the NPF's instructions.sh file for execution, if one exists
+    pub fn load_instructions(&self) -> Result<Option<InstructionsExecutor>, NPFExplorationError> {
+        self.load_script("instructions.sh")
+    }
+
+    /// Retrieves a handle over the NPF's pre-install.sh, if one exists
+    pub fn open_pre_install(&self) -> Result<Option<NPFFile>, NPFExplorationError> {
+        self.open_optional_file("pre-install.sh")
+    }
+
+    /// Loads the NPF's pre-install.sh script for execution, if one exists
+    pub fn load_pre_install(&self) -> Result<Option<InstructionsExecutor>, NPFExplorationError> {
+        self.load_script("pre-install.sh")
+    }
+
+    /// Retrieves a handle over the NPF's post-install.sh, if one exists
+    pub fn open_post_install(&self) -> Result<Option<NPFFile>, NPFExplorationError> {
+        self.open_optional_file("post-install.sh")
+    }
+
+    /// Loads the NPF's post-install.sh script for execution, if one exists
+    pub fn load_post_install(&self) -> Result<Option<InstructionsExecutor>, NPFExplorationError> {
+        self.load_script("post-install.sh")
+    }
 }
 
 impl Drop for NPFExplorer {
@@ -1,7 +1,9 @@
 //! Package requirement, used to find packages matching given criteria
 
+use std::fmt::{self, Display, Formatter};
+
 use failure::{Context, Error, ResultExt};
-use semver::VersionReq;
+use semver::{Version, VersionReq};
 use serde::de::Visitor;
 use serde_derive::{Deserialize, Serialize};
 
@@ -10,6 +12,156 @@ use super::identification::{PackageFullName, PackageID};
 use super::REGEX_PACKAGE_ID;
 use super::{CategoryName, PackageName, RepositoryName};
 
+/// A single predicate of a [`VersionRequirement`]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+enum VersionPredicate {
+    /// The version must match a SemVer requirement
+    Matches(VersionReq),
+
+    /// The version must not be the given one
+    Excludes(Version),
+}
+
+impl Display for VersionPredicate {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            VersionPredicate::Matches(req) => write!(f, "{}", req),
+            VersionPredicate::Excludes(version) => write!(f, "!= {}", version),
+        }
+    }
+}
+
+/// A version requirement, made of a comma-separated list of predicates that must all hold.
+///
+/// Each predicate is either a SemVer requirement (like `>=1.0` or `^1.2`), or the exclusion of a
+/// specific version (like `!=1.4.2`).
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct VersionRequirement {
+    predicates: Vec<VersionPredicate>,
+}
+
+impl VersionRequirement {
+    /// Creates a version requirement that matches any version
+    #[inline]
+    pub fn any() -> VersionRequirement {
+        VersionRequirement {
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Creates a version requirement that matches only the given version
+    #[inline]
+    pub fn exact(version: &Version) -> VersionRequirement {
+        VersionRequirement::from(VersionReq::exact(version))
+    }
+
+    /// Parses a string into a [`VersionRequirement`], or returns a [`VersionRequirementParseError`]
+    /// if the parsing failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate semver;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::package::VersionRequirement;
+    /// use semver::Version;
+    ///
+    /// let req = VersionRequirement::parse(">=1.0,<2.0,!=1.4.2")?;
+    /// assert!(req.matches(&Version::parse("1.4.1")?));
+    /// assert!(!req.matches(&Version::parse("1.4.2")?));
+    /// assert!(!req.matches(&Version::parse("2.0.0")?));
+    /// assert_eq!(VersionRequirement::parse(&req.to_string())?, req);
+    ///
+    /// assert_eq!(VersionRequirement::parse("*")?, VersionRequirement::any());
+    /// assert!(VersionRequirement::parse(">=1.0,,<2.0").is_err());
+    /// assert!(VersionRequirement::parse("!=1.x").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse(repr: &str) -> Result<VersionRequirement, VersionRequirementParseError> {
+        let predicates = repr
+            .split(',')
+            .map(str::trim)
+            .map(|predicate| {
+                if predicate.starts_with("!=") {
+                    Version::parse(predicate[2..].trim())
+                        .map(VersionPredicate::Excludes)
+                        .ok()
+                } else if !predicate.is_empty() {
+                    VersionReq::parse(predicate)
+                        .map(VersionPredicate::Matches)
+                        .ok()
+                } else {
+                    None
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| VersionRequirementParseError(repr.to_string()))?;
+
+        // Predicates matching any version are dropped, so that `*` parses back to `VersionRequirement::any()`
+        Ok(VersionRequirement {
+            predicates: predicates
+                .into_iter()
+                .filter(|predicate| predicate.to_string() != "*")
+                .collect(),
+        })
+    }
+
+    /// Tests if the given version holds for all the predicates of this requirement
+    #[inline]
+    pub fn matches(&self, version: &Version) -> bool {
+        self.predicates.iter().all(|predicate| match predicate {
+            VersionPredicate::Matches(req) => req.matches(version),
+            VersionPredicate::Excludes(excluded) => excluded != version,
+        })
+    }
+}
+
+impl From<VersionReq> for VersionRequirement {
+    #[inline]
+    fn from(req: VersionReq) -> Self {
+        VersionRequirement {
+            predicates: vec![VersionPredicate::Matches(req)],
+        }
+    }
+}
+
+impl Display for VersionRequirement {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.predicates.is_empty() {
+            write!(f, "*")
+        } else {
+            let predicates: Vec<_> = self.predicates.iter().map(ToString::to_string).collect();
+            write!(f, "{}", predicates.join(", "))
+        }
+    }
+}
+
+struct VersionRequirementVisitor;
+
+impl<'de> Visitor<'de> for VersionRequirementVisitor {
+    type Value = VersionRequirement;
+
+    #[inline]
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str("a version requirement")
+    }
+
+    #[inline]
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        VersionRequirement::parse(value).map_err(E::custom)
+    }
+}
+
+impl_serde_visitor!(VersionRequirement, VersionRequirementVisitor);
+
 /// A structure representing a soft package requirement: parts of a package name and a
 /// version requirement.
 ///
@@ -22,13 +174,16 @@ pub struct SoftPackageRequirement {
     repository: Option<RepositoryName>,
     category: Option<CategoryName>,
     name: PackageName,
-    version_requirement: VersionReq,
+    version_requirement: VersionRequirement,
 }
 
 impl SoftPackageRequirement {
     /// Creates a package requirement that matches the given [`PackageFullName`] and version requirement
     #[inline]
-    pub fn from(full_name: PackageFullName, version_req: VersionReq) -> SoftPackageRequirement {
+    pub fn from(
+        full_name: PackageFullName,
+        version_req: VersionRequirement,
+    ) -> SoftPackageRequirement {
         let (repository, category, name) = full_name.into_tuple();
         SoftPackageRequirement {
             repository: Some(repository),
@@ -45,7 +200,7 @@ impl SoftPackageRequirement {
             repository: Some(id.repository().clone()),
             category: Some(id.category().clone()),
             name: id.name().clone(),
-            version_requirement: VersionReq::exact(id.version()),
+            version_requirement: VersionRequirement::exact(id.version()),
         }
     }
 
@@ -67,6 +222,10 @@ impl SoftPackageRequirement {
     /// assert_eq!(req.version_requirement().to_string(), "^1.0");
     ///
     /// assert!(SoftPackageRequirement::parse("sys-bin/coreutils#not_a_version").is_err());
+    ///
+    /// let req = SoftPackageRequirement::parse("stable::sys-bin/coreutils#>=1.0,<2.0,!=1.4.2")?;
+    /// assert_eq!(req.version_requirement().to_string(), ">= 1.0, < 2.0, != 1.4.2");
+    /// assert_eq!(SoftPackageRequirement::parse(&req.to_string())?, req);
     /// # Ok(())
     /// # }
     /// ```
@@ -81,11 +240,11 @@ impl SoftPackageRequirement {
 
         let version_req = {
             if let Some(req) = matches.name("version") {
-                VersionReq::parse(req.as_str())
+                VersionRequirement::parse(req.as_str())
                     .context(repr.to_string())
                     .context(SoftPackageRequirementParseErrorKind::InvalidVersion)?
             } else {
-                VersionReq::any()
+                VersionRequirement::any()
             }
         };
 
@@ -118,7 +277,7 @@ impl SoftPackageRequirement {
     /// Changes the version requirement to match any version
     #[inline]
     pub fn any_version(mut self) -> Self {
-        self.version_requirement = VersionReq::any();
+        self.version_requirement = VersionRequirement::any();
         self
     }
 
@@ -142,7 +301,7 @@ impl SoftPackageRequirement {
 
     /// Returns the version requirement that the target package's version must match
     #[inline]
-    pub fn version_requirement(&self) -> &VersionReq {
+    pub fn version_requirement(&self) -> &VersionRequirement {
         &self.version_requirement
     }
 
@@ -256,13 +415,13 @@ pub struct PackageRequirement {
     repository: Option<RepositoryName>,
     category: CategoryName,
     name: PackageName,
-    version_requirement: VersionReq,
+    version_requirement: VersionRequirement,
 }
 
 impl PackageRequirement {
     /// Creates a package requirement that matches the given [`PackageFullName`] and version requirement
     #[inline]
-    pub fn from(full_name: PackageFullName, version_req: VersionReq) -> PackageRequirement {
+    pub fn from(full_name: PackageFullName, version_req: VersionRequirement) -> PackageRequirement {
         let (repository, category, name) = full_name.into_tuple();
 
         PackageRequirement {
@@ -280,7 +439,7 @@ impl PackageRequirement {
             repository: Some(id.repository().clone()),
             category: id.category().clone(),
             name: id.name().clone(),
-            version_requirement: VersionReq::exact(id.version()),
+            version_requirement: VersionRequirement::exact(id.version()),
         }
     }
 
@@ -302,6 +461,10 @@ impl PackageRequirement {
     /// assert_eq!(req.version_requirement().to_string(), "^1.0");
     ///
     /// assert!(PackageRequirement::parse("sys-bin/coreutils#not_a_version").is_err());
+    ///
+    /// let req = PackageRequirement::parse("sys-bin/coreutils#>=1.0,<2.0,!=1.4.2")?;
+    /// assert_eq!(req.version_requirement().to_string(), ">= 1.0, < 2.0, != 1.4.2");
+    /// assert_eq!(PackageRequirement::parse(&req.to_string())?, req);
     /// # Ok(())
     /// # }
     /// ```
@@ -318,11 +481,11 @@ impl PackageRequirement {
             (Some(category), Some(package)) => {
                 let version_req = {
                     if let Some(req) = matches.name("version") {
-                        VersionReq::parse(req.as_str())
+                        VersionRequirement::parse(req.as_str())
                             .context(repr.to_string())
                             .context(PackageRequirementParseErrorKind::InvalidVersion)?
                     } else {
-                        VersionReq::any()
+                        VersionRequirement::any()
                     }
                 };
 
@@ -354,7 +517,7 @@ impl PackageRequirement {
     /// Changes the version requirement to match any version
     #[inline]
     pub fn any_version(mut self) -> Self {
-        self.version_requirement = VersionReq::any();
+        self.version_requirement = VersionRequirement::any();
         self
     }
 
@@ -378,7 +541,7 @@ impl PackageRequirement {
 
     /// Returns the version requirement that the target package's version must match
     #[inline]
-    pub fn version_requirement(&self) -> &VersionReq {
+    pub fn version_requirement(&self) -> &VersionRequirement {
         &self.version_requirement
     }
 
@@ -522,12 +685,12 @@ impl From<HardPackageRequirement> for PackageRequirement {
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct HardPackageRequirement {
     full_name: PackageFullName,
-    version_requirement: VersionReq,
+    version_requirement: VersionRequirement,
 }
 
 impl HardPackageRequirement {
-    /// Creates a [`HardPackageRequirement`] from a [`PackageFullName`] and a [`VersionReq`]
-    pub fn from(full_name: PackageFullName, version_requirement: VersionReq) -> Self {
+    /// Creates a [`HardPackageRequirement`] from a [`PackageFullName`] and a [`VersionRequirement`]
+    pub fn from(full_name: PackageFullName, version_requirement: VersionRequirement) -> Self {
         HardPackageRequirement {
             full_name,
             version_requirement,
@@ -543,7 +706,7 @@ impl HardPackageRequirement {
     /// Changes the version requirement to match any version
     #[inline]
     pub fn any_version(mut self) -> Self {
-        self.version_requirement = VersionReq::any();
+        self.version_requirement = VersionRequirement::any();
         self
     }
 
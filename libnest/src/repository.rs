@@ -1,6 +1,26 @@
 //! Repository: wrapper around a name and a [`RepositoryConfig`]
 
-use crate::config::RepositoryConfig;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use failure::Error;
+use lazy_static::lazy_static;
+use semver::Version;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::cache::available::{last_pull_path, AvailablePackages};
+use crate::config::{Config, MirrorUrl, RepositoryConfig};
+use crate::lock_file::LockFileReadOwnership;
+use crate::package::PackageFullName;
+
+lazy_static! {
+    /// The mirrors of each repository ranked by latency, along with the mirrors and timeout they were ranked with
+    static ref MIRROR_RANKINGS: Mutex<HashMap<String, (Vec<MirrorUrl>, Duration, Vec<MirrorUrl>)>> =
+        Mutex::new(HashMap::new());
+}
 
 /// A repository
 ///
@@ -25,4 +45,260 @@ impl<'a, 'b> Repository<'a, 'b> {
     pub fn config(&self) -> &RepositoryConfig {
         self.config
     }
+
+    /// Returns the time elapsed since the last successful pull of this repository, or [`None`] if it was never pulled.
+    ///
+    /// Reading the date of the last pull requires an ownership over the lock file, so it isn't rewritten meanwhile.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate chrono;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use chrono::Utc;
+    /// use libnest::config::Config;
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-cache-age-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let repository = config.repositories()[0];
+    /// assert_eq!(repository.cache_age(&config, &lock_file_ownership), None);
+    ///
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// cache.save_last_pull(&repository, Utc::now() - chrono::Duration::hours(2))?;
+    ///
+    /// let age = repository
+    ///     .cache_age(&config, &lock_file_ownership)
+    ///     .expect("the repository was pulled");
+    /// assert!(age.as_secs() >= 2 * 3600 && age.as_secs() < 3 * 3600);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cache_age<L: LockFileReadOwnership>(&self, config: &Config, _: &L) -> Option<Duration> {
+        let content =
+            fs::read_to_string(last_pull_path(config.paths().available(), self.name)).ok()?;
+        let last_pull = DateTime::parse_from_rfc3339(content.trim()).ok()?;
+
+        // A pull in the future, like after the clock went backward, is considered as recent as can be
+        Some(
+            Utc::now()
+                .signed_duration_since(last_pull)
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns the mirrors of this repository, sorted from the fastest to respond to the slowest.
+    ///
+    /// The ranking is computed with [`RepositoryConfig::mirrors_by_latency`][1] the first time it's asked for,
+    /// and reused afterwards for the rest of the run, unless the mirrors of the repository or the timeout change.
+    ///
+    /// [1]: ../config/struct.RepositoryConfig.html#method.mirrors_by_latency
+    pub fn mirrors_by_latency(&self, timeout: Duration) -> Vec<MirrorUrl> {
+        let mirrors = self.config.mirrors();
+        if let Some((ranked_mirrors, ranked_timeout, ranking)) = MIRROR_RANKINGS
+            .lock()
+            .expect("the mirror rankings are poisoned")
+            .get(self.name)
+        {
+            if ranked_mirrors == mirrors && *ranked_timeout == timeout {
+                return ranking.clone();
+            }
+        }
+
+        // The lock isn't held while pinging, so other repositories can be ranked meanwhile
+        let ranking = self.config.mirrors_by_latency(timeout);
+        MIRROR_RANKINGS
+            .lock()
+            .expect("the mirror rankings are poisoned")
+            .insert(
+                self.name.to_string(),
+                (mirrors.clone(), timeout, ranking.clone()),
+            );
+        ranking
+    }
+}
+
+/// A package available in two caches, but with different versions in each of them
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct VersionDiff {
+    name: PackageFullName,
+    only_in_a: Vec<Version>,
+    only_in_b: Vec<Version>,
+}
+
+impl VersionDiff {
+    /// Returns the full name of the package
+    #[inline]
+    pub fn name(&self) -> &PackageFullName {
+        &self.name
+    }
+
+    /// Returns the versions of the package only available in the first cache, sorted
+    #[inline]
+    pub fn only_in_a(&self) -> &[Version] {
+        &self.only_in_a
+    }
+
+    /// Returns the versions of the package only available in the second cache, sorted
+    #[inline]
+    pub fn only_in_b(&self) -> &[Version] {
+        &self.only_in_b
+    }
+}
+
+/// The differences between two caches of available packages, as returned by [`diff_caches`]
+///
+/// [`diff_caches`]: fn.diff_caches.html
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Default, Debug)]
+pub struct CacheDiff {
+    only_in_a: Vec<PackageFullName>,
+    only_in_b: Vec<PackageFullName>,
+    version_differing: Vec<VersionDiff>,
+}
+
+impl CacheDiff {
+    /// Returns the packages only available in the first cache, sorted
+    #[inline]
+    pub fn only_in_a(&self) -> &[PackageFullName] {
+        &self.only_in_a
+    }
+
+    /// Returns the packages only available in the second cache, sorted
+    #[inline]
+    pub fn only_in_b(&self) -> &[PackageFullName] {
+        &self.only_in_b
+    }
+
+    /// Returns the packages available in both caches, but not with the same versions, sorted by name
+    #[inline]
+    pub fn version_differing(&self) -> &[VersionDiff] {
+        &self.version_differing
+    }
+
+    /// Returns whether both caches hold the same versions of the same packages
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.version_differing.is_empty()
+    }
+}
+
+/// Compares two caches of available packages, like those of a staging and a production system, and
+/// returns the packages and versions that differ between them.
+///
+/// Packages are compared by full name, so a package of a repository is never matched with a package
+/// of another repository.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate serde_json;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::Config;
+/// use libnest::package::{PackageFullName, PackageManifest};
+/// use libnest::repository::diff_caches;
+///
+/// let manifest = |name: &str, versions: &[&str]| -> Result<PackageManifest, failure::Error> {
+///     let versions: Vec<_> = versions
+///         .iter()
+///         .map(|version| {
+///             format!(r#""{}": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] }}"#, version)
+///         })
+///         .collect();
+///     Ok(serde_json::from_str(&format!(
+///         r#"{{
+///             "name": "{}",
+///             "category": "sys-apps",
+///             "repository": "tests",
+///             "metadata": {{
+///                 "description": "",
+///                 "tags": [],
+///                 "maintainer": "nest@raven-os.org",
+///                 "licenses": [],
+///                 "upstream_url": null
+///             }},
+///             "versions": {{ {} }}
+///         }}"#,
+///         name,
+///         versions.join(", "),
+///     ))?)
+/// };
+///
+/// let mut staging: Config = toml::from_str("")?;
+/// let mut production: Config = toml::from_str("")?;
+/// let root = std::env::temp_dir().join(format!("nest-doctest-diff-caches-{}", std::process::id()));
+/// *staging.paths_mut() = staging.paths().chroot(root.join("staging"));
+/// *production.paths_mut() = production.paths().chroot(root.join("production"));
+///
+/// let staging_lock = staging.acquire_lock_file_ownership(true)?;
+/// let production_lock = production.acquire_lock_file_ownership(true)?;
+/// let staging_cache = staging.available_packages_cache(&staging_lock);
+/// let production_cache = production.available_packages_cache(&production_lock);
+///
+/// staging_cache.update(&manifest("bash", &["5.0.0"])?)?;
+/// staging_cache.update(&manifest("gcc", &["8.3.0", "9.1.0"])?)?;
+/// staging_cache.update(&manifest("vim", &["8.1.0"])?)?;
+/// production_cache.update(&manifest("bash", &["5.0.0"])?)?;
+/// production_cache.update(&manifest("gcc", &["8.2.0", "8.3.0"])?)?;
+/// production_cache.update(&manifest("emacs", &["26.2.0"])?)?;
+///
+/// let diff = diff_caches(&staging_cache, &production_cache)?;
+/// assert_eq!(diff.only_in_a(), &[PackageFullName::parse("tests::sys-apps/vim")?][..]);
+/// assert_eq!(diff.only_in_b(), &[PackageFullName::parse("tests::sys-apps/emacs")?][..]);
+///
+/// let gcc = &diff.version_differing()[0];
+/// assert_eq!(diff.version_differing().len(), 1);
+/// assert_eq!(gcc.name(), &PackageFullName::parse("tests::sys-apps/gcc")?);
+/// assert_eq!(gcc.only_in_a(), &["9.1.0".parse()?][..]);
+/// assert_eq!(gcc.only_in_b(), &["8.2.0".parse()?][..]);
+///
+/// assert!(diff_caches(&staging_cache, &staging_cache)?.is_empty());
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff_caches(a: &AvailablePackages, b: &AvailablePackages) -> Result<CacheDiff, Error> {
+    let a_manifests = a.package_manifests()?;
+    let b_manifests = b.package_manifests()?;
+    let mut diff = CacheDiff::default();
+
+    for (name, a_manifest) in &a_manifests {
+        match b_manifests.get(name) {
+            Some(b_manifest) => {
+                let a_versions: BTreeSet<_> = a_manifest.versions().keys().collect();
+                let b_versions: BTreeSet<_> = b_manifest.versions().keys().collect();
+
+                if a_versions != b_versions {
+                    diff.version_differing.push(VersionDiff {
+                        name: name.clone(),
+                        only_in_a: a_versions
+                            .difference(&b_versions)
+                            .map(|version| (*version).clone())
+                            .collect(),
+                        only_in_b: b_versions
+                            .difference(&a_versions)
+                            .map(|version| (*version).clone())
+                            .collect(),
+                    });
+                }
+            }
+            None => diff.only_in_a.push(name.clone()),
+        }
+    }
+    diff.only_in_b = b_manifests
+        .keys()
+        .filter(|name| !a_manifests.contains_key(name))
+        .cloned()
+        .collect();
+    Ok(diff)
 }
@@ -0,0 +1,57 @@
+use failure::Error;
+
+use crate::config::Config;
+use crate::lock_file::LockFileOwnership;
+use crate::package::PackageID;
+
+use super::download::PackageDownload;
+use super::observer::observe;
+use super::upgrade::UpgradeTransaction;
+use super::{Transaction, TransactionObserver};
+
+/// Structure representing a downgrade transaction, replacing a package by an older version of it
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DowngradeTransaction {
+    old: PackageID,
+    new: PackageID,
+}
+
+impl DowngradeTransaction {
+    /// Creates a [`DowngradeTransaction`] from an old [`PackageID`] and a new [`PackageID`]
+    pub fn from(old: PackageID, new: PackageID) -> Self {
+        DowngradeTransaction { old, new }
+    }
+
+    /// Retrieves a reference over the old target package for this transaction
+    pub fn old_target(&self) -> &PackageID {
+        &self.old
+    }
+
+    /// Retrieves a reference over the new target package for this transaction
+    pub fn new_target(&self) -> &PackageID {
+        &self.new
+    }
+
+    /// Get the download associated to this transaction
+    pub fn associated_download(&self) -> PackageDownload {
+        PackageDownload::from(self.new_target().clone())
+    }
+
+    /// Perform the downgrade transaction, notifying the given observer of its progress
+    ///
+    /// The old package is replaced the same way it is when upgrading, only the version of the new one differs.
+    pub fn perform(
+        &self,
+        config: &Config,
+        lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
+    ) -> Result<(), Error> {
+        observe(observer, Transaction::Downgrade(self.clone()), |observer| {
+            UpgradeTransaction::from(self.old.clone(), self.new.clone()).replace(
+                config,
+                lock_ownership,
+                observer,
+            )
+        })
+    }
+}
@@ -1,10 +1,129 @@
-use std::fs::{self, File};
-use std::io::{Seek, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
 
-use failure::{Error, ResultExt};
+use failure::{format_err, Error, ResultExt};
 
-use crate::config::Config;
-use crate::package::PackageID;
+use crate::cache::available::AvailablePackages;
+use crate::config::{Config, LogLevel, MirrorUrl};
+use crate::package::{PackageID, SoftPackageRequirement};
+
+use super::TransactionObserver;
+
+/// Returns the string representation of a mirror's URL, without the password it may contain
+fn redact_mirror(mirror: &MirrorUrl) -> String {
+    let mut url = (**mirror).clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    url.to_string()
+}
+
+/// Runs `perform` with each of the given mirrors, in order, until it succeeds.
+///
+/// Each mirror that fails is reported to the observer as an [`Info`][1] event before the next one is
+/// tried. Returns the mirror that succeeded along with the value returned by `perform`, or an error listing
+/// all the mirrors if none of them worked.
+///
+/// [1]: ../config/enum.LogLevel.html#variant.Info
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # fn main() -> Result<(), failure::Error> {
+/// use failure::format_err;
+/// use libnest::config::{parse_mirror_list, LogLevel};
+/// use libnest::transaction::{try_mirrors, TransactionObserver};
+///
+/// #[derive(Default)]
+/// struct Recorder(Vec<(LogLevel, String)>);
+///
+/// impl TransactionObserver for Recorder {
+///     fn on_log(&mut self, level: LogLevel, message: &str) {
+///         self.0.push((level, message.to_string()));
+///     }
+/// }
+///
+/// let mirrors = parse_mirror_list("https://down.example.org\nhttps://up.example.org")?;
+/// let mut recorder = Recorder::default();
+///
+/// let (mirror, answer) = try_mirrors(&mirrors, &mut recorder, |mirror| {
+///     match mirror.host_str() {
+///         Some("up.example.org") => Ok(42),
+///         _ => Err(format_err!("connection refused")),
+///     }
+/// })?;
+/// assert_eq!(mirror.as_str(), "https://up.example.org/");
+/// assert_eq!(answer, 42);
+/// assert_eq!(
+///     recorder.0,
+///     vec![(
+///         LogLevel::Info,
+///         "mirror https://down.example.org/ failed: connection refused, trying the next one".to_string(),
+///     )],
+/// );
+///
+/// let error = try_mirrors(&mirrors[..1], &mut recorder, |_| Err::<(), _>(format_err!("timeout"))).unwrap_err();
+/// assert_eq!(error.to_string(), "no working mirror found (tried https://down.example.org/)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn try_mirrors<'m, T, F>(
+    mirrors: &'m [MirrorUrl],
+    observer: &mut dyn TransactionObserver,
+    mut perform: F,
+) -> Result<(&'m MirrorUrl, T), Error>
+where
+    F: FnMut(&MirrorUrl) -> Result<T, Error>,
+{
+    for (index, mirror) in mirrors.iter().enumerate() {
+        match perform(mirror) {
+            Ok(value) => return Ok((mirror, value)),
+            Err(e) => {
+                let next = if index + 1 < mirrors.len() {
+                    ", trying the next one"
+                } else {
+                    ""
+                };
+                observer.on_log(
+                    LogLevel::Info,
+                    &format!("mirror {} failed: {}{}", redact_mirror(mirror), e, next),
+                );
+            }
+        }
+    }
+    Err(format_err!(
+        "no working mirror found (tried {})",
+        mirrors
+            .iter()
+            .map(redact_mirror)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// The total size of a set of downloads, as computed by [`PackageDownload::total_size`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct DownloadSize {
+    known: u64,
+    unknown: Vec<PackageID>,
+}
+
+impl DownloadSize {
+    /// Returns the sum of the sizes of the downloads whose size is known, in bytes
+    #[inline]
+    pub fn known(&self) -> u64 {
+        self.known
+    }
+
+    /// Returns the packages whose download size is unknown
+    #[inline]
+    pub fn unknown(&self) -> &[PackageID] {
+        &self.unknown
+    }
+}
 
 /// Structure representing a package download
 #[derive(Clone, Hash, Debug)]
@@ -21,9 +140,104 @@ impl PackageDownload {
         &self.0
     }
 
-    /// Creates the download file and returns a handle to it
-    pub fn create_download_file(&self, config: &Config) -> Result<(impl Write + Seek), Error> {
-        // Create target folder and destination file
+    /// Returns the size of the package to download in bytes, as advertised by its manifest in the given cache.
+    ///
+    /// Returns `None` if the package can't be found or if its manifest doesn't carry its size.
+    pub fn size(&self, cache: &AvailablePackages) -> Result<Option<u64>, Error> {
+        let requirement = SoftPackageRequirement::from_id(self.target());
+
+        Ok(cache
+            .query(&requirement)
+            .perform()?
+            .into_iter()
+            .find(|result| result.id() == *self.target())
+            .and_then(|result| result.manifest().download_size()))
+    }
+
+    /// Sums the sizes of the given downloads, keeping track of the packages whose size is unknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest};
+    /// use libnest::transaction::PackageDownload;
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-download-size-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    ///
+    /// for (name, size) in &[("app", r#", "download_size": 1024"#), ("lib", r#", "download_size": 512"#), ("doc", "")] {
+    ///     let manifest: PackageManifest = serde_json::from_str(&format!(
+    ///         r#"{{
+    ///             "name": "{}",
+    ///             "category": "sys-apps",
+    ///             "repository": "tests",
+    ///             "metadata": {{
+    ///                 "description": "",
+    ///                 "tags": [],
+    ///                 "maintainer": "nest@raven-os.org",
+    ///                 "licenses": [],
+    ///                 "upstream_url": null
+    ///             }},
+    ///             "versions": {{
+    ///                 "1.0.0": {{ "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [] {} }}
+    ///             }}
+    ///         }}"#,
+    ///         name, size,
+    ///     ))?;
+    ///     cache.update(&manifest)?;
+    /// }
+    ///
+    /// let download = |id: &str| -> Result<PackageDownload, failure::Error> {
+    ///     Ok(PackageDownload::from(PackageID::parse(id)?))
+    /// };
+    /// let downloads = vec![
+    ///     download("tests::sys-apps/app#1.0.0")?,
+    ///     download("tests::sys-apps/lib#1.0.0")?,
+    ///     download("tests::sys-apps/doc#1.0.0")?,
+    /// ];
+    ///
+    /// assert_eq!(downloads[0].size(&cache)?, Some(1024));
+    /// assert_eq!(downloads[2].size(&cache)?, None);
+    ///
+    /// let total = PackageDownload::total_size(&downloads, &cache)?;
+    /// assert_eq!(total.known(), 1536);
+    /// assert_eq!(total.unknown(), &[PackageID::parse("tests::sys-apps/doc#1.0.0")?][..]);
+    ///
+    /// let total = PackageDownload::total_size(&downloads[..2], &cache)?;
+    /// assert_eq!(total.known(), 1536);
+    /// assert!(total.unknown().is_empty());
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn total_size<'a, I>(downloads: I, cache: &AvailablePackages) -> Result<DownloadSize, Error>
+    where
+        I: IntoIterator<Item = &'a PackageDownload>,
+    {
+        let mut total = DownloadSize::default();
+
+        for download in downloads {
+            match download.size(cache)? {
+                Some(size) => total.known += size,
+                None => total.unknown.push(download.target().clone()),
+            }
+        }
+        Ok(total)
+    }
+
+    /// Creates the folder holding the download file and returns the path of the download file
+    fn download_file_path(&self, config: &Config) -> Result<PathBuf, Error> {
         let npf_path = config
             .paths()
             .downloaded()
@@ -31,15 +245,38 @@ impl PackageDownload {
             .join(self.target().category().as_str())
             .join(self.target().name().as_str());
         fs::create_dir_all(&npf_path).with_context(|_| npf_path.display().to_string())?;
-        let tarball_path = npf_path.join(format!(
+        Ok(npf_path.join(format!(
             "{}-{}.nest",
             self.target().name(),
             self.target().version()
-        ));
+        )))
+    }
+
+    /// Creates the download file and returns a handle to it
+    pub fn create_download_file(&self, config: &Config) -> Result<(impl Write + Seek), Error> {
+        let tarball_path = self.download_file_path(config)?;
 
         // Open the destination file and return it as the writer handle
         let tarball_file =
             File::create(&tarball_path).with_context(|_| tarball_path.display().to_string())?;
         Ok(tarball_file)
     }
+
+    /// Opens the download file without truncating it, and returns a handle to it positioned at its end
+    /// along with the number of bytes that were already downloaded.
+    ///
+    /// This is used to resume a download that was previously interrupted.
+    pub fn resume_download_file(&self, config: &Config) -> Result<(impl Write + Seek, u64), Error> {
+        let tarball_path = self.download_file_path(config)?;
+
+        let mut tarball_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(&tarball_path)
+            .with_context(|_| tarball_path.display().to_string())?;
+        let offset = tarball_file
+            .seek(SeekFrom::End(0))
+            .with_context(|_| tarball_path.display().to_string())?;
+        Ok((tarball_file, offset))
+    }
 }
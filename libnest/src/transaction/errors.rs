@@ -17,6 +17,20 @@ pub enum InstallErrorKind {
     #[fail(display = "{:?}: file already exists", _0)]
     FileAlreadyExists(std::path::PathBuf),
 
+    /// The package could not be installed because one of its files is already owned by another package
+    #[fail(display = "{:?}: file already owned by {}", path, owner)]
+    FileConflict {
+        /// The path of the conflicting file
+        path: std::path::PathBuf,
+
+        /// The package owning the conflicting file
+        owner: crate::package::PackageID,
+    },
+
+    /// The package could not be installed because it requires a more recent version of Nest
+    #[fail(display = "the package requires Nest {} or newer", _0)]
+    IncompatibleNestVersion(semver::Version),
+
     /// The package could not be installed because it is already installed
     #[fail(display = "package already installed")]
     PackageAlreadyInstalled,
@@ -25,7 +39,16 @@ pub enum InstallErrorKind {
     #[fail(display = "invalid package file")]
     InvalidPackageFile,
 
-    /// The package could not be installed because the contained data.tar.gz was invalid
+    /// The package could not be installed because the downloaded NPF doesn't match the hash of its manifest
+    #[fail(display = "the package file doesn't match the hash of its manifest")]
+    PackageHashMismatch,
+
+    /// The package could not be installed because its manifest could not be retrieved from the cache of
+    /// available packages to check the downloaded NPF against it
+    #[fail(display = "unable to retrieve the manifest of the package")]
+    PackageManifestUnavailable,
+
+    /// The package could not be installed because the contained data archive was invalid
     #[fail(display = "invalid package data")]
     InvalidPackageData,
 
@@ -33,6 +56,14 @@ pub enum InstallErrorKind {
     #[fail(display = "unable to extract")]
     ExtractError(#[cause] std::io::Error),
 
+    /// The package could not be installed because its data could not be completely extracted.
+    /// The files extracted before the failure were removed, and those taken over from other packages restored.
+    ///
+    /// The error that made the extraction fail is the cause of this one. The other transactions extracting a package
+    /// report it as a [`TransactionErrorKind::RolledBack`].
+    #[fail(display = "unable to extract, the installation was rolled back")]
+    RolledBack,
+
     /// The package could not be installed because its associated log files could not be created
     #[fail(display = "unable to create the log")]
     LogCreationError(#[cause] std::io::Error),
@@ -41,13 +72,35 @@ pub enum InstallErrorKind {
     #[fail(display = "pre-install instructions reported an error: {}", _0)]
     PreInstallInstructionsFailure(#[cause] InstructionsExecutionError),
 
-    /// The package could not be installed its post-install instructions returned an error
+    /// The package could not be installed its post-install instructions returned an error.
+    /// The files it extracted were removed.
     #[fail(display = "post-install instructions reported an error: {}", _0)]
     PostInstallInstructionsFailure(#[cause] InstructionsExecutionError),
+
+    /// The package could not be installed because its pre-install.sh script returned an error
+    #[fail(display = "pre-install script reported an error: {}", _0)]
+    PreInstallScriptFailure(#[cause] InstructionsExecutionError),
+
+    /// The package could not be installed because its post-install.sh script returned an error.
+    /// The files it extracted were removed.
+    #[fail(display = "post-install script reported an error: {}", _0)]
+    PostInstallScriptFailure(#[cause] InstructionsExecutionError),
 }
 
 use_as_error!(InstallError, InstallErrorKind);
 
+impl InstallError {
+    /// Converts this error into a [`failure::Error`] for the transactions that aren't installations,
+    /// which report an extraction that was rolled back as a [`TransactionErrorKind::RolledBack`]
+    pub(crate) fn into_error(self) -> failure::Error {
+        if let InstallErrorKind::RolledBack = self.kind() {
+            TransactionError::from(self.inner.map(|_| TransactionErrorKind::RolledBack)).into()
+        } else {
+            self.into()
+        }
+    }
+}
+
 /// Error type for errors related to package removal
 #[derive(Debug)]
 pub struct RemoveError {
@@ -69,6 +122,14 @@ pub enum RemoveErrorKind {
     #[fail(display = "cannot remove log file")]
     LogFileRemoveError,
 
+    /// The package was removed, but the files it took over from other packages could not be given back to them
+    #[fail(display = "cannot give back the files taken over from other packages")]
+    DisplacedFilesRestoreError(#[cause] std::io::Error),
+
+    /// The package could not be removed because it isn't installed
+    #[fail(display = "package not installed")]
+    PackageNotInstalled,
+
     /// The package could not be removed because the previously downloaded NPF is corrupted
     #[fail(display = "corrupted cached package file")]
     InvalidCachedPackageFile,
@@ -84,6 +145,65 @@ pub enum RemoveErrorKind {
 
 use_as_error!(RemoveError, RemoveErrorKind);
 
+/// Error type for errors related to pulling a repository
+#[derive(Debug)]
+pub struct PullError {
+    inner: Context<PullErrorKind>,
+}
+
+/// Error kind describing a kind of error related to pulling a repository
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Fail)]
+pub enum PullErrorKind {
+    /// The signature of the pulled manifests doesn't match the public key of the repository,
+    /// or it couldn't be checked
+    #[fail(display = "signature verification failed")]
+    SignatureVerificationFailed,
+}
+
+use_as_error!(PullError, PullErrorKind);
+
+/// Error type for errors related to a list of transactions as a whole
+#[derive(Debug)]
+pub struct TransactionError {
+    inner: Context<TransactionErrorKind>,
+}
+
+/// Error kind describing a kind of error related to a list of transactions as a whole
+#[derive(Clone, Eq, PartialEq, Debug, Fail)]
+pub enum TransactionErrorKind {
+    /// The transactions can't be processed because the filesystem holding the given path doesn't have
+    /// enough space available
+    #[fail(
+        display = "{:?}: not enough space available ({} bytes needed, {} bytes available)",
+        path, needed, available
+    )]
+    InsufficientSpace {
+        /// A path on the filesystem lacking space
+        path: std::path::PathBuf,
+
+        /// The space needed on this filesystem, in bytes
+        needed: u64,
+
+        /// The space available on this filesystem, in bytes
+        available: u64,
+    },
+
+    /// A transaction failed midway, and the changes it made to the system were undone.
+    ///
+    /// The error that made it fail is the cause of this one.
+    #[fail(display = "the transaction failed and was rolled back")]
+    RolledBack,
+
+    /// The journal left by an interrupted run couldn't be parsed, and was moved to the given path
+    #[fail(display = "unparsable journal, moved to {:?}", set_aside)]
+    UnparsableJournal {
+        /// The path the unparsable journal was moved to
+        set_aside: std::path::PathBuf,
+    },
+}
+
+use_as_error!(TransactionError, TransactionErrorKind);
+
 /// Error type for errors related to the execution of the instructions.sh script
 #[derive(Debug)]
 pub struct InstructionsExecutionError {
@@ -1,56 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs;
-use std::io::{Seek, SeekFrom};
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use flate2::read::GzDecoder;
+use failure::Fail;
 use tar::Archive;
 
 use crate::cache::installed::log::{FileLogEntry, Log};
-use crate::chroot::Chroot;
-use crate::config::Config;
+use crate::cache::installed::normalize;
+use crate::chroot::{extraction_path, Chroot};
+use crate::config::{Config, LogLevel};
 use crate::lock_file::LockFileOwnership;
-use crate::package::{Kind, NPFExplorer, PackageID};
+use crate::package::{HashAlgorithm, Kind, NPFExplorer, PackageID};
 
-use super::{InstallError, InstallErrorKind::*};
+use super::journal::keep_displaced_files;
+use super::log::TransactionLog;
+use super::{InstallError, InstallErrorKind::*, TransactionObserver};
 
-/// Extract the package from a given [`NPFExplorer`] as a given [`PackageID`]
+/// Returns the given path and those of its ancestors within `root` that don't exist yet, from the outermost to the innermost
+fn missing_paths(root: &Path, path: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<_> = path
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(root) && *ancestor != root)
+        .filter(|ancestor| fs::symlink_metadata(ancestor).is_err())
+        .map(Path::to_path_buf)
+        .collect();
+    missing.reverse();
+    missing
+}
+
+/// Removes the given paths, starting with the last one so that directories are emptied before being removed
+fn remove_paths(paths: &[PathBuf]) {
+    for path in paths.iter().rev() {
+        let _ = match fs::symlink_metadata(path) {
+            Ok(ref metadata) if metadata.is_dir() => fs::remove_dir(path),
+            Ok(_) => fs::remove_file(path),
+            Err(_) => Ok(()),
+        };
+    }
+}
+
+/// Returns the path next to the given one where the new version of a preserved configuration file is written
+fn nest_new_path(path: &Path) -> PathBuf {
+    let mut nest_new = OsString::from(path.as_os_str());
+    nest_new.push(".nest-new");
+    PathBuf::from(nest_new)
+}
+
+/// Returns the path next to the given one where a file taken over from another package is kept until the
+/// extraction is over
+fn nest_backup_path(path: &Path) -> PathBuf {
+    let mut nest_backup = OsString::from(path.as_os_str());
+    nest_backup.push(".nest-backup");
+    PathBuf::from(nest_backup)
+}
+
+/// Moves the files of the given log that aren't directories next to their location within `root`, and
+/// returns the pairs of original and backup paths.
+///
+/// If one of them can't be moved, those already moved are restored.
+pub(super) fn back_up_files(root: &Path, log: &Log) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut backups = Vec::new();
+    for entry in log.files() {
+        let rel_path = root.with_content(entry.path());
+        if entry.file_type().is_dir() || fs::symlink_metadata(&rel_path).is_err() {
+            continue;
+        }
+
+        let backup = nest_backup_path(&rel_path);
+        if let Err(e) = fs::rename(&rel_path, &backup) {
+            restore_backups(&backups);
+            return Err(e);
+        }
+        backups.push((rel_path, backup));
+    }
+    Ok(backups)
+}
+
+/// Moves the given backups back to their original location
+pub(super) fn restore_backups(backups: &[(PathBuf, PathBuf)]) {
+    for (original, backup) in backups.iter().rev() {
+        let _ = fs::rename(backup, original);
+    }
+}
+
+/// Removes the given backups, once they aren't needed anymore
+pub(super) fn remove_backups(backups: &[(PathBuf, PathBuf)]) {
+    for (_, backup) in backups {
+        let _ = fs::remove_file(backup);
+    }
+}
+
+/// Extract the package from a given [`NPFExplorer`] as a given [`PackageID`], notifying the given
+/// observer of each extracted file.
+///
+/// If `force_overwrite` is set, files owned by other packages are overwritten instead of aborting the
+/// extraction, and their ownership is transferred to the extracted package. They are backed up until the
+/// extraction is over, so that they are restored if it fails, and kept aside afterwards until the journal is
+/// cleared, so that they are given back if the package is removed when rolling back.
+///
+/// The files whose normalized path is in `preserved` are left untouched: the content shipped by the package
+/// is written next to them, with a `.nest-new` extension, and the observer is warned about it.
+///
+/// If the post-install script or instructions fail, the extracted files are removed, as they would be if the
+/// extraction failed midway.
 pub(crate) fn extract_package(
     config: &Config,
     lock_ownership: &LockFileOwnership,
     npf_explorer: NPFExplorer,
     target_id: &PackageID,
+    force_overwrite: bool,
+    preserved: &HashSet<PathBuf>,
+    observer: &mut dyn TransactionObserver,
 ) -> Result<(), InstallError> {
+    // Packages may rely on features that this version of Nest doesn't know about
+    let manifest = npf_explorer.manifest();
+    if let Some(min_nest_version) = manifest.min_nest_version() {
+        if !manifest.is_supported_by_running_nest() {
+            return Err(IncompatibleNestVersion(min_nest_version.clone()).into());
+        }
+    }
+
     let instructions_handle = npf_explorer
         .load_instructions()
         .map_err(|_| InvalidPackageFile)?;
 
+    // The pre-install.sh and post-install.sh scripts are only executed if the configuration allows it
+    let (pre_install_handle, post_install_handle) = if config.install().run_scripts() {
+        (
+            npf_explorer
+                .load_pre_install()
+                .map_err(|_| InvalidPackageFile)?,
+            npf_explorer
+                .load_post_install()
+                .map_err(|_| InvalidPackageFile)?,
+        )
+    } else {
+        (None, None)
+    };
+
     if let Some(executor) = &instructions_handle {
         executor
             .execute_before_install(config.paths().root())
             .map_err(PreInstallInstructionsFailure)?;
     }
 
-    if npf_explorer.manifest().kind() == Kind::Effective {
-        let tarball_handle = npf_explorer
-            .open_data()
+    if let Some(executor) = &pre_install_handle {
+        executor
+            .execute(config.paths().root())
+            .map_err(PreInstallScriptFailure)?;
+    }
+
+    let installed_cache = config.installed_packages_cache(lock_ownership);
+    let slot = npf_explorer.manifest().slot();
+    let extracted = if npf_explorer.manifest().kind() == Kind::Effective {
+        let tarball = npf_explorer
+            .open_data_decoder()
             .map_err(|_| InvalidPackageFile)?
             .unwrap();
 
-        let mut tarball = tarball_handle.file();
-        let mut archive = Archive::new(GzDecoder::new(tarball));
+        let mut archive = Archive::new(tarball);
         let mut files = Vec::new();
+        let mut displaced = Vec::new();
+        let mut total = 0;
+
+        // Files already owned by other packages can't be overwritten, unless forced to
+        let owners = installed_cache
+            .file_owners(target_id)
+            .map_err(ExtractError)?;
 
         // List all the files in the archive and check whether they already exist
         for entry in archive.entries().map_err(|_| InvalidPackageData)? {
-            let entry = entry.map_err(|_| InvalidPackageData)?;
-            let entry_path = entry.path().map_err(|_| InvalidPackageData)?;
+            let mut entry = entry.map_err(|_| InvalidPackageData)?;
+            let entry_path = entry.path().map_err(|_| InvalidPackageData)?.to_path_buf();
             let entry_type = entry.header().entry_type();
 
             let abs_path = Path::new("/").with_content(&entry_path);
-            let rel_path = config.paths().root().with_content(&entry_path);
+            let rel_path = extraction_path(config.paths().root(), &entry_path)
+                .map_err(ExtractError)?
+                .unwrap_or_else(|| config.paths().root().to_path_buf());
+
+            // Preserved files are kept as they are, so they can't conflict with anything.
+            // Only regular files can be preserved.
+            let is_preserved = entry_type.is_file() && preserved.contains(&normalize(&abs_path));
+
+            let mut displaced_owner = None;
+            if !entry_type.is_dir() && !is_preserved {
+                if let Some(owner) = owners.get(&normalize(&abs_path)) {
+                    if !force_overwrite {
+                        return Err(FileConflict {
+                            path: abs_path,
+                            owner: owner.clone(),
+                        }
+                        .into());
+                    }
+                    displaced_owner = Some(owner.clone());
+                }
+            }
 
-            // Check whether the target file exists and retrieve its metadata (without following any symlink)
-            if let Ok(metadata) = fs::symlink_metadata(&rel_path) {
+            // Check whether the target file exists and retrieve its metadata (without following any symlink).
+            // A file taken over from another package is expected to exist.
+            let metadata = match displaced_owner {
+                Some(_) => None,
+                None if is_preserved => None,
+                None => fs::symlink_metadata(&rel_path).ok(),
+            };
+            if let Some(metadata) = metadata {
                 match (entry_type.is_dir(), metadata.file_type().is_dir()) {
                     // Both files are directories, there is no conflict
                     (true, true) => (),
@@ -68,31 +224,171 @@ pub(crate) fn extract_package(
                     _ => return Err(FileAlreadyExists(abs_path).into()),
                 }
             }
-            files.push(FileLogEntry::new(abs_path.to_path_buf(), entry_type.into()));
+
+            // Record the hash of regular files, to be able to detect later modifications
+            let mut log_entry = FileLogEntry::new(abs_path.to_path_buf(), entry_type.into());
+            if entry_type.is_file() {
+                let sha256 = HashAlgorithm::Sha256
+                    .digest(&mut entry)
+                    .map_err(|_| InvalidPackageData)?;
+                log_entry = log_entry.with_sha256(sha256);
+            }
+
+            // The new version of a preserved file belongs to the package too, so it's removed along with it
+            if is_preserved {
+                let mut nest_new_entry =
+                    FileLogEntry::new(nest_new_path(&abs_path), entry_type.into());
+                if let Some(sha256) = log_entry.sha256() {
+                    nest_new_entry = nest_new_entry.with_sha256(sha256.to_string());
+                }
+                files.push(nest_new_entry);
+            }
+
+            if let Some(owner) = displaced_owner {
+                log_entry = log_entry.with_displaced_owner(owner.clone());
+                displaced.push((owner, abs_path));
+            }
+            files.push(log_entry);
+            total += 1;
         }
 
         // Log each file to install to the log file
-        config
-            .installed_packages_cache(lock_ownership)
-            .save_package_log(target_id, &Log::new(files))
+        installed_cache
+            .save_package_log(target_id, slot, &Log::new(files))
             .map_err(LogCreationError)?;
 
-        // Extract the tarball in the root folder
+        // The archive is read again from its start
+        let tarball = npf_explorer
+            .open_data_decoder()
+            .map_err(|_| InvalidPackageFile)?
+            .unwrap();
+
+        // Extract the tarball in the root folder, keeping track of the files that didn't exist before
+        // so they can be removed if the extraction fails midway, and of the files taken over from other
+        // packages so they can be restored
+        let root = config.paths().root();
+        let displaced_paths: HashSet<_> =
+            displaced.iter().map(|(_, path)| normalize(path)).collect();
+        let mut created_paths = Vec::new();
+        let mut backups = Vec::new();
+        let mut backup_paths = HashMap::new();
+        let mut extracted_paths = Vec::new();
         let res: Result<_, std::io::Error> = try {
-            tarball.seek(SeekFrom::Start(0))?;
-            let mut archive = Archive::new(GzDecoder::new(tarball));
-            for entry in archive.entries()? {
-                entry?.unpack_in(config.paths().root())?;
+            let mut archive = Archive::new(tarball);
+            let entries = archive.entries()?;
+            for (i, entry) in entries.enumerate() {
+                let mut entry = entry?;
+                let entry_path = entry.path()?;
+                let entry_path = entry_path.to_path_buf();
+                if let Some(rel_path) = extraction_path(root, &entry_path)? {
+                    let mut abs_path = Path::new("/").with_content(&entry_path);
+                    let is_preserved = entry.header().entry_type().is_file()
+                        && preserved.contains(&normalize(&abs_path));
+
+                    // The new version of a preserved file is written next to it
+                    let rel_path = if is_preserved {
+                        observer.on_log(
+                            LogLevel::Warn,
+                            &format!(
+                                "{} was modified and is kept as is, its new version is written to {}",
+                                abs_path.display(),
+                                nest_new_path(&abs_path).display(),
+                            ),
+                        );
+                        abs_path = nest_new_path(&abs_path);
+                        nest_new_path(&rel_path)
+                    } else {
+                        rel_path
+                    };
+
+                    created_paths.extend(missing_paths(root, &rel_path));
+                    if let Some(parent) = rel_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    if displaced_paths.contains(&normalize(&abs_path))
+                        && fs::symlink_metadata(&rel_path).is_ok()
+                    {
+                        let backup = nest_backup_path(&rel_path);
+                        fs::rename(&rel_path, &backup)?;
+                        backup_paths.insert(normalize(&abs_path), backup.clone());
+                        backups.push((rel_path.clone(), backup));
+                    }
+
+                    // Hard links target another entry, which is looked for within the root by tar itself
+                    if entry.header().entry_type().is_hard_link() {
+                        entry.unpack_in(root)?;
+                    } else {
+                        entry.unpack(&rel_path)?;
+                    }
+                    extracted_paths.push(abs_path);
+                }
+                observer.on_progress(i + 1, total);
             }
         };
-        res.map_err(ExtractError)?;
-    }
 
-    if let Some(executor) = &instructions_handle {
-        executor
+        if let Err(e) = res {
+            remove_paths(&created_paths);
+            restore_backups(&backups);
+            let _ = installed_cache.remove_package_log(target_id, slot);
+            return Err(e.context(RolledBack).into());
+        }
+
+        Some((
+            created_paths,
+            backups,
+            backup_paths,
+            displaced,
+            extracted_paths,
+        ))
+    } else {
+        None
+    };
+
+    let res = match &post_install_handle {
+        Some(executor) => executor
+            .execute(config.paths().root())
+            .map(|_| ())
+            .map_err(PostInstallScriptFailure),
+        None => Ok(()),
+    };
+    let res = res.and_then(|_| match &instructions_handle {
+        Some(executor) => executor
             .execute_after_install(config.paths().root())
-            .map_err(PostInstallInstructionsFailure)?;
+            .map(|_| ())
+            .map_err(PostInstallInstructionsFailure),
+        None => Ok(()),
+    });
+
+    if let Some((created_paths, backups, backup_paths, displaced, extracted_paths)) = extracted {
+        // A package whose post-install steps failed is rolled back, as if it couldn't be extracted
+        if res.is_err() {
+            remove_paths(&created_paths);
+            restore_backups(&backups);
+            let _ = installed_cache.remove_package_log(target_id, slot);
+        } else {
+            // The files taken over from other packages aren't theirs anymore, but they're kept aside until the
+            // journal is cleared so that they can be given back if the installation is rolled back
+            let mut released = Vec::new();
+            for (owner, path) in displaced {
+                let entry = installed_cache
+                    .release_file(&owner, &path)
+                    .map_err(LogCreationError)?;
+                if let (Some(entry), Some(backup)) = (entry, backup_paths.get(&normalize(&path))) {
+                    released.push((owner, entry, backup.clone()));
+                }
+            }
+            keep_displaced_files(config, target_id, &released).map_err(LogCreationError)?;
+            remove_backups(&backups);
+
+            // The extracted files are only logged once the installation can't be rolled back anymore
+            let mut transaction_log = TransactionLog::open(config, "install", target_id);
+            for path in &extracted_paths {
+                transaction_log.log(path);
+            }
+        }
     }
 
+    res?;
     Ok(())
 }
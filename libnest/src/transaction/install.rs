@@ -1,22 +1,31 @@
+use std::collections::HashSet;
+
+use failure::format_err;
+
 use crate::config::Config;
 use crate::lock_file::LockFileOwnership;
-use crate::package::PackageID;
+use crate::package::{PackageID, SoftPackageRequirement};
 
 use super::download::PackageDownload;
 use super::extract::extract_package;
-use super::{InstallError, InstallErrorKind::*};
+use super::observer::observe;
+use super::{InstallError, InstallErrorKind::*, Transaction, TransactionObserver};
 
 /// Structure representing an "install" transaction
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct InstallTransaction {
     target: PackageID,
+    force_overwrite: bool,
 }
 
 impl InstallTransaction {
     /// Creates an [`InstallTransaction`] from a given [`PackageID`]
     #[inline]
     pub fn from(target: PackageID) -> Self {
-        InstallTransaction { target }
+        InstallTransaction {
+            target,
+            force_overwrite: false,
+        }
     }
 
     /// Returns the target [`PackageID`] for this transaction
@@ -24,22 +33,225 @@ impl InstallTransaction {
         &self.target
     }
 
+    /// Returns whether files owned by other packages are overwritten by this transaction, instead of
+    /// aborting it.
+    ///
+    /// The ownership of the overwritten files is transferred to the installed package: they are removed
+    /// from the log of their previous owner, and the log of the installed package records who owned them.
+    /// Their previous version is kept aside until the journal is cleared with [`Config::clear_journal`][1], so that
+    /// removing the installed package meanwhile, like when rolling back, gives them back to their previous owner.
+    ///
+    /// [1]: ../config/struct.Config.html#method.clear_journal
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use std::path::{Path, PathBuf};
+    /// # use libnest::cache::installed::log::{FileLogEntry, FileType, Log};
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, Slot};
+    /// use libnest::transaction::{InstallErrorKind, InstallTransaction, NoopObserver, RemoveTransaction};
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-force-overwrite-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    ///
+    /// // Busybox is installed and owns /usr/bin/ls, which `coreutils`, a downloaded package, ships too
+    /// let busybox = PackageID::parse("tests::sys-apps/busybox#1.0.0")?;
+    /// # installed.save_package_log(
+    /// #     &busybox,
+    /// #     &Slot::default(),
+    /// #     &Log::new(vec![
+    /// #         FileLogEntry::new(PathBuf::from("/usr/bin"), FileType::Directory),
+    /// #         FileLogEntry::new(PathBuf::from("/usr/bin/ls"), FileType::File),
+    /// #         FileLogEntry::new(PathBuf::from("/usr/bin/cp"), FileType::File),
+    /// #     ]),
+    /// # )?;
+    /// # fs::create_dir_all(root.join("usr/bin"))?;
+    /// # fs::write(root.join("usr/bin/ls"), "busybox's ls")?;
+    /// # let coreutils = download_npf(&config, &lock_file_ownership, "tests::sys-apps/coreutils#1.0.0", "", &[],
+    /// #     &[("usr/bin/ls", 0o755, "coreutils' ls"), ("usr/bin/dir => usr/lib/coreutils/dir", 0o755, "")])?;
+    ///
+    /// // By default, the conflict aborts the installation
+    /// let mut install = InstallTransaction::from(coreutils.clone());
+    /// let error = install.extract(&config, &lock_file_ownership, &mut NoopObserver).unwrap_err();
+    /// assert!(match error.kind() {
+    ///     InstallErrorKind::FileConflict { owner, .. } => *owner == busybox,
+    ///     _ => false,
+    /// });
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/ls"))?, "busybox's ls");
+    ///
+    /// // When forced, the file is overwritten, but restored if the extraction fails midway, like when
+    /// // the target of a hard link is missing
+    /// *install.force_overwrite_mut() = true;
+    /// let error = install.extract(&config, &lock_file_ownership, &mut NoopObserver).unwrap_err();
+    /// assert!(match error.kind() {
+    ///     InstallErrorKind::RolledBack => true,
+    ///     _ => false,
+    /// });
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/ls"))?, "busybox's ls");
+    /// assert!(!root.join("usr/bin/ls.nest-backup").exists());
+    /// let transactions = fs::read_to_string(config.paths().transaction_log()).unwrap_or_default();
+    /// assert!(!transactions.contains("install tests::sys-apps/coreutils#1.0.0"));
+    /// fs::create_dir_all(root.join("usr/lib/coreutils"))?;
+    /// fs::write(root.join("usr/lib/coreutils/dir"), "dir")?;
+    ///
+    /// // Otherwise, it changes hands
+    /// install.extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/ls"))?, "coreutils' ls");
+    /// let transactions = fs::read_to_string(config.paths().transaction_log())?;
+    /// assert!(transactions.contains("install tests::sys-apps/coreutils#1.0.0 /usr/bin/ls"));
+    ///
+    /// let coreutils_log = installed.package_log(&coreutils, &Slot::default())?;
+    /// let ls = coreutils_log.files().iter().find(|file| file.path() == Path::new("/usr/bin/ls")).unwrap();
+    /// assert_eq!(ls.displaced_owner(), Some(&busybox));
+    ///
+    /// let busybox_files: Vec<_> = installed
+    ///     .package_log(&busybox, &Slot::default())?
+    ///     .files()
+    ///     .iter()
+    ///     .map(|file| file.path().to_path_buf())
+    ///     .collect();
+    /// assert_eq!(busybox_files, vec![PathBuf::from("/usr/bin"), PathBuf::from("/usr/bin/cp")]);
+    /// assert_eq!(installed.find_owner(Path::new("/usr/bin/ls"))?, Some(coreutils.clone()));
+    ///
+    /// // Until the journal is cleared, removing it, like when rolling back, gives the file back
+    /// RemoveTransaction::from(coreutils).perform(&config, &lock_file_ownership, &mut NoopObserver)?;
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/ls"))?, "busybox's ls");
+    /// assert_eq!(installed.find_owner(Path::new("/usr/bin/ls"))?, Some(busybox));
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn force_overwrite(&self) -> bool {
+        self.force_overwrite
+    }
+
+    /// Returns a mutable reference over whether files owned by other packages are overwritten by this transaction
+    #[inline]
+    pub fn force_overwrite_mut(&mut self) -> &mut bool {
+        &mut self.force_overwrite
+    }
+
     /// Create a download associated to this transaction
     pub fn associated_download(&self) -> PackageDownload {
         PackageDownload::from(self.target().clone())
     }
 
-    /// Extracts the downloaded file and performs the installation
+    /// Extracts the downloaded file and performs the installation, notifying the given observer of its progress.
+    ///
+    /// Packages requiring a more recent version of Nest than the running one are refused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::transaction::{InstallErrorKind, InstallTransaction, NoopObserver};
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-min-nest-version-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `download` puts a virtual package requiring the given version of Nest in the cache of downloaded packages
+    /// # let download = |name: &str, min_nest_version: &str| {
+    /// #     download_npf(&config, &lock_file_ownership, &format!("tests::sys-apps/{}#1.0.0", name),
+    /// #         &format!("kind = \"virtual\"\n{}", min_nest_version), &[], &[])
+    /// # };
+    /// let future = download("future", r#"min_nest_version = "99.0.0""#)?;
+    /// let error = InstallTransaction::from(future)
+    ///     .extract(&config, &lock_file_ownership, &mut NoopObserver)
+    ///     .unwrap_err();
+    /// assert!(match error.kind() {
+    ///     InstallErrorKind::IncompatibleNestVersion(version) => version.to_string() == "99.0.0",
+    ///     _ => false,
+    /// });
+    ///
+    /// let current = download("current", r#"min_nest_version = "0.1.0""#)?;
+    /// InstallTransaction::from(current).extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    ///
+    /// let any = download("any", "")?;
+    /// InstallTransaction::from(any).extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn extract(
         &self,
         config: &Config,
         lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
     ) -> Result<(), InstallError> {
-        let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
-        let npf_explorer = downloaded_packages
-            .explore_package(self.target())
-            .map_err(|_| InvalidPackageFile)?;
+        observe(observer, Transaction::Install(self.clone()), |observer| {
+            check_package_hash(config, lock_ownership, self.target())?;
+
+            let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
+            let npf_explorer = downloaded_packages
+                .explore_package(self.target())
+                .map_err(|_| InvalidPackageFile)?;
+
+            extract_package(
+                config,
+                lock_ownership,
+                npf_explorer,
+                self.target(),
+                self.force_overwrite,
+                &HashSet::new(),
+                observer,
+            )
+        })
+    }
+}
+
+/// Checks the downloaded NPF of a package against the hash carried by its manifest in the cache of
+/// available packages.
+///
+/// Packages whose manifest doesn't carry any hash are left to the check made against the server-issued
+/// hash when they were downloaded. Failing to find the manifest is an error, so that a broken cache can't
+/// be used to skip the check.
+pub(super) fn check_package_hash(
+    config: &Config,
+    lock_ownership: &LockFileOwnership,
+    target: &PackageID,
+) -> Result<(), InstallError> {
+    let requirement = SoftPackageRequirement::from_id(target);
+    let result = config
+        .available_packages_cache(lock_ownership)
+        .query(&requirement)
+        .perform()
+        .map_err(|e| e.context(PackageManifestUnavailable))?
+        .into_iter()
+        .find(|result| result.id() == *target)
+        .ok_or_else(|| {
+            format_err!("{}: not found in the cache of available packages", target)
+                .context(PackageManifestUnavailable)
+        })?;
 
-        extract_package(config, lock_ownership, npf_explorer, self.target())
+    if result.manifest().hash().is_some()
+        && !config
+            .downloaded_packages_cache(lock_ownership)
+            .verify_against_manifest(target, result.manifest())
+            .map_err(|_| InvalidPackageFile)?
+    {
+        return Err(PackageHashMismatch.into());
     }
+    Ok(())
 }
@@ -99,6 +99,25 @@ impl InstructionsExecutor {
         }
     }
 
+    /// Executes the whole script as a standalone program, like the `pre-install.sh` and `post-install.sh` scripts of NPFs
+    pub fn execute(&self, root: &Path) -> Result<ExecutionOutput, InstructionsExecutionError> {
+        let shell = Self::find_suitable_shell(root).ok_or(CannotFindShell)?;
+        let mut cmd = Command::new("chroot");
+
+        cmd.arg(root);
+        cmd.arg(shell);
+        cmd.arg("-c");
+        cmd.arg(&self.script_source);
+
+        let output = cmd.output().map_err(|_| CannotExecuteShell)?;
+
+        if !output.status.success() {
+            Err(FailureExitStatus(output).into())
+        } else {
+            Ok(output)
+        }
+    }
+
     /// Executes the pre-installation script
     pub fn execute_before_install(
         &self,
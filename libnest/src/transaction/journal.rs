@@ -0,0 +1,352 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::cache::depgraph::DependencyGraph;
+use crate::cache::installed::log::FileLogEntry;
+use crate::chroot::Chroot;
+use crate::config::{Config, ConfigPaths};
+use crate::lock_file::LockFileOwnership;
+use crate::package::PackageID;
+
+use super::{
+    DowngradeTransaction, InstallTransaction, ReinstallTransaction, RemoveTransaction, Transaction,
+    UpgradeTransaction,
+};
+
+/// A transaction as it is recorded in a [`Journal`]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum JournalEntry {
+    Install {
+        target: PackageID,
+        #[serde(default)]
+        force_overwrite: bool,
+    },
+    Remove {
+        target: PackageID,
+    },
+    Upgrade {
+        old: PackageID,
+        new: PackageID,
+    },
+    Downgrade {
+        old: PackageID,
+        new: PackageID,
+    },
+    Reinstall {
+        target: PackageID,
+    },
+}
+
+impl JournalEntry {
+    fn from(transaction: &Transaction) -> Option<JournalEntry> {
+        match transaction {
+            Transaction::Install(install) => Some(JournalEntry::Install {
+                target: install.target().clone(),
+                force_overwrite: install.force_overwrite(),
+            }),
+            Transaction::Remove(remove) => Some(JournalEntry::Remove {
+                target: remove.target().clone(),
+            }),
+            Transaction::Upgrade(upgrade) => Some(JournalEntry::Upgrade {
+                old: upgrade.old_target().clone(),
+                new: upgrade.new_target().clone(),
+            }),
+            Transaction::Downgrade(downgrade) => Some(JournalEntry::Downgrade {
+                old: downgrade.old_target().clone(),
+                new: downgrade.new_target().clone(),
+            }),
+            Transaction::Reinstall(reinstall) => Some(JournalEntry::Reinstall {
+                target: reinstall.target().clone(),
+            }),
+            Transaction::Pull(_) => None,
+        }
+    }
+
+    fn transaction<'a, 'b>(&self) -> Transaction<'a, 'b> {
+        match self {
+            JournalEntry::Install {
+                target,
+                force_overwrite,
+            } => {
+                let mut install = InstallTransaction::from(target.clone());
+                *install.force_overwrite_mut() = *force_overwrite;
+                Transaction::Install(install)
+            }
+            JournalEntry::Remove { target } => {
+                Transaction::Remove(RemoveTransaction::from(target.clone()))
+            }
+            JournalEntry::Upgrade { old, new } => {
+                Transaction::Upgrade(UpgradeTransaction::from(old.clone(), new.clone()))
+            }
+            JournalEntry::Downgrade { old, new } => {
+                Transaction::Downgrade(DowngradeTransaction::from(old.clone(), new.clone()))
+            }
+            JournalEntry::Reinstall { target } => {
+                Transaction::Reinstall(ReinstallTransaction::from(target.clone()))
+            }
+        }
+    }
+
+    fn reverse_transaction<'a, 'b>(&self) -> Transaction<'a, 'b> {
+        match self {
+            JournalEntry::Install { target, .. } => {
+                Transaction::Remove(RemoveTransaction::from(target.clone()))
+            }
+            JournalEntry::Remove { target } => {
+                Transaction::Install(InstallTransaction::from(target.clone()))
+            }
+            JournalEntry::Upgrade { old, new } => {
+                Transaction::Downgrade(DowngradeTransaction::from(new.clone(), old.clone()))
+            }
+            JournalEntry::Downgrade { old, new } => {
+                Transaction::Upgrade(UpgradeTransaction::from(new.clone(), old.clone()))
+            }
+            // Reinstalling a package leaves it as it was
+            JournalEntry::Reinstall { target } => {
+                Transaction::Reinstall(ReinstallTransaction::from(target.clone()))
+            }
+        }
+    }
+}
+
+/// A file taken over from another package by a forced installation, along with the entry it had in the log
+/// of its owner
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
+struct DisplacedFile {
+    owner: PackageID,
+    entry: FileLogEntry,
+}
+
+/// Returns the directory where the files taken over by forced installations are kept until the journal is cleared
+pub(crate) fn displaced_files_root(paths: &ConfigPaths) -> PathBuf {
+    paths.journal().with_extension("backups")
+}
+
+/// Returns the directory where the files taken over by the forced installation of the given package are kept
+fn displaced_files_path(config: &Config, target: &PackageID) -> PathBuf {
+    displaced_files_root(config.paths()).join(target.to_string())
+}
+
+fn load_displaced_files(dir: &Path) -> io::Result<Vec<DisplacedFile>> {
+    match fs::read(dir.join("owners.json")) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Keeps the backups of the files the forced installation of the given package took over until the journal is
+/// cleared, along with the entries they had in the logs of their owners, so that the installation can be rolled back.
+///
+/// Each displaced file is given as its previous owner, its entry in the log of this owner and the path of its backup.
+pub(crate) fn keep_displaced_files(
+    config: &Config,
+    target: &PackageID,
+    displaced: &[(PackageID, FileLogEntry, PathBuf)],
+) -> io::Result<()> {
+    if displaced.is_empty() {
+        return Ok(());
+    }
+
+    let dir = displaced_files_path(config, target);
+    let mut kept = load_displaced_files(&dir)?;
+    for (owner, entry, backup) in displaced {
+        let kept_path = dir.join("files").with_content(entry.path());
+        if let Some(parent) = kept_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(backup, &kept_path)?;
+        kept.push(DisplacedFile {
+            owner: owner.clone(),
+            entry: entry.clone(),
+        });
+    }
+    fs::write(dir.join("owners.json"), serde_json::to_vec(&kept)?)
+}
+
+/// Gives back the files the forced installation of the given package took over to their previous owners, like
+/// when this installation is rolled back.
+///
+/// The files of the owners that were removed meanwhile are dropped.
+pub(crate) fn restore_displaced_files(
+    config: &Config,
+    lock_ownership: &LockFileOwnership,
+    target: &PackageID,
+) -> io::Result<()> {
+    let dir = displaced_files_path(config, target);
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let installed_cache = config.installed_packages_cache(lock_ownership);
+    for file in load_displaced_files(&dir)? {
+        if installed_cache.claim_file(&file.owner, &file.entry)? {
+            let path = config.paths().root().with_content(file.entry.path());
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(dir.join("files").with_content(file.entry.path()), path)?;
+        }
+    }
+    fs::remove_dir_all(&dir)
+}
+
+/// The journal of a list of transactions being processed.
+///
+/// It records the ordered list of transactions, how many of them were completed, and the
+/// dependency graph they lead to. It's saved on disk using [`Config::write_journal`] before and after
+/// each transaction, so that an interrupted run can be resumed or rolled back later on.
+///
+/// Pull transactions aren't recorded.
+///
+/// The files taken over from other packages by forced installations are kept aside until the journal is cleared,
+/// and given back to their owners if the package that took them over is removed meanwhile, like when rolling back.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::Config;
+/// use libnest::package::PackageID;
+/// use libnest::transaction::{
+///     InstallTransaction, Journal, Transaction, TransactionError, TransactionErrorKind,
+/// };
+///
+/// let mut config: Config = toml::from_str("")?;
+/// let root = std::env::temp_dir().join(format!("nest-doctest-journal-{}", std::process::id()));
+/// *config.paths_mut() = config.paths().chroot(&root);
+///
+/// let transactions: Vec<Transaction> = ["a", "b", "c"]
+///     .iter()
+///     .map(|name| {
+///         let id = PackageID::parse(&format!("tests::sys-libs/{}#1.0.0", name))?;
+///         Ok(Transaction::Install(InstallTransaction::from(id)))
+///     })
+///     .collect::<Result<_, failure::Error>>()?;
+///
+/// // Process two transactions, then "crash"
+/// {
+///     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+///     let graph = config.dependency_graph(&lock_file_ownership)?;
+///
+///     let mut journal = Journal::new(&transactions, graph);
+///     config.write_journal(&journal, &lock_file_ownership)?;
+///     for _ in 0..2 {
+///         journal.mark_completed();
+///         config.write_journal(&journal, &lock_file_ownership)?;
+///     }
+/// }
+///
+/// // The next run finds the journal and resumes where it stopped
+/// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+/// let mut journal = config.load_journal(&lock_file_ownership)?.expect("no journal found");
+/// assert_eq!(journal.completed(), 2);
+/// assert_eq!(journal.pending(), vec![transactions[2].clone()]);
+///
+/// // Rolling back would undo the completed transactions, most recent first
+/// let targets: Vec<_> = journal
+///     .rollback_transactions()
+///     .iter()
+///     .map(|transaction| match transaction {
+///         Transaction::Remove(remove) => remove.target().to_string(),
+///         _ => panic!("unexpected transaction"),
+///     })
+///     .collect();
+/// assert_eq!(targets, vec!["tests::sys-libs/b#1.0.0", "tests::sys-libs/a#1.0.0"]);
+///
+/// journal.mark_completed();
+/// assert!(journal.is_finished());
+///
+/// config.clear_journal(&lock_file_ownership)?;
+/// assert!(config.load_journal(&lock_file_ownership)?.is_none());
+///
+/// // A truncated journal is set aside instead of blocking the next runs
+/// std::fs::write(config.paths().journal(), "{\"transactions\": [")?;
+/// let error = config.load_journal(&lock_file_ownership).unwrap_err();
+/// match error.downcast_ref::<TransactionError>().unwrap().kind() {
+///     TransactionErrorKind::UnparsableJournal { set_aside } => assert!(set_aside.exists()),
+///     kind => panic!("unexpected error: {}", kind),
+/// }
+/// assert!(config.load_journal(&lock_file_ownership)?.is_none());
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct Journal<'lock_file> {
+    transactions: Vec<JournalEntry>,
+    completed: usize,
+    graph: DependencyGraph<'lock_file>,
+}
+
+impl<'lock_file> Journal<'lock_file> {
+    /// Creates a [`Journal`] for the given transactions, none of which are completed yet, and the
+    /// dependency graph they lead to
+    pub fn new(transactions: &[Transaction], graph: DependencyGraph<'lock_file>) -> Self {
+        Journal {
+            transactions: transactions.iter().filter_map(JournalEntry::from).collect(),
+            completed: 0,
+            graph,
+        }
+    }
+
+    /// Returns all the transactions recorded in the journal, in order
+    pub fn transactions<'a, 'b>(&self) -> Vec<Transaction<'a, 'b>> {
+        self.transactions
+            .iter()
+            .map(JournalEntry::transaction)
+            .collect()
+    }
+
+    /// Returns the number of transactions that were completed
+    #[inline]
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Returns the transactions that weren't completed yet, in order
+    pub fn pending<'a, 'b>(&self) -> Vec<Transaction<'a, 'b>> {
+        self.transactions[self.completed..]
+            .iter()
+            .map(JournalEntry::transaction)
+            .collect()
+    }
+
+    /// Returns the transactions undoing the completed ones, in the order they should be processed
+    ///
+    /// The transaction that was interrupted, if any, isn't part of them.
+    pub fn rollback_transactions<'a, 'b>(&self) -> Vec<Transaction<'a, 'b>> {
+        self.transactions[..self.completed]
+            .iter()
+            .rev()
+            .map(JournalEntry::reverse_transaction)
+            .collect()
+    }
+
+    /// Marks the next pending transaction as completed
+    #[inline]
+    pub fn mark_completed(&mut self) {
+        if self.completed < self.transactions.len() {
+            self.completed += 1;
+        }
+    }
+
+    /// Returns whether all the transactions were completed
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.completed == self.transactions.len()
+    }
+
+    /// Returns a reference over the dependency graph the transactions lead to
+    #[inline]
+    pub fn graph(&self) -> &DependencyGraph<'lock_file> {
+        &self.graph
+    }
+}
@@ -0,0 +1,79 @@
+//! An append-only log of the files created and removed by transactions
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::{SecondsFormat, Utc};
+
+use crate::config::Config;
+use crate::package::PackageID;
+
+/// A logger appending a line to the [transaction log][1] for each file created or removed by a transaction.
+///
+/// Each line is made of the time of the event, the action that was performed (`install` or `remove`),
+/// the [`PackageID`] of the package it was performed for and the absolute path of the file, separated by
+/// spaces:
+///
+/// ```text
+/// 2019-05-27T16:34:15Z install stable::sys-apps/coreutils#8.31.0 /usr/bin/ls
+/// ```
+///
+/// The files extracted by an installation are only logged once it succeeded, so that an installation that was
+/// rolled back doesn't leave any line behind.
+///
+/// Logging is best-effort: a log that can't be opened or written to is ignored, so that it never makes
+/// a transaction fail.
+///
+/// [1]: ../config/struct.ConfigPaths.html#method.transaction_log
+pub(crate) struct TransactionLog<'a> {
+    writer: Option<BufWriter<File>>,
+    action: &'static str,
+    target: &'a PackageID,
+}
+
+impl<'a> TransactionLog<'a> {
+    /// Opens the transaction log of the given configuration, to log the files of the given package
+    /// affected by the given action
+    pub(crate) fn open(config: &Config, action: &'static str, target: &'a PackageID) -> Self {
+        TransactionLog {
+            writer: Self::open_file(config.paths().transaction_log()).map(BufWriter::new),
+            action,
+            target,
+        }
+    }
+
+    fn open_file(path: &Path) -> Option<File> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok()?;
+        }
+        OpenOptions::new().create(true).append(true).open(path).ok()
+    }
+
+    /// Logs the given absolute path
+    pub(crate) fn log(&mut self, path: &Path) {
+        if let Some(writer) = &mut self.writer {
+            let res = writeln!(
+                writer,
+                "{} {} {} {}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                self.action,
+                self.target,
+                path.display(),
+            );
+
+            // Stop logging on the first error instead of writing truncated lines
+            if res.is_err() {
+                self.writer = None;
+            }
+        }
+    }
+}
+
+impl<'a> Drop for TransactionLog<'a> {
+    fn drop(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
@@ -6,25 +6,41 @@
 //! - Installation
 //! - Removal
 //! - Upgrade
+//! - Downgrade
+//! - Reinstallation
 //!
 
+mod downgrade;
 mod download;
 mod errors;
 mod extract;
 mod install;
 mod instructions;
+mod journal;
+mod log;
+mod observer;
 mod pull;
+mod reinstall;
 mod remove;
+mod space;
 mod upgrade;
 
-pub use self::download::PackageDownload;
+pub use self::downgrade::DowngradeTransaction;
+pub use self::download::{try_mirrors, DownloadSize, PackageDownload};
 pub use self::errors::*;
 pub use self::install::InstallTransaction;
 pub use self::instructions::{ExecutionOutput, InstructionsExecutor};
-pub use self::pull::PullTransaction;
+pub(crate) use self::journal::displaced_files_root;
+pub use self::journal::Journal;
+pub use self::observer::{NoopObserver, TransactionObserver};
+pub use self::pull::{verify_signature, PullOutcome, PullTransaction};
+pub use self::reinstall::ReinstallTransaction;
 pub use self::remove::RemoveTransaction;
+pub use self::space::{check_available_space, check_available_space_with};
 pub use self::upgrade::UpgradeTransaction;
 
+use std::fmt::{self, Display, Formatter};
+
 /// The different possible variants of transactions
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Transaction<'a, 'b> {
@@ -39,4 +55,98 @@ pub enum Transaction<'a, 'b> {
 
     /// The transaction is an "upgrade" transaction
     Upgrade(UpgradeTransaction),
+
+    /// The transaction is a "downgrade" transaction
+    Downgrade(DowngradeTransaction),
+
+    /// The transaction is a "reinstall" transaction
+    Reinstall(ReinstallTransaction),
+}
+
+impl<'a, 'b> Transaction<'a, 'b> {
+    /// Returns the verb describing the action of the transaction, like `install` or `upgrade`
+    pub fn action(&self) -> &'static str {
+        match self {
+            Transaction::Pull(_) => "pull",
+            Transaction::Install(_) => "install",
+            Transaction::Remove(_) => "remove",
+            Transaction::Upgrade(_) => "upgrade",
+            Transaction::Downgrade(_) => "downgrade",
+            Transaction::Reinstall(_) => "reinstall",
+        }
+    }
+
+    /// Returns a description of what the transaction acts on: the name of the pulled repository, the
+    /// target package, or the old package and the version it's replaced with
+    pub fn subject(&self) -> String {
+        match self {
+            Transaction::Pull(pull) => pull.target_repository().name().to_string(),
+            Transaction::Install(install) => install.target().to_string(),
+            Transaction::Remove(remove) => remove.target().to_string(),
+            Transaction::Upgrade(upgrade) => format!(
+                "{} -> {}",
+                upgrade.old_target(),
+                upgrade.new_target().version()
+            ),
+            Transaction::Downgrade(downgrade) => format!(
+                "{} -> {}",
+                downgrade.old_target(),
+                downgrade.new_target().version()
+            ),
+            Transaction::Reinstall(reinstall) => reinstall.target().to_string(),
+        }
+    }
+}
+
+/// Renders a one-line summary of the transaction, made of its [action] and its [subject], so that all frontends
+/// describe transactions the same way.
+///
+/// [action]: enum.Transaction.html#method.action
+/// [subject]: enum.Transaction.html#method.subject
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::Config;
+/// use libnest::package::PackageID;
+/// use libnest::transaction::*;
+///
+/// let config: Config = toml::from_str("[repositories.stable]\nmirrors = []")?;
+/// let old = PackageID::parse("stable::sys-apps/hello#1.0.0")?;
+/// let new = PackageID::parse("stable::sys-apps/hello#1.2.0")?;
+///
+/// let summaries: Vec<_> = vec![
+///     Transaction::Pull(PullTransaction::from(config.repositories().remove(0))),
+///     Transaction::Install(InstallTransaction::from(new.clone())),
+///     Transaction::Remove(RemoveTransaction::from(old.clone())),
+///     Transaction::Upgrade(UpgradeTransaction::from(old.clone(), new.clone())),
+///     Transaction::Downgrade(DowngradeTransaction::from(new.clone(), old.clone())),
+///     Transaction::Reinstall(ReinstallTransaction::from(old.clone())),
+/// ]
+/// .iter()
+/// .map(ToString::to_string)
+/// .collect();
+///
+/// assert_eq!(
+///     summaries,
+///     vec![
+///         "pull stable",
+///         "install stable::sys-apps/hello#1.2.0",
+///         "remove stable::sys-apps/hello#1.0.0",
+///         "upgrade stable::sys-apps/hello#1.0.0 -> 1.2.0",
+///         "downgrade stable::sys-apps/hello#1.2.0 -> 1.0.0",
+///         "reinstall stable::sys-apps/hello#1.0.0",
+///     ],
+/// );
+/// # Ok(())
+/// # }
+/// ```
+impl<'a, 'b> Display for Transaction<'a, 'b> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.action(), self.subject())
+    }
 }
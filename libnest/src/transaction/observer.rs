@@ -0,0 +1,119 @@
+//! Observation of transactions while they are performed
+
+use failure::{AsFail, Fail};
+
+use crate::config::LogLevel;
+
+use super::Transaction;
+
+/// A trait for types that are notified of the progress of transactions, like a progress bar.
+///
+/// All the methods do nothing by default, so implementors only have to override the events they are
+/// interested in.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate flate2;
+/// # extern crate tar;
+/// # extern crate toml;
+/// # include!("../../doctests/npf.rs");
+/// # fn main() -> Result<(), failure::Error> {
+/// use failure::Fail;
+/// use libnest::config::Config;
+/// use libnest::transaction::{InstallTransaction, Transaction, TransactionObserver};
+///
+/// #[derive(Default)]
+/// struct Recorder(Vec<String>);
+///
+/// impl TransactionObserver for Recorder {
+///     fn on_transaction_start(&mut self, transaction: &Transaction) {
+///         if let Transaction::Install(install) = transaction {
+///             self.0.push(format!("start {}", install.target()));
+///         }
+///     }
+///
+///     fn on_progress(&mut self, current: usize, max: usize) {
+///         self.0.push(format!("progress {}/{}", current, max));
+///     }
+///
+///     fn on_transaction_end(&mut self, _: &Transaction, result: Result<(), &dyn Fail>) {
+///         self.0.push(format!("end {}", if result.is_ok() { "ok" } else { "err" }));
+///     }
+/// }
+///
+/// # let mut config: Config = toml::from_str("")?;
+/// # let root = std::env::temp_dir().join(format!("nest-doctest-observer-{}", std::process::id()));
+/// # *config.paths_mut() = config.paths().chroot(&root);
+/// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+/// // `hello` is a downloaded package shipping two files
+/// # let hello = download_npf(&config, &lock_file_ownership, "tests::sys-apps/hello#1.0.0", "", &[],
+/// #     &[("usr/bin/hello", 0o755, ""), ("usr/share/hello", 0o644, "")])?;
+/// let install = InstallTransaction::from(hello);
+/// let mut recorder = Recorder::default();
+/// install.extract(&config, &lock_file_ownership, &mut recorder)?;
+///
+/// assert_eq!(
+///     recorder.0,
+///     vec![
+///         "start tests::sys-apps/hello#1.0.0",
+///         "progress 1/2",
+///         "progress 2/2",
+///         "end ok",
+///     ],
+/// );
+/// assert!(root.join("usr/bin/hello").exists());
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait TransactionObserver {
+    /// Called right before a transaction is performed
+    fn on_transaction_start(&mut self, _transaction: &Transaction) {}
+
+    /// Called while a transaction is performed, with the number of files processed so far and the
+    /// total number of files to process.
+    ///
+    /// Transactions removing a package and then installing another one, like upgrades, go through
+    /// the files of both packages one after the other.
+    fn on_progress(&mut self, _current: usize, _max: usize) {}
+
+    /// Called once a transaction is over, with its result
+    fn on_transaction_end(&mut self, _transaction: &Transaction, _result: Result<(), &dyn Fail>) {}
+
+    /// Called when an event worth reporting happens, like a mirror that failed and was replaced by the next one.
+    ///
+    /// All events are sent regardless of their level: it's up to the observer to filter them, usually according
+    /// to the [configured level][1].
+    ///
+    /// [1]: ../config/struct.UiConfig.html#method.log_level
+    fn on_log(&mut self, _level: LogLevel, _message: &str) {}
+}
+
+/// A [`TransactionObserver`] ignoring all events
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct NoopObserver;
+
+impl TransactionObserver for NoopObserver {}
+
+/// Notifies the given observer of the start of the given transaction, runs `perform` and notifies
+/// the observer of its result
+pub(super) fn observe<E, F>(
+    observer: &mut dyn TransactionObserver,
+    transaction: Transaction,
+    perform: F,
+) -> Result<(), E>
+where
+    E: AsFail,
+    F: FnOnce(&mut dyn TransactionObserver) -> Result<(), E>,
+{
+    observer.on_transaction_start(&transaction);
+    let result = perform(observer);
+    observer.on_transaction_end(
+        &transaction,
+        result.as_ref().map(|_| ()).map_err(AsFail::as_fail),
+    );
+    result
+}
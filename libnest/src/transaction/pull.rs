@@ -1,20 +1,43 @@
 /// The "pull" transaction
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::File;
 use std::io::{Cursor, Seek, Write};
+use std::path::Path;
 use std::str;
 
-use failure::{Error, ResultExt};
+use chrono::Utc;
+use failure::{format_err, Error, ResultExt};
+use pgp::{Deserializable, SignedPublicKey, StandaloneSignature};
 use serde_json;
 
+use crate::cache::available::PullValidator;
 use crate::cache::CacheErrorKind;
+use crate::chroot::Chroot;
+use crate::config::MirrorUrl;
 use crate::lock_file::LockFileOwnership;
-use crate::package::PackageManifest;
+use crate::package::{PackageFullName, PackageManifest};
 use crate::repository::Repository;
 
+use super::{PullError, PullErrorKind};
+
+/// Whether pulling a repository changed its manifests in the available packages cache
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum PullOutcome {
+    /// The manifests didn't change since the last pull, so the cache was left untouched
+    Unchanged,
+    /// The manifests were written to the cache
+    Updated,
+}
+
 /// Structure representing a "pull" transaction
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct PullTransaction<'a, 'b> {
     target_repository: Repository<'a, 'b>,
     data: Vec<u8>,
+    signature: Vec<u8>,
+    response: Option<(MirrorUrl, PullValidator)>,
+    outcome: PullOutcome,
 }
 
 impl<'a, 'b> PullTransaction<'a, 'b> {
@@ -23,6 +46,9 @@ impl<'a, 'b> PullTransaction<'a, 'b> {
         PullTransaction {
             target_repository: repository,
             data: Vec::new(),
+            signature: Vec::new(),
+            response: None,
+            outcome: PullOutcome::Updated,
         }
     }
 
@@ -31,33 +57,221 @@ impl<'a, 'b> PullTransaction<'a, 'b> {
         &self.target_repository
     }
 
-    /// Returns a writer to store data
+    /// Returns a writer to store data, discarding the data previously written, if any
     pub fn writer(&mut self) -> impl Write + Seek + '_ {
+        self.data.clear();
         Cursor::new(&mut self.data)
     }
 
+    /// Returns a writer to store the detached signature of the data, if the repository has a public key,
+    /// discarding the signature previously written, if any
+    pub fn signature_writer(&mut self) -> impl Write + Seek + '_ {
+        self.signature.clear();
+        Cursor::new(&mut self.signature)
+    }
+
+    /// Records the mirror that answered the pull, the validator of its response, and whether the manifests
+    /// changed since the response carrying the validator that was sent along with the request.
+    ///
+    /// The validator is sent with the next pull of this mirror, so it can tell the manifests didn't change
+    /// instead of sending them again.
+    pub fn set_response(
+        &mut self,
+        mirror: &MirrorUrl,
+        validator: PullValidator,
+        outcome: PullOutcome,
+    ) {
+        self.response = Some((mirror.clone(), validator));
+        self.outcome = outcome;
+    }
+
     /// Save the stored data to the available packages cache
+    ///
+    /// If the repository has a public key, the data is checked against the stored signature first,
+    /// and nothing is written to the cache if it doesn't match.
+    ///
+    /// If the mirror answered that the manifests didn't change, the cache is left untouched and
+    /// [`PullOutcome::Unchanged`] is returned. Either way, the date of the pull is recorded, so the age of the cache
+    /// can be known later on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::io::Write;
+    /// use libnest::cache::available::PullValidator;
+    /// use libnest::config::Config;
+    /// use libnest::transaction::{PullOutcome, PullTransaction};
+    ///
+    /// let mut config: Config = toml::from_str(
+    ///     "repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = [\"http://localhost:8000\"]",
+    /// )?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-pull-outcome-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let cache = config.available_packages_cache(&lock_file_ownership);
+    /// let repository = config.repositories()[0];
+    /// let mirror = &repository.config().mirrors()[0];
+    ///
+    /// // The first pull receives the manifests, along with their ETag
+    /// let mut pull = PullTransaction::from(repository);
+    /// pull.writer().write_all(b"[]")?;
+    /// pull.set_response(mirror, PullValidator::from(Some("\"v1\"".to_string()), None), PullOutcome::Updated);
+    /// assert_eq!(pull.save_to_cache(&config, &lock_file_ownership)?, PullOutcome::Updated);
+    ///
+    /// let validators = cache.pull_validators(&repository)?;
+    /// assert_eq!(validators.get(mirror).and_then(PullValidator::etag), Some("\"v1\""));
+    ///
+    /// // The next one is told the manifests didn't change, and doesn't receive any data
+    /// let mut pull = PullTransaction::from(repository);
+    /// pull.set_response(mirror, PullValidator::from(Some("\"v1\"".to_string()), None), PullOutcome::Unchanged);
+    /// assert_eq!(pull.save_to_cache(&config, &lock_file_ownership)?, PullOutcome::Unchanged);
+    /// assert_eq!(cache.pull_validators(&repository)?, validators);
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn save_to_cache(
         &self,
         config: &crate::config::Config,
         ownership: &LockFileOwnership,
-    ) -> Result<(), Error> {
+    ) -> Result<PullOutcome, Error> {
+        let cache = config.available_packages_cache(ownership);
+        let mut validators = cache.pull_validators(&self.target_repository)?;
+
+        if self.outcome == PullOutcome::Unchanged {
+            if let Some((mirror, validator)) = &self.response {
+                validators.insert(mirror, validator.clone());
+                cache.save_pull_validators(&self.target_repository, &validators)?;
+            }
+            cache.save_last_pull(&self.target_repository, Utc::now())?;
+            return Ok(PullOutcome::Unchanged);
+        }
+
+        if let Some(pubkey) = self.target_repository.config().pubkey() {
+            // The key belongs to the system Nest is managing, which may be chrooted
+            let pubkey = config.paths().root().with_content(pubkey);
+            verify_signature(&pubkey, &self.data, &self.signature).with_context(|_| {
+                format_err!(
+                    "unable to verify the manifests of repository '{}'",
+                    self.target_repository.name()
+                )
+            })?;
+        }
+
         let res: Result<Vec<PackageManifest>, Error> = try {
             let utf8 = str::from_utf8(&self.data)?;
             serde_json::from_str(utf8)?
         };
 
         let manifests = res.context(CacheErrorKind::CacheWriteError)?;
-        let cache = config.available_packages_cache(ownership);
+
+        // The same package may be served more than once, each time with a different set of versions
+        let mut merged_manifests: HashMap<PackageFullName, PackageManifest> = HashMap::new();
+        for manifest in manifests {
+            match merged_manifests.entry(manifest.full_name()) {
+                Entry::Occupied(mut entry) => entry
+                    .get_mut()
+                    .merge(manifest)
+                    .context(CacheErrorKind::CacheWriteError)?,
+                Entry::Vacant(entry) => {
+                    entry.insert(manifest);
+                }
+            }
+        }
 
         cache.erase_repository(&self.target_repository)?;
 
-        for manifest in manifests {
+        for manifest in merged_manifests.values() {
             cache
-                .update(&manifest)
+                .update(manifest)
                 .with_context(|_| manifest.name().to_string())
                 .context(CacheErrorKind::CacheWriteError)?;
         }
+
+        // The other mirrors' validators match manifests older than those that were just written
+        validators.clear();
+        if let Some((mirror, validator)) = &self.response {
+            validators.insert(mirror, validator.clone());
+        }
+        cache.save_pull_validators(&self.target_repository, &validators)?;
+        cache.save_last_pull(&self.target_repository, Utc::now())?;
+        Ok(PullOutcome::Updated)
+    }
+}
+
+/// Checks a detached OpenPGP signature of the given data against a public key.
+///
+/// The public key and the signature may either be in binary form, as exported by `gpg --export`, or
+/// ASCII-armored. The signature may have been made by the primary key or by any of its subkeys.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # fn main() -> Result<(), failure::Error> {
+/// use std::fs;
+/// use std::process::Command;
+/// use libnest::transaction::{verify_signature, PullErrorKind};
+///
+/// // Generate a throwaway key, and sign some data with it
+/// let dir = std::env::temp_dir().join(format!("nest-doctest-signature-{}", std::process::id()));
+/// fs::create_dir_all(&dir)?;
+/// let gpg = |args: &[&str]| Command::new("gpg").arg("--homedir").arg(&dir).arg("--batch").args(args).output();
+/// gpg(&["--passphrase", "", "--quick-gen-key", "Nest <nest@raven-os.org>", "ed25519", "sign", "never"])?;
+/// let pubkey = dir.join("pubkey.gpg");
+/// fs::write(&pubkey, gpg(&["--export"])?.stdout)?;
+/// fs::write(dir.join("pull"), b"[]")?;
+/// gpg(&["--detach-sign", "--output", dir.join("pull.sig").to_str().unwrap(), dir.join("pull").to_str().unwrap()])?;
+/// let signature = fs::read(dir.join("pull.sig"))?;
+///
+/// // The signature matches the data
+/// assert!(verify_signature(&pubkey, b"[]", &signature).is_ok());
+///
+/// // So does an ASCII-armored export of the key
+/// let armored = dir.join("pubkey.asc");
+/// fs::write(&armored, gpg(&["--export", "--armor"])?.stdout)?;
+/// assert!(verify_signature(&armored, b"[]", &signature).is_ok());
+///
+/// // The data was tampered with
+/// let error = verify_signature(&pubkey, b"[{}]", &signature).unwrap_err();
+/// assert_eq!(*error.kind(), PullErrorKind::SignatureVerificationFailed);
+///
+/// // The signature is missing
+/// assert!(verify_signature(&pubkey, b"[]", b"").is_err());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_signature(pubkey: &Path, data: &[u8], signature: &[u8]) -> Result<(), PullError> {
+    let key: Result<SignedPublicKey, Error> = try {
+        let file = File::open(pubkey)?;
+        let (key, _) = SignedPublicKey::from_reader_single(file)?;
+        key.verify()?;
+        key
+    };
+    let key = key
+        .with_context(|_| format_err!("{}: invalid public key", pubkey.display()))
+        .context(PullErrorKind::SignatureVerificationFailed)?;
+
+    let (signature, _) = StandaloneSignature::from_reader_single(signature)
+        .map_err(Error::from)
+        .context(format_err!("invalid signature"))
+        .context(PullErrorKind::SignatureVerificationFailed)?;
+
+    let verified = signature.verify(&key, data).is_ok()
+        || key
+            .public_subkeys
+            .iter()
+            .any(|subkey| signature.verify(subkey, data).is_ok());
+    if verified {
         Ok(())
+    } else {
+        Err(PullErrorKind::SignatureVerificationFailed.into())
     }
 }
@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use failure::Error;
+
+use crate::config::Config;
+use crate::lock_file::LockFileOwnership;
+use crate::package::{Kind, PackageID};
+
+use super::download::PackageDownload;
+use super::extract::{back_up_files, extract_package, remove_backups, restore_backups};
+use super::install::check_package_hash;
+use super::observer::observe;
+use super::remove::remove_package;
+use super::{
+    InstallError, InstallErrorKind, RemoveError, RemoveErrorKind, Transaction, TransactionObserver,
+};
+
+/// Structure representing a "reinstall" transaction, replacing the files of an installed package by
+/// those of the same version
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ReinstallTransaction {
+    target: PackageID,
+}
+
+impl ReinstallTransaction {
+    /// Creates a [`ReinstallTransaction`] from a given [`PackageID`]
+    #[inline]
+    pub fn from(target: PackageID) -> Self {
+        ReinstallTransaction { target }
+    }
+
+    /// Returns the target [`PackageID`] for this transaction
+    pub fn target(&self) -> &PackageID {
+        &self.target
+    }
+
+    /// Create a download associated to this transaction
+    pub fn associated_download(&self) -> PackageDownload {
+        PackageDownload::from(self.target().clone())
+    }
+
+    /// Removes the files of the package, and extracts them again from the downloaded package, notifying
+    /// the given observer of its progress.
+    ///
+    /// Files that were removed in the meantime are restored. If the extraction fails, the files of the package
+    /// are left as they were before the transaction. If the package isn't installed, a
+    /// [`RemoveError`] of kind [`RemoveErrorKind::PackageNotInstalled`] is returned.
+    ///
+    /// [`RemoveErrorKind::PackageNotInstalled`]: enum.RemoveErrorKind.html#variant.PackageNotInstalled
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::Config;
+    /// use libnest::transaction::{InstallTransaction, NoopObserver, ReinstallTransaction};
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-reinstall-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `hello` is a downloaded package shipping a binary and its documentation
+    /// # let hello = download_npf(&config, &lock_file_ownership, "tests::sys-apps/hello#1.0.0", "", &[],
+    /// #     &[("usr/bin/hello", 0o644, "hello"), ("usr/share/hello/doc", 0o644, "hello")])?;
+    /// InstallTransaction::from(hello.clone()).extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    ///
+    /// // A file is in the way of the documentation, so it can't be extracted again
+    /// fs::remove_dir_all(root.join("usr/share/hello"))?;
+    /// fs::write(root.join("usr/share/hello"), "in the way")?;
+    /// let reinstall = ReinstallTransaction::from(hello.clone());
+    /// assert!(reinstall.perform(&config, &lock_file_ownership, &mut NoopObserver).is_err());
+    ///
+    /// // The package is left as it was
+    /// assert_eq!(fs::read_to_string(root.join("usr/bin/hello"))?, "hello");
+    /// assert!(!root.join("usr/bin/hello.nest-backup").exists());
+    /// let installed = config.installed_packages_cache(&lock_file_ownership);
+    /// assert_eq!(installed.package_files(&hello)?.len(), 2);
+    ///
+    /// // Once the file is moved out of the way, the documentation is restored
+    /// fs::remove_file(root.join("usr/share/hello"))?;
+    /// reinstall.perform(&config, &lock_file_ownership, &mut NoopObserver)?;
+    /// assert_eq!(fs::read_to_string(root.join("usr/share/hello/doc"))?, "hello");
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn perform(
+        &self,
+        config: &Config,
+        lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
+    ) -> Result<(), Error> {
+        observe(observer, Transaction::Reinstall(self.clone()), |observer| {
+            self.reinstall(config, lock_ownership, observer)
+        })
+    }
+
+    fn reinstall(
+        &self,
+        config: &Config,
+        lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
+    ) -> Result<(), Error> {
+        check_package_hash(config, lock_ownership, self.target())?;
+
+        let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
+        let npf_explorer = downloaded_packages
+            .explore_package(self.target())
+            .map_err(|_| RemoveError::from(RemoveErrorKind::InvalidCachedPackageFile))?;
+
+        // Effective packages are installed if and only if they have a log
+        if npf_explorer.manifest().kind() == Kind::Effective {
+            let installed = config
+                .installed_packages_cache(lock_ownership)
+                .packages()
+                .map_err(|e| RemoveError::from(RemoveErrorKind::LogFileLoadError(e)))?
+                .into_iter()
+                .any(|(id, slot)| id == *self.target() && slot == *npf_explorer.manifest().slot());
+            if !installed {
+                return Err(RemoveError::from(RemoveErrorKind::PackageNotInstalled).into());
+            }
+        }
+
+        // The files of the package are kept aside until the new ones are extracted, so that they can be
+        // restored if the extraction fails
+        let installed_cache = config.installed_packages_cache(lock_ownership);
+        let slot = npf_explorer.manifest().slot().clone();
+        let backup = if npf_explorer.manifest().kind() == Kind::Effective {
+            let log = installed_cache
+                .package_log(self.target(), &slot)
+                .map_err(|e| RemoveError::from(RemoveErrorKind::LogFileLoadError(e)))?;
+            let backups = back_up_files(config.paths().root(), &log)
+                .map_err(|e| InstallError::from(InstallErrorKind::ExtractError(e)))?;
+            Some((log, backups))
+        } else {
+            None
+        };
+
+        let res: Result<(), Error> = try {
+            remove_package(
+                config,
+                lock_ownership,
+                npf_explorer,
+                self.target(),
+                &HashSet::new(),
+                observer,
+            )?;
+
+            let npf_explorer = downloaded_packages
+                .explore_package(self.target())
+                .map_err(|_| InstallError::from(InstallErrorKind::InvalidPackageFile))?;
+            extract_package(
+                config,
+                lock_ownership,
+                npf_explorer,
+                self.target(),
+                false,
+                &HashSet::new(),
+                observer,
+            )
+            .map_err(InstallError::into_error)?;
+        };
+
+        if let Some((log, backups)) = backup {
+            if res.is_ok() {
+                remove_backups(&backups);
+            } else {
+                restore_backups(&backups);
+                installed_cache
+                    .save_package_log(self.target(), &slot, &log)
+                    .map_err(|e| InstallError::from(InstallErrorKind::LogCreationError(e)))?;
+            }
+        }
+        res?;
+
+        Ok(())
+    }
+}
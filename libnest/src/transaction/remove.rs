@@ -1,14 +1,19 @@
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use failure::ResultExt;
 
+use crate::cache::installed::normalize;
 use crate::chroot::Chroot;
 use crate::config::Config;
 use crate::lock_file::LockFileOwnership;
 use crate::package::{Kind, NPFExplorer, PackageID};
 
-use super::{RemoveError, RemoveErrorKind::*};
+use super::journal::restore_displaced_files;
+use super::log::TransactionLog;
+use super::observer::observe;
+use super::{RemoveError, RemoveErrorKind::*, Transaction, TransactionObserver};
 
 /// Structure representing a "remove" transaction
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -27,18 +32,38 @@ impl RemoveTransaction {
         &self.target
     }
 
-    /// Performs the removal of the package
+    /// Performs the removal of the package, notifying the given observer of its progress
+    ///
+    /// The files the package took over from other packages when it was installed with
+    /// [`InstallTransaction::force_overwrite`][1] are given back to them, as long as the journal wasn't cleared since.
+    ///
+    /// [1]: struct.InstallTransaction.html#method.force_overwrite
     pub fn perform(
         &self,
         config: &Config,
         lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
     ) -> Result<(), RemoveError> {
-        let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
-        let npf_explorer = downloaded_packages
-            .explore_package(self.target())
-            .map_err(|_| InvalidCachedPackageFile)?;
-
-        remove_package(config, lock_ownership, npf_explorer, self.target())
+        observe(observer, Transaction::Remove(self.clone()), |observer| {
+            let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
+            let npf_explorer = downloaded_packages
+                .explore_package(self.target())
+                .map_err(|_| InvalidCachedPackageFile)?;
+
+            remove_package(
+                config,
+                lock_ownership,
+                npf_explorer,
+                self.target(),
+                &HashSet::new(),
+                observer,
+            )?;
+
+            // The files the package took over from other packages are given back to them, if they were kept aside
+            restore_displaced_files(config, lock_ownership, self.target())
+                .map_err(DisplacedFilesRestoreError)?;
+            Ok(())
+        })
     }
 }
 
@@ -48,12 +73,17 @@ fn is_empty_directory(dir_path: &Path) -> std::io::Result<bool> {
     Ok(it.next().is_none())
 }
 
-/// Remove the package from a given [`NPFExplorer`], using a given [`PackageID`]'s log
+/// Remove the package from a given [`NPFExplorer`], using a given [`PackageID`]'s log, notifying the
+/// given observer of each removed file.
+///
+/// The files whose normalized path is in `preserved` are left untouched.
 pub(crate) fn remove_package(
     config: &Config,
     lock_ownership: &LockFileOwnership,
     npf_explorer: NPFExplorer,
     target_id: &PackageID,
+    preserved: &HashSet<PathBuf>,
+    observer: &mut dyn TransactionObserver,
 ) -> Result<(), RemoveError> {
     let instructions_handle = npf_explorer
         .load_instructions()
@@ -70,38 +100,48 @@ pub(crate) fn remove_package(
         // Open the log file, and remove all the files listed in it
         let log = config
             .installed_packages_cache(lock_ownership)
-            .package_log(target_id)
+            .package_log(target_id, npf_explorer.manifest().slot())
             .map_err(LogFileLoadError)?;
 
         // Iterate backwards to ensure removal of nested files before that of top-level directories
-        for entry in log.files().into_iter().rev() {
+        let total = log.files().len();
+        let mut transaction_log = TransactionLog::open(config, "remove", target_id);
+        for (i, entry) in log.files().into_iter().rev().enumerate() {
             let abs_path = Path::new("/").with_content(entry.path());
             let rel_path = config.paths().root().with_content(entry.path());
 
+            if preserved.contains(&normalize(&abs_path)) {
+                observer.on_progress(i + 1, total);
+                continue;
+            }
+
             if let Ok(metadata) = fs::symlink_metadata(&rel_path) {
-                match (entry.file_type().is_dir(), metadata.file_type().is_dir()) {
+                let removed = match (entry.file_type().is_dir(), metadata.file_type().is_dir()) {
                     // The file to remove is a directory, remove it if it is empty
                     (true, true) => {
                         if let Ok(true) = is_empty_directory(&rel_path) {
-                            fs::remove_dir(&rel_path)
+                            fs::remove_dir(&rel_path).map(|_| true)
                         } else {
-                            Ok(())
+                            Ok(false)
                         }
                     }
 
                     // The file was expected to be a directory, but is a symlink, leave it
-                    (true, false) if metadata.file_type().is_symlink() => Ok(()),
+                    (true, false) if metadata.file_type().is_symlink() => Ok(false),
 
                     // The file to remove is a regular file, remove it
-                    _ => fs::remove_file(&rel_path),
+                    _ => fs::remove_file(&rel_path).map(|_| true),
+                };
+                if removed.with_context(|_| FileRemoveError(abs_path.clone()))? {
+                    transaction_log.log(&abs_path);
                 }
-                .with_context(|_| FileRemoveError(abs_path))?;
             }
+            observer.on_progress(i + 1, total);
         }
 
         config
             .installed_packages_cache(lock_ownership)
-            .remove_package_log(target_id)
+            .remove_package_log(target_id, npf_explorer.manifest().slot())
             .with_context(|_| target_id.to_string())
             .with_context(|_| LogFileRemoveError)?;
     }
@@ -0,0 +1,203 @@
+//! Checks of the space available on disk before processing a list of transactions
+
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+
+use crate::config::Config;
+use crate::lock_file::LockFileOwnership;
+
+use super::{Transaction, TransactionError, TransactionErrorKind};
+
+/// Returns the closest ancestor of the given path that exists, along with the device it's on
+fn existing_ancestor(path: &Path) -> io::Result<(&Path, u64)> {
+    let mut path = path;
+    loop {
+        match path.metadata() {
+            Ok(metadata) => return Ok((path, metadata.dev())),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => match path.parent() {
+                Some(parent) => path = parent,
+                None => return Err(io::ErrorKind::NotFound.into()),
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Checks that there is enough space available to process the given transactions, downloading packages to
+/// `download_path` and installing them to `install_path`.
+///
+/// The space needed is the sum of the download sizes of the packages that aren't downloaded yet, and of the
+/// installed sizes of all the packages to install, as advertised by their manifests. Packages whose manifest
+/// doesn't carry those sizes are ignored, as is the space freed by the packages that are removed.
+///
+/// If there isn't enough space, a [`TransactionError`] of kind [`TransactionErrorKind::InsufficientSpace`] is returned.
+///
+/// [`TransactionErrorKind::InsufficientSpace`]: enum.TransactionErrorKind.html#variant.InsufficientSpace
+pub fn check_available_space(
+    config: &Config,
+    lock_ownership: &LockFileOwnership,
+    transactions: &[Transaction],
+    download_path: &Path,
+    install_path: &Path,
+) -> Result<(), Error> {
+    check_available_space_with(
+        config,
+        lock_ownership,
+        transactions,
+        download_path,
+        install_path,
+        |path| fs2::available_space(path),
+    )
+}
+
+/// Same as [`check_available_space`], but queries the space available on the filesystem holding a path with the given
+/// function instead of asking the operating system.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate libnest;
+/// # extern crate failure;
+/// # extern crate serde_json;
+/// # extern crate toml;
+/// # fn main() -> Result<(), failure::Error> {
+/// use libnest::config::Config;
+/// use libnest::package::{PackageID, PackageManifest};
+/// use libnest::transaction::{
+///     check_available_space_with, InstallTransaction, RemoveTransaction, Transaction, TransactionError,
+///     TransactionErrorKind,
+/// };
+///
+/// let mut config: Config =
+///     toml::from_str("repositories_order = [\"tests\"]\n[repositories.tests]\nmirrors = []")?;
+/// let root = std::env::temp_dir().join(format!("nest-doctest-available-space-{}", std::process::id()));
+/// *config.paths_mut() = config.paths().chroot(&root);
+///
+/// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+/// let cache = config.available_packages_cache(&lock_file_ownership);
+/// for name in &["gcc", "binutils"] {
+///     let manifest: PackageManifest = serde_json::from_str(&format!(
+///         r#"{{
+///             "name": "{}",
+///             "category": "sys-devel",
+///             "repository": "tests",
+///             "metadata": {{
+///                 "description": "",
+///                 "tags": [],
+///                 "maintainer": "nest@raven-os.org",
+///                 "licenses": [],
+///                 "upstream_url": null
+///             }},
+///             "versions": {{
+///                 "1.0.0": {{
+///                     "wrap_date": "2019-05-27T16:34:15Z",
+///                     "dependencies": [],
+///                     "download_size": 1000,
+///                     "installed_size": 4000
+///                 }}
+///             }}
+///         }}"#,
+///         name,
+///     ))?;
+///     cache.update(&manifest)?;
+/// }
+///
+/// let transactions = vec![
+///     Transaction::Install(InstallTransaction::from(PackageID::parse("tests::sys-devel/gcc#1.0.0")?)),
+///     Transaction::Install(InstallTransaction::from(PackageID::parse("tests::sys-devel/binutils#1.0.0")?)),
+///     Transaction::Remove(RemoveTransaction::from(PackageID::parse("tests::sys-devel/clang#1.0.0")?)),
+/// ];
+/// let check = |available: u64| {
+///     check_available_space_with(
+///         &config,
+///         &lock_file_ownership,
+///         &transactions,
+///         config.paths().downloaded(),
+///         config.paths().root(),
+///         |_| Ok(available),
+///     )
+/// };
+///
+/// // Both paths are on the same filesystem, which must hold the downloads and the installed files
+/// assert!(check(10_000).is_ok());
+///
+/// let error = check(9_999).unwrap_err();
+/// match error.downcast_ref::<TransactionError>().unwrap().kind() {
+///     TransactionErrorKind::InsufficientSpace { needed, available, .. } => {
+///         assert_eq!((*needed, *available), (10_000, 9_999));
+///     }
+///     kind => panic!("unexpected error: {}", kind),
+/// }
+/// # std::fs::remove_dir_all(&root)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn check_available_space_with<F>(
+    config: &Config,
+    lock_ownership: &LockFileOwnership,
+    transactions: &[Transaction],
+    download_path: &Path,
+    install_path: &Path,
+    available_space: F,
+) -> Result<(), Error>
+where
+    F: Fn(&Path) -> io::Result<u64>,
+{
+    let available_packages = config.available_packages_cache(lock_ownership);
+    let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
+
+    let targets = transactions
+        .iter()
+        .filter_map(|transaction| match transaction {
+            Transaction::Install(install) => Some(install.target()),
+            Transaction::Upgrade(upgrade) => Some(upgrade.new_target()),
+            Transaction::Downgrade(downgrade) => Some(downgrade.new_target()),
+            Transaction::Reinstall(reinstall) => Some(reinstall.target()),
+            Transaction::Remove(_) | Transaction::Pull(_) => None,
+        });
+
+    let mut download_size = 0;
+    let mut installed_size = 0;
+    for target in targets {
+        let manifest = available_packages
+            .package_manifest(&target.clone().into())?
+            .and_then(|manifest| manifest.get_manifest_for_version(target.version().clone()));
+
+        if let Some(manifest) = manifest {
+            if !downloaded_packages.has_package(target) {
+                download_size += manifest.download_size().unwrap_or(0);
+            }
+            installed_size += manifest.installed_size().unwrap_or(0);
+        }
+    }
+
+    let (download_path, download_device) = existing_ancestor(download_path)?;
+    let (install_path, install_device) = existing_ancestor(install_path)?;
+
+    // The downloads and the installed files may end up on the same filesystem
+    let mut needs: Vec<(&Path, u64)> = Vec::new();
+    if download_device == install_device {
+        needs.push((install_path, download_size + installed_size));
+    } else {
+        needs.push((download_path, download_size));
+        needs.push((install_path, installed_size));
+    }
+
+    for (path, needed) in needs {
+        let available = available_space(path)?;
+        if needed > available {
+            return Err(
+                TransactionError::from(TransactionErrorKind::InsufficientSpace {
+                    path: PathBuf::from(path),
+                    needed,
+                    available,
+                })
+                .into(),
+            );
+        }
+    }
+    Ok(())
+}
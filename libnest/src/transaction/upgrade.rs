@@ -1,13 +1,24 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
 use failure::Error;
+use semver::Version;
 
+use crate::cache::installed::log::FileStatus;
+use crate::cache::installed::normalize;
 use crate::config::Config;
-use crate::lock_file::LockFileOwnership;
-use crate::package::{NPFExplorer, PackageID};
+use crate::lock_file::{LockFileOwnership, LockFileReadOwnership};
+use crate::package::{Kind, NPFExplorer, PackageFullName, PackageID};
 
 use super::download::PackageDownload;
 use super::extract::extract_package;
+use super::install::check_package_hash;
+use super::observer::observe;
 use super::remove::remove_package;
-use super::{InstallError, InstallErrorKind::*, RemoveError, RemoveErrorKind::*};
+use super::{
+    InstallError, InstallErrorKind::*, RemoveError, RemoveErrorKind::*, Transaction,
+    TransactionObserver,
+};
 
 /// Structure representing an upgrade transaction
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -37,11 +48,93 @@ impl UpgradeTransaction {
         PackageDownload::from(self.new_target().clone())
     }
 
-    fn remove_old_package(
+    /// Returns the changelog entries of the versions crossed by this upgrade, sorted by ascending version.
+    ///
+    /// They are read from the cache of available packages, and only the versions greater than the old target and lower
+    /// than or equal to the new target are considered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate semver;
+    /// # extern crate serde_json;
+    /// # extern crate toml;
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use libnest::config::Config;
+    /// use libnest::package::{PackageID, PackageManifest};
+    /// use libnest::transaction::UpgradeTransaction;
+    /// use semver::Version;
+    ///
+    /// let mut config: Config = toml::from_str("")?;
+    /// let root = std::env::temp_dir().join(format!("nest-doctest-changelog-{}", std::process::id()));
+    /// *config.paths_mut() = config.paths().chroot(&root);
+    ///
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    /// let manifest: PackageManifest = serde_json::from_str(
+    ///     r#"{
+    ///         "name": "hello",
+    ///         "category": "sys-apps",
+    ///         "repository": "tests",
+    ///         "metadata": {
+    ///             "description": "Prints hello",
+    ///             "tags": [],
+    ///             "maintainer": "nest@raven-os.org",
+    ///             "licenses": [],
+    ///             "upstream_url": null
+    ///         },
+    ///         "versions": {
+    ///             "0.9.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "First beta" },
+    ///             "1.0.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "First release" },
+    ///             "1.1.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Say it louder" },
+    ///             "1.2.0": { "wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "changelog": "Say it in French" }
+    ///         }
+    ///     }"#,
+    /// )?;
+    /// config.available_packages_cache(&lock_file_ownership).update(&manifest)?;
+    ///
+    /// let upgrade = UpgradeTransaction::from(
+    ///     PackageID::parse("tests::sys-apps/hello#1.0.0")?,
+    ///     PackageID::parse("tests::sys-apps/hello#1.2.0")?,
+    /// );
+    /// assert_eq!(
+    ///     upgrade.changelog(&config, &lock_file_ownership)?,
+    ///     vec![
+    ///         (Version::parse("1.1.0")?, "Say it louder".to_string()),
+    ///         (Version::parse("1.2.0")?, "Say it in French".to_string()),
+    ///     ],
+    /// );
+    /// # std::fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn changelog<L: LockFileReadOwnership>(
         &self,
         config: &Config,
-        lock_ownership: &LockFileOwnership,
-    ) -> Result<(), RemoveError> {
+        lock_ownership: &L,
+    ) -> Result<Vec<(Version, String)>, Error> {
+        let full_name = PackageFullName::from(
+            self.new_target().repository().clone(),
+            self.new_target().category().clone(),
+            self.new_target().name().clone(),
+        );
+        let manifest = config
+            .available_packages_cache(lock_ownership)
+            .package_manifest(&full_name)?;
+
+        Ok(manifest
+            .map(|manifest| {
+                manifest
+                    .changelog_between(self.old_target().version(), self.new_target().version())
+                    .into_iter()
+                    .map(|(version, changelog)| (version.clone(), changelog.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn open_old_package(&self, config: &Config) -> Result<NPFExplorer, RemoveError> {
         let npf_path = config
             .paths()
             .downloaded()
@@ -54,33 +147,181 @@ impl UpgradeTransaction {
                 self.old_target().version()
             ));
 
-        let npf_explorer = NPFExplorer::from(&npf_path).map_err(|_| InvalidCachedPackageFile)?;
-
-        remove_package(config, lock_ownership, npf_explorer, self.old_target())
+        Ok(NPFExplorer::from(&npf_path).map_err(|_| InvalidCachedPackageFile)?)
     }
 
-    fn install_new_package(
+    fn open_new_package(
         &self,
         config: &Config,
         lock_ownership: &LockFileOwnership,
-    ) -> Result<(), InstallError> {
+    ) -> Result<NPFExplorer, InstallError> {
         let downloaded_packages = config.downloaded_packages_cache(lock_ownership);
-        let npf_explorer = downloaded_packages
+        Ok(downloaded_packages
             .explore_package(self.new_target())
-            .map_err(|_| InvalidPackageFile)?;
+            .map_err(|_| InvalidPackageFile)?)
+    }
+
+    /// Returns the normalized paths of the configuration files of the new package that were modified by the user
+    /// since the old package installed them.
+    ///
+    /// Files that can't be read to be compared are considered modified, so they aren't overwritten blindly.
+    fn modified_config_files(
+        &self,
+        config: &Config,
+        lock_ownership: &LockFileOwnership,
+        old: &NPFExplorer,
+        new: &NPFExplorer,
+    ) -> Result<HashSet<PathBuf>, RemoveError> {
+        let config_files: HashSet<_> = new
+            .manifest()
+            .config_files()
+            .iter()
+            .map(|path| normalize(path))
+            .collect();
+        if config_files.is_empty() || old.manifest().kind() != Kind::Effective {
+            return Ok(HashSet::new());
+        }
 
-        extract_package(config, lock_ownership, npf_explorer, self.new_target())
+        let log = config
+            .installed_packages_cache(lock_ownership)
+            .package_log(self.old_target(), old.manifest().slot())
+            .map_err(LogFileLoadError)?;
+
+        Ok(log
+            .files()
+            .iter()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| (normalize(entry.path()), entry))
+            .filter(|(path, _)| config_files.contains(path))
+            .filter(|(_, entry)| match entry.verify(config.paths().root()) {
+                Ok(FileStatus::Intact) | Ok(FileStatus::Missing) => false,
+                Ok(FileStatus::Modified) | Err(_) => true,
+            })
+            .map(|(path, _)| path)
+            .collect())
     }
 
-    /// Perform the upgrade transaction
-    pub fn perform(
+    /// Replaces the old package by the new one, without notifying the observer of the start and end
+    /// of the transaction
+    pub(super) fn replace(
         &self,
         config: &Config,
         lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
     ) -> Result<(), Error> {
-        self.remove_old_package(config, lock_ownership)?;
-        self.install_new_package(config, lock_ownership)?;
+        // Check the new package before removing the old one, so a tampered package doesn't leave the system without either
+        check_package_hash(config, lock_ownership, self.new_target())?;
+        let old = self.open_old_package(config)?;
+        let new = self.open_new_package(config, lock_ownership)?;
+
+        // The configuration files modified by the user are kept, the new version being written next to them
+        let preserved = self.modified_config_files(config, lock_ownership, &old, &new)?;
+
+        remove_package(
+            config,
+            lock_ownership,
+            old,
+            self.old_target(),
+            &preserved,
+            observer,
+        )?;
+        extract_package(
+            config,
+            lock_ownership,
+            new,
+            self.new_target(),
+            false,
+            &preserved,
+            observer,
+        )
+        .map_err(InstallError::into_error)?;
 
         Ok(())
     }
+
+    /// Perform the upgrade transaction, notifying the given observer of its progress.
+    ///
+    /// The [configuration files][1] of the new package that were modified since the old package installed them
+    /// are kept as they are: their new version is written next to them with a `.nest-new` extension, and the
+    /// observer is warned about it.
+    ///
+    /// [1]: ../package/struct.Manifest.html#method.config_files
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate libnest;
+    /// # extern crate failure;
+    /// # extern crate flate2;
+    /// # extern crate tar;
+    /// # extern crate toml;
+    /// # include!("../../doctests/npf.rs");
+    /// # fn main() -> Result<(), failure::Error> {
+    /// use std::fs;
+    /// use libnest::config::{Config, LogLevel};
+    /// use libnest::transaction::{
+    ///     InstallTransaction, NoopObserver, RemoveTransaction, TransactionObserver, UpgradeTransaction,
+    /// };
+    ///
+    /// #[derive(Default)]
+    /// struct Recorder(Vec<(LogLevel, String)>);
+    ///
+    /// impl TransactionObserver for Recorder {
+    ///     fn on_log(&mut self, level: LogLevel, message: &str) {
+    ///         self.0.push((level, message.to_string()));
+    ///     }
+    /// }
+    ///
+    /// # let mut config: Config = toml::from_str("")?;
+    /// # let root = std::env::temp_dir().join(format!("nest-doctest-config-files-{}", std::process::id()));
+    /// # *config.paths_mut() = config.paths().chroot(&root);
+    /// let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    ///
+    /// // `hello` is a downloaded package whose versions ship different contents for the configuration file
+    /// // `/etc/hello.conf`
+    /// # let download = |version: &str, conf: &str| {
+    /// #     download_npf(&config, &lock_file_ownership, &format!("tests::sys-apps/hello#{}", version),
+    /// #         r#"config_files = ["/etc/hello.conf"]"#, &[], &[("etc/hello.conf", 0o644, conf)])
+    /// # };
+    /// # let v1 = download("1.0.0", "greeting = \"hello\"\n")?;
+    /// # let v2 = download("2.0.0", "greeting = \"hello\"\ncolor = true\n")?;
+    /// # let v3 = download("3.0.0", "greeting = \"hi\"\ncolor = true\n")?;
+    /// let conf = root.join("etc/hello.conf");
+    /// let conf_new = root.join("etc/hello.conf.nest-new");
+    ///
+    /// InstallTransaction::from(v1.clone()).extract(&config, &lock_file_ownership, &mut NoopObserver)?;
+    ///
+    /// // The configuration wasn't modified, so it's overwritten
+    /// let mut recorder = Recorder::default();
+    /// UpgradeTransaction::from(v1, v2.clone()).perform(&config, &lock_file_ownership, &mut recorder)?;
+    /// assert_eq!(fs::read_to_string(&conf)?, "greeting = \"hello\"\ncolor = true\n");
+    /// assert!(!conf_new.exists());
+    /// assert!(recorder.0.is_empty());
+    ///
+    /// // The configuration was modified, so it's kept and the new one is written next to it
+    /// fs::write(&conf, "greeting = \"howdy\"\n")?;
+    /// let mut recorder = Recorder::default();
+    /// UpgradeTransaction::from(v2, v3.clone()).perform(&config, &lock_file_ownership, &mut recorder)?;
+    /// assert_eq!(fs::read_to_string(&conf)?, "greeting = \"howdy\"\n");
+    /// assert_eq!(fs::read_to_string(&conf_new)?, "greeting = \"hi\"\ncolor = true\n");
+    /// assert_eq!(recorder.0.len(), 1);
+    /// assert_eq!(recorder.0[0].0, LogLevel::Warn);
+    ///
+    /// // The new version belongs to the package, so it doesn't outlive it
+    /// RemoveTransaction::from(v3).perform(&config, &lock_file_ownership, &mut NoopObserver)?;
+    /// assert!(!conf_new.exists());
+    /// # fs::remove_dir_all(&root)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn perform(
+        &self,
+        config: &Config,
+        lock_ownership: &LockFileOwnership,
+        observer: &mut dyn TransactionObserver,
+    ) -> Result<(), Error> {
+        observe(observer, Transaction::Upgrade(self.clone()), |observer| {
+            self.replace(config, lock_ownership, observer)
+        })
+    }
 }
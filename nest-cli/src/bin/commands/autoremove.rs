@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+
+use clap::ArgMatches;
+use failure::Error;
+use libnest::cache::depgraph::{DependencyGraphDiff, RequirementManagementMethod};
+use libnest::config::Config;
+
+use super::{ask_confirmation, print_transactions, process_transactions};
+
+pub fn autoremove(config: &Config, _: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    let mut graph = config.dependency_graph(&lock_file_ownership)?;
+    let original_graph = graph.clone();
+
+    let orphans: HashSet<_> = graph.orphans().into_iter().collect();
+
+    // Remove the automatic requirements of the root group that are fulfilled by an orphan,
+    // the orphans are then removed when solving the graph
+    let root_node = graph.nodes().get(&graph.root_id()).unwrap().clone();
+    for req_id in root_node.requirements() {
+        let req = graph.requirements().get(req_id).unwrap();
+        let fulfilled_by_orphan = req
+            .fulfilling_node_id()
+            .and_then(|node_id| graph.nodes()[&node_id].kind().package())
+            .map_or(false, |id| orphans.contains(id));
+
+        if req.management_method() == RequirementManagementMethod::Auto && fulfilled_by_orphan {
+            graph.remove_requirement(*req_id);
+        }
+    }
+
+    graph.solve(&config)?;
+
+    let transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
+
+    if transactions.is_empty() {
+        println!("No orphan packages to remove, quitting.");
+        return Ok(());
+    }
+
+    print_transactions(config, &transactions);
+
+    if !ask_confirmation(
+        format!(
+            "Would you like to apply th{} transaction{}?",
+            if transactions.len() <= 1 { "is" } else { "ese" },
+            if transactions.len() <= 1 { "" } else { "s" },
+        )
+        .as_str(),
+        true,
+    )? {
+        println!(
+            "Transaction{} cancelled.",
+            if transactions.len() <= 1 { "" } else { "s" }
+        );
+        return Ok(());
+    }
+
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use failure::{format_err, Error, ResultExt};
+use libnest::config::Config;
+
+pub fn check(config: &Config) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    config
+        .dependency_graph(&lock_file_ownership)?
+        .check_invariants()
+        .with_context(|_| format_err!("the dependency graph is inconsistent"))?;
+    config
+        .scratch_dependency_graph(&lock_file_ownership)?
+        .check_invariants()
+        .with_context(|_| format_err!("the scratch dependency graph is inconsistent"))?;
+
+    println!("The dependency graphs are consistent.");
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use clap::ArgMatches;
+use colored::*;
+use failure::Error;
+use libnest::cache::available::AvailablePackagesCacheQueryStrategy;
+use libnest::cache::depgraph::{NodeID, RequirementKind, RequirementManagementMethod};
+use libnest::config::Config;
+use libnest::package::{HardPackageRequirement, PackageID, SoftPackageRequirement};
+use libnest::transaction::PackageDownload;
+
+use super::operations::download::{download_hash, download_package_verified, HostLimiter};
+use super::theme::{Element, Theme};
+
+pub fn download(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    // The graph is only used to resolve the requirements: it's never saved
+    let mut graph = config.dependency_graph(&lock_file_ownership)?;
+
+    let mut requirement_ids = Vec::new();
+    {
+        let packages_cache = config.available_packages_cache(&lock_file_ownership);
+
+        for target in &matches.values_of_lossy("PACKAGE").unwrap() {
+            let requirement = SoftPackageRequirement::parse(&target)?;
+
+            let matched_packages = packages_cache
+                .query(&requirement)
+                .set_strategy(AvailablePackagesCacheQueryStrategy::BestMatch)
+                .perform_and_sort_by_preference(config)?;
+            if matched_packages.is_empty() {
+                return Err(packages_cache.no_package_found(&requirement).into());
+            }
+            let matched_package = &matched_packages[0];
+
+            let package_req = HardPackageRequirement::from(
+                matched_package.full_name(),
+                requirement.version_requirement().clone(),
+            );
+            requirement_ids.push(graph.node_add_requirement(
+                graph.root_id(),
+                RequirementKind::Package {
+                    package_req: package_req.into(),
+                },
+                RequirementManagementMethod::Static,
+            ));
+        }
+    }
+
+    graph.solve(&config)?;
+
+    // Walk the solved graph to find the requested packages and all their dependencies
+    let mut visited: HashSet<NodeID> = HashSet::new();
+    let mut to_visit: Vec<NodeID> = requirement_ids
+        .iter()
+        .filter_map(|id| *graph.requirements()[id].fulfilling_node_id())
+        .collect();
+    let mut packages: Vec<PackageID> = Vec::new();
+    while let Some(node_id) = to_visit.pop() {
+        if !visited.insert(node_id) {
+            continue;
+        }
+        let node = &graph.nodes()[&node_id];
+        if let Some(id) = node.kind().package() {
+            packages.push(id.clone());
+        }
+        to_visit.extend(
+            node.requirements()
+                .iter()
+                .filter_map(|id| *graph.requirements()[id].fulfilling_node_id()),
+        );
+    }
+    packages.sort();
+
+    let theme = Theme::load(config);
+    let downloaded_cache = config.downloaded_packages_cache(&lock_file_ownership);
+    let host_limiter = HostLimiter::from(config);
+
+    for package in packages {
+        let package_download = PackageDownload::from(package);
+        let hash = download_hash(config, package_download.target(), &host_limiter)?;
+
+        let status = if downloaded_cache.has_package_matching_hash(
+            package_download.target(),
+            hash.hash(),
+            hash.algorithm(),
+        )? {
+            theme.paint(Element::Install, "cached")
+        } else {
+            download_package_verified(
+                config,
+                &package_download,
+                &hash,
+                &host_limiter,
+                &lock_file_ownership,
+            )?;
+            theme.paint(Element::Pull, "download")
+        };
+        println!(
+            "{}",
+            format!("{:>10.10} {}", status, package_download.target()).bold()
+        );
+    }
+
+    Ok(())
+}
@@ -60,6 +60,38 @@ pub fn group_remove(config: &Config, matches: &ArgMatches) -> Result<(), Error>
     Ok(())
 }
 
+pub fn group_rename(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let old = GroupName::from_str(matches.value_of("OLD").unwrap())?;
+    let new = GroupName::from_str(matches.value_of("NEW").unwrap())?;
+
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    let mut graph = config.scratch_dependency_graph(&lock_file_ownership)?;
+
+    println!("Renaming group {} to {}...", *old, *new);
+    graph.rename_group(&old, &new)?;
+
+    graph.save_to_cache(config.paths().scratch_depgraph(), &lock_file_ownership)?;
+
+    println!("Successfully renamed the group.");
+
+    Ok(())
+}
+
+pub fn group_show(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let group = GroupName::from_str(matches.value_of("GROUP").unwrap())?;
+
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    let graph = config.scratch_dependency_graph(&lock_file_ownership)?;
+
+    for member in graph.group_members(&group)? {
+        println!("{}", member);
+    }
+
+    Ok(())
+}
+
 pub fn group_list(config: &Config) -> Result<(), Error> {
     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
 
@@ -0,0 +1,67 @@
+use clap::ArgMatches;
+use failure::{format_err, Error};
+use libnest::config::Config;
+use libnest::package::{PackageFullName, SoftPackageRequirement};
+
+/// Returns the full names of the installed packages matching the given targets
+pub(super) fn installed_packages_matching(
+    packages: &[PackageFullName],
+    targets: &[String],
+) -> Result<Vec<PackageFullName>, Error> {
+    let mut full_names = Vec::new();
+
+    for target in targets {
+        let requirement = SoftPackageRequirement::parse(&target)?.any_version();
+        let full_name = packages
+            .iter()
+            .find(|full_name| {
+                requirement
+                    .repository()
+                    .as_ref()
+                    .map_or(true, |repository| repository == full_name.repository())
+                    && requirement
+                        .category()
+                        .as_ref()
+                        .map_or(true, |category| category == full_name.category())
+                    && requirement.name() == full_name.name()
+            })
+            .ok_or_else(|| {
+                format_err!("unable to find an installed package matching '{}'", &target)
+            })?;
+        full_names.push(full_name.clone());
+    }
+    Ok(full_names)
+}
+
+pub fn hold(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let mut graph = config.dependency_graph(&lock_file_ownership)?;
+
+    let targets = matches.values_of_lossy("PACKAGE").unwrap();
+    let packages: Vec<_> = graph.packages().cloned().collect();
+    for full_name in installed_packages_matching(&packages, &targets)? {
+        graph.hold(&full_name)?;
+        println!("Holding {}", full_name);
+    }
+
+    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    Ok(())
+}
+
+pub fn unhold(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let mut graph = config.dependency_graph(&lock_file_ownership)?;
+
+    let targets = matches.values_of_lossy("PACKAGE").unwrap();
+    let packages: Vec<_> = graph.packages().cloned().collect();
+    for full_name in installed_packages_matching(&packages, &targets)? {
+        if graph.unhold(&full_name) {
+            println!("Releasing {}", full_name);
+        } else {
+            println!("{} isn't held", full_name);
+        }
+    }
+
+    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    Ok(())
+}
@@ -1,24 +1,48 @@
+use std::path::Path;
+
 use clap::ArgMatches;
-use failure::{format_err, Error};
+use failure::{format_err, Error, ResultExt};
 use libnest::cache::available::AvailablePackagesCacheQueryStrategy;
-use libnest::cache::depgraph::{DependencyGraphDiff, RequirementKind, RequirementManagementMethod};
+use libnest::cache::depgraph::{DependencyGraphDiff, GroupName, RequirementManagementMethod};
 use libnest::config::Config;
 use libnest::package::{HardPackageRequirement, SoftPackageRequirement};
+use libnest::transaction::Transaction;
 
 use super::{
     ask_confirmation, download_required_packages, print_transactions, process_transactions,
+    warn_about_stale_repositories,
 };
 
 pub fn install(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let mut config = config.clone();
+    if matches.is_present("with-optional") {
+        *config.install_mut().optional_dependencies_mut() = true;
+    }
+    let config = &config;
+
     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    warn_about_stale_repositories(config, &lock_file_ownership);
 
     let mut graph = config.dependency_graph(&lock_file_ownership)?;
     let original_graph = graph.clone();
 
+    // Local NPFs are required directly, while the other targets are looked up in the repositories
+    let (local_npfs, targets): (Vec<_>, Vec<_>) = matches
+        .values_of_lossy("PACKAGE")
+        .unwrap()
+        .into_iter()
+        .partition(|target| target.ends_with(".nest") && Path::new(target).is_file());
+    for npf in &local_npfs {
+        config
+            .require_local_package(Path::new(npf), &mut graph, &lock_file_ownership)
+            .with_context(|_| format_err!("unable to install {}", npf))?;
+    }
+
+    let mut requirements = Vec::new();
     {
         let packages_cache = config.available_packages_cache(&lock_file_ownership);
 
-        for target in &matches.values_of_lossy("PACKAGE").unwrap() {
+        for target in &targets {
             let requirement = SoftPackageRequirement::parse(&target)?;
 
             let matched_packages = packages_cache
@@ -26,10 +50,7 @@ pub fn install(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
                 .set_strategy(AvailablePackagesCacheQueryStrategy::BestMatch)
                 .perform_and_sort_by_preference(config)?;
             if matched_packages.is_empty() {
-                return Err(format_err!(
-                    "no package found for requirement '{}'",
-                    &target
-                ));
+                return Err(packages_cache.no_package_found(&requirement).into());
             }
             let matched_package = &matched_packages[0];
 
@@ -37,19 +58,22 @@ pub fn install(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
                 matched_package.full_name(),
                 requirement.version_requirement().clone(),
             );
-            graph.node_add_requirement(
-                graph.root_id(),
-                RequirementKind::Package {
-                    package_req: package_req.into(),
-                },
-                RequirementManagementMethod::Static,
-            );
+            requirements.push((GroupName::root_group(), package_req.into()));
         }
     }
 
-    graph.solve(&config)?;
+    // All the packages are solved together, so that the conflicts between them are found at once
+    graph.add_requirements(&config, requirements, RequirementManagementMethod::Static)?;
 
-    let transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
+    let mut transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
+
+    if matches.is_present("force-overwrite") {
+        for transaction in &mut transactions {
+            if let Transaction::Install(install) = transaction {
+                *install.force_overwrite_mut() = true;
+            }
+        }
+    }
 
     if transactions.is_empty() {
         println!("No transactions are required, quitting.");
@@ -57,7 +81,7 @@ pub fn install(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
         return Ok(());
     }
 
-    print_transactions(&transactions);
+    print_transactions(config, &transactions);
 
     if !ask_confirmation(
         format!(
@@ -77,9 +101,7 @@ pub fn install(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
 
     download_required_packages(config, &transactions, &lock_file_ownership)?;
 
-    process_transactions(config, &transactions, &lock_file_ownership)?;
-
-    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
 
     Ok(())
 }
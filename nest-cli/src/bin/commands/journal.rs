@@ -0,0 +1,80 @@
+use colored::*;
+use failure::Error;
+use libnest::config::Config;
+use libnest::transaction::{TransactionError, TransactionErrorKind};
+
+use super::theme::{Element, Theme};
+use super::{
+    ask_confirmation, download_required_packages, print_transactions, process_journal,
+    process_transactions,
+};
+
+/// Offers to resume or roll back the transactions of a previous run that was interrupted, if any
+pub fn recover_journal(config: &Config) -> Result<(), Error> {
+    if !config.paths().journal().exists() {
+        return Ok(());
+    }
+
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+
+    let theme = Theme::load(config);
+    let journal = match config.load_journal(&lock_file_ownership) {
+        Ok(Some(journal)) => journal,
+        Ok(None) => return Ok(()),
+        Err(e) => match e.downcast_ref::<TransactionError>().map(|e| e.kind()) {
+            Some(TransactionErrorKind::UnparsableJournal { set_aside }) => {
+                println!(
+                    "{}",
+                    theme
+                        .paint(
+                            Element::Warning,
+                            &format!(
+                                "The journal of a previous run of nest couldn't be parsed and was moved to {}.",
+                                set_aside.display()
+                            )
+                        )
+                        .bold()
+                );
+                println!();
+                return Ok(());
+            }
+            _ => return Err(e),
+        },
+    };
+
+    println!(
+        "{}",
+        theme
+            .paint(
+                Element::Warning,
+                "A previous run of nest was interrupted before all its transactions were completed."
+            )
+            .bold()
+    );
+    println!();
+
+    let pending = journal.pending();
+    print_transactions(config, &pending);
+
+    if ask_confirmation(
+        "Would you like to resume them? Otherwise, the completed ones will be rolled back",
+        true,
+    )? {
+        download_required_packages(config, &pending, &lock_file_ownership)?;
+        process_journal(config, journal, &lock_file_ownership)?;
+        println!("Interrupted transactions resumed successfully.");
+    } else {
+        let rollback = journal.rollback_transactions();
+        let graph = config.dependency_graph(&lock_file_ownership)?;
+
+        if !rollback.is_empty() {
+            print_transactions(config, &rollback);
+            println!();
+        }
+        download_required_packages(config, &rollback, &lock_file_ownership)?;
+        process_transactions(config, &rollback, &graph, &lock_file_ownership)?;
+        println!("Interrupted transactions rolled back successfully.");
+    }
+    println!();
+    Ok(())
+}
@@ -5,7 +5,7 @@ use libnest::cache::depgraph::{NodeKind, RequirementManagementMethod};
 use libnest::config::Config;
 
 pub fn list(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
-    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let lock_file_ownership = config.acquire_lock_file_ownership_shared(true)?;
     let depgraph = config.dependency_graph(&lock_file_ownership)?;
 
     let mut packages = Vec::new();
@@ -23,7 +23,7 @@ pub fn list(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
                     .get(&req.fulfilling_node_id().unwrap())
                     .unwrap();
 
-                if let NodeKind::Package { id } = node.kind() {
+                if let NodeKind::Package { id, .. } = node.kind() {
                     packages.push(format!(
                         "{}::{}/{}",
                         id.repository(),
@@ -3,7 +3,8 @@ use libnest::cache::depgraph::DependencyGraphDiff;
 use libnest::config::Config;
 
 use super::{
-    ask_confirmation, download_required_packages, print_transactions, process_transactions,
+    ask_confirmation, download_required_packages, print_changelogs, print_transactions,
+    process_transactions,
 };
 
 pub fn merge(config: &Config) -> Result<(), Error> {
@@ -22,7 +23,8 @@ pub fn merge(config: &Config) -> Result<(), Error> {
         return Ok(());
     }
 
-    print_transactions(&transactions);
+    print_transactions(config, &transactions);
+    print_changelogs(config, &transactions, &lock_file_ownership)?;
 
     if !ask_confirmation(
         format!(
@@ -42,9 +44,7 @@ pub fn merge(config: &Config) -> Result<(), Error> {
 
     download_required_packages(config, &transactions, &lock_file_ownership)?;
 
-    process_transactions(config, &transactions, &lock_file_ownership)?;
-
-    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
 
     Ok(())
 }
@@ -0,0 +1,150 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use failure::{format_err, Error, ResultExt};
+use libnest::cache::depgraph::DependencyGraphDiff;
+use libnest::config::Config;
+use libnest::lock_file::LockFileOwnership;
+use libnest::transaction::Transaction;
+
+/// Returns the total size, in bytes, of the files contained in the given directory.
+///
+/// A missing directory is considered empty.
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut size = 0;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += directory_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Returns the timestamp of the last successful pull of each repository, or 0 for those that were never pulled
+fn last_pull_timestamps(
+    config: &Config,
+    lock_file_ownership: &LockFileOwnership,
+) -> Vec<(String, u64)> {
+    config
+        .repositories()
+        .into_iter()
+        .map(|repository| {
+            let timestamp = repository
+                .cache_age(config, lock_file_ownership)
+                .and_then(|age| SystemTime::now().checked_sub(age))
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            (
+                format!("{{repository=\"{}\"}}", repository.name()),
+                timestamp,
+            )
+        })
+        .collect()
+}
+
+fn write_metric(output: &mut String, name: &str, help: &str, samples: &[(&str, u64)]) {
+    // Writing to a string can't fail
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} gauge", name);
+    for (labels, value) in samples {
+        let _ = writeln!(output, "{}{} {}", name, labels, value);
+    }
+}
+
+/// Gathers metrics about the current state of Nest, in the Prometheus text format
+fn gather_metrics(config: &Config) -> Result<String, Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let graph = config.dependency_graph(&lock_file_ownership)?;
+
+    let installed = graph.packages().count() as u64;
+
+    let mut updated_graph = graph.clone();
+    updated_graph.update(config)?;
+    let pending_upgrades = DependencyGraphDiff::new()
+        .perform(&graph, &updated_graph)
+        .iter()
+        .filter(|transaction| match transaction {
+            Transaction::Upgrade(_) => true,
+            _ => false,
+        })
+        .count() as u64;
+
+    let last_pull_timestamps = last_pull_timestamps(config, &lock_file_ownership);
+    let available_bytes = directory_size(config.paths().available())
+        .with_context(|_| config.paths().available().display().to_string())?;
+    let downloaded_bytes = directory_size(config.paths().downloaded())
+        .with_context(|_| config.paths().downloaded().display().to_string())?;
+
+    let mut output = String::new();
+    write_metric(
+        &mut output,
+        "nest_packages_installed",
+        "Number of installed packages",
+        &[("", installed)],
+    );
+    write_metric(
+        &mut output,
+        "nest_last_pull_timestamp",
+        "Unix timestamp of the last successful pull of each repository",
+        &last_pull_timestamps
+            .iter()
+            .map(|(labels, timestamp)| (labels.as_str(), *timestamp))
+            .collect::<Vec<_>>(),
+    );
+    write_metric(
+        &mut output,
+        "nest_cache_bytes",
+        "Size of Nest's caches, in bytes",
+        &[
+            ("{cache=\"available\"}", available_bytes),
+            ("{cache=\"downloaded\"}", downloaded_bytes),
+        ],
+    );
+    write_metric(
+        &mut output,
+        "nest_pending_upgrades",
+        "Number of installed packages that can be upgraded",
+        &[("", pending_upgrades)],
+    );
+    Ok(output)
+}
+
+/// Writes the metrics to the given file.
+///
+/// The metrics are written to a temporary file first, which is then renamed to the target path so
+/// that readers never see a partially written file.
+pub fn metrics(config: &Config, path: &str) -> Result<(), Error> {
+    let path = Path::new(path);
+    let output = gather_metrics(config)?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format_err!("invalid metrics file path '{}'", path.display()))?;
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let res: Result<(), io::Error> = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(output.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res.with_context(|_| path.display().to_string())?;
+    Ok(())
+}
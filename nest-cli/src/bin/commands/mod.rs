@@ -1,37 +1,67 @@
+mod autoremove;
+mod check;
+mod download;
 mod group;
+mod hold;
 mod install;
+mod journal;
 mod list;
 mod merge;
+mod metrics;
 pub mod operations;
 mod pull;
 mod reinstall;
 mod requirement;
+mod search;
+mod selection;
+mod stats;
+pub mod theme;
 mod uninstall;
 mod upgrade;
+mod verify;
 
-pub use self::group::{group_add, group_list, group_remove};
+pub use self::autoremove::autoremove;
+pub use self::check::check;
+pub use self::download::download;
+pub use self::group::{group_add, group_list, group_remove, group_rename, group_show};
+pub use self::hold::{hold, unhold};
 pub use self::install::install;
+pub use self::journal::recover_journal;
 pub use self::list::list;
 pub use self::merge::merge;
+pub use self::metrics::metrics;
+use self::operations::downgrade::downgrade_package;
 use self::operations::download::{download_hashes, download_packages};
 use self::operations::install::install_package;
+use self::operations::reinstall::reinstall_package;
 use self::operations::uninstall::uninstall_package;
 use self::operations::upgrade::upgrade_package;
-pub use self::pull::pull;
+pub use self::pull::{load_mirror_lists, pull};
 pub use self::reinstall::reinstall;
 pub use self::requirement::{requirement_add, requirement_remove};
+pub use self::search::search;
+pub use self::selection::{selection_export, selection_import};
+pub use self::stats::stats;
 pub use self::uninstall::uninstall;
 pub use self::upgrade::upgrade;
+pub use self::verify::verify;
 
 use colored::*;
 use failure::{Error, ResultExt};
+use indicatif::BinaryBytes;
 use std::io::{self, Write};
 
-use libnest::config::Config;
-use libnest::lock_file::LockFileOwnership;
-use libnest::transaction::Transaction;
+use libnest::cache::depgraph::DependencyGraph;
+use libnest::cache::QueryError;
+use libnest::config::{Config, LOCAL_REPOSITORY};
+use libnest::lock_file::{LockFileOwnership, LockFileReadOwnership};
+use libnest::transaction::{check_available_space, Journal, PackageDownload, Transaction};
+
+use self::theme::{Element, Theme};
+
+pub fn print_transactions(config: &Config, transactions: &[Transaction]) {
+    let theme = Theme::load(config);
 
-pub fn print_transactions(transactions: &[Transaction]) {
     println!(
         "{}",
         format!(
@@ -43,22 +73,101 @@ pub fn print_transactions(transactions: &[Transaction]) {
     );
     println!();
     for transaction in transactions {
+        let element = match transaction {
+            Transaction::Pull(_) => Element::Pull,
+            Transaction::Install(_) | Transaction::Reinstall(_) => Element::Install,
+            Transaction::Remove(_) => Element::Remove,
+            Transaction::Upgrade(_) => Element::Upgrade,
+            Transaction::Downgrade(_) => Element::Downgrade,
+        };
         println!(
             "{}",
-            match transaction {
-                Transaction::Pull(p) => {
-                    format!("{:>10.10} {}", "pull".cyan(), p.target_repository().name()).bold()
-                }
-                Transaction::Install(i) => {
-                    format!("{:>10.10} {}", "install".green(), i.target()).bold()
-                }
-                Transaction::Remove(r) =>
-                    format!("{:>10.10} {}", "remove".red(), r.target()).bold(),
-                Transaction::Upgrade(u) => {
-                    format!("{:>10.10} {}", "upgrade".yellow(), u.new_target()).bold()
+            format!(
+                "{:>10.10} {}",
+                theme.paint(element, transaction.action()),
+                transaction.subject()
+            )
+            .bold()
+        );
+    }
+}
+
+/// Prints the changelog entries of the versions crossed by the upgrades among the given transactions
+pub fn print_changelogs(
+    config: &Config,
+    transactions: &[Transaction],
+    lock_file_ownership: &LockFileOwnership,
+) -> Result<(), Error> {
+    for transaction in transactions {
+        if let Transaction::Upgrade(upgrade) = transaction {
+            let entries = upgrade.changelog(config, lock_file_ownership)?;
+            if entries.is_empty() {
+                continue;
+            }
+
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "Changes from {} to {}:",
+                    upgrade.old_target(),
+                    upgrade.new_target().version()
+                )
+                .bold()
+            );
+            for (version, changelog) in entries {
+                println!("  {}", version.to_string().bold());
+                for line in changelog.lines() {
+                    println!("    {}", line);
                 }
             }
+        }
+    }
+    Ok(())
+}
+
+/// Warns about the repositories that weren't pulled for longer than the maximum age of the cache
+pub fn warn_about_stale_repositories<L: LockFileReadOwnership>(
+    config: &Config,
+    lock_file_ownership: &L,
+) {
+    let stale = config.stale_repositories(lock_file_ownership);
+
+    if !stale.is_empty() {
+        let theme = Theme::load(config);
+        let names: Vec<_> = stale.iter().map(|name| name.as_str()).collect();
+        println!(
+            "{}",
+            theme
+                .paint(
+                    Element::Warning,
+                    format!(
+                        "The cache of {} is outdated, consider running `nest pull` first.",
+                        names.join(", ")
+                    )
+                    .as_str()
+                )
+                .bold()
         );
+        println!();
+    }
+}
+
+/// Prints the given error and its causes, followed by the advice of the first of them giving one
+pub fn print_error(e: &Error) {
+    let fail = e.as_fail();
+    eprint!("error: {}", fail);
+    for cause in fail.iter_causes() {
+        eprint!(": {}", cause);
+    }
+    eprintln!();
+
+    let advice = fail
+        .iter_chain()
+        .filter_map(|cause| cause.downcast_ref::<QueryError>())
+        .find_map(QueryError::advice);
+    if let Some(advice) = advice {
+        eprintln!("hint: {}", advice);
     }
 }
 
@@ -88,20 +197,58 @@ pub fn ask_confirmation(question: &str, default: bool) -> Result<bool, Error> {
 pub fn process_transactions(
     config: &Config,
     transactions: &[Transaction],
+    graph: &DependencyGraph,
     lock_file_ownership: &LockFileOwnership,
 ) -> Result<(), Error> {
-    for transaction in transactions.iter() {
+    process_journal(
+        config,
+        Journal::new(transactions, graph.clone()),
+        lock_file_ownership,
+    )
+}
+
+/// Processes the pending transactions of the given journal, saving it after each of them,
+/// and then saves the dependency graph they lead to
+pub fn process_journal(
+    config: &Config,
+    mut journal: Journal,
+    lock_file_ownership: &LockFileOwnership,
+) -> Result<(), Error> {
+    config
+        .installed_packages_cache(lock_file_ownership)
+        .migrate_legacy_logs()
+        .context("unable to migrate the logs of installed packages")?;
+
+    config.write_journal(&journal, lock_file_ownership)?;
+
+    for transaction in journal.pending() {
         match transaction {
             Transaction::Install(install) => {
-                install_package(config, install, &lock_file_ownership)?
+                install_package(config, &install, &lock_file_ownership)?
             }
             Transaction::Upgrade(upgrade) => {
-                upgrade_package(config, upgrade, &lock_file_ownership)?
+                upgrade_package(config, &upgrade, &lock_file_ownership)?
+            }
+            Transaction::Downgrade(downgrade) => {
+                downgrade_package(config, &downgrade, &lock_file_ownership)?
+            }
+            Transaction::Reinstall(reinstall) => {
+                reinstall_package(config, &reinstall, &lock_file_ownership)?
+            }
+            Transaction::Remove(remove) => {
+                uninstall_package(config, &remove, &lock_file_ownership)?
             }
-            Transaction::Remove(remove) => uninstall_package(config, remove, &lock_file_ownership)?,
             _ => unimplemented!(),
         };
+
+        journal.mark_completed();
+        config.write_journal(&journal, lock_file_ownership)?;
     }
+
+    journal
+        .graph()
+        .save_to_cache(config.paths().depgraph(), lock_file_ownership)?;
+    config.clear_journal(lock_file_ownership)?;
     Ok(())
 }
 
@@ -110,6 +257,14 @@ pub fn download_required_packages(
     transactions: &[Transaction],
     lock_file_ownership: &LockFileOwnership,
 ) -> Result<(), Error> {
+    check_available_space(
+        config,
+        lock_file_ownership,
+        transactions,
+        config.paths().downloaded(),
+        config.paths().root(),
+    )?;
+
     println!("Checking for packages to download...");
 
     let downloaded_cache = config.downloaded_packages_cache(lock_file_ownership);
@@ -117,6 +272,8 @@ pub fn download_required_packages(
     let downloads = transactions.iter().filter_map(|trans| match trans {
         Transaction::Install(install) => Some(install.associated_download()),
         Transaction::Upgrade(upgrade) => Some(upgrade.associated_download()),
+        Transaction::Downgrade(downgrade) => Some(downgrade.associated_download()),
+        Transaction::Reinstall(reinstall) => Some(reinstall.associated_download()),
         _ => None,
     });
 
@@ -125,9 +282,12 @@ pub fn download_required_packages(
         .clone()
         .filter(|download| !downloaded_cache.has_package(download.target()));
 
-    // List the packages that are already in the cache
-    let already_downloaded =
-        downloads.filter(|download| downloaded_cache.has_package(download.target()));
+    // List the packages that are already in the cache. Packages installed from a local NPF have no server to
+    // compare their hash with, so they are used as-is
+    let already_downloaded = downloads.filter(|download| {
+        downloaded_cache.has_package(download.target())
+            && &**download.target().repository() != LOCAL_REPOSITORY
+    });
 
     // Retrieve (download, server-issued hash) pairs for packages that are in the cache
     let downloads_with_hashes = download_hashes(config, already_downloaded)?;
@@ -136,7 +296,7 @@ pub fn download_required_packages(
     let downloads_with_validities = downloads_with_hashes
         .map(|(download, hash)| {
             downloaded_cache
-                .has_package_matching_hash(download.target(), &hash)
+                .has_package_matching_hash(download.target(), hash.hash(), hash.algorithm())
                 .map(|valid| (download, valid))
         })
         .collect::<Result<Vec<_>, _>>()?;
@@ -169,6 +329,26 @@ pub fn download_required_packages(
         }
         println!();
 
+        let total_size = PackageDownload::total_size(
+            &to_download.clone().collect::<Vec<_>>(),
+            &config.available_packages_cache(lock_file_ownership),
+        )?;
+        if total_size.unknown().is_empty() {
+            println!("Total download size: {}", BinaryBytes(total_size.known()));
+        } else {
+            println!(
+                "Total download size: {} (unknown for {} package{})",
+                BinaryBytes(total_size.known()),
+                total_size.unknown().len(),
+                if total_size.unknown().len() <= 1 {
+                    ""
+                } else {
+                    "s"
+                },
+            );
+        }
+        println!();
+
         println!("Downloading packages...");
         download_packages(config, to_download)
     } else {
@@ -0,0 +1,35 @@
+use failure::{format_err, Error, ResultExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use libnest::config::Config;
+use libnest::lock_file::LockFileOwnership;
+
+use libnest::transaction::DowngradeTransaction;
+
+use super::ProgressBarObserver;
+
+pub fn downgrade_package(
+    config: &Config,
+    trans: &DowngradeTransaction,
+    ownership: &LockFileOwnership,
+) -> Result<(), Error> {
+    let progress_bar = ProgressBar::new(80);
+    progress_bar.set_style(ProgressStyle::default_bar().template("[{pos:>3}/{len:3}] {bar:80}"));
+
+    // Downgrade the package
+    progress_bar.println(format!(
+        "Downgrading {} to {}...",
+        trans.old_target(),
+        trans.new_target()
+    ));
+    trans
+        .perform(
+            config,
+            ownership,
+            &mut ProgressBarObserver::from(&progress_bar),
+        )
+        .with_context(|_| format_err!("unable to extract package"))?;
+
+    progress_bar.finish_and_clear();
+    println!("Successfully downgraded to {}", trans.new_target());
+    Ok(())
+}
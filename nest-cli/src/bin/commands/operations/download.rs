@@ -1,24 +1,266 @@
-use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::iter::Iterator;
+use std::path::Path;
+use std::str;
 use std::sync::mpsc::channel;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
-use curl::easy::Easy;
+use curl::easy::{Easy, List, NetRc};
 use failure::{format_err, Error, ResultExt};
-use libnest::config::{Config, MirrorUrl};
-use libnest::package::PackageID;
-use libnest::transaction::PackageDownload;
+use libnest::cache::available::{PullValidator, PullValidators};
+use libnest::config::{Config, Credentials, MirrorUrl, NetworkConfig, UiConfig};
+use libnest::lock_file::LockFileOwnership;
+use libnest::package::{HashAlgorithm, PackageID};
+use libnest::transaction::{try_mirrors, PackageDownload, PullOutcome};
 use serde_derive::{Deserialize, Serialize};
 use threadpool::ThreadPool;
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+use super::LogPrinter;
+
+/// Limits the number of concurrent transfers targeting the same host.
+///
+/// It's shared by all the threads performing downloads, so that a mirror isn't hammered with
+/// more connections than allowed by the configuration, whatever the number of threads.
+#[derive(Debug)]
+pub struct HostLimiter {
+    max_connections_per_host: Option<usize>,
+    connections: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+}
+
+impl HostLimiter {
+    /// Creates a limiter enforcing the maximum number of connections per host of the given configuration
+    pub fn from(config: &Config) -> Self {
+        HostLimiter {
+            // A limit of 0 would block all transfers forever
+            max_connections_per_host: config
+                .network()
+                .max_connections_per_host()
+                .map(|n| n.max(1)),
+            connections: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until a new transfer can target the given host, and reserves a connection for it.
+    ///
+    /// The connection is released when the returned [`HostPermit`] is dropped.
+    pub fn acquire(&self, host: &str) -> HostPermit {
+        let mut connections = self
+            .connections
+            .lock()
+            .expect("the host limiter is poisoned");
+
+        if let Some(max) = self.max_connections_per_host {
+            while connections.get(host).cloned().unwrap_or(0) >= max {
+                connections = self
+                    .released
+                    .wait(connections)
+                    .expect("the host limiter is poisoned");
+            }
+        }
+        *connections.entry(host.to_string()).or_insert(0) += 1;
+
+        HostPermit {
+            limiter: self,
+            host: host.to_string(),
+        }
+    }
+}
+
+/// A connection to a host reserved through a [`HostLimiter`]
+#[derive(Debug)]
+pub struct HostPermit<'a> {
+    limiter: &'a HostLimiter,
+    host: String,
+}
+
+impl<'a> Drop for HostPermit<'a> {
+    fn drop(&mut self) {
+        let mut connections = self
+            .limiter
+            .connections
+            .lock()
+            .expect("the host limiter is poisoned");
+
+        if let Some(count) = connections.get_mut(&self.host) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.host);
+            }
+        }
+        self.limiter.released.notify_all();
+    }
+}
+
+/// A curl handle set up according to the network configuration of Nest.
+///
+/// Redirections are followed, HTTP errors fail the transfer, and the configured proxy, maximum download speed and
+/// timeouts apply. Without any proxy in the configuration, curl's own handling of the `<scheme>_proxy` environment
+/// variables applies.
+pub struct Handle {
+    easy: Easy,
+    max_download_speed: Option<u64>,
+    connect_timeout: Duration,
+    low_speed_limit: u32,
+    low_speed_time: Duration,
+}
+
+impl Handle {
+    /// Returns the underlying curl handle
+    pub fn into_inner(self) -> Easy {
+        self.easy
+    }
+
+    /// Returns the maximum download speed set on the handle, in bytes per second, if any
+    pub fn max_download_speed(&self) -> Option<u64> {
+        self.max_download_speed
+    }
+
+    /// Returns the timeout set on the handle for connecting to a mirror
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Returns the speed, in bytes per second, below which the transfers of the handle are considered stalled
+    pub fn low_speed_limit(&self) -> u32 {
+        self.low_speed_limit
+    }
+
+    /// Returns the time after which the stalled transfers of the handle are aborted
+    pub fn low_speed_time(&self) -> Duration {
+        self.low_speed_time
+    }
+}
+
+impl<'a> TryFrom<&'a NetworkConfig> for Handle {
+    type Error = Error;
+
+    fn try_from(network_config: &'a NetworkConfig) -> Result<Self, Self::Error> {
+        let mut curl = Easy::new();
+        curl.follow_location(true)?;
+        curl.fail_on_error(true)?;
+
+        if let Some(proxy) = network_config.proxy() {
+            curl.proxy(proxy.host())?;
+            if let Some(port) = proxy.port() {
+                curl.proxy_port(port)?;
+            }
+            if let Some(credentials) = proxy.credentials() {
+                curl.proxy_username(credentials.username())?;
+                if let Some(password) = credentials
+                    .password()
+                    .context("unable to read the password of the proxy")?
+                {
+                    curl.proxy_password(&password)?;
+                }
+            }
+        }
+
+        // The limit applies to each transfer on its own, not to all the concurrent transfers as a whole
+        let max_download_speed = network_config.max_download_speed();
+        if let Some(max_download_speed) = max_download_speed {
+            curl.max_recv_speed(max_download_speed)?;
+        }
+
+        // A hung mirror can't block the transfer forever
+        curl.connect_timeout(network_config.connect_timeout())?;
+        curl.low_speed_limit(network_config.low_speed_limit())?;
+        curl.low_speed_time(network_config.low_speed_time())?;
+
+        Ok(Handle {
+            easy: curl,
+            max_download_speed,
+            connect_timeout: network_config.connect_timeout(),
+            low_speed_limit: network_config.low_speed_limit(),
+            low_speed_time: network_config.low_speed_time(),
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a Config> for Handle {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(config: &'a Config) -> Result<Self, Self::Error> {
+        Handle::try_from(config.network())
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Download<'a> {
     target_route: &'a str,
+    credentials: Option<&'a Credentials>,
+    netrc: Option<&'a Path>,
+    host_limiter: Option<&'a HostLimiter>,
+    network_config: Option<&'a NetworkConfig>,
+    ui_config: Option<&'a UiConfig>,
 }
 
 impl<'a> Download<'a> {
     /// Creates a download from a given route
     pub fn from(target_route: &'a str) -> Self {
-        Download { target_route }
+        Download {
+            target_route,
+            credentials: None,
+            netrc: None,
+            host_limiter: None,
+            network_config: None,
+            ui_config: None,
+        }
+    }
+
+    /// Sets the credentials used to authenticate against the mirrors.
+    ///
+    /// Without credentials, those found for each mirror in Nest's [netrc file][1] or in the user's `~/.netrc` are
+    /// used.
+    ///
+    /// [1]: #method.with_netrc
+    pub fn with_credentials(mut self, credentials: Option<&'a Credentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Sets the path of Nest's netrc file, holding the credentials of the mirrors.
+    ///
+    /// Without it, only the user's `~/.netrc` is used.
+    pub fn with_netrc(mut self, netrc: Option<&'a Path>) -> Self {
+        self.netrc = netrc;
+        self
+    }
+
+    /// Sets the limiter used to restrict the number of concurrent transfers targeting each mirror's host
+    pub fn with_host_limiter(mut self, host_limiter: Option<&'a HostLimiter>) -> Self {
+        self.host_limiter = host_limiter;
+        self
+    }
+
+    /// Sets the network configuration, used to set up the curl handles of the download, see [`Handle`].
+    ///
+    /// Without it, the default configuration applies.
+    pub fn with_network_config(mut self, network_config: Option<&'a NetworkConfig>) -> Self {
+        self.network_config = network_config;
+        self
+    }
+
+    /// Sets the user interface configuration, used to decide which events, like a failing mirror, are printed.
+    ///
+    /// Without it, only warnings and errors are printed.
+    pub fn with_ui_config(mut self, ui_config: Option<&'a UiConfig>) -> Self {
+        self.ui_config = ui_config;
+        self
+    }
+
+    /// Returns the observer printing the events of the download
+    fn log_printer(&self) -> LogPrinter {
+        match self.ui_config {
+            Some(ui_config) => LogPrinter::from(ui_config),
+            None => LogPrinter::from(&UiConfig::default()),
+        }
     }
 
     /// Performs the download, using any of the specified mirrors
@@ -30,35 +272,234 @@ impl<'a> Download<'a> {
     where
         W: Write + Seek,
     {
-        let mut curl = Easy::new();
-        curl.follow_location(true)?;
-        curl.fail_on_error(true)?;
+        self.perform_with_mirrors_from(writer, mirrors, 0)
+    }
+
+    /// Sets up the authentication of the given handle for the given mirror
+    fn authenticate(&self, curl: &mut Easy, mirror: &MirrorUrl) -> Result<(), Error> {
+        let credentials = match self.credentials {
+            Some(credentials) => Some(credentials.clone()),
+            None => match (self.netrc, mirror.host_str()) {
+                (Some(netrc), Some(host)) => Credentials::from_netrc(netrc, host)
+                    .with_context(|_| format_err!("unable to read {}", netrc.display()))?,
+                _ => None,
+            },
+        };
+
+        match credentials {
+            Some(credentials) => {
+                curl.username(credentials.username())?;
+                if let Some(password) = credentials
+                    .password()
+                    .context("unable to read the password of the repository")?
+                {
+                    curl.password(&password)?;
+                }
+            }
+            None => curl.netrc(NetRc::Optional)?,
+        }
+        Ok(())
+    }
+
+    /// Performs the download starting at the given byte offset, using any of the specified mirrors.
+    ///
+    /// When a mirror fails midway, the next one resumes the download where it stopped.
+    pub fn perform_with_mirrors_from<W>(
+        &self,
+        writer: &mut W,
+        mirrors: &[MirrorUrl],
+        offset: u64,
+    ) -> Result<(), Error>
+    where
+        W: Write + Seek,
+    {
+        let mut position = offset;
+
+        try_mirrors(mirrors, &mut self.log_printer(), |mirror| {
+            match self.perform_with_mirror(writer, mirror, &mut position) {
+                Err(ref e)
+                    if position > 0
+                        && e.downcast_ref::<curl::Error>()
+                            .map_or(false, curl::Error::is_range_error) =>
+                {
+                    // The mirror can't resume the download, so it's performed again from scratch
+                    position = 0;
+                    self.perform_with_mirror(writer, mirror, &mut position)
+                }
+                res => res,
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Performs the download using any of the specified mirrors, sending the validator of the mirror's previous
+    /// response, if any, so it can answer that the data didn't change instead of sending it again.
+    ///
+    /// Returns the mirror that answered, the validator of its response, and whether data was received.
+    pub fn perform_conditional_with_mirrors<W>(
+        &self,
+        writer: &mut W,
+        mirrors: &[MirrorUrl],
+        validators: &PullValidators,
+    ) -> Result<(MirrorUrl, PullValidator, PullOutcome), Error>
+    where
+        W: Write + Seek,
+    {
+        let (mirror, (validator, outcome)) =
+            try_mirrors(mirrors, &mut self.log_printer(), |mirror| {
+                let mut position = 0;
+                self.perform_conditional_with_mirror(
+                    writer,
+                    mirror,
+                    &mut position,
+                    validators.get(mirror),
+                )
+            })?;
+        Ok((mirror.clone(), validator, outcome))
+    }
+
+    /// Performs the download using the given mirror, starting at the given position.
+    ///
+    /// The position is moved forward as data is received, even if the download fails.
+    fn perform_with_mirror<W>(
+        &self,
+        writer: &mut W,
+        mirror: &MirrorUrl,
+        position: &mut u64,
+    ) -> Result<(), Error>
+    where
+        W: Write + Seek,
+    {
+        self.perform_conditional_with_mirror(writer, mirror, position, None)
+            .map(|_| ())
+    }
+
+    /// Performs the download using the given mirror, starting at the given position, unless the data didn't change
+    /// since the response carrying the given validator.
+    ///
+    /// Returns the validator of the response, and whether data was received.
+    fn perform_conditional_with_mirror<W>(
+        &self,
+        writer: &mut W,
+        mirror: &MirrorUrl,
+        position: &mut u64,
+        validator: Option<&PullValidator>,
+    ) -> Result<(PullValidator, PullOutcome), Error>
+    where
+        W: Write + Seek,
+    {
+        // Local mirrors are read from the filesystem directly
+        if mirror.scheme() == "file" {
+            return self.perform_with_local_mirror(writer, mirror, position);
+        }
+
+        let default_config = NetworkConfig::default();
+        let network_config = self.network_config.unwrap_or(&default_config);
+
+        let mut curl = Handle::try_from(network_config)?.into_inner();
         curl.progress(true)?;
+        curl.resume_from(*position)?;
+        self.authenticate(&mut curl, mirror)?;
 
-        let succeeded = mirrors.iter().any(|mirror| {
-            let res: Result<_, Error> = try {
-                // Overwrite any data from a previous failed attempt
-                writer.seek(SeekFrom::Start(0))?;
+        // Overwrite any data received after the given position
+        writer.seek(SeekFrom::Start(*position))?;
 
-                let url = mirror.join(self.target_route)?;
-                curl.url(url.as_str())?;
+        let url = mirror.join(self.target_route)?;
+        curl.url(url.as_str())?;
 
-                let mut transfer = curl.transfer();
-                transfer.write_function(|data| Ok(writer.write(data).unwrap_or(0)))?;
-                transfer.perform()?;
-            };
-            res.is_ok()
-        });
+        let _permit = match (self.host_limiter, url.host_str()) {
+            (Some(host_limiter), Some(host)) => Some(host_limiter.acquire(host)),
+            _ => None,
+        };
 
-        if !succeeded {
-            Err(format_err!("no working mirror found"))
+        if let Some(validator) = validator {
+            let mut headers = List::new();
+            if let Some(etag) = validator.etag() {
+                headers.append(&format!("If-None-Match: {}", etag))?;
+            }
+            if let Some(last_modified) = validator.last_modified() {
+                headers.append(&format!("If-Modified-Since: {}", last_modified))?;
+            }
+            curl.http_headers(headers)?;
+        }
+
+        let mut etag = None;
+        let mut last_modified = None;
+        {
+            let mut transfer = curl.transfer();
+            transfer.header_function(|header| {
+                if let Ok(header) = str::from_utf8(header) {
+                    // Each response of a redirection starts with a status line, and only the last one matters
+                    if header.starts_with("HTTP/") {
+                        etag = None;
+                        last_modified = None;
+                    } else if let Some(colon) = header.find(':') {
+                        let (name, value) = (header[..colon].trim(), header[colon + 1..].trim());
+                        if name.eq_ignore_ascii_case("ETag") {
+                            etag = Some(value.to_string());
+                        } else if name.eq_ignore_ascii_case("Last-Modified") {
+                            last_modified = Some(value.to_string());
+                        }
+                    }
+                }
+                true
+            })?;
+            transfer.write_function(|data| {
+                let written = writer.write(data).unwrap_or(0);
+                *position += written as u64;
+                Ok(written)
+            })?;
+            transfer.perform()?;
+        }
+
+        let outcome = if curl.response_code()? == 304 {
+            PullOutcome::Unchanged
         } else {
-            Ok(())
+            PullOutcome::Updated
+        };
+        Ok((PullValidator::from(etag, last_modified), outcome))
+    }
+
+    /// Performs the download by reading the file of the given `file://` mirror, starting at the given position.
+    ///
+    /// Local mirrors are laid out like the routes of remote ones. As they don't send any validator, the data
+    /// they hold is always considered updated.
+    fn perform_with_local_mirror<W>(
+        &self,
+        writer: &mut W,
+        mirror: &MirrorUrl,
+        position: &mut u64,
+    ) -> Result<(PullValidator, PullOutcome), Error>
+    where
+        W: Write + Seek,
+    {
+        let url = mirror.join(self.target_route)?;
+        let path = url
+            .to_file_path()
+            .map_err(|_| format_err!("invalid local mirror: {}", url))?;
+        let mut file = File::open(&path).with_context(|_| path.display().to_string())?;
+
+        file.seek(SeekFrom::Start(*position))?;
+        writer.seek(SeekFrom::Start(*position))?;
+
+        let mut buffer = [0; 8192];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            *position += read as u64;
         }
+        Ok((PullValidator::from(None, None), PullOutcome::Updated))
     }
 }
 
-pub fn download_package(config: &Config, package_download: &PackageDownload) -> Result<(), Error> {
+pub fn download_package(
+    config: &Config,
+    package_download: &PackageDownload,
+    host_limiter: &HostLimiter,
+) -> Result<(), Error> {
     // Find the repository hosting the package
     let repo = config
         .repositories()
@@ -80,11 +521,16 @@ pub fn download_package(config: &Config, package_download: &PackageDownload) ->
     );
 
     // Download the package archive
-    let download = Download::from(&target_url);
+    let download = Download::from(&target_url)
+        .with_credentials(repo.config().credentials())
+        .with_netrc(Some(config.paths().netrc()))
+        .with_host_limiter(Some(host_limiter))
+        .with_network_config(Some(config.network()))
+        .with_ui_config(Some(config.ui()));
     download
         .perform_with_mirrors(
             &mut package_download.create_download_file(config)?,
-            &repo.config().mirrors(),
+            &repo.mirrors_by_latency(config.network().mirror_ping_timeout()),
         )
         .context(format_err!(
             "unable to download package from repository '{}'",
@@ -94,19 +540,105 @@ pub fn download_package(config: &Config, package_download: &PackageDownload) ->
     Ok(())
 }
 
+/// Downloads a package, resuming any previously interrupted download of it.
+///
+/// The downloaded archive is checked against the given hash: if it doesn't match (like when the
+/// partial file was corrupted or the server ignored the requested range), the package is downloaded
+/// again from scratch.
+pub fn download_package_verified(
+    config: &Config,
+    package_download: &PackageDownload,
+    hash: &HashResponse,
+    host_limiter: &HostLimiter,
+    lock_file_ownership: &LockFileOwnership,
+) -> Result<(), Error> {
+    let repo = config
+        .repositories()
+        .into_iter()
+        .find(|repository| repository.name() == **package_download.target().repository())
+        .ok_or_else(|| {
+            format_err!(
+                "unable to find repository '{}'",
+                package_download.target().repository()
+            )
+        })?;
+
+    let target_url = format!(
+        "api/p/{}/{}/{}/download",
+        package_download.target().category(),
+        package_download.target().name(),
+        package_download.target().version(),
+    );
+    let download = Download::from(&target_url)
+        .with_credentials(repo.config().credentials())
+        .with_netrc(Some(config.paths().netrc()))
+        .with_host_limiter(Some(host_limiter))
+        .with_network_config(Some(config.network()))
+        .with_ui_config(Some(config.ui()));
+    let downloaded_cache = config.downloaded_packages_cache(lock_file_ownership);
+
+    // Resume the download where it was left, if some data was already downloaded
+    let (mut file, offset) = package_download.resume_download_file(config)?;
+    if offset > 0
+        && download
+            .perform_with_mirrors_from(
+                &mut file,
+                &repo.mirrors_by_latency(config.network().mirror_ping_timeout()),
+                offset,
+            )
+            .is_ok()
+        && downloaded_cache.has_package_matching_hash(
+            package_download.target(),
+            hash.hash(),
+            hash.algorithm(),
+        )?
+    {
+        return Ok(());
+    }
+    drop(file);
+
+    download_package(config, package_download, host_limiter)?;
+    if !downloaded_cache.has_package_matching_hash(
+        package_download.target(),
+        hash.hash(),
+        hash.algorithm(),
+    )? {
+        return Err(format_err!(
+            "the downloaded archive of package {} doesn't match the hash issued by repository '{}'",
+            package_download.target(),
+            repo.name()
+        ));
+    }
+    Ok(())
+}
+
+/// Creates the pool of threads performing the downloads, whose size is given by the configuration
+fn download_pool(config: &Config) -> ThreadPool {
+    // A pool without any thread would never perform the downloads
+    let threads = config
+        .network()
+        .max_concurrent_downloads()
+        .unwrap_or_else(num_cpus::get)
+        .max(1);
+
+    ThreadPool::new(threads)
+}
+
 pub fn download_packages(
     config: &Config,
     downloads: impl Iterator<Item = PackageDownload>,
 ) -> Result<(), Error> {
-    let pool = ThreadPool::new(num_cpus::get());
+    let pool = download_pool(config);
+    let host_limiter = Arc::new(HostLimiter::from(config));
     let (sender, receiver) = channel();
     let mut n = 0;
 
     for download in downloads {
         let sender = sender.clone();
         let config = config.clone();
+        let host_limiter = host_limiter.clone();
         pool.execute(move || {
-            let result = download_package(&config, &download);
+            let result = download_package(&config, &download, &host_limiter);
             sender
                 .send(result)
                 .expect("cannot communicate with main thread");
@@ -121,12 +653,31 @@ pub fn download_packages(
     Ok(())
 }
 
-#[derive(Serialize, Deserialize)]
-struct HashResponse {
+/// The hash of a package's NPF, as issued by a repository
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HashResponse {
     sha256: String,
+    #[serde(default)]
+    algorithm: HashAlgorithm,
 }
 
-pub fn download_hash(config: &Config, package_id: &PackageID) -> Result<String, Error> {
+impl HashResponse {
+    /// Returns the hash, computed with the algorithm given by `algorithm()`
+    pub fn hash(&self) -> &str {
+        &self.sha256
+    }
+
+    /// Returns the algorithm the hash was computed with
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+}
+
+pub fn download_hash(
+    config: &Config,
+    package_id: &PackageID,
+    host_limiter: &HostLimiter,
+) -> Result<HashResponse, Error> {
     let repo = config
         .repositories()
         .into_iter()
@@ -142,10 +693,18 @@ pub fn download_hash(config: &Config, package_id: &PackageID) -> Result<String,
     );
 
     // Download the hash
-    let download = Download::from(&target_url);
+    let download = Download::from(&target_url)
+        .with_credentials(repo.config().credentials())
+        .with_netrc(Some(config.paths().netrc()))
+        .with_host_limiter(Some(host_limiter))
+        .with_network_config(Some(config.network()))
+        .with_ui_config(Some(config.ui()));
     let mut json = Vec::new();
     download
-        .perform_with_mirrors(&mut Cursor::new(&mut json), &repo.config().mirrors())
+        .perform_with_mirrors(
+            &mut Cursor::new(&mut json),
+            &repo.mirrors_by_latency(config.network().mirror_ping_timeout()),
+        )
         .context(format_err!(
             "unable to download the hash for package {} from repository '{}'",
             &package_id,
@@ -158,22 +717,24 @@ pub fn download_hash(config: &Config, package_id: &PackageID) -> Result<String,
         repo.name()
     ))?;
 
-    Ok(response.sha256)
+    Ok(response)
 }
 
 pub fn download_hashes(
     config: &Config,
     downloads: impl Iterator<Item = PackageDownload>,
-) -> Result<impl Iterator<Item = (PackageDownload, String)> + Clone, Error> {
-    let pool = ThreadPool::new(num_cpus::get());
+) -> Result<impl Iterator<Item = (PackageDownload, HashResponse)> + Clone, Error> {
+    let pool = download_pool(config);
+    let host_limiter = Arc::new(HostLimiter::from(config));
     let (sender, receiver) = channel();
     let mut n = 0;
 
     for download in downloads {
         let sender = sender.clone();
         let config = config.clone();
+        let host_limiter = host_limiter.clone();
         pool.execute(move || {
-            let result = download_hash(&config, &download.target());
+            let result = download_hash(&config, &download.target(), &host_limiter);
             sender
                 .send(result.map(|hash| (download, hash)))
                 .expect("cannot communicate with main thread");
@@ -186,3 +747,83 @@ pub fn download_hashes(
         .collect::<Result<Vec<_>, _>>()
         .map(|v| v.into_iter())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use libnest::config::{Config, Credentials, ProxyConfig};
+
+    use super::*;
+
+    #[test]
+    fn handle_goes_through_the_configured_proxy() -> Result<(), Error> {
+        // A fake proxy answering a single request, and returning its head
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let proxy = thread::spawn(move || -> Result<Vec<String>, Error> {
+            let (mut stream, _) = listener.accept()?;
+            let mut head = Vec::new();
+            for line in BufReader::new(stream.try_clone()?).lines() {
+                let line = line?;
+                if line.is_empty() {
+                    break;
+                }
+                head.push(line);
+            }
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            )?;
+            Ok(head)
+        });
+
+        let mut config: Config = serde_json::from_str("{}")?;
+        let mut proxy_config = ProxyConfig::new(String::from("127.0.0.1"));
+        *proxy_config.port_mut() = Some(port);
+        *proxy_config.credentials_mut() = Some(Credentials::new(
+            String::from("alice"),
+            String::from("s3cr3t"),
+        ));
+        *config.network_mut().proxy_mut() = Some(proxy_config);
+
+        let mut curl = Handle::try_from(&config)?.into_inner();
+        curl.url("http://mirror.invalid/api/pull")?;
+        let mut body = Vec::new();
+        {
+            let mut transfer = curl.transfer();
+            transfer.write_function(|data| {
+                body.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+        assert_eq!(body, b"ok");
+
+        let head = proxy.join().expect("the proxy panicked")?;
+        assert_eq!(head[0], "GET http://mirror.invalid/api/pull HTTP/1.1");
+        assert!(head
+            .iter()
+            .any(|line| line == "Proxy-Authorization: Basic YWxpY2U6czNjcjN0"));
+        Ok(())
+    }
+
+    #[test]
+    fn handle_applies_the_configured_limits() -> Result<(), Error> {
+        let mut config: Config = serde_json::from_str("{}")?;
+        let handle = Handle::try_from(&config)?;
+        assert_eq!(handle.max_download_speed(), None);
+
+        *config.network_mut().max_download_speed_mut() = Some(50 * 1024);
+        *config.network_mut().connect_timeout_mut() = Some(5_000);
+        *config.network_mut().low_speed_limit_mut() = Some(1);
+        *config.network_mut().low_speed_time_mut() = Some(1_000);
+        let handle = Handle::try_from(&config)?;
+        assert_eq!(handle.max_download_speed(), Some(50 * 1024));
+        assert_eq!(handle.connect_timeout(), Duration::from_secs(5));
+        assert_eq!(handle.low_speed_limit(), 1);
+        assert_eq!(handle.low_speed_time(), Duration::from_secs(1));
+        Ok(())
+    }
+}
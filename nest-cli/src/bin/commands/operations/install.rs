@@ -1,17 +1,31 @@
 use failure::{format_err, Error, ResultExt};
+use indicatif::{ProgressBar, ProgressStyle};
 use libnest::config::Config;
 use libnest::lock_file::LockFileOwnership;
+
 use libnest::transaction::InstallTransaction;
 
+use super::ProgressBarObserver;
+
 pub fn install_package(
     config: &Config,
     trans: &InstallTransaction,
     ownership: &LockFileOwnership,
 ) -> Result<(), Error> {
+    let progress_bar = ProgressBar::new(80);
+    progress_bar.set_style(ProgressStyle::default_bar().template("[{pos:>3}/{len:3}] {bar:80}"));
+
+    // Install the package
+    progress_bar.println(format!("Installing {}...", trans.target()));
     trans
-        .extract(&config, ownership)
+        .extract(
+            &config,
+            ownership,
+            &mut ProgressBarObserver::from(&progress_bar),
+        )
         .context(format_err!("unable to extract package"))?;
 
+    progress_bar.finish_and_clear();
     println!("Successfully installed {}", trans.target());
     Ok(())
 }
@@ -1,4 +1,66 @@
+pub mod downgrade;
 pub mod download;
 pub mod install;
+pub mod reinstall;
 pub mod uninstall;
 pub mod upgrade;
+
+use colored::*;
+use indicatif::ProgressBar;
+use libnest::config::{LogLevel, UiConfig};
+use libnest::transaction::TransactionObserver;
+
+use super::theme::{Element, Theme};
+
+/// Reflects the progress of a transaction on a progress bar
+pub struct ProgressBarObserver<'a>(&'a ProgressBar);
+
+impl<'a> ProgressBarObserver<'a> {
+    pub fn from(progress_bar: &'a ProgressBar) -> Self {
+        ProgressBarObserver(progress_bar)
+    }
+}
+
+impl<'a> TransactionObserver for ProgressBarObserver<'a> {
+    fn on_progress(&mut self, current: usize, max: usize) {
+        self.0.set_length(max as u64);
+        self.0.set_position(current as u64);
+    }
+
+    fn on_log(&mut self, level: LogLevel, message: &str) {
+        // Printed above the bar so it isn't garbled, and kept visible once the bar is cleared
+        if level <= LogLevel::Warn {
+            self.0.println(format!("{}: {}", level, message));
+        }
+    }
+}
+
+/// Prints the events reported by libnest on the standard error, if they are at least as important as
+/// the configured level
+pub struct LogPrinter {
+    level: LogLevel,
+    theme: Theme,
+}
+
+impl LogPrinter {
+    pub fn from(ui_config: &UiConfig) -> Self {
+        LogPrinter {
+            level: ui_config.log_level(),
+            theme: Theme::from(ui_config.colors()),
+        }
+    }
+}
+
+impl TransactionObserver for LogPrinter {
+    fn on_log(&mut self, level: LogLevel, message: &str) {
+        if level <= self.level {
+            let message = format!("{}: {}", level, message);
+            match level {
+                LogLevel::Error | LogLevel::Warn => {
+                    eprintln!("{}", self.theme.paint(Element::Warning, &message).bold())
+                }
+                LogLevel::Info | LogLevel::Debug => eprintln!("{}", message),
+            }
+        }
+    }
+}
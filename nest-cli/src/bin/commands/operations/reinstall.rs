@@ -0,0 +1,31 @@
+use failure::{format_err, Error, ResultExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use libnest::config::Config;
+use libnest::lock_file::LockFileOwnership;
+
+use libnest::transaction::ReinstallTransaction;
+
+use super::ProgressBarObserver;
+
+pub fn reinstall_package(
+    config: &Config,
+    trans: &ReinstallTransaction,
+    ownership: &LockFileOwnership,
+) -> Result<(), Error> {
+    let progress_bar = ProgressBar::new(80);
+    progress_bar.set_style(ProgressStyle::default_bar().template("[{pos:>3}/{len:3}] {bar:80}"));
+
+    // Reinstall the package
+    progress_bar.println(format!("Reinstalling {}...", trans.target()));
+    trans
+        .perform(
+            config,
+            ownership,
+            &mut ProgressBarObserver::from(&progress_bar),
+        )
+        .with_context(|_| format_err!("unable to reinstall package"))?;
+
+    progress_bar.finish_and_clear();
+    println!("Successfully reinstalled {}", trans.target());
+    Ok(())
+}
@@ -4,6 +4,8 @@ use libnest::config::Config;
 use libnest::lock_file::LockFileOwnership;
 use libnest::transaction::RemoveTransaction;
 
+use super::ProgressBarObserver;
+
 pub fn uninstall_package(
     config: &Config,
     trans: &RemoveTransaction,
@@ -14,7 +16,11 @@ pub fn uninstall_package(
 
     // Remove the package
     progress_bar.println(format!("Removing {}...", trans.target()));
-    trans.perform(config, ownernship)?;
+    trans.perform(
+        config,
+        ownernship,
+        &mut ProgressBarObserver::from(&progress_bar),
+    )?;
 
     progress_bar.finish_and_clear();
     println!("Successfully uninstalled {}", trans.target());
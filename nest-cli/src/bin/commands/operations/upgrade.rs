@@ -5,6 +5,8 @@ use libnest::lock_file::LockFileOwnership;
 
 use libnest::transaction::UpgradeTransaction;
 
+use super::ProgressBarObserver;
+
 pub fn upgrade_package(
     config: &Config,
     trans: &UpgradeTransaction,
@@ -20,7 +22,11 @@ pub fn upgrade_package(
         trans.new_target()
     ));
     trans
-        .perform(config, ownership)
+        .perform(
+            config,
+            ownership,
+            &mut ProgressBarObserver::from(&progress_bar),
+        )
         .with_context(|_| format_err!("unable to extract package"))?;
 
     progress_bar.finish_and_clear();
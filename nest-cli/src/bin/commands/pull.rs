@@ -1,12 +1,61 @@
+use std::io::Cursor;
+use std::slice;
+
+use clap::ArgMatches;
 use failure::{format_err, Error, ResultExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use libnest::config::Config;
-use libnest::transaction::{PullTransaction, Transaction};
+use libnest::cache::available::PullValidators;
+use libnest::config::{parse_mirror_list, Config, LogLevel, MirrorListErrorKind};
+use libnest::transaction::{PullOutcome, PullTransaction, Transaction, TransactionObserver};
 
 use super::operations::download::Download;
+use super::operations::ProgressBarObserver;
 use super::{ask_confirmation, print_transactions};
 
-pub fn pull(config: &Config) -> Result<(), Error> {
+/// Loads the cached mirror list of each repository having one, fetching them first if the given command is a pull
+pub fn load_mirror_lists(config: &mut Config, matches: &ArgMatches) -> Result<(), Error> {
+    // Repositories' mirror lists are fetched before pulling, so the pull can use their mirrors
+    if let ("pull", _) = matches.subcommand() {
+        fetch_mirror_lists(config)?;
+    }
+    config.load_mirror_lists()?;
+    Ok(())
+}
+
+/// Fetches the mirror list of each repository having one, and caches it so it's used from now on
+fn fetch_mirror_lists(config: &Config) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let cache = config.available_packages_cache(&lock_file_ownership);
+
+    for repo in config.repositories() {
+        if let Some(url) = repo.config().mirrorlist_url() {
+            let mut content = Vec::new();
+
+            // Download errors may be transient, so the download is retried a few times
+            config
+                .network()
+                .retry_with_backoff(
+                    || {
+                        content.clear();
+                        Download::from("")
+                            .with_netrc(Some(config.paths().netrc()))
+                            .with_network_config(Some(config.network()))
+                            .with_ui_config(Some(config.ui()))
+                            .perform_with_mirrors(&mut Cursor::new(&mut content), &[url.clone()])
+                    },
+                    |_, _, _| (),
+                )
+                .context(format_err!("repository '{}'", repo.name()))
+                .context(MirrorListErrorKind::MirrorListFetchError)?;
+
+            let mirrors = parse_mirror_list(&String::from_utf8_lossy(&content))?;
+            cache.save_mirror_list(&repo, &mirrors)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn pull(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
     let transactions: Vec<_> = config
         .repositories()
         .into_iter()
@@ -18,7 +67,7 @@ pub fn pull(config: &Config) -> Result<(), Error> {
         return Ok(());
     }
 
-    print_transactions(&transactions);
+    print_transactions(config, &transactions);
 
     if !ask_confirmation(
         format!(
@@ -39,28 +88,96 @@ pub fn pull(config: &Config) -> Result<(), Error> {
     let progress_bar = ProgressBar::new(transactions.len() as u64);
     progress_bar.set_style(ProgressStyle::default_bar().template("[{pos:>3}/{len:3}] {bar:80}"));
 
+    let mut observer = ProgressBarObserver::from(&progress_bar);
     let mut transactions = transactions;
-    let download = Download::from("api/pull");
+    let mut unchanged = Vec::new();
 
     {
         let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
 
+        // The validators of a repository whose cache is corrupted would keep the mirrors from sending its manifests
+        // again, so they are ignored like when the pull is forced
+        let corrupted = config
+            .available_packages_cache(&lock_file_ownership)
+            .verify_integrity()?;
+
         for pull in transactions.iter_mut() {
             if let Transaction::Pull(pull) = pull {
                 let repo = *pull.target_repository();
 
                 progress_bar.println(format!("Pulling {}...", repo.name()).as_str());
 
-                download
-                    .perform_with_mirrors(&mut pull.writer(), repo.config().mirrors())
-                    .context(format_err!("unable to pull repository '{}'", repo.name()))?;
-                pull.save_to_cache(config, &lock_file_ownership)?;
+                let repo_cache = config.paths().available().join(repo.name());
+                let validators = if matches.is_present("force")
+                    || corrupted.iter().any(|path| path.starts_with(&repo_cache))
+                {
+                    PullValidators::default()
+                } else {
+                    config
+                        .available_packages_cache(&lock_file_ownership)
+                        .pull_validators(&repo)?
+                };
+
+                // Download errors may be transient, so the download is retried a few times
+                config.network().retry_with_backoff(
+                    || -> Result<(), Error> {
+                        let (mirror, validator, outcome) = Download::from("api/pull")
+                            .with_credentials(repo.config().credentials())
+                            .with_netrc(Some(config.paths().netrc()))
+                            .with_network_config(Some(config.network()))
+                            .with_ui_config(Some(config.ui()))
+                            .perform_conditional_with_mirrors(
+                                &mut pull.writer(),
+                                repo.config().mirrors(),
+                                &validators,
+                            )
+                            .context(format_err!("unable to pull repository '{}'", repo.name()))?;
+                        pull.set_response(&mirror, validator, outcome);
+
+                        // The signature must come from the mirror that sent the manifests, as another one may
+                        // be serving a different version of them
+                        if outcome == PullOutcome::Updated && repo.config().pubkey().is_some() {
+                            Download::from("api/pull.sig")
+                                .with_credentials(repo.config().credentials())
+                                .with_netrc(Some(config.paths().netrc()))
+                                .with_network_config(Some(config.network()))
+                                .with_ui_config(Some(config.ui()))
+                                .perform_with_mirrors(
+                                    &mut pull.signature_writer(),
+                                    slice::from_ref(&mirror),
+                                )
+                                .context(format_err!(
+                                    "unable to pull the signature of repository '{}'",
+                                    repo.name()
+                                ))?;
+                        }
+                        Ok(())
+                    },
+                    |retry, delay, _| {
+                        observer.on_log(
+                            LogLevel::Warn,
+                            &format!(
+                                "pulling {} failed, retrying in {:.1}s ({}/{})...",
+                                repo.name(),
+                                delay.as_millis() as f64 / 1000.0,
+                                retry,
+                                config.network().pull_retries(),
+                            ),
+                        )
+                    },
+                )?;
+                if pull.save_to_cache(config, &lock_file_ownership)? == PullOutcome::Unchanged {
+                    unchanged.push(repo.name().to_string());
+                }
 
                 progress_bar.inc(1);
             }
         }
     }
     progress_bar.finish_and_clear();
+    for name in &unchanged {
+        println!("Repository '{}' is already up to date", name);
+    }
     println!(
         "Successfully pulled {} repositor{}",
         transactions.len(),
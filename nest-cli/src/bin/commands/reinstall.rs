@@ -3,18 +3,16 @@ use failure::{format_err, Error};
 use libnest::cache::depgraph::NodeKind;
 use libnest::config::Config;
 use libnest::package::SoftPackageRequirement;
-use libnest::transaction::{InstallTransaction, RemoveTransaction};
+use libnest::transaction::{ReinstallTransaction, Transaction};
 
-use super::operations::download::download_packages;
-use super::operations::install::install_package;
-use super::operations::uninstall::uninstall_package;
+use super::{download_required_packages, process_transactions};
 
 pub fn reinstall(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
 
     let graph = config.dependency_graph(&lock_file_ownership)?;
 
-    let packages = matches
+    let transactions = matches
         .values_of_lossy("PACKAGE")
         .unwrap()
         .iter()
@@ -29,7 +27,9 @@ pub fn reinstall(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
                     .collect::<Vec<_>>();
 
                 match matching_installed_packages.len() {
-                    1 => Ok(matching_installed_packages[0].clone()),
+                    1 => Ok(Transaction::Reinstall(ReinstallTransaction::from(
+                        matching_installed_packages[0].clone(),
+                    ))),
                     0 => Err(format_err!("no package matches the {} requirement", &s)),
                     _ => Err(format_err!(
                         "multiple installed packages match the {} requirement, please disambiguate",
@@ -40,26 +40,9 @@ pub fn reinstall(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let (installs, removals): (Vec<_>, Vec<_>) = packages
-        .into_iter()
-        .map(|pkg_id| {
-            (
-                InstallTransaction::from(pkg_id.clone()),
-                RemoveTransaction::from(pkg_id),
-            )
-        })
-        .unzip();
-
-    println!("Downloading packages...");
-    download_packages(
-        config,
-        installs.iter().map(InstallTransaction::associated_download),
-    )?;
+    download_required_packages(config, &transactions, &lock_file_ownership)?;
 
-    for (install, removal) in installs.into_iter().zip(removals.into_iter()) {
-        uninstall_package(config, &removal, &lock_file_ownership)?;
-        install_package(config, &install, &lock_file_ownership)?;
-    }
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
 
     Ok(())
 }
@@ -22,10 +22,6 @@ pub fn requirement_add(
         config.dependency_graph(&lock_file_ownership)?
     };
 
-    let group_id = *scratch_graph
-        .node_names()
-        .get(&group.clone().into())
-        .ok_or_else(|| format_err!("Unknown group"))?;
     let packages_cache = config.available_packages_cache(&lock_file_ownership);
 
     for target in &matches.values_of_lossy("PACKAGE").unwrap() {
@@ -36,10 +32,7 @@ pub fn requirement_add(
             .set_strategy(AvailablePackagesCacheQueryStrategy::BestMatch)
             .perform_and_sort_by_preference(config)?;
         if matched_packages.is_empty() {
-            return Err(format_err!(
-                "no package found for requirement '{}'",
-                &target
-            ));
+            return Err(packages_cache.no_package_found(&requirement).into());
         }
         let matched_package = &matched_packages[0];
 
@@ -49,13 +42,11 @@ pub fn requirement_add(
         );
 
         println!("Adding requirement {} to group {}...", package_req, *group);
-        scratch_graph.node_add_requirement(
-            group_id,
-            RequirementKind::Package {
-                package_req: package_req.into(),
-            },
+        scratch_graph.add_requirement_to_group(
+            &group,
+            package_req.into(),
             RequirementManagementMethod::Static,
-        );
+        )?;
     }
 
     scratch_graph.solve(&config)?;
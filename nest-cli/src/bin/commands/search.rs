@@ -0,0 +1,25 @@
+use clap::ArgMatches;
+use colored::*;
+use failure::Error;
+
+use libnest::config::Config;
+
+pub fn search(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership_shared(true)?;
+    let cache = config.available_packages_cache(&lock_file_ownership);
+
+    let keywords = matches.values_of_lossy("KEYWORD").unwrap();
+    let keywords: Vec<_> = keywords.iter().map(String::as_str).collect();
+
+    let results = cache.search(&keywords)?;
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string(&results)?);
+        return Ok(());
+    }
+
+    for result in results {
+        println!("{}", result.id().to_string().bold());
+        println!("    {}", result.manifest().metadata().description());
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::fs::{self, File};
+
+use clap::ArgMatches;
+use failure::{format_err, Error, ResultExt};
+use libnest::cache::depgraph::SelectionManifest;
+use libnest::config::Config;
+
+pub fn selection_export(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let path = matches.value_of("FILE").unwrap();
+
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let graph = config.dependency_graph(&lock_file_ownership)?;
+
+    let selection = serde_json::to_string_pretty(&graph.export_selection())?;
+    fs::write(path, selection + "\n")
+        .with_context(|_| format_err!("unable to write the selection to '{}'", path))?;
+
+    println!("Successfully exported the selection to '{}'.", path);
+    Ok(())
+}
+
+pub fn selection_import(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let path = matches.value_of("FILE").unwrap();
+
+    let file = File::open(path)
+        .with_context(|_| format_err!("unable to open the selection '{}'", path))?;
+    let selection: SelectionManifest = serde_json::from_reader(file)
+        .with_context(|_| format_err!("unable to parse the selection '{}'", path))?;
+
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    config.apply_selection(&selection, &lock_file_ownership)?;
+
+    println!("Successfully imported the selection into the scratch dependency graph.");
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use clap::ArgMatches;
+use failure::Error;
+
+use libnest::config::Config;
+
+pub fn stats(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership_shared(true)?;
+    let statistics = config.dependency_graph(&lock_file_ownership)?.statistics();
+
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string(&statistics)?);
+    } else {
+        println!(
+            "Explicitly installed packages: {}",
+            statistics.explicit_packages()
+        );
+        println!(
+            "Automatically installed packages: {}",
+            statistics.auto_packages()
+        );
+        println!("Groups: {}", statistics.groups());
+        println!("Requirements: {}", statistics.requirements());
+        println!("Orphans: {}", statistics.orphans());
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+use colored::*;
+use libnest::config::{ColorTheme, ColorsConfig, Config};
+
+/// The different kinds of elements that can be highlighted when printed
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Element {
+    Install,
+    Remove,
+    Upgrade,
+    Downgrade,
+    Pull,
+    Warning,
+}
+
+impl Element {
+    fn default_color(self) -> Color {
+        match self {
+            Element::Install => Color::Green,
+            Element::Remove => Color::Red,
+            Element::Upgrade => Color::Yellow,
+            Element::Downgrade => Color::Blue,
+            Element::Pull => Color::Cyan,
+            Element::Warning => Color::Magenta,
+        }
+    }
+
+    fn configured_color(self, colors: &ColorsConfig) -> Option<&str> {
+        match self {
+            Element::Install => colors.install(),
+            Element::Remove => colors.remove(),
+            Element::Upgrade => colors.upgrade(),
+            Element::Downgrade => colors.downgrade(),
+            Element::Pull => colors.pull(),
+            Element::Warning => colors.warning(),
+        }
+    }
+}
+
+/// The colors to use when printing each kind of [`Element`]
+#[derive(Clone, Debug)]
+pub struct Theme {
+    mono: bool,
+    install: Color,
+    remove: Color,
+    upgrade: Color,
+    downgrade: Color,
+    pull: Color,
+    warning: Color,
+}
+
+impl Theme {
+    /// Creates a theme from the colors configuration, falling back to default colors
+    /// for elements that aren't configured or whose color name is unknown
+    pub fn from(colors: &ColorsConfig) -> Self {
+        let color_of = |element: Element| {
+            element
+                .configured_color(colors)
+                .and_then(|name| Color::from_str(name).ok())
+                .unwrap_or_else(|| element.default_color())
+        };
+
+        Theme {
+            mono: colors.theme() == ColorTheme::Mono,
+            install: color_of(Element::Install),
+            remove: color_of(Element::Remove),
+            upgrade: color_of(Element::Upgrade),
+            downgrade: color_of(Element::Downgrade),
+            pull: color_of(Element::Pull),
+            warning: color_of(Element::Warning),
+        }
+    }
+
+    /// Loads the theme described by the given configuration
+    pub fn load(config: &Config) -> Self {
+        Theme::from(config.ui().colors())
+    }
+
+    /// Returns whether colors are disabled entirely
+    pub fn is_mono(&self) -> bool {
+        self.mono
+    }
+
+    /// Returns the color associated to the given element
+    pub fn color(&self, element: Element) -> Color {
+        match element {
+            Element::Install => self.install,
+            Element::Remove => self.remove,
+            Element::Upgrade => self.upgrade,
+            Element::Downgrade => self.downgrade,
+            Element::Pull => self.pull,
+            Element::Warning => self.warning,
+        }
+    }
+
+    /// Paints the given text with the color of the given element
+    pub fn paint(&self, element: Element, text: &str) -> ColoredString {
+        if self.mono {
+            text.normal()
+        } else {
+            text.color(self.color(element))
+        }
+    }
+}
@@ -55,7 +55,7 @@ pub fn uninstall(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
         return Ok(());
     }
 
-    print_transactions(&transactions);
+    print_transactions(config, &transactions);
 
     if !ask_confirmation(
         format!(
@@ -73,9 +73,7 @@ pub fn uninstall(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
         return Ok(());
     }
 
-    process_transactions(config, &transactions, &lock_file_ownership)?;
-
-    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
 
     Ok(())
 }
@@ -1,18 +1,31 @@
+use std::collections::HashSet;
+
 use clap::ArgMatches;
 use failure::Error;
 use libnest::cache::depgraph::DependencyGraphDiff;
 use libnest::config::Config;
 
+use super::hold::installed_packages_matching;
 use super::{
     ask_confirmation, download_required_packages, print_transactions, process_transactions,
+    warn_about_stale_repositories,
 };
 
-pub fn upgrade(config: &Config, _: &ArgMatches) -> Result<(), Error> {
+pub fn upgrade(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
     let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    warn_about_stale_repositories(config, &lock_file_ownership);
+
     let mut graph = config.dependency_graph(&lock_file_ownership)?;
     let original_graph = graph.clone();
 
-    graph.update(config)?;
+    // Packages to leave out of this upgrade are kept at their current version
+    let targets = matches.values_of_lossy("ignore").unwrap_or_default();
+    let packages: Vec<_> = graph.packages().cloned().collect();
+    let ignored: HashSet<_> = installed_packages_matching(&packages, &targets)?
+        .into_iter()
+        .collect();
+
+    graph.update_with_ignored(config, &ignored)?;
 
     let transactions = DependencyGraphDiff::new().perform(&original_graph, &graph);
 
@@ -21,7 +34,7 @@ pub fn upgrade(config: &Config, _: &ArgMatches) -> Result<(), Error> {
         return Ok(());
     }
 
-    print_transactions(&transactions);
+    print_transactions(config, &transactions);
 
     if !ask_confirmation(
         format!(
@@ -41,9 +54,7 @@ pub fn upgrade(config: &Config, _: &ArgMatches) -> Result<(), Error> {
 
     download_required_packages(config, &transactions, &lock_file_ownership)?;
 
-    process_transactions(config, &transactions, &lock_file_ownership)?;
-
-    graph.save_to_cache(config.paths().depgraph(), &lock_file_ownership)?;
+    process_transactions(config, &transactions, &graph, &lock_file_ownership)?;
 
     Ok(())
 }
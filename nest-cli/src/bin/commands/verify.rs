@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+
+use clap::ArgMatches;
+use colored::*;
+use failure::{format_err, Error, ResultExt};
+use libnest::cache::installed::log::FileStatus;
+use libnest::cache::installed::InstalledPackages;
+use libnest::config::Config;
+use libnest::package::{PackageID, SoftPackageRequirement};
+use threadpool::ThreadPool;
+
+use super::theme::{Element, Theme};
+
+pub fn verify(config: &Config, matches: &ArgMatches) -> Result<(), Error> {
+    let lock_file_ownership = config.acquire_lock_file_ownership(true)?;
+    let installed_cache = config.installed_packages_cache(&lock_file_ownership);
+
+    installed_cache
+        .migrate_legacy_logs()
+        .context("unable to migrate the logs of installed packages")?;
+
+    let mut packages = installed_cache
+        .packages()
+        .context("unable to list the installed packages")?;
+
+    if !matches.is_present("all") {
+        let requirements = matches
+            .values_of_lossy("PACKAGE")
+            .unwrap()
+            .iter()
+            .map(|target| SoftPackageRequirement::parse(target))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (target, requirement) in matches
+            .values_of_lossy("PACKAGE")
+            .unwrap()
+            .iter()
+            .zip(requirements.iter())
+        {
+            if !packages
+                .iter()
+                .any(|(id, _)| requirement.matches_precisely(id))
+            {
+                return Err(format_err!(
+                    "no installed package matches the {} requirement",
+                    target
+                ));
+            }
+        }
+        packages.retain(|(id, _)| requirements.iter().any(|req| req.matches_precisely(id)));
+    }
+    packages.sort();
+
+    let mut ids = packages.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    ids.dedup();
+    let nb_packages = ids.len();
+
+    // The verification runs on other threads, which share the configuration and the lock file with this one
+    let config = Arc::new(config.clone());
+    let lock_file_ownership = Arc::new(lock_file_ownership);
+
+    let pool = ThreadPool::new(num_cpus::get());
+    let (sender, receiver) = channel();
+
+    for (index, id) in ids.into_iter().enumerate() {
+        let sender = sender.clone();
+        let config = config.clone();
+        let lock_file_ownership = lock_file_ownership.clone();
+        pool.execute(move || {
+            let installed_cache = config.installed_packages_cache(&lock_file_ownership);
+            let result = altered_files(&installed_cache, &id, config.paths().root())
+                .with_context(|_| format_err!("unable to verify {}", id));
+            sender
+                .send((index, id, result))
+                .expect("cannot communicate with main thread");
+        });
+    }
+
+    let mut results = receiver.into_iter().take(nb_packages).collect::<Vec<_>>();
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let theme = Theme::load(&config);
+    let mut nb_altered = 0;
+    for (_, id, result) in results {
+        let altered = result?;
+        if altered.is_empty() {
+            continue;
+        }
+
+        println!("{}", id.to_string().bold());
+        for (path, status) in altered {
+            let status = match status {
+                FileStatus::Modified => theme.paint(Element::Upgrade, "modified"),
+                FileStatus::Missing => theme.paint(Element::Remove, "missing"),
+                FileStatus::Intact => theme.paint(Element::Install, "intact"),
+            };
+            println!("{:>10.10} {}", status, path.display());
+            nb_altered += 1;
+        }
+    }
+
+    if nb_altered > 0 {
+        Err(format_err!(
+            "{} file{} failed the verification",
+            nb_altered,
+            if nb_altered <= 1 { "" } else { "s" }
+        ))
+    } else {
+        println!(
+            "All the files of {} package{} are intact.",
+            nb_packages,
+            if nb_packages <= 1 { "" } else { "s" }
+        );
+        Ok(())
+    }
+}
+
+/// Returns the files of the given package that aren't intact anymore, with their status
+fn altered_files(
+    installed_cache: &InstalledPackages,
+    id: &PackageID,
+    root: &Path,
+) -> Result<Vec<(PathBuf, FileStatus)>, std::io::Error> {
+    let files = installed_cache.package_files(id)?;
+    let statuses = installed_cache.verify_package(id, root)?;
+    Ok(files
+        .into_iter()
+        .zip(statuses)
+        .filter(|(_, status)| *status != FileStatus::Intact)
+        .collect())
+}
@@ -36,7 +36,13 @@ fn main() {
                 .takes_value(true)
         )
         .subcommand(
-            SubCommand::with_name("pull").about("Pull repositories and update the local cache"),
+            SubCommand::with_name("pull")
+                .about("Pull repositories and update the local cache")
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Download the manifests even if the mirrors report they didn't change"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("group")
@@ -69,6 +75,29 @@ fn main() {
                                 .required(true),
                         )
                 )
+                .subcommand(
+                    SubCommand::with_name("rename")
+                        .about("Rename an existing group")
+                        .arg(
+                            Arg::with_name("OLD")
+                                .help("Group to rename")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("NEW")
+                                .help("New name of the group")
+                                .required(true),
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show the requirements of a group")
+                        .arg(
+                            Arg::with_name("GROUP")
+                                .help("Group to show")
+                                .required(true),
+                        )
+                )
                 .subcommand(
                     SubCommand::with_name("list")
                         .about("List existing groups")
@@ -117,6 +146,33 @@ fn main() {
             SubCommand::with_name("merge")
                 .about("Merge the scratch dependency graph with the regular dependency graph")
         )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check the consistency of the dependency graphs")
+        )
+        .subcommand(
+            SubCommand::with_name("selection")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .about("Share the selected packages with other systems")
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Export the groups and requirements of the dependency graph")
+                        .arg(
+                            Arg::with_name("FILE")
+                                .help("File to export the selection to")
+                                .required(true),
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Rebuild the scratch dependency graph from an exported selection")
+                        .arg(
+                            Arg::with_name("FILE")
+                                .help("File to import the selection from")
+                                .required(true),
+                        )
+                )
+        )
         .get_matches();
 
     let result: Result<(), failure::Error> = try {
@@ -126,8 +182,18 @@ fn main() {
             *config.paths_mut() = config.paths().chroot(chroot_path);
         }
 
+        // Each `-v` makes the reported events one level more verbose
+        *config.ui_mut().log_level_mut() =
+            config.ui().log_level().raised(matches.occurrences_of("v"));
+
+        commands::load_mirror_lists(&mut config, &matches)?;
+
+        if commands::theme::Theme::load(&config).is_mono() {
+            colored::control::set_override(false);
+        }
+
         match matches.subcommand() {
-            ("pull", _) => commands::pull(&config),
+            ("pull", Some(matches)) => commands::pull(&config, &matches),
             ("group", Some(sub_matches)) => match sub_matches.subcommand() {
                 ("add", Some(cmd_matches)) => commands::group_add(
                     &config,
@@ -135,6 +201,8 @@ fn main() {
                     &cmd_matches,
                 ),
                 ("remove", Some(cmd_matches)) => commands::group_remove(&config, &cmd_matches),
+                ("rename", Some(cmd_matches)) => commands::group_rename(&config, &cmd_matches),
+                ("show", Some(cmd_matches)) => commands::group_show(&config, &cmd_matches),
                 ("list", _) => commands::group_list(&config),
                 _ => unimplemented!(),
             },
@@ -152,6 +220,12 @@ fn main() {
                 _ => unimplemented!(),
             },
             ("merge", _) => commands::merge(&config),
+            ("check", _) => commands::check(&config),
+            ("selection", Some(sub_matches)) => match sub_matches.subcommand() {
+                ("export", Some(cmd_matches)) => commands::selection_export(&config, &cmd_matches),
+                ("import", Some(cmd_matches)) => commands::selection_import(&config, &cmd_matches),
+                _ => unimplemented!(),
+            },
             _ => unimplemented!(),
         }?;
     };
@@ -159,13 +233,7 @@ fn main() {
     if let Err(e) = result {
         use std::process::exit;
 
-        let fail = e.as_fail();
-        eprint!("error: {}", fail);
-        for cause in fail.iter_causes() {
-            eprint!(": {}", cause);
-        }
-        eprintln!();
-
+        commands::print_error(&e);
         exit(1);
     }
 }
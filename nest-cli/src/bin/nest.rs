@@ -35,8 +35,20 @@ fn main() {
                 .help("Use the current configuration but operate on the given folder, as if it was the root folder")
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("metrics-file")
+                .long("metrics-file")
+                .help("Write metrics about the state of the system to the given path, in the Prometheus textfile format")
+                .takes_value(true)
+        )
         .subcommand(
-            SubCommand::with_name("pull").about("Pull repositories and update the local cache"),
+            SubCommand::with_name("pull")
+                .about("Pull repositories and update the local cache")
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .help("Download the manifests even if the mirrors report they didn't change"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("install")
@@ -44,7 +56,27 @@ fn main() {
                 .about("Download and install the given packages [alias: add]")
                 .arg(
                     Arg::with_name("PACKAGE")
-                        .help("Packages to install")
+                        .help("Packages to install, or paths to local NPFs ending with .nest")
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("force-overwrite")
+                        .long("force-overwrite")
+                        .help("Overwrite the files owned by other packages instead of aborting, transferring their ownership"),
+                )
+                .arg(
+                    Arg::with_name("with-optional")
+                        .long("with-optional")
+                        .help("Install the optional dependencies of the packages too"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("download")
+                .about("Download the given packages and their dependencies without installing them")
+                .arg(
+                    Arg::with_name("PACKAGE")
+                        .help("Packages to download")
                         .multiple(true)
                         .required(true),
                 )
@@ -53,6 +85,15 @@ fn main() {
             SubCommand::with_name("upgrade")
                 .alias("update")
                 .about("Upgrade all installed packages [alias: update]")
+                .arg(
+                    Arg::with_name("ignore")
+                        .long("ignore")
+                        .value_name("PACKAGE")
+                        .help("Keep the given installed package at its current version for this upgrade")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
         )
         .subcommand(
             SubCommand::with_name("uninstall")
@@ -65,6 +106,10 @@ fn main() {
                         .required(true),
                 )
         )
+        .subcommand(
+            SubCommand::with_name("autoremove")
+                .about("Uninstall the packages that were installed automatically and aren't needed anymore")
+        )
         .subcommand(
             SubCommand::with_name("reinstall")
                 .about("Reinstall an already-installed package")
@@ -75,6 +120,57 @@ fn main() {
                         .required(true),
                 )
         )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Check that the files of installed packages weren't modified or removed")
+                .arg(
+                    Arg::with_name("PACKAGE")
+                        .help("Packages to verify")
+                        .multiple(true)
+                        .required_unless("all")
+                        .conflicts_with("all"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Verify all the installed packages")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("hold")
+                .about("Prevent the given installed packages from being upgraded")
+                .arg(
+                    Arg::with_name("PACKAGE")
+                        .help("Packages to hold")
+                        .multiple(true)
+                        .required(true),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("unhold")
+                .about("Allow the given held packages to be upgraded again")
+                .arg(
+                    Arg::with_name("PACKAGE")
+                        .help("Packages to release")
+                        .multiple(true)
+                        .required(true),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Search the available packages whose name or description contain the given keywords")
+                .arg(
+                    Arg::with_name("KEYWORD")
+                        .help("Keywords to search for")
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print the results as a JSON array"),
+                )
+        )
         .subcommand(
             SubCommand::with_name("list")
                 .about("List installed packages")
@@ -84,6 +180,15 @@ fn main() {
                         .help("Include the dependencies of installed packages")
                 )
         )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print statistics about the dependency graph")
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print the statistics as a JSON object"),
+                )
+        )
         .get_matches();
 
     let result: Result<(), failure::Error> = try {
@@ -93,27 +198,44 @@ fn main() {
             *config.paths_mut() = config.paths().chroot(chroot_path);
         }
 
+        // Each `-v` makes the reported events one level more verbose
+        *config.ui_mut().log_level_mut() =
+            config.ui().log_level().raised(matches.occurrences_of("v"));
+
+        commands::load_mirror_lists(&mut config, &matches)?;
+
+        if commands::theme::Theme::load(&config).is_mono() {
+            colored::control::set_override(false);
+        }
+
+        commands::recover_journal(&config)?;
+
         match matches.subcommand() {
-            ("pull", _) => commands::pull(&config),
+            ("pull", Some(matches)) => commands::pull(&config, &matches),
             ("install", Some(matches)) => commands::install(&config, &matches),
+            ("download", Some(matches)) => commands::download(&config, &matches),
             ("upgrade", Some(matches)) => commands::upgrade(&config, &matches),
             ("uninstall", Some(matches)) => commands::uninstall(&config, &matches),
+            ("autoremove", Some(matches)) => commands::autoremove(&config, &matches),
             ("reinstall", Some(matches)) => commands::reinstall(&config, &matches),
+            ("verify", Some(matches)) => commands::verify(&config, &matches),
+            ("hold", Some(matches)) => commands::hold(&config, &matches),
+            ("unhold", Some(matches)) => commands::unhold(&config, &matches),
+            ("search", Some(matches)) => commands::search(&config, &matches),
             ("list", Some(matches)) => commands::list(&config, &matches),
+            ("stats", Some(matches)) => commands::stats(&config, &matches),
             _ => unimplemented!(),
         }?;
+
+        if let Some(metrics_path) = matches.value_of("metrics-file") {
+            commands::metrics(&config, metrics_path)?;
+        }
     };
 
     if let Err(e) = result {
         use std::process::exit;
 
-        let fail = e.as_fail();
-        eprint!("error: {}", fail);
-        for cause in fail.iter_causes() {
-            eprint!(": {}", cause);
-        }
-        eprintln!();
-
+        commands::print_error(&e);
         exit(1);
     }
 }
@@ -1,3 +1,4 @@
+import io
 import json
 import os
 import shutil
@@ -34,6 +35,7 @@ class Package:
         self.upstream_url = upstream_url or "https://google.com"
         self.dependencies = []
         self.files = {}
+        self.scripts = {}
 
     def full_name(self) -> str:
         return f"tests::{self.category}/{self.name}"
@@ -46,9 +48,14 @@ class Package:
         return self
 
     def add_file(self, path, with_content=None, from_reader=None) -> 'Package':
-        if not (with_content ^ from_reader):
+        if (with_content is None) == (from_reader is None):
             raise ValueError("Invalid arguments: exactly one of 'with_content' and 'from_reader' must be used")
-        # self.files[path] =
+        content = with_content if with_content is not None else from_reader.read()
+        self.files[path] = content.encode() if isinstance(content, str) else content
+        return self
+
+    def add_script(self, name: str, content: str) -> 'Package':
+        self.scripts[name] = content
         return self
 
     def add_symlink(self, path: str, target: str) -> 'Package':
@@ -83,9 +90,17 @@ class Package:
 
         if self.kind == "effective":
             with tarfile.open(f"{directory}/data.tar.gz", "w:gz") as tar:
-                pass
+                for path, content in self.files.items():
+                    info = tarfile.TarInfo(name=path.lstrip("/"))
+                    info.size = len(content)
+                    tar.addfile(info, io.BytesIO(content))
             files.append((f"{directory}/data.tar.gz", "data.tar.gz"))
 
+        for name, content in self.scripts.items():
+            with open(f"{directory}/{name}", 'x') as f:
+                f.write(content)
+            files.append((f"{directory}/{name}", name))
+
         with tarfile.open(f"{directory}/{self.name}-{self.version}.nest", "x") as tar:
             for name, arcname in files:
                 tar.add(name, arcname=arcname)
@@ -134,6 +149,16 @@ def nest_server(packages: List[Package] = None):
             shutil.rmtree(f"{nest_server_path}/cache")
 
 
+def install_host_shell(chroot: str):
+    """Copies the host's /bin/sh and the libraries it needs within the given chroot, so scripts can run inside it"""
+    libraries = subprocess.run(["ldd", "/bin/sh"], capture_output=True).stdout.decode()
+    paths = ["/bin/sh"] + [word for word in libraries.split() if word.startswith("/")]
+    for path in paths:
+        target = f"{chroot}/{path.lstrip('/')}"
+        os.makedirs(os.path.dirname(target), exist_ok=True)
+        shutil.copy(os.path.realpath(path), target)
+
+
 @contextmanager
 def create_config(entries: Dict[str, Dict[str, Any]] = None):
     entries = entries or {
@@ -173,9 +198,10 @@ class _Depgraph:
 
 
 class _Nest:
-    def __init__(self, config: str = None, chroot: str = None):
+    def __init__(self, config: str = None, chroot: str = None, metrics_file: str = None):
         self.config = config
         self.chroot = chroot
+        self.metrics_file = metrics_file
 
     def _run(self, *args: str, input_str: str = None):
         cmd = ["sudo", "RUST_BACKTRACE=1", f"PATH={os.getenv('PATH')}", "env", "cargo", "run", "-q", "--bin", "nest",
@@ -184,18 +210,34 @@ class _Nest:
             cmd += ("--config", self.config)
         if self.chroot:
             cmd += ("--chroot", self.chroot)
+        if self.metrics_file:
+            cmd += ("--metrics-file", self.metrics_file)
         cmd += args
         return subprocess.run(cmd, capture_output=True, input=input_str and input_str.encode())
 
-    def pull(self, confirm=True):
-        return self._run("pull", input_str="yes" if confirm else "no")
+    def pull(self, confirm=True, force=False):
+        flags = ("--force",) if force else ()
+        return self._run("pull", *flags, input_str="yes" if confirm else "no")
+
+    def install(self, *packages: str, confirm=True, force_overwrite=False):
+        flags = ("--force-overwrite",) if force_overwrite else ()
+        return self._run("install", *flags, *packages, input_str="yes" if confirm else "no")
 
-    def install(self, *packages: str, confirm=True):
-        return self._run("install", *packages, input_str="yes" if confirm else "no")
+    def download(self, *packages: str):
+        return self._run("download", *packages)
 
     def uninstall(self, *packages: str, confirm=True):
         return self._run("uninstall", *packages, input_str="yes" if confirm else "no")
 
+    def reinstall(self, *packages: str):
+        return self._run("reinstall", *packages)
+
+    def verify(self, *packages: str, all=False):
+        if all:
+            return self._run("verify", "--all")
+        else:
+            return self._run("verify", *packages)
+
     def list(self, with_deps=False):
         if with_deps:
             return self._run("list", "--with-deps")
@@ -209,9 +251,9 @@ class _Nest:
         return self._run("help")
 
 
-def nest(config: str = None, chroot: str = None) -> _Nest:
+def nest(config: str = None, chroot: str = None, metrics_file: str = None) -> _Nest:
     chroot = chroot or os.getenv("NEST_CHROOT")
-    return _Nest(config, chroot)
+    return _Nest(config, chroot, metrics_file)
 
 
 class _Finest:
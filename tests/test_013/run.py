@@ -0,0 +1,23 @@
+#!/usr/bin/env python3.7
+
+"""
+Colors used when printing transactions should follow the configured theme, and the mono theme should disable them
+"""
+
+from nesttests import *
+
+config = {
+    "repositories": {"tests": {"mirrors": ["http://localhost:8000"]}},
+    "repositories_order": ["tests"],
+}
+
+with nest_server(), create_config(entries={**config, "ui": {"colors": {"pull": "blue"}}}) as config_path:
+    out = nest(config=config_path).pull(confirm=False)
+    assert out.returncode == 0
+    assert b"\x1b[34m      pull" in out.stdout
+    assert b"\x1b[36m" not in out.stdout
+
+with nest_server(), create_config(entries={**config, "ui": {"colors": {"theme": "mono"}}}) as config_path:
+    out = nest(config=config_path).pull(confirm=False)
+    assert out.returncode == 0
+    assert b"\x1b[" not in out.stdout
@@ -0,0 +1,39 @@
+#!/usr/bin/env python3.7
+
+"""
+Downloading a package should fetch it and its dependencies into the cache without installing anything
+"""
+
+import os
+from nesttests import *
+
+dependency = Package(
+    name="dependency",
+    category="sys-libs",
+    version="1.0.0",
+    kind="effective",
+)
+
+package = Package(
+    name="package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_dependency(dependency, "1.0.0")
+
+with nest_server(packages=[package, dependency]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+
+    assert nest.download("package").returncode == 0
+    for pkg in (package, dependency):
+        path = f"{nest.chroot}/var/nest/downloaded/tests/{pkg.category}/{pkg.name}/{pkg.name}-{pkg.version}.nest"
+        assert os.path.exists(path)
+
+    assert "tests::sys-apps/package" not in nest.depgraph().installed_packages()
+    assert "tests::sys-libs/dependency" not in nest.depgraph().installed_packages()
+
+    # Archives that are already downloaded and verified aren't downloaded again
+    out = nest.download("package")
+    assert out.returncode == 0
+    assert b"download" not in out.stdout
@@ -0,0 +1,52 @@
+#!/usr/bin/env python3.7
+
+"""
+Metrics about the state of the system should be written to the file given by --metrics-file
+"""
+
+import tempfile
+from nesttests import *
+
+package = Package(
+    name="package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+)
+
+package_v2 = Package(
+    name="package",
+    category="sys-apps",
+    version="2.0.0",
+    kind="effective",
+)
+
+other_package = Package(
+    name="other-package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+)
+
+metrics_path = tempfile.NamedTemporaryFile().name
+
+with nest_server(packages=[package, other_package]), create_config() as config_path:
+    nest = nest(config=config_path, metrics_file=metrics_path)
+    assert nest.pull().returncode == 0
+    assert nest.install("package", "other-package").returncode == 0
+
+with nest_server(packages=[package_v2, other_package]):
+    assert nest.pull().returncode == 0
+    assert nest.list().returncode == 0
+
+    with open(metrics_path) as f:
+        lines = f.read().splitlines()
+
+    assert "# TYPE nest_packages_installed gauge" in lines
+    assert "nest_packages_installed 2" in lines
+    assert "nest_pending_upgrades 1" in lines
+    assert any(line.startswith('nest_last_pull_timestamp{repository="tests"} ') and not line.endswith(" 0") for line in lines)
+    assert any(line.startswith('nest_cache_bytes{cache="available"} ') for line in lines)
+    assert any(line.startswith('nest_cache_bytes{cache="downloaded"} ') for line in lines)
+
+os.remove(metrics_path)
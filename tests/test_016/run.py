@@ -0,0 +1,53 @@
+#!/usr/bin/env python3.7
+
+"""
+Queries should still find the remaining packages when a category of the cache is removed concurrently
+"""
+
+import tempfile
+import threading
+from nesttests import *
+
+kept_package = Package(
+    name="kept-package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+)
+
+removed_package = Package(
+    name="removed-package",
+    category="sys-libs",
+    version="1.0.0",
+    kind="effective",
+)
+
+with nest_server(packages=[kept_package, removed_package]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+
+    category = f"{nest.chroot}/var/nest/available/tests/sys-libs"
+    backup = f"{tempfile.mkdtemp()}/sys-libs"
+    subprocess.run(["sudo", "cp", "-a", category, backup], check=True)
+
+    # Simulate a concurrent clean removing the category (and a pull restoring it) while the queries are running
+    stop = threading.Event()
+
+    def remove_category_repeatedly():
+        while not stop.is_set():
+            subprocess.run(["sudo", "rm", "-rf", category], check=True)
+            subprocess.run(["sudo", "cp", "-a", backup, category], check=True)
+
+    remover = threading.Thread(target=remove_category_repeatedly)
+    remover.start()
+    try:
+        for _ in range(10):
+            assert nest.download("kept-package").returncode == 0
+    finally:
+        stop.set()
+        remover.join()
+
+    subprocess.run(["sudo", "rm", "-rf", category, backup], check=True)
+    assert nest.install("removed-package").returncode == 1
+    assert nest.install("kept-package").returncode == 0
+    assert "tests::sys-apps/kept-package" in nest.depgraph().installed_packages()
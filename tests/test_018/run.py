@@ -0,0 +1,36 @@
+#!/usr/bin/env python3.7
+
+"""
+Verifying installed packages should report exactly the files that were modified
+"""
+
+from nesttests import *
+
+packages = [
+    Package(
+        name=f"package-{i}",
+        category="sys-apps",
+        version="1.0.0",
+        kind="effective",
+    ).add_file(f"/usr/share/package-{i}/data", with_content=f"data of package {i}")
+    for i in range(3)
+]
+
+with nest_server(packages=packages), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+    assert nest.install(*[p.name for p in packages]).returncode == 0
+
+    assert nest.verify(all=True).returncode == 0
+
+    tampered_path = f"{nest.chroot}/usr/share/package-1/data"
+    subprocess.run(["sudo", "sh", "-c", f"echo tampered > {tampered_path}"], check=True)
+
+    out = nest.verify(all=True)
+    assert out.returncode == 1
+    assert packages[1].package_id().encode() in out.stdout
+    assert b"/usr/share/package-1/data" in out.stdout
+    for package in (packages[0], packages[2]):
+        assert package.package_id().encode() not in out.stdout
+
+    assert nest.verify("package-0", "package-2").returncode == 0
@@ -0,0 +1,82 @@
+#!/usr/bin/env python3.7
+
+"""
+Downloads targeting the same host should never use more connections than allowed by max_connections_per_host
+"""
+
+import os
+import threading
+import urllib.request
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+lock = threading.Lock()
+connections = {}
+max_connections = {}
+
+
+class SlowProxyHandler(BaseHTTPRequestHandler):
+    """Forwards requests to the nest server, slowing down and counting the concurrent downloads per host"""
+
+    def do_GET(self):
+        host = self.headers.get("Host")
+        slow = self.path.endswith("/download") or self.path.endswith("/hash")
+        if slow:
+            with lock:
+                connections[host] = connections.get(host, 0) + 1
+                max_connections[host] = max(max_connections.get(host, 0), connections[host])
+            sleep(0.3)
+        try:
+            with urllib.request.urlopen(f"http://localhost:8000{self.path}") as response:
+                body = response.read()
+            self.send_response(200)
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+        finally:
+            if slow:
+                with lock:
+                    connections[host] -= 1
+
+    def log_message(self, *args):
+        pass
+
+
+def config_with_limit(limit):
+    config = {
+        "repositories": {"tests": {"mirrors": ["http://localhost:8002"]}},
+        "repositories_order": ["tests"],
+    }
+    if limit is not None:
+        config["network"] = {"max_connections_per_host": limit}
+    return config
+
+
+packages = [
+    Package(name=f"package-{i}", category="sys-apps", version="1.0.0", kind="effective")
+    for i in range(4)
+]
+
+proxy = ThreadingHTTPServer(("localhost", 8002), SlowProxyHandler)
+threading.Thread(target=proxy.serve_forever, daemon=True).start()
+
+try:
+    with nest_server(packages=packages):
+        with create_config(entries=config_with_limit(1)) as config_path:
+            limited_nest = nest(config=config_path)
+            assert limited_nest.pull().returncode == 0
+            assert limited_nest.install(*[p.name for p in packages]).returncode == 0
+            for package in packages:
+                assert package.full_name() in limited_nest.depgraph().installed_packages()
+            assert max_connections == {"localhost:8002": 1}
+            assert limited_nest.uninstall(*[p.name for p in packages]).returncode == 0
+
+        # Without any limit, the downloads are performed concurrently
+        if os.cpu_count() > 1:
+            max_connections.clear()
+            with create_config(entries=config_with_limit(None)) as config_path:
+                unlimited_nest = nest(config=config_path)
+                assert unlimited_nest.install(*[p.name for p in packages]).returncode == 0
+                assert max_connections["localhost:8002"] > 1
+finally:
+    proxy.shutdown()
@@ -0,0 +1,38 @@
+#!/usr/bin/env python3.7
+
+"""
+A package whose extraction fails midway should not leave any of its files behind
+"""
+
+from nesttests import *
+
+broken_package = Package(
+    name="broken-package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file(
+    "/usr/share/broken-package/first", with_content="extracted before the failure"
+).add_file(
+    # The parent of this file is a regular file in the chroot, so it can't be extracted
+    "/usr/share/blocker/second", with_content="never extracted"
+)
+
+with nest_server(packages=[broken_package]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+
+    subprocess.run(["sudo", "mkdir", "-p", f"{nest.chroot}/usr/share"], check=True)
+    subprocess.run(["sudo", "touch", f"{nest.chroot}/usr/share/blocker"], check=True)
+
+    out = nest.install("broken-package")
+    assert out.returncode == 1
+    assert b"rolled back" in out.stderr
+
+    assert not os.path.exists(f"{nest.chroot}/usr/share/broken-package")
+    assert os.path.isfile(f"{nest.chroot}/usr/share/blocker")
+    for _, _, logs in os.walk(f"{nest.chroot}/var/nest/installed/tests/sys-apps/broken-package"):
+        assert not logs
+    assert "tests::sys-apps/broken-package" not in nest.depgraph().installed_packages()
+
+    subprocess.run(["sudo", "rm", f"{nest.chroot}/usr/share/blocker"], check=True)
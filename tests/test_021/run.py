@@ -0,0 +1,147 @@
+#!/usr/bin/env python3.7
+
+"""
+A download interrupted midway should be resumed by the next mirror, or restarted if it can't resume
+"""
+
+import os
+import threading
+import urllib.request
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+received_ranges = {}
+
+
+class MirrorHandler(BaseHTTPRequestHandler):
+    """Forwards requests to the nest server, altering the way packages are served"""
+
+    port = None
+    ping_delay = 0
+
+    def do_HEAD(self):
+        # Answer the pings measuring the latency of the mirrors
+        sleep(self.ping_delay)
+        self.send_response(200)
+        self.send_header("Content-Length", "0")
+        self.end_headers()
+
+    def do_GET(self):
+        with urllib.request.urlopen(f"http://localhost:8000{self.path}") as response:
+            body = response.read()
+        if self.path.endswith("/download"):
+            received_ranges.setdefault(self.port, []).append(self.headers.get("Range"))
+            self.serve_package(body)
+        else:
+            self.send_response(200)
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+
+    def serve_package(self, body):
+        raise NotImplementedError
+
+    def log_message(self, *args):
+        pass
+
+
+class InterruptingHandler(MirrorHandler):
+    """Closes the connection after sending half of the package"""
+
+    port = 8003
+
+    def serve_package(self, body):
+        self.send_response(200)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body[:len(body) // 2])
+        self.wfile.flush()
+        self.close_connection = True
+
+
+class ResumingHandler(MirrorHandler):
+    """Honors the requested range with a partial response"""
+
+    port = 8004
+    ping_delay = 0.3
+
+    def serve_package(self, body):
+        requested = self.headers.get("Range")
+        start = int(requested[len("bytes="):].split("-")[0]) if requested else 0
+        self.send_response(206 if start else 200)
+        if start:
+            self.send_header("Content-Range", f"bytes {start}-{len(body) - 1}/{len(body)}")
+        self.send_header("Content-Length", str(len(body) - start))
+        self.end_headers()
+        self.wfile.write(body[start:])
+
+
+class NonResumingHandler(MirrorHandler):
+    """Ignores the requested range, always sending the whole package"""
+
+    port = 8005
+    ping_delay = 0.3
+
+    def serve_package(self, body):
+        self.send_response(200)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+
+content = os.urandom(512 * 1024).hex()
+package = Package(
+    name="big-package",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/share/big-package/data", with_content=content)
+
+servers = [
+    ThreadingHTTPServer(("localhost", handler.port), handler)
+    for handler in (InterruptingHandler, ResumingHandler, NonResumingHandler)
+]
+for server in servers:
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+
+
+def config_with_mirrors(*ports):
+    return {
+        "repositories": {"tests": {"mirrors": [f"http://localhost:{port}" for port in ports]}},
+        "repositories_order": ["tests"],
+    }
+
+
+try:
+    with nest_server(packages=[package]):
+        # The second mirror resumes the download where the first one stopped
+        with create_config(entries=config_with_mirrors(8003, 8004)) as config_path:
+            resuming_nest = nest(config=config_path)
+            assert resuming_nest.pull().returncode == 0
+            assert resuming_nest.install(package.name).returncode == 0
+            assert received_ranges[8003] == [None]
+            assert len(received_ranges[8004]) == 1
+            assert received_ranges[8004][0].startswith("bytes=")
+            assert received_ranges[8004][0] != "bytes=0-"
+            with open(f"{resuming_nest.chroot}/usr/share/big-package/data") as f:
+                assert f.read() == content
+            assert resuming_nest.uninstall(package.name).returncode == 0
+
+        # Forget the downloaded package, so it's downloaded again
+        subprocess.run(["sudo", "rm", "-rf", f"{resuming_nest.chroot}/var/nest/downloaded"], check=True)
+        received_ranges.clear()
+
+        # A mirror ignoring the requested range is used to download the whole package again
+        with create_config(entries=config_with_mirrors(8003, 8005)) as config_path:
+            restarting_nest = nest(config=config_path)
+            assert restarting_nest.pull().returncode == 0
+            assert restarting_nest.install(package.name).returncode == 0
+            assert received_ranges[8003] == [None]
+            assert len(received_ranges[8005]) == 2
+            assert received_ranges[8005][0].startswith("bytes=")
+            assert received_ranges[8005][1] is None
+            with open(f"{restarting_nest.chroot}/usr/share/big-package/data") as f:
+                assert f.read() == content
+finally:
+    for server in servers:
+        server.shutdown()
@@ -0,0 +1,43 @@
+#!/usr/bin/env python3.7
+
+"""
+A package shouldn't be installed if one of its files is already owned by another package
+"""
+
+from nesttests import *
+
+busybox = Package(
+    name="busybox",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/ls", with_content="busybox's ls")
+
+coreutils = Package(
+    name="coreutils",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/ls", with_content="coreutils' ls")
+
+findutils = Package(
+    name="findutils",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/find", with_content="findutils' find")
+
+with nest_server(packages=[busybox, coreutils, findutils]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+    assert nest.install(busybox.name).returncode == 0
+
+    # Sharing the /usr/bin directory isn't a conflict
+    assert nest.install(findutils.name).returncode == 0
+
+    out = nest.install(coreutils.name)
+    assert out.returncode == 1
+    assert b"already owned by" in out.stderr
+    assert busybox.package_id().encode() in out.stderr
+    with open(f"{nest.chroot}/usr/bin/ls") as f:
+        assert f.read() == "busybox's ls"
@@ -0,0 +1,54 @@
+#!/usr/bin/env python3.7
+
+"""
+The pre-install.sh and post-install.sh scripts of a package should only run if enabled in the configuration,
+and a failing script should make the installation fail
+"""
+
+from nesttests import *
+
+scripted = Package(
+    name="scripted",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/scripted", with_content="scripted") \
+    .add_script("pre-install.sh", "echo pre > /pre-install-marker") \
+    .add_script("post-install.sh", "echo post > /post-install-marker")
+
+failing = Package(
+    name="failing",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/failing", with_content="failing") \
+    .add_script("post-install.sh", "exit 1")
+
+entries = {
+    "repositories": {"tests": {"mirrors": ["http://localhost:8000"]}},
+    "repositories_order": ["tests"],
+    "install": {"run_scripts": True},
+}
+
+with nest_server(packages=[scripted, failing]):
+    # Scripts are ignored by default
+    with create_config() as config_path:
+        default_nest = nest(config=config_path)
+        install_host_shell(default_nest.chroot)
+        assert default_nest.pull().returncode == 0
+        assert default_nest.install(scripted.name).returncode == 0
+        assert os.path.exists(f"{default_nest.chroot}/usr/bin/scripted")
+        assert not os.path.exists(f"{default_nest.chroot}/post-install-marker")
+        assert default_nest.uninstall(scripted.name).returncode == 0
+
+    with create_config(entries) as config_path:
+        scripts_nest = nest(config=config_path)
+        assert scripts_nest.install(scripted.name).returncode == 0
+        with open(f"{scripts_nest.chroot}/pre-install-marker") as f:
+            assert f.read() == "pre\n"
+        with open(f"{scripts_nest.chroot}/post-install-marker") as f:
+            assert f.read() == "post\n"
+
+        out = scripts_nest.install(failing.name)
+        assert out.returncode == 1
+        assert b"post-install script reported an error" in out.stderr
@@ -0,0 +1,30 @@
+#!/usr/bin/env python3.7
+
+"""
+Downloads should be throttled when a maximum download speed is configured
+"""
+
+from nesttests import *
+
+big = Package(
+    name="big",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/share/big/data", with_content=os.urandom(200 * 1024))
+
+entries = {
+    "repositories": {"tests": {"mirrors": ["http://localhost:8000"]}},
+    "repositories_order": ["tests"],
+    "network": {"max_download_speed": 50 * 1024},
+}
+
+with nest_server(packages=[big]), create_config(entries) as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+
+    # The limit itself is checked on the curl handle by the unit tests of nest-cli, this makes sure a throttled
+    # download still goes through
+    assert nest.download(big.name).returncode == 0
+    path = f"{nest.chroot}/var/nest/downloaded/tests/{big.category}/{big.name}/{big.name}-{big.version}.nest"
+    assert os.path.getsize(path) > 200 * 1024
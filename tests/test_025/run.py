@@ -0,0 +1,81 @@
+#!/usr/bin/env python3.7
+
+"""
+No more downloads than allowed by max_concurrent_downloads should be performed at the same time
+"""
+
+import os
+import threading
+import urllib.request
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+lock = threading.Lock()
+downloads = 0
+peak_downloads = 0
+
+
+class SlowProxyHandler(BaseHTTPRequestHandler):
+    """Forwards requests to the nest server, slowing down and counting the concurrent downloads"""
+
+    def do_GET(self):
+        global downloads, peak_downloads
+
+        slow = self.path.endswith("/download") or self.path.endswith("/hash")
+        if slow:
+            with lock:
+                downloads += 1
+                peak_downloads = max(peak_downloads, downloads)
+            sleep(0.3)
+        try:
+            with urllib.request.urlopen(f"http://localhost:8000{self.path}") as response:
+                body = response.read()
+            self.send_response(200)
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+        finally:
+            if slow:
+                with lock:
+                    downloads -= 1
+
+    def log_message(self, *args):
+        pass
+
+
+def config_with_limit(limit):
+    return {
+        "repositories": {"tests": {"mirrors": ["http://localhost:8006"]}},
+        "repositories_order": ["tests"],
+        "network": {"max_concurrent_downloads": limit},
+    }
+
+
+packages = [
+    Package(name=f"package-{i}", category="sys-apps", version="1.0.0", kind="effective")
+    for i in range(4)
+]
+
+proxy = ThreadingHTTPServer(("localhost", 8006), SlowProxyHandler)
+threading.Thread(target=proxy.serve_forever, daemon=True).start()
+
+try:
+    with nest_server(packages=packages):
+        # A limit of 1 makes the downloads serial
+        with create_config(entries=config_with_limit(1)) as config_path:
+            serial_nest = nest(config=config_path)
+            assert serial_nest.pull().returncode == 0
+            assert serial_nest.install(*[p.name for p in packages]).returncode == 0
+            for package in packages:
+                assert package.full_name() in serial_nest.depgraph().installed_packages()
+            assert peak_downloads == 1
+            assert serial_nest.uninstall(*[p.name for p in packages]).returncode == 0
+
+        if os.cpu_count() > 2:
+            peak_downloads = 0
+            with create_config(entries=config_with_limit(2)) as config_path:
+                limited_nest = nest(config=config_path)
+                assert limited_nest.install(*[p.name for p in packages]).returncode == 0
+                assert peak_downloads == 2
+finally:
+    proxy.shutdown()
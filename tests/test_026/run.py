@@ -0,0 +1,65 @@
+#!/usr/bin/env python3.7
+
+"""
+Transactions interrupted midway should be recorded in the journal, and the next run should offer to
+either resume them or roll back the completed ones
+"""
+
+from nesttests import *
+
+base = Package(
+    name="base",
+    category="sys-libs",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/lib/base", with_content="base")
+
+flaky = Package(
+    name="flaky",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/flaky", with_content="flaky") \
+    .add_script("pre-install.sh", "test -f /allow-flaky") \
+    .add_dependency(base, "=1.0.0")
+
+entries = {
+    "repositories": {"tests": {"mirrors": ["http://localhost:8000"]}},
+    "repositories_order": ["tests"],
+    "install": {"run_scripts": True},
+}
+
+with nest_server(packages=[base, flaky]):
+    # Resume the interrupted transactions
+    with create_config(entries) as config_path:
+        resumed_nest = nest(config=config_path)
+        install_host_shell(resumed_nest.chroot)
+        journal = f"{resumed_nest.chroot}/var/nest/journal"
+
+        assert resumed_nest.pull().returncode == 0
+        assert resumed_nest.install(flaky.name).returncode == 1
+        assert os.path.exists(journal)
+        assert os.path.exists(f"{resumed_nest.chroot}/usr/lib/base")
+        assert not os.path.exists(f"{resumed_nest.chroot}/usr/bin/flaky")
+        assert flaky.full_name() not in resumed_nest.depgraph().installed_packages()
+
+        open(f"{resumed_nest.chroot}/allow-flaky", "w").close()
+        assert resumed_nest._run("list", input_str="yes").returncode == 0
+        assert not os.path.exists(journal)
+        assert os.path.exists(f"{resumed_nest.chroot}/usr/bin/flaky")
+        assert flaky.full_name() in resumed_nest.depgraph().installed_packages()
+
+    # Roll back the completed transactions
+    with create_config(entries) as config_path:
+        rolled_back_nest = nest(config=config_path)
+        install_host_shell(rolled_back_nest.chroot)
+        journal = f"{rolled_back_nest.chroot}/var/nest/journal"
+
+        assert rolled_back_nest.pull().returncode == 0
+        assert rolled_back_nest.install(flaky.name).returncode == 1
+        assert os.path.exists(journal)
+
+        assert rolled_back_nest._run("list", input_str="no").returncode == 0
+        assert not os.path.exists(journal)
+        assert not os.path.exists(f"{rolled_back_nest.chroot}/usr/lib/base")
+        assert flaky.full_name() not in rolled_back_nest.depgraph().installed_packages()
@@ -0,0 +1,40 @@
+#!/usr/bin/env python3.7
+
+"""
+Reinstalling a package should restore its files, and fail if the package isn't installed
+"""
+
+import os
+from nesttests import *
+
+installed = Package(
+    name="installed",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/share/installed/data", with_content="data of the installed package")
+
+not_installed = Package(
+    name="not-installed",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+)
+
+with nest_server(packages=[installed, not_installed]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+    assert nest.install(installed.name).returncode == 0
+
+    data_path = f"{nest.chroot}/usr/share/installed/data"
+    subprocess.run(["sudo", "rm", data_path], check=True)
+    assert not os.path.exists(data_path)
+
+    assert nest.reinstall(installed.name).returncode == 0
+    assert os.path.exists(data_path)
+    with open(data_path) as f:
+        assert f.read() == "data of the installed package"
+    assert installed.full_name() in nest.depgraph().installed_packages()
+
+    assert nest.reinstall(not_installed.name).returncode == 1
+    assert not_installed.full_name() not in nest.depgraph().installed_packages()
@@ -0,0 +1,71 @@
+#!/usr/bin/env python3.7
+
+"""
+Pulling a repository should be retried when downloading its manifests fails
+"""
+
+import threading
+import urllib.request
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+lock = threading.Lock()
+failures_left = 0
+
+
+class FlakyProxyHandler(BaseHTTPRequestHandler):
+    """Forwards requests to the nest server, failing the pulls as long as there are failures left"""
+
+    def do_GET(self):
+        global failures_left
+
+        with lock:
+            fail = self.path.endswith("/api/pull") and failures_left > 0
+            if fail:
+                failures_left -= 1
+        if fail:
+            self.send_response(503)
+            self.send_header("Content-Length", "0")
+            self.end_headers()
+            return
+
+        with urllib.request.urlopen(f"http://localhost:8000{self.path}") as response:
+            body = response.read()
+        self.send_response(200)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args):
+        pass
+
+
+def config_with_retries(retries):
+    return {
+        "repositories": {"tests": {"mirrors": ["http://localhost:8007"]}},
+        "repositories_order": ["tests"],
+        "network": {"pull_retries": retries, "pull_retry_delay": 10},
+    }
+
+
+package = Package(name="package", category="sys-apps", version="1.0.0", kind="effective")
+
+proxy = ThreadingHTTPServer(("localhost", 8007), FlakyProxyHandler)
+threading.Thread(target=proxy.serve_forever, daemon=True).start()
+
+try:
+    with nest_server(packages=[package]):
+        # Without any retry, a single failure aborts the pull
+        failures_left = 2
+        with create_config(entries=config_with_retries(0)) as config_path:
+            assert nest(config=config_path).pull().returncode == 1
+
+        # The mirror fails twice, then succeeds
+        failures_left = 2
+        with create_config(entries=config_with_retries(3)) as config_path:
+            flaky_nest = nest(config=config_path)
+            assert flaky_nest.pull().returncode == 0
+            assert failures_left == 0
+            assert flaky_nest.install(package.name).returncode == 0
+finally:
+    proxy.shutdown()
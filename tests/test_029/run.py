@@ -0,0 +1,92 @@
+#!/usr/bin/env python3.7
+
+"""
+Pulling a repository whose manifests didn't change since the last pull shouldn't download them again
+"""
+
+import threading
+import urllib.request
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+etag = '"v1"'
+pulls = []
+
+
+class CachingProxyHandler(BaseHTTPRequestHandler):
+    """Forwards requests to the nest server, tagging the pulls with an ETag and honoring If-None-Match"""
+
+    def do_GET(self):
+        if self.path.endswith("/api/pull"):
+            pulls.append(self.headers.get("If-None-Match"))
+            if self.headers.get("If-None-Match") == etag:
+                self.send_response(304)
+                self.send_header("ETag", etag)
+                self.end_headers()
+                return
+
+        with urllib.request.urlopen(f"http://localhost:8000{self.path}") as response:
+            body = response.read()
+        self.send_response(200)
+        self.send_header("Content-Length", str(len(body)))
+        if self.path.endswith("/api/pull"):
+            self.send_header("ETag", etag)
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args):
+        pass
+
+
+package = Package(name="package", category="sys-apps", version="1.0.0", kind="effective")
+
+proxy = ThreadingHTTPServer(("localhost", 8008), CachingProxyHandler)
+threading.Thread(target=proxy.serve_forever, daemon=True).start()
+
+try:
+    with nest_server(packages=[package]):
+        config = {
+            "repositories": {"tests": {"mirrors": ["http://localhost:8008"]}},
+            "repositories_order": ["tests"],
+            "manifest_checksums": True,
+        }
+        with create_config(entries=config) as config_path:
+            cached_nest = nest(config=config_path)
+
+            # The first pull doesn't know any ETag yet
+            out = cached_nest.pull()
+            assert out.returncode == 0
+            assert pulls == [None]
+            assert b"already up to date" not in out.stdout
+
+            # The next one sends it back, and the mirror answers the manifests didn't change
+            out = cached_nest.pull()
+            assert out.returncode == 0
+            assert pulls == [None, etag]
+            assert b"Repository 'tests' is already up to date" in out.stdout
+
+            # The cache was left untouched
+            assert cached_nest.install(package.name).returncode == 0
+
+            # A forced pull doesn't send the ETag back
+            out = cached_nest.pull(force=True)
+            assert out.returncode == 0
+            assert pulls == [None, etag, None]
+            assert b"already up to date" not in out.stdout
+
+            # Neither does a pull of a repository whose cache is corrupted
+            manifest = f"{cached_nest.chroot}/var/nest/available/tests/{package.category}/{package.name}"
+            subprocess.run(["sudo", "tee", "-a", manifest], input=b"garbage", capture_output=True, check=True)
+            out = cached_nest.pull()
+            assert out.returncode == 0
+            assert pulls == [None, etag, None, None]
+            assert b"already up to date" not in out.stdout
+
+            # Once the manifests change, they are downloaded again
+            etag = '"v2"'
+            out = cached_nest.pull()
+            assert out.returncode == 0
+            assert pulls == [None, '"v1"', None, None, '"v1"']
+            assert b"already up to date" not in out.stdout
+finally:
+    proxy.shutdown()
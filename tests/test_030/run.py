@@ -0,0 +1,50 @@
+#!/usr/bin/env python3.7
+
+"""
+Repositories with a mirror list should use the mirrors it lists
+"""
+
+import threading
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+
+class MirrorListHandler(BaseHTTPRequestHandler):
+    """Serves a mirror list pointing to the nest server"""
+
+    def do_GET(self):
+        body = b"# Mirrors of the tests repository\nhttp://localhost:8000\n"
+        self.send_response(200)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args):
+        pass
+
+
+package = Package(name="package", category="sys-apps", version="1.0.0", kind="effective")
+
+mirror_list_server = ThreadingHTTPServer(("localhost", 8009), MirrorListHandler)
+threading.Thread(target=mirror_list_server.serve_forever, daemon=True).start()
+
+try:
+    with nest_server(packages=[package]):
+        config = {
+            "repositories": {
+                "tests": {
+                    "mirrors": [],
+                    "mirrorlist_url": "http://localhost:8009/mirrorlist",
+                }
+            },
+            "repositories_order": ["tests"],
+        }
+        with create_config(entries=config) as config_path:
+            listed_nest = nest(config=config_path)
+            assert listed_nest.pull().returncode == 0
+
+            # The cached mirror list is used by the next runs
+            mirror_list_server.shutdown()
+            assert listed_nest.install(package.name).returncode == 0
+finally:
+    mirror_list_server.shutdown()
@@ -0,0 +1,49 @@
+#!/usr/bin/env python3.7
+
+"""
+Downloads from a mirror that stops sending data should time out instead of hanging
+"""
+
+import threading
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from nesttests import *
+
+stalled = threading.Event()
+
+
+class StalledHandler(BaseHTTPRequestHandler):
+    """Answers with a promising response, then never sends its body"""
+
+    def do_GET(self):
+        self.send_response(200)
+        self.send_header("Content-Length", "1048576")
+        self.end_headers()
+        self.wfile.flush()
+        stalled.wait()
+
+    def log_message(self, *args):
+        pass
+
+
+config = {
+    "repositories": {"tests": {"mirrors": ["http://localhost:8010"]}},
+    "repositories_order": ["tests"],
+    "network": {"pull_retries": 0, "low_speed_limit": 1, "low_speed_time": 1000},
+    "ui": {"log_level": "info"},
+}
+
+server = ThreadingHTTPServer(("localhost", 8010), StalledHandler)
+server.daemon_threads = True
+threading.Thread(target=server.serve_forever, daemon=True).start()
+
+try:
+    with create_config(entries=config) as config_path:
+        out = nest(config=config_path).pull()
+        assert out.returncode == 1
+
+        # The failure of the mirror, logged at the info level, shows the transfer was aborted by curl's low speed
+        # timeout rather than by the connection being closed
+        assert b"[28] Timeout was reached" in out.stderr
+finally:
+    stalled.set()
+    server.shutdown()
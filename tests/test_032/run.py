@@ -0,0 +1,22 @@
+#!/usr/bin/env python3.7
+
+"""
+Installing a mistyped package should suggest the packages named closely to it
+"""
+
+from nesttests import *
+
+coreutils = Package(name="coreutils", category="sys-apps", version="1.0.0", kind="effective")
+util_linux = Package(name="util-linux", category="sys-apps", version="1.0.0", kind="effective")
+
+with nest_server(packages=[coreutils, util_linux]), create_config() as config_path:
+    typo_nest = nest(config=config_path)
+    assert typo_nest.pull().returncode == 0
+
+    out = typo_nest.install("corutils")
+    assert out.returncode == 1
+    assert b"did you mean sys-apps/coreutils?" in out.stderr
+
+    out = typo_nest.install("emacs")
+    assert out.returncode == 1
+    assert b"did you mean" not in out.stderr
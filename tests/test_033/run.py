@@ -0,0 +1,41 @@
+#!/usr/bin/env python3.7
+
+"""
+A package forced to overwrite a file owned by another package should take over its ownership
+"""
+
+from nesttests import *
+
+busybox = Package(
+    name="busybox",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/ls", with_content="busybox's ls").add_file("/usr/bin/cp", with_content="busybox's cp")
+
+coreutils = Package(
+    name="coreutils",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/ls", with_content="coreutils' ls")
+
+with nest_server(packages=[busybox, coreutils]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+    assert nest.install(busybox.name).returncode == 0
+
+    # Without the flag, the conflict still aborts the installation
+    out = nest.install(coreutils.name)
+    assert out.returncode == 1
+    assert b"already owned by" in out.stderr
+
+    assert nest.install(coreutils.name, force_overwrite=True).returncode == 0
+    with open(f"{nest.chroot}/usr/bin/ls") as f:
+        assert f.read() == "coreutils' ls"
+
+    # The file now belongs to coreutils, so removing busybox leaves it in place
+    assert nest.uninstall(busybox.name).returncode == 0
+    assert not os.path.exists(f"{nest.chroot}/usr/bin/cp")
+    with open(f"{nest.chroot}/usr/bin/ls") as f:
+        assert f.read() == "coreutils' ls"
@@ -0,0 +1,38 @@
+#!/usr/bin/env python3.7
+
+"""
+Installing and removing a package should append an entry per affected file to the transaction log
+"""
+
+from nesttests import *
+
+files = ["/usr/bin/hello", "/usr/share/hello/greeting", "/usr/share/man/man1/hello.1"]
+
+hello = Package(
+    name="hello",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+)
+for path in files:
+    hello.add_file(path, with_content="hello")
+
+with nest_server(packages=[hello]), create_config() as config_path:
+    nest = nest(config=config_path)
+    assert nest.pull().returncode == 0
+    assert nest.install(hello.name).returncode == 0
+
+    with open(f"{nest.chroot}/var/log/nest/transactions.log") as f:
+        entries = [line.split() for line in f.read().splitlines()]
+    assert len(entries) == len(files)
+    for entry in entries:
+        assert entry[1:3] == ["install", "tests::sys-apps/hello#1.0.0"]
+    assert sorted(entry[3] for entry in entries) == sorted(files)
+
+    # Removals are appended after the installation
+    assert nest.uninstall(hello.name).returncode == 0
+    with open(f"{nest.chroot}/var/log/nest/transactions.log") as f:
+        entries = [line.split() for line in f.read().splitlines()]
+    assert len(entries) == 2 * len(files)
+    assert all(entry[1] == "install" for entry in entries[:len(files)])
+    assert all(entry[1] == "remove" for entry in entries[len(files):])
@@ -0,0 +1,59 @@
+#!/usr/bin/env python3.7
+
+"""
+A file:// mirror should serve packages from a local directory, and mirrors with an unsupported scheme should be rejected
+"""
+
+import hashlib
+from nesttests import *
+
+hello = Package(
+    name="hello",
+    category="sys-apps",
+    version="1.0.0",
+    kind="effective",
+).add_file("/usr/bin/hello", with_content="hello")
+
+# Lay out a local mirror like the routes of a remote one
+mirror = tempfile.mkdtemp()
+hello._create_in(f"{mirror}/build")
+route = f"{mirror}/api/p/{hello.category}/{hello.name}/{hello.version}"
+os.makedirs(route)
+shutil.move(f"{mirror}/build/{hello.category}/{hello.name}/{hello.name}-{hello.version}.nest", f"{route}/download")
+with open(f"{route}/download", "rb") as f:
+    sha256 = hashlib.sha256(f.read()).hexdigest().upper()
+with open(f"{route}/hash", "w") as f:
+    json.dump({"sha256": sha256}, f)
+with open(f"{mirror}/api/pull", "w") as f:
+    json.dump([{
+        "name": hello.name,
+        "category": hello.category,
+        "repository": "tests",
+        "metadata": {
+            "description": hello.description,
+            "tags": hello.tags,
+            "maintainer": hello.maintainer,
+            "licenses": hello.licenses,
+            "upstream_url": hello.upstream_url,
+        },
+        "versions": {
+            hello.version: {"wrap_date": "2019-05-27T16:34:15Z", "dependencies": [], "kind": hello.kind},
+        },
+    }], f)
+
+try:
+    config = {"repositories": {"tests": {"mirrors": [f"file://{mirror}/"]}}, "repositories_order": ["tests"]}
+    with create_config(entries=config) as config_path:
+        nest = nest(config=config_path)
+        assert nest.pull().returncode == 0
+        assert nest.install(hello.name).returncode == 0
+        with open(f"{nest.chroot}/usr/bin/hello") as f:
+            assert f.read() == "hello"
+
+    config = {"repositories": {"tests": {"mirrors": ["ftp://localhost/"]}}, "repositories_order": ["tests"]}
+    with create_config(entries=config) as config_path:
+        out = nest(config=config_path).pull()
+        assert out.returncode == 1
+        assert b"unsupported scheme 'ftp'" in out.stderr
+finally:
+    shutil.rmtree(mirror)